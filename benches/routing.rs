@@ -0,0 +1,195 @@
+use std::{
+    hash::{BuildHasher, Hash, RandomState},
+    num::NonZeroUsize,
+    time::Duration,
+};
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use tokio::runtime::{Builder, Runtime};
+use tokio_sticky_channel::{
+    BatchingSender, StickyChannelBuilder, UnboundedReceiver, unbounded_sticky_channel_with_hasher,
+};
+
+const CONSUMERS: usize = 8;
+const MESSAGES: u64 = 1_000;
+
+fn consumers() -> NonZeroUsize {
+    NonZeroUsize::new(CONSUMERS).unwrap()
+}
+
+fn runtime() -> Runtime {
+    Builder::new_current_thread().enable_time().build().unwrap()
+}
+
+fn drain<T>(receivers: &mut [UnboundedReceiver<T>]) {
+    for receiver in receivers {
+        while receiver.try_recv().is_ok() {}
+    }
+}
+
+/// Sends `MESSAGES` messages through an unbounded channel routed with `build_hasher`, then drains it.
+fn bench_hasher<S, K>(c: &mut Criterion, name: &str, build_hasher: S, key: impl Fn(u64) -> K)
+where
+    S: BuildHasher + Clone,
+    K: Hash,
+{
+    let keys: Vec<K> = (0..MESSAGES).map(key).collect();
+    let (sender, mut receivers) =
+        unbounded_sticky_channel_with_hasher::<&K, u64, S>(consumers(), build_hasher);
+
+    let mut group = c.benchmark_group("hasher");
+    group.throughput(Throughput::Elements(MESSAGES));
+    group.bench_function(name, |b| {
+        b.iter(|| {
+            for (message, key) in keys.iter().enumerate() {
+                sender.send(key, message as u64).unwrap();
+            }
+            drain(&mut receivers);
+        })
+    });
+    group.finish();
+}
+
+fn hashers(c: &mut Criterion) {
+    bench_hasher(c, "default/u64", RandomState::new(), |id| id);
+    bench_hasher(c, "ahash/u64", ahash::RandomState::new(), |id| id);
+    bench_hasher(c, "fxhash/u64", fxhash::FxBuildHasher::default(), |id| id);
+
+    let key = |id: u64| format!("tenant-{id:08}");
+    bench_hasher(c, "default/string", RandomState::new(), key);
+    bench_hasher(c, "ahash/string", ahash::RandomState::new(), key);
+    bench_hasher(c, "fxhash/string", fxhash::FxBuildHasher::default(), key);
+}
+
+fn channels(c: &mut Criterion) {
+    let mut group = c.benchmark_group("channel");
+    group.throughput(Throughput::Elements(MESSAGES));
+
+    group.bench_function("unbounded", |b| {
+        let (sender, mut receivers) =
+            unbounded_sticky_channel_with_hasher::<u64, u64, _>(consumers(), RandomState::new());
+        b.iter(|| {
+            for id in 0..MESSAGES {
+                sender.send(id, id).unwrap();
+            }
+            drain(&mut receivers);
+        })
+    });
+
+    group.bench_function("bounded", |b| {
+        let (sender, mut receivers) =
+            StickyChannelBuilder::<u64, u64>::new(consumers(), MESSAGES as usize).build();
+        b.iter(|| {
+            for id in 0..MESSAGES {
+                sender.try_send(id, id).unwrap();
+            }
+            for receiver in &mut receivers {
+                while receiver.try_recv().is_ok() {}
+            }
+        })
+    });
+
+    for block_size in [16, 64] {
+        group.bench_with_input(
+            BenchmarkId::new("bounded/block", block_size),
+            &block_size,
+            |b, &block_size| {
+                let (sender, mut receivers) =
+                    StickyChannelBuilder::<u64, u64>::new(consumers(), MESSAGES as usize)
+                        .block_size(NonZeroUsize::new(block_size).unwrap())
+                        .build();
+                b.iter(|| {
+                    for id in 0..MESSAGES {
+                        sender.try_send(id, id).unwrap();
+                    }
+                    for receiver in &mut receivers {
+                        while receiver.try_recv().is_ok() {}
+                    }
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn batching(c: &mut Criterion) {
+    let runtime = runtime();
+    let mut group = c.benchmark_group("send");
+    group.throughput(Throughput::Elements(MESSAGES));
+
+    group.bench_function("single", |b| {
+        let (sender, mut receivers) =
+            StickyChannelBuilder::<u64, u64>::new(consumers(), MESSAGES as usize).build();
+        b.iter(|| {
+            runtime.block_on(async {
+                for id in 0..MESSAGES {
+                    sender.send(id, id).await.unwrap();
+                }
+            });
+            for receiver in &mut receivers {
+                while receiver.try_recv().is_ok() {}
+            }
+        })
+    });
+
+    for max_batch in [16, 64] {
+        group.bench_with_input(
+            BenchmarkId::new("batched", max_batch),
+            &max_batch,
+            |b, &max_batch| {
+                let (sender, mut receivers) =
+                    StickyChannelBuilder::<u64, u64>::new(consumers(), MESSAGES as usize).build();
+                let sender = runtime.block_on(async {
+                    BatchingSender::new(
+                        sender,
+                        Duration::from_secs(60),
+                        NonZeroUsize::new(max_batch).unwrap(),
+                    )
+                });
+                b.iter(|| {
+                    runtime.block_on(async {
+                        for id in 0..MESSAGES {
+                            sender.send(id, id).await.unwrap();
+                        }
+                    });
+                    sender.flush();
+                    for receiver in &mut receivers {
+                        while receiver.try_recv().is_ok() {}
+                    }
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+#[cfg(feature = "bytes")]
+fn bytes_backend(c: &mut Criterion) {
+    use bytes::Bytes;
+    use tokio_sticky_channel::sticky_bytes_channel;
+
+    let payload = Bytes::from_static(&[0; 64]);
+    let mut group = c.benchmark_group("backend");
+    group.throughput(Throughput::Elements(MESSAGES));
+    group.bench_function("bytes", |b| {
+        let (sender, mut receivers) =
+            sticky_bytes_channel::<u64>(consumers(), MESSAGES as usize * payload.len());
+        b.iter(|| {
+            for id in 0..MESSAGES {
+                sender.try_send(id, payload.clone()).unwrap();
+            }
+            for receiver in &mut receivers {
+                while receiver.try_recv().is_ok() {}
+            }
+        })
+    });
+    group.finish();
+}
+
+#[cfg(not(feature = "bytes"))]
+fn bytes_backend(_: &mut Criterion) {}
+
+criterion_group!(benches, hashers, channels, batching, bytes_backend);
+criterion_main!(benches);
@@ -0,0 +1,244 @@
+use std::{
+    collections::HashMap,
+    future::poll_fn,
+    hash::Hash,
+    num::NonZeroUsize,
+    task::{Context, Poll, ready},
+};
+
+use tokio::sync::mpsc::UnboundedSender as MpscSender;
+
+use crate::{KeyReceiver, StickyReceiver, TryRecvError, subscribe::key_channel};
+
+/// Which sub-queue a [`Demux`] closes when a new key arrives while all sub-queues are in use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Eviction {
+    /// Closes the sub-queue of the key that received a message least recently.
+    #[default]
+    LeastRecentlyUsed,
+    /// Closes the sub-queue that was opened first.
+    Oldest,
+}
+
+/// Receiver adapter that splits the messages of a receiver into sub-receivers by a secondary key.
+///
+/// Sticky routing keeps all messages of an ID on one consumer. When that consumer wants to shard its work further,
+/// for example by a field of the message, `Demux` computes a key for every message and delivers it to the
+/// [`KeyReceiver`] of that key. The sub-receiver of a key is handed out by [`accept`](Demux::accept) together with the
+/// first message of the key, so the usual pattern is a loop that spawns a worker per sub-receiver:
+///
+/// ```rust
+/// use tokio_sticky_channel::{Demux, unbounded_sticky_channel};
+/// use std::num::NonZeroUsize;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let (sender, mut receivers) = unbounded_sticky_channel::<u64, (u64, String)>(NonZeroUsize::new(1).unwrap());
+/// let receiver = receivers.pop().unwrap();
+///
+/// sender.send(1, (7, "a".to_owned())).unwrap();
+/// sender.send(1, (8, "b".to_owned())).unwrap();
+/// drop(sender);
+///
+/// let mut demux = Demux::new(receiver, NonZeroUsize::new(16).unwrap(), |(account, _): &(u64, String)| *account);
+/// let mut workers = Vec::new();
+/// while let Some((account, mut messages)) = demux.accept().await {
+///     workers.push(tokio::spawn(async move {
+///         let mut received = 0;
+///         while let Some((_, message)) = messages.recv().await {
+///             received += message.len();
+///         }
+///         (account, received)
+///     }));
+/// }
+/// for worker in workers {
+///     let (_account, received) = worker.await.unwrap();
+///     assert_eq!(received, 1);
+/// }
+/// # }
+/// ```
+///
+/// Messages are only distributed while `accept` is being polled, so it has to be called again as soon as it returns.
+/// Once the underlying receiver is closed and drained, all sub-receivers end and `accept` returns `None`.
+///
+/// At most `max_queues` sub-queues are open at a time. When a message of a new key arrives while all of them are in
+/// use, the sub-queue chosen by the [`Eviction`] policy is closed: its sub-receiver still receives the messages it was
+/// given and then ends, and later messages of that key are handed out with a new sub-receiver. Messages of an evicted
+/// key may therefore be processed concurrently by two workers. Sub-queues whose receiver was dropped are closed
+/// before anything is evicted.
+///
+/// Sub-queues are unbounded, and messages moved to them no longer occupy capacity in a bounded channel, so a slow
+/// sub-receiver makes its queue grow instead of applying backpressure to the channel.
+pub struct Demux<R, K, F>
+where
+    R: StickyReceiver,
+{
+    inner: R,
+    key: F,
+    queues: HashMap<K, SubQueue<R::Item>>,
+    max_queues: usize,
+    eviction: Eviction,
+    clock: u64,
+    evicted: u64,
+}
+
+struct SubQueue<T> {
+    sender: MpscSender<T>,
+    opened: u64,
+    used: u64,
+}
+
+impl<R, K, F> Demux<R, K, F>
+where
+    R: StickyReceiver,
+    K: Eq + Hash + Clone,
+    F: Fn(&R::Item) -> K,
+{
+    /// Wraps a receiver, splitting its messages by `key` into at most `max_queues` sub-receivers.
+    ///
+    /// Sub-queues are evicted in [`Eviction::LeastRecentlyUsed`] order by default.
+    pub fn new(inner: R, max_queues: NonZeroUsize, key: F) -> Self {
+        Self {
+            inner,
+            key,
+            queues: HashMap::new(),
+            max_queues: max_queues.get(),
+            eviction: Eviction::default(),
+            clock: 0,
+            evicted: 0,
+        }
+    }
+
+    /// Sets which sub-queue is closed when a new key arrives while all sub-queues are in use.
+    pub fn with_eviction(mut self, eviction: Eviction) -> Self {
+        self.eviction = eviction;
+        self
+    }
+
+    /// Distributes messages to their sub-receivers until a message of a key without an open sub-queue arrives, and
+    /// returns the key with a new sub-receiver that holds the message.
+    ///
+    /// This method returns `None` once the underlying receiver is closed and all its messages have been distributed.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. Messages received before cancellation have already been distributed.
+    pub async fn accept(&mut self) -> Option<(K, KeyReceiver<R::Item>)> {
+        poll_fn(|cx| self.poll_accept(cx)).await
+    }
+
+    /// Polls to distribute messages until a sub-receiver for a new key is opened.
+    ///
+    /// See [`accept`](Demux::accept).
+    pub fn poll_accept(&mut self, cx: &mut Context<'_>) -> Poll<Option<(K, KeyReceiver<R::Item>)>> {
+        loop {
+            let Some(message) = ready!(self.inner.poll_recv(cx)) else {
+                self.queues.clear();
+                return Poll::Ready(None);
+            };
+            if let Some(opened) = self.dispatch(message) {
+                return Poll::Ready(Some(opened));
+            }
+        }
+    }
+
+    /// Distributes the messages that are available without waiting, returning a sub-receiver for a new key if one is
+    /// opened.
+    ///
+    /// Returns [`TryRecvError::Empty`] once all available messages have been distributed to open sub-queues.
+    pub fn try_accept(&mut self) -> Result<(K, KeyReceiver<R::Item>), TryRecvError> {
+        loop {
+            let message = match self.inner.try_recv() {
+                Ok(message) => message,
+                Err(error) => {
+                    if error == TryRecvError::Disconnected {
+                        self.queues.clear();
+                    }
+                    return Err(error);
+                }
+            };
+            if let Some(opened) = self.dispatch(message) {
+                return Ok(opened);
+            }
+        }
+    }
+
+    /// Closes the underlying receiver. Buffered messages are still distributed by [`accept`](Demux::accept).
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+
+    /// Returns the number of open sub-queues.
+    pub fn len(&self) -> usize {
+        self.queues.len()
+    }
+
+    /// Returns `true` if no sub-queue is open.
+    pub fn is_empty(&self) -> bool {
+        self.queues.is_empty()
+    }
+
+    /// Returns the number of sub-queues that were closed to make room for a new key.
+    pub fn evicted(&self) -> u64 {
+        self.evicted
+    }
+
+    /// Delivers a message to the sub-queue of its key, opening one if needed.
+    fn dispatch(&mut self, message: R::Item) -> Option<(K, KeyReceiver<R::Item>)> {
+        let key = (self.key)(&message);
+        self.clock += 1;
+
+        let message = match self.queues.get_mut(&key) {
+            Some(queue) => {
+                queue.used = self.clock;
+                match queue.sender.send(message) {
+                    Ok(()) => return None,
+                    Err(error) => {
+                        // The sub-receiver was dropped, so the key gets a new one.
+                        self.queues.remove(&key);
+                        error.0
+                    }
+                }
+            }
+            None => message,
+        };
+
+        if self.queues.len() >= self.max_queues {
+            self.evict();
+        }
+        let (sender, receiver) = key_channel();
+        // The receiver is still alive, so this cannot fail.
+        let _ = sender.send(message);
+        self.queues.insert(
+            key.clone(),
+            SubQueue {
+                sender,
+                opened: self.clock,
+                used: self.clock,
+            },
+        );
+        Some((key, receiver))
+    }
+
+    /// Closes sub-queues whose receiver was dropped, or the one chosen by the eviction policy if there are none.
+    fn evict(&mut self) {
+        self.queues.retain(|_, queue| !queue.sender.is_closed());
+        if self.queues.len() < self.max_queues {
+            return;
+        }
+
+        let eviction = self.eviction;
+        let victim = self
+            .queues
+            .iter()
+            .min_by_key(|(_, queue)| match eviction {
+                Eviction::LeastRecentlyUsed => queue.used,
+                Eviction::Oldest => queue.opened,
+            })
+            .map(|(key, _)| key.clone());
+        if let Some(victim) = victim {
+            self.queues.remove(&victim);
+            self.evicted += 1;
+        }
+    }
+}
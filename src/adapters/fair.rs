@@ -0,0 +1,184 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    future::poll_fn,
+    hash::Hash,
+    task::{Context, Poll},
+};
+
+use crate::{StickyReceiver, StickyRoute, TryRecvError};
+
+type Key<R> = <<R as StickyReceiver>::Item as StickyRoute>::Key;
+
+type WeightFn<K> = Box<dyn Fn(&K) -> usize + Send + Sync>;
+
+/// Default number of messages a [`FairReceiver`] pulls ahead from its channel.
+const DEFAULT_LOOKAHEAD: usize = 128;
+
+/// Receiver adapter that interleaves messages of different keys instead of delivering them strictly in arrival order.
+///
+/// With a plain receiver, one chatty key can delay every other key that shares its consumer. `FairReceiver` pulls up
+/// to a fixed number of messages ahead from the underlying receiver, queues them per
+/// [`route_key`](StickyRoute::route_key) and serves the keys round-robin. Messages of the same key are always delivered
+/// in the order they were received.
+///
+/// By default every key gets one message per turn. [`with_weights`](FairReceiver::with_weights) lets some keys take
+/// several consecutive messages per turn.
+///
+/// Messages pulled ahead no longer occupy capacity in a bounded channel, so a fair receiver buffers up to
+/// [`lookahead`](FairReceiver::with_lookahead) messages on top of the channel capacity.
+pub struct FairReceiver<R>
+where
+    R: StickyReceiver,
+    R::Item: StickyRoute,
+{
+    inner: R,
+    queues: HashMap<Key<R>, VecDeque<R::Item>>,
+    ready: VecDeque<Key<R>>,
+    credit: usize,
+    buffered: usize,
+    lookahead: usize,
+    weight: Option<WeightFn<Key<R>>>,
+}
+
+impl<R> FairReceiver<R>
+where
+    R: StickyReceiver,
+    R::Item: StickyRoute,
+    Key<R>: Eq + Hash + Clone,
+{
+    /// Wraps a receiver, serving its keys round-robin with equal weights.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            queues: HashMap::new(),
+            ready: VecDeque::new(),
+            credit: 0,
+            buffered: 0,
+            lookahead: DEFAULT_LOOKAHEAD,
+            weight: None,
+        }
+    }
+
+    /// Uses `weight` to decide how many consecutive messages a key may receive per turn.
+    ///
+    /// A weight of `0` is treated as `1`.
+    pub fn with_weights<F>(mut self, weight: F) -> Self
+    where
+        F: Fn(&Key<R>) -> usize + Send + Sync + 'static,
+    {
+        self.weight = Some(Box::new(weight));
+        self
+    }
+
+    /// Sets the maximum number of messages pulled ahead from the underlying receiver. Defaults to `128`.
+    ///
+    /// Only messages that have been pulled ahead take part in scheduling, so a larger lookahead gives fairer results at
+    /// the cost of more buffering. A lookahead of `0` is treated as `1`.
+    pub fn with_lookahead(mut self, lookahead: usize) -> Self {
+        self.lookahead = lookahead.max(1);
+        self
+    }
+
+    /// Receives the next message, choosing among keys round-robin.
+    ///
+    /// This method returns `None` once the underlying receiver is closed and all buffered messages have been
+    /// delivered.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. Messages pulled ahead are kept in the adapter.
+    pub async fn recv(&mut self) -> Option<R::Item> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Polls to receive the next message, choosing among keys round-robin.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<R::Item>> {
+        self.fill();
+
+        match self.pop() {
+            Some(message) => Poll::Ready(Some(message)),
+            None => self.inner.poll_recv(cx),
+        }
+    }
+
+    /// Tries to receive the next message without waiting, choosing among keys round-robin.
+    pub fn try_recv(&mut self) -> Result<R::Item, TryRecvError> {
+        self.fill();
+
+        match self.pop() {
+            Some(message) => Ok(message),
+            None => self.inner.try_recv(),
+        }
+    }
+
+    /// Closes the underlying receiver. Buffered messages can still be received.
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+
+    /// Pulls available messages from the underlying receiver until the lookahead is reached.
+    fn fill(&mut self) {
+        while self.buffered < self.lookahead {
+            match self.inner.try_recv() {
+                Ok(message) => self.push(message),
+                Err(_) => break,
+            }
+        }
+    }
+
+    fn push(&mut self, message: R::Item) {
+        let key = message.route_key();
+        match self.queues.get_mut(&key) {
+            Some(queue) => queue.push_back(message),
+            None => {
+                self.ready.push_back(key.clone());
+                self.queues.insert(key, VecDeque::from([message]));
+            }
+        }
+        self.buffered += 1;
+    }
+
+    fn pop(&mut self) -> Option<R::Item> {
+        let key = self.ready.front()?;
+
+        if self.credit == 0 {
+            self.credit = self.weight.as_ref().map_or(1, |weight| weight(key).max(1));
+        }
+        self.credit -= 1;
+
+        let queue = self.queues.get_mut(key)?;
+        let message = queue.pop_front();
+        self.buffered -= 1;
+
+        if queue.is_empty() {
+            let key = self.ready.pop_front()?;
+            self.queues.remove(&key);
+            self.credit = 0;
+        } else if self.credit == 0 {
+            self.ready.rotate_left(1);
+        }
+
+        message
+    }
+}
+
+impl<R> StickyReceiver for FairReceiver<R>
+where
+    R: StickyReceiver,
+    R::Item: StickyRoute,
+    Key<R>: Eq + Hash + Clone,
+{
+    type Item = R::Item;
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<R::Item>> {
+        FairReceiver::poll_recv(self, cx)
+    }
+
+    fn try_recv(&mut self) -> Result<R::Item, TryRecvError> {
+        FairReceiver::try_recv(self)
+    }
+
+    fn close(&mut self) {
+        FairReceiver::close(self)
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    future::poll_fn,
+    task::{Context, Poll},
+};
+
+use crate::{StickyReceiver, TryRecvError};
+
+/// Receiver adapter that skips the messages for which a predicate returns `false`.
+///
+/// Created by [`Receiver::filter`](crate::Receiver::filter),
+/// [`UnboundedReceiver::filter`](crate::UnboundedReceiver::filter) or [`FilterReceiver::new`] for any other receiver.
+/// Skipped messages are dropped as they are received, so they free their capacity like received messages do, and are
+/// counted in [`skipped`](FilterReceiver::skipped).
+pub struct FilterReceiver<R, F> {
+    inner: R,
+    predicate: F,
+    skipped: u64,
+}
+
+impl<R, F> FilterReceiver<R, F>
+where
+    R: StickyReceiver,
+    F: FnMut(&R::Item) -> bool,
+{
+    /// Wraps a receiver, only yielding the messages for which `predicate` returns `true`.
+    pub fn new(inner: R, predicate: F) -> Self {
+        Self {
+            inner,
+            predicate,
+            skipped: 0,
+        }
+    }
+
+    /// Receives the next message that passes the predicate.
+    ///
+    /// This method returns `None` once the underlying receiver is closed and has no messages left.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe.
+    pub async fn recv(&mut self) -> Option<R::Item> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Receives up to `limit` messages that pass the predicate, appending them to `buffer`.
+    ///
+    /// Waits for the first message that passes and then takes the ones available without waiting. Like
+    /// [`Receiver::recv_many`](crate::Receiver::recv_many), this method only returns `0` for a non-zero `limit` once
+    /// the underlying receiver is closed and has no messages left.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe.
+    pub async fn recv_many(&mut self, buffer: &mut Vec<R::Item>, limit: usize) -> usize {
+        if limit == 0 {
+            return 0;
+        }
+        let Some(message) = self.recv().await else {
+            return 0;
+        };
+        buffer.push(message);
+
+        let mut count = 1;
+        while count < limit {
+            match self.try_recv() {
+                Ok(message) => {
+                    buffer.push(message);
+                    count += 1;
+                }
+                Err(_) => break,
+            }
+        }
+        count
+    }
+
+    /// Polls to receive the next message that passes the predicate.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<R::Item>> {
+        loop {
+            match self.inner.poll_recv(cx) {
+                Poll::Ready(Some(message)) => {
+                    if let Some(message) = self.check(message) {
+                        return Poll::Ready(Some(message));
+                    }
+                }
+                poll => return poll,
+            }
+        }
+    }
+
+    /// Tries to receive the next message that passes the predicate without waiting.
+    pub fn try_recv(&mut self) -> Result<R::Item, TryRecvError> {
+        loop {
+            let message = self.inner.try_recv()?;
+            if let Some(message) = self.check(message) {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Closes the underlying receiver. Buffered messages can still be received.
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+
+    /// Returns the number of messages skipped so far.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Returns a reference to the underlying receiver.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the underlying receiver.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn check(&mut self, message: R::Item) -> Option<R::Item> {
+        if (self.predicate)(&message) {
+            Some(message)
+        } else {
+            self.skipped += 1;
+            None
+        }
+    }
+}
+
+impl<R, F> StickyReceiver for FilterReceiver<R, F>
+where
+    R: StickyReceiver,
+    F: FnMut(&R::Item) -> bool,
+{
+    type Item = R::Item;
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<R::Item>> {
+        FilterReceiver::poll_recv(self, cx)
+    }
+
+    fn try_recv(&mut self) -> Result<R::Item, TryRecvError> {
+        FilterReceiver::try_recv(self)
+    }
+
+    fn close(&mut self) {
+        FilterReceiver::close(self)
+    }
+}
@@ -0,0 +1,24 @@
+mod demux;
+mod fair;
+mod filter;
+#[cfg(feature = "sled")]
+mod persist;
+mod queued;
+mod redeliver;
+mod reorder;
+mod sequence;
+mod shared;
+
+#[cfg(feature = "sled")]
+pub use self::persist::SledQueue;
+
+pub use self::{
+    demux::{Demux, Eviction},
+    fair::FairReceiver,
+    filter::FilterReceiver,
+    queued::{ConsumerQueue, PriorityQueue, QueuedReceiver},
+    redeliver::{Delivery, RedeliveryReceiver},
+    reorder::ReorderReceiver,
+    sequence::SequencedReceiver,
+    shared::SharedReceiver,
+};
@@ -0,0 +1,108 @@
+use std::marker::PhantomData;
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use super::ConsumerQueue;
+use crate::WireCodec;
+
+/// [`ConsumerQueue`] that keeps the messages of a consumer in a [`sled`] tree, so they survive restarts and deep
+/// backlogs do not live in memory.
+///
+/// Messages are encoded with a [`WireCodec`] and stored under big-endian sequence numbers, so they are handed out in
+/// the order they were pushed. Opening a tree that still holds messages, for example after a restart, hands those out
+/// first. Each consumer needs a tree of its own, which [`sled::Db::open_tree`] provides:
+///
+/// ```rust,ignore
+/// let db = sled::open("partitions")?;
+/// let mut index = 0;
+/// let (sender, receivers) = StickyChannelBuilder::new(consumers, 1024).build_queued(|| {
+///     index += 1;
+///     SledQueue::open(db.open_tree(format!("consumer-{index}")).unwrap(), Bincode).unwrap()
+/// });
+/// ```
+///
+/// Only messages moved into the queue are persisted: messages still buffered in the channel are lost when the process
+/// stops. A [`QueuedReceiver`](crate::QueuedReceiver) moves messages into the queue while it is polled, up to its
+/// capacity, so the channel's capacity decides how much of a backlog is moved to the store. A message is removed from
+/// the tree as soon as it is handed out.
+///
+/// # Panics
+///
+/// [`push`](ConsumerQueue::push) and [`pop`](ConsumerQueue::pop) panic if the store fails or a stored message cannot
+/// be encoded or decoded.
+pub struct SledQueue<T, C> {
+    tree: sled::Tree,
+    codec: C,
+    next: u64,
+    len: usize,
+    _message: PhantomData<fn() -> T>,
+}
+
+impl<T, C> SledQueue<T, C>
+where
+    C: WireCodec,
+{
+    /// Opens a queue on `tree`, handing out the messages it already holds first.
+    pub fn open(tree: sled::Tree, codec: C) -> sled::Result<Self> {
+        let next = match tree.last()? {
+            Some((key, _)) => sequence(&key) + 1,
+            None => 0,
+        };
+        let len = tree.len();
+        Ok(Self {
+            tree,
+            codec,
+            next,
+            len,
+            _message: PhantomData,
+        })
+    }
+
+    /// Returns the tree the messages are stored in.
+    pub fn tree(&self) -> &sled::Tree {
+        &self.tree
+    }
+}
+
+impl<T, C> ConsumerQueue<T> for SledQueue<T, C>
+where
+    T: Serialize + DeserializeOwned,
+    C: WireCodec,
+{
+    fn push(&mut self, message: T) {
+        let bytes = self
+            .codec
+            .encode(&message)
+            .expect("failed to encode queued message");
+        self.tree
+            .insert(self.next.to_be_bytes(), bytes)
+            .expect("failed to store queued message");
+        self.next += 1;
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        let (_, bytes) = self
+            .tree
+            .pop_min()
+            .expect("failed to remove queued message")?;
+        self.len -= 1;
+        Some(
+            self.codec
+                .decode(&bytes)
+                .expect("failed to decode queued message"),
+        )
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn close(&mut self) {
+        self.tree.flush().expect("failed to flush queued messages");
+    }
+}
+
+fn sequence(key: &[u8]) -> u64 {
+    u64::from_be_bytes(key.try_into().expect("queue keys are sequence numbers"))
+}
@@ -0,0 +1,280 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, VecDeque},
+    future::poll_fn,
+    num::NonZeroUsize,
+    task::{Context, Poll},
+};
+
+use crate::{StickyReceiver, TryRecvError};
+
+/// Buffer that decides in which order a [`QueuedReceiver`] hands out the messages of its consumer.
+///
+/// Implementations are provided for [`VecDeque`] (first in, first out), [`Vec`] (last in, first out) and
+/// [`BinaryHeap`] (largest first, or smallest first with [`Reverse`]). [`PriorityQueue`] hands out the largest message
+/// first like a [`BinaryHeap`], but keeps equal messages in the order they arrived.
+pub trait ConsumerQueue<T> {
+    /// Adds a message to the queue.
+    fn push(&mut self, message: T);
+
+    /// Removes the next message to hand out, or returns `None` if the queue is empty.
+    fn pop(&mut self) -> Option<T>;
+
+    /// Returns the number of messages in the queue.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if the queue holds no messages.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Called once no more messages will be pushed. Messages still in the queue are handed out afterwards.
+    fn close(&mut self) {}
+}
+
+impl<T> ConsumerQueue<T> for VecDeque<T> {
+    fn push(&mut self, message: T) {
+        self.push_back(message);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+}
+
+impl<T> ConsumerQueue<T> for Vec<T> {
+    fn push(&mut self, message: T) {
+        Vec::push(self, message);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        Vec::pop(self)
+    }
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+}
+
+impl<T: Ord> ConsumerQueue<T> for BinaryHeap<T> {
+    fn push(&mut self, message: T) {
+        BinaryHeap::push(self, message);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        BinaryHeap::pop(self)
+    }
+
+    fn len(&self) -> usize {
+        BinaryHeap::len(self)
+    }
+}
+
+impl<T: Ord> ConsumerQueue<T> for BinaryHeap<Reverse<T>> {
+    fn push(&mut self, message: T) {
+        BinaryHeap::push(self, Reverse(message));
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        BinaryHeap::pop(self).map(|Reverse(message)| message)
+    }
+
+    fn len(&self) -> usize {
+        BinaryHeap::len(self)
+    }
+}
+
+/// Queue that hands out the largest message first and equal messages in the order they were pushed.
+///
+/// Used by the receivers of a [`sticky_priority_channel`](crate::sticky_priority_channel).
+pub struct PriorityQueue<T> {
+    heap: BinaryHeap<Prioritized<T>>,
+    next_seq: u64,
+}
+
+impl<T: Ord> PriorityQueue<T> {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+            next_seq: 0,
+        }
+    }
+}
+
+impl<T: Ord> Default for PriorityQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> ConsumerQueue<T> for PriorityQueue<T> {
+    fn push(&mut self, message: T) {
+        self.heap.push(Prioritized {
+            message,
+            seq: self.next_seq,
+        });
+        self.next_seq += 1;
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.heap.pop().map(|prioritized| prioritized.message)
+    }
+
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+}
+
+/// A queued message, ordered by the message and then by arrival, earliest first.
+struct Prioritized<T> {
+    message: T,
+    seq: u64,
+}
+
+impl<T: Ord> PartialEq for Prioritized<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Ord> Eq for Prioritized<T> {}
+
+impl<T: Ord> PartialOrd for Prioritized<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> Ord for Prioritized<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.message
+            .cmp(&other.message)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+/// Receiver adapter that hands out the messages of its consumer in the order decided by a [`ConsumerQueue`].
+///
+/// Whenever a message is received, all messages available in the underlying receiver are moved into the queue first,
+/// up to the adapter's capacity, and the queue picks the message to hand out. The queue can therefore only reorder
+/// messages that have arrived by the time a message is received. Routing is unaffected: every message is still
+/// delivered to the consumer its ID is routed to.
+///
+/// Channels whose receivers are wrapped in this adapter can be built with
+/// [`StickyChannelBuilder::build_queued`](crate::StickyChannelBuilder::build_queued) and
+/// [`UnboundedStickyChannelBuilder::build_queued`](crate::UnboundedStickyChannelBuilder::build_queued).
+pub struct QueuedReceiver<R, Q> {
+    inner: R,
+    queue: Q,
+    capacity: usize,
+    closed: bool,
+}
+
+impl<R, Q> QueuedReceiver<R, Q>
+where
+    R: StickyReceiver,
+    Q: ConsumerQueue<R::Item>,
+{
+    /// Wraps a receiver, handing out its messages in the order decided by `queue`.
+    ///
+    /// At most `capacity` messages are moved into the queue at a time. Messages beyond that wait in the underlying
+    /// receiver, so a bounded channel keeps applying backpressure.
+    pub fn new(inner: R, queue: Q, capacity: NonZeroUsize) -> Self {
+        Self {
+            inner,
+            queue,
+            capacity: capacity.get(),
+            closed: false,
+        }
+    }
+
+    /// Receives the next message picked by the queue.
+    ///
+    /// This method returns `None` once the underlying receiver is closed and the queue is empty.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. Queued messages are kept in the adapter.
+    pub async fn recv(&mut self) -> Option<R::Item> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Polls to receive the next message picked by the queue.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<R::Item>> {
+        while !self.closed && self.queue.len() < self.capacity {
+            match self.inner.poll_recv(cx) {
+                Poll::Ready(Some(message)) => self.queue.push(message),
+                Poll::Ready(None) => self.close_queue(),
+                Poll::Pending => break,
+            }
+        }
+
+        match self.queue.pop() {
+            Some(message) => Poll::Ready(Some(message)),
+            None if self.closed => Poll::Ready(None),
+            None => Poll::Pending,
+        }
+    }
+
+    /// Tries to receive the next message picked by the queue without waiting.
+    pub fn try_recv(&mut self) -> Result<R::Item, TryRecvError> {
+        while !self.closed && self.queue.len() < self.capacity {
+            match self.inner.try_recv() {
+                Ok(message) => self.queue.push(message),
+                Err(TryRecvError::Disconnected) => self.close_queue(),
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        match self.queue.pop() {
+            Some(message) => Ok(message),
+            None if self.closed => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Closes the underlying receiver. Queued and buffered messages can still be received.
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+
+    /// Returns a reference to the queue.
+    pub fn queue(&self) -> &Q {
+        &self.queue
+    }
+
+    /// Returns a reference to the underlying receiver.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    fn close_queue(&mut self) {
+        self.closed = true;
+        self.queue.close();
+    }
+}
+
+impl<R, Q> StickyReceiver for QueuedReceiver<R, Q>
+where
+    R: StickyReceiver,
+    Q: ConsumerQueue<R::Item>,
+{
+    type Item = R::Item;
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<R::Item>> {
+        QueuedReceiver::poll_recv(self, cx)
+    }
+
+    fn try_recv(&mut self) -> Result<R::Item, TryRecvError> {
+        QueuedReceiver::try_recv(self)
+    }
+
+    fn close(&mut self) {
+        QueuedReceiver::close(self)
+    }
+}
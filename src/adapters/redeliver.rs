@@ -0,0 +1,232 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    future::poll_fn,
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use crate::{StickyReceiver, TryRecvError};
+
+/// Receiver adapter that hands out messages wrapped in a [`Delivery`] guard and redelivers them unless they are
+/// completed.
+///
+/// If a guard is dropped without calling [`complete`](Delivery::complete), for example because the task processing the
+/// message panicked or was cancelled, the message is put back at the front of the adapter's queue and is the next
+/// message received. Messages put back by several guards are redelivered in the order they were first received.
+///
+/// Messages are taken out of the underlying channel when they are first received, so a message awaiting redelivery no
+/// longer takes up capacity in a bounded channel. The adapter only returns `None` once the underlying receiver is
+/// closed and every outstanding guard has been completed or redelivered.
+pub struct RedeliveryReceiver<R>
+where
+    R: StickyReceiver,
+{
+    inner: R,
+    shared: Arc<Mutex<Shared<R::Item>>>,
+    next_seq: u64,
+}
+
+struct Shared<T> {
+    /// Messages put back by dropped guards with their last attempt, sorted by their sequence number.
+    requeued: VecDeque<(u64, u32, T)>,
+    /// Number of guards that have not been completed or dropped yet.
+    outstanding: usize,
+    waker: Option<Waker>,
+}
+
+impl<R> RedeliveryReceiver<R>
+where
+    R: StickyReceiver,
+{
+    /// Wraps a receiver, handing out its messages in [`Delivery`] guards.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            shared: Arc::new(Mutex::new(Shared {
+                requeued: VecDeque::new(),
+                outstanding: 0,
+                waker: None,
+            })),
+            next_seq: 0,
+        }
+    }
+
+    /// Receives the next message, preferring messages put back by dropped guards.
+    ///
+    /// This method returns `None` once the underlying receiver is closed, no message is awaiting redelivery and no
+    /// guard is outstanding.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe.
+    pub async fn recv(&mut self) -> Option<Delivery<R::Item>> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Polls to receive the next message, preferring messages put back by dropped guards.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<Delivery<R::Item>>> {
+        {
+            let mut shared = self.shared.lock().unwrap();
+            if let Some((seq, attempt, message)) = shared.requeued.pop_front() {
+                shared.outstanding += 1;
+                drop(shared);
+                return Poll::Ready(Some(self.deliver(seq, attempt + 1, message)));
+            }
+            shared.waker = Some(cx.waker().clone());
+        }
+
+        match self.inner.poll_recv(cx) {
+            Poll::Ready(Some(message)) => Poll::Ready(Some(self.deliver_new(message))),
+            Poll::Ready(None) => {
+                let shared = self.shared.lock().unwrap();
+                if shared.outstanding > 0 || !shared.requeued.is_empty() {
+                    // A guard may still put its message back; its drop wakes this task.
+                    Poll::Pending
+                } else {
+                    Poll::Ready(None)
+                }
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    /// Tries to receive the next message without waiting, preferring messages put back by dropped guards.
+    ///
+    /// [`TryRecvError::Disconnected`] is only returned once no guard is outstanding.
+    pub fn try_recv(&mut self) -> Result<Delivery<R::Item>, TryRecvError> {
+        {
+            let mut shared = self.shared.lock().unwrap();
+            if let Some((seq, attempt, message)) = shared.requeued.pop_front() {
+                shared.outstanding += 1;
+                drop(shared);
+                return Ok(self.deliver(seq, attempt + 1, message));
+            }
+        }
+
+        match self.inner.try_recv() {
+            Ok(message) => Ok(self.deliver_new(message)),
+            Err(TryRecvError::Disconnected) => {
+                let shared = self.shared.lock().unwrap();
+                if shared.outstanding > 0 || !shared.requeued.is_empty() {
+                    Err(TryRecvError::Empty)
+                } else {
+                    Err(TryRecvError::Disconnected)
+                }
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Closes the underlying receiver. Buffered messages and messages put back later can still be received.
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+
+    fn deliver_new(&mut self, message: R::Item) -> Delivery<R::Item> {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.shared.lock().unwrap().outstanding += 1;
+        self.deliver(seq, 1, message)
+    }
+
+    fn deliver(&self, seq: u64, attempt: u32, message: R::Item) -> Delivery<R::Item> {
+        Delivery {
+            message: Some(message),
+            seq,
+            attempt,
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<R> StickyReceiver for RedeliveryReceiver<R>
+where
+    R: StickyReceiver,
+{
+    type Item = Delivery<R::Item>;
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        RedeliveryReceiver::poll_recv(self, cx)
+    }
+
+    fn try_recv(&mut self) -> Result<Self::Item, TryRecvError> {
+        RedeliveryReceiver::try_recv(self)
+    }
+
+    fn close(&mut self) {
+        RedeliveryReceiver::close(self)
+    }
+}
+
+/// A message received from a [`RedeliveryReceiver`] that is redelivered unless it is completed.
+///
+/// The guard dereferences to the message. Call [`complete`](Delivery::complete) once the message has been processed.
+/// Dropping the guard instead puts the message back at the front of its receiver's queue.
+pub struct Delivery<T> {
+    message: Option<T>,
+    seq: u64,
+    attempt: u32,
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T> Delivery<T> {
+    /// Returns how many times the message has been delivered, including this delivery, starting at `1`.
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Marks the message as processed and returns it. The message is not redelivered.
+    pub fn complete(mut self) -> T {
+        self.message.take().expect("message is only taken once")
+    }
+}
+
+impl<T> Deref for Delivery<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.message.as_ref().expect("message is only taken once")
+    }
+}
+
+impl<T> DerefMut for Delivery<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.message.as_mut().expect("message is only taken once")
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Delivery<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Delivery")
+            .field("message", &self.message)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Drop for Delivery<T> {
+    fn drop(&mut self) {
+        let waker = {
+            // A poisoned lock means another guard panicked while putting its message back; the message is lost either
+            // way, so do not panic again while possibly unwinding.
+            let Ok(mut shared) = self.shared.lock() else {
+                return;
+            };
+            shared.outstanding -= 1;
+            if let Some(message) = self.message.take() {
+                let index = shared
+                    .requeued
+                    .partition_point(|(seq, _, _)| *seq < self.seq);
+                shared
+                    .requeued
+                    .insert(index, (self.seq, self.attempt, message));
+            }
+            shared.waker.take()
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
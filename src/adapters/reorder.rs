@@ -0,0 +1,218 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    future::poll_fn,
+    task::{Context, Poll},
+};
+
+use crate::{Event, StickyReceiver, TryRecvError};
+
+/// Default number of messages a [`ReorderReceiver`] holds back.
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Receiver adapter that restores event-time order for sources that deliver slightly out of order.
+///
+/// Messages are held back and released sorted by the timestamp returned by a user-supplied extractor. A held message is
+/// released once one of the following is true:
+///
+/// - a watermark at or past its timestamp has been received, see
+///   [`Sender::advance_watermark`](crate::Sender::advance_watermark),
+/// - its timestamp is at least [`lateness`](ReorderReceiver::with_lateness) behind the newest timestamp seen so far,
+/// - more than [`capacity`](ReorderReceiver::with_capacity) messages are held back.
+///
+/// Once the underlying receiver is closed, all held messages are released in order. Messages that arrive after a
+/// later message has already been released cannot be reordered and are released as soon as possible.
+pub struct ReorderReceiver<R, F>
+where
+    R: StickyReceiver,
+{
+    inner: R,
+    timestamp: F,
+    held: BinaryHeap<Reverse<Held<R::Item>>>,
+    next_seq: u64,
+    watermark: Option<u64>,
+    newest: Option<u64>,
+    lateness: Option<u64>,
+    capacity: usize,
+    closed: bool,
+}
+
+impl<R, F> ReorderReceiver<R, F>
+where
+    R: StickyReceiver,
+    F: Fn(&R::Item) -> u64,
+{
+    /// Wraps a receiver, ordering its messages by the timestamp returned by `timestamp`.
+    pub fn new(inner: R, timestamp: F) -> Self {
+        Self {
+            inner,
+            timestamp,
+            held: BinaryHeap::new(),
+            next_seq: 0,
+            watermark: None,
+            newest: None,
+            lateness: None,
+            capacity: DEFAULT_CAPACITY,
+            closed: false,
+        }
+    }
+
+    /// Releases messages whose timestamp is at least `lateness` behind the newest timestamp seen so far, without
+    /// waiting for a watermark.
+    pub fn with_lateness(mut self, lateness: u64) -> Self {
+        self.lateness = Some(lateness);
+        self
+    }
+
+    /// Sets the maximum number of messages held back. Defaults to `1024`.
+    ///
+    /// When the limit is exceeded, the earliest held message is released. A capacity of `0` disables reordering.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Receives the next message in event-time order.
+    ///
+    /// This method returns `None` once the underlying receiver is closed and all held messages have been released.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. Held messages are kept in the adapter.
+    pub async fn recv(&mut self) -> Option<R::Item> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Polls to receive the next message in event-time order.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<R::Item>> {
+        loop {
+            if let Some(message) = self.pop() {
+                return Poll::Ready(Some(message));
+            }
+
+            match self.inner.poll_recv_event(cx) {
+                Poll::Ready(Some(event)) => self.push(event),
+                Poll::Ready(None) => {
+                    self.closed = true;
+                    return Poll::Ready(self.pop());
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    /// Tries to receive the next message in event-time order without waiting.
+    ///
+    /// Returns [`Empty`](TryRecvError::Empty) while all available messages are held back.
+    pub fn try_recv(&mut self) -> Result<R::Item, TryRecvError> {
+        loop {
+            if let Some(message) = self.pop() {
+                return Ok(message);
+            }
+
+            match self.inner.try_recv_event() {
+                Ok(event) => self.push(event),
+                Err(TryRecvError::Disconnected) => {
+                    self.closed = true;
+                    return self.pop().ok_or(TryRecvError::Disconnected);
+                }
+                Err(TryRecvError::Empty) => return Err(TryRecvError::Empty),
+            }
+        }
+    }
+
+    /// Closes the underlying receiver. Held messages can still be received.
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+
+    fn push(&mut self, event: Event<R::Item>) {
+        match event {
+            Event::Data(message) => {
+                let timestamp = (self.timestamp)(&message);
+                self.newest = self.newest.max(Some(timestamp));
+                self.held.push(Reverse(Held {
+                    timestamp,
+                    seq: self.next_seq,
+                    message,
+                }));
+                self.next_seq += 1;
+            }
+            Event::Watermark(timestamp) => self.watermark = self.watermark.max(Some(timestamp)),
+            Event::Control(never) => match never {},
+            // Nothing sent before the sentinel can be overtaken any more.
+            Event::Finished => self.watermark = Some(u64::MAX),
+            Event::Barrier(_) => {}
+        }
+    }
+
+    /// Releases the earliest held message if it is due.
+    fn pop(&mut self) -> Option<R::Item> {
+        let Reverse(earliest) = self.held.peek()?;
+
+        let due = self.closed
+            || self.held.len() > self.capacity
+            || self
+                .watermark
+                .is_some_and(|watermark| earliest.timestamp <= watermark)
+            || self
+                .lateness
+                .zip(self.newest)
+                .is_some_and(|(lateness, newest)| {
+                    earliest.timestamp <= newest.saturating_sub(lateness)
+                });
+
+        if due {
+            self.held.pop().map(|Reverse(held)| held.message)
+        } else {
+            None
+        }
+    }
+}
+
+impl<R, F> StickyReceiver for ReorderReceiver<R, F>
+where
+    R: StickyReceiver,
+    F: Fn(&R::Item) -> u64,
+{
+    type Item = R::Item;
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<R::Item>> {
+        ReorderReceiver::poll_recv(self, cx)
+    }
+
+    fn try_recv(&mut self) -> Result<R::Item, TryRecvError> {
+        ReorderReceiver::try_recv(self)
+    }
+
+    fn close(&mut self) {
+        ReorderReceiver::close(self)
+    }
+}
+
+/// A held message, ordered by timestamp and then by arrival.
+struct Held<T> {
+    timestamp: u64,
+    seq: u64,
+    message: T,
+}
+
+impl<T> PartialEq for Held<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Held<T> {}
+
+impl<T> PartialOrd for Held<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Held<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.timestamp, self.seq).cmp(&(other.timestamp, other.seq))
+    }
+}
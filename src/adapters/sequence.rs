@@ -0,0 +1,199 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    future::poll_fn,
+    task::{Context, Poll},
+};
+
+use crate::{Sequenced, StickyReceiver, TryRecvError};
+
+/// Default number of messages a [`SequencedReceiver`] holds back.
+const DEFAULT_WINDOW: usize = 64;
+
+/// Receiver adapter that restores the per-ID order in which messages were sent through a
+/// [`SequencedSender`](crate::SequencedSender).
+///
+/// A message that arrives before an earlier message of its ID is held back until the earlier message arrives. If more
+/// than [`window`](SequencedReceiver::with_window) messages are held back, the missing message of the ID whose held
+/// message arrived first is given up on and the held messages of that ID are released. A message that arrives after it
+/// was given up on is released as soon as possible. Messages without a sequence number are never held back.
+///
+/// Once the underlying receiver is closed, all held messages are released, ordered per ID. The position of every ID
+/// seen so far is kept.
+pub struct SequencedReceiver<R, T>
+where
+    R: StickyReceiver<Item = Sequenced<T>>,
+{
+    inner: R,
+    next_seqs: HashMap<u64, u64>,
+    /// Messages that arrived too early, by ID and sequence number, with their arrival order.
+    held: BTreeMap<(u64, u64), (u64, T)>,
+    ready: VecDeque<T>,
+    arrivals: u64,
+    window: usize,
+    closed: bool,
+}
+
+impl<R, T> SequencedReceiver<R, T>
+where
+    R: StickyReceiver<Item = Sequenced<T>>,
+{
+    /// Wraps a receiver of [`Sequenced`] messages.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            next_seqs: HashMap::new(),
+            held: BTreeMap::new(),
+            ready: VecDeque::new(),
+            arrivals: 0,
+            window: DEFAULT_WINDOW,
+            closed: false,
+        }
+    }
+
+    /// Sets the maximum number of messages held back. Defaults to `64`.
+    ///
+    /// A window of `0` disables reordering.
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Receives the next message in the order it was sent.
+    ///
+    /// This method returns `None` once the underlying receiver is closed and all held messages have been released.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. Held messages are kept in the adapter.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Polls to receive the next message in the order it was sent.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        loop {
+            if let Some(message) = self.pop() {
+                return Poll::Ready(Some(message));
+            }
+
+            match self.inner.poll_recv(cx) {
+                Poll::Ready(Some(message)) => self.push(message),
+                Poll::Ready(None) => {
+                    self.closed = true;
+                    return Poll::Ready(self.pop());
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    /// Tries to receive the next message in the order it was sent without waiting.
+    ///
+    /// Returns [`Empty`](TryRecvError::Empty) while all available messages are held back.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        loop {
+            if let Some(message) = self.pop() {
+                return Ok(message);
+            }
+
+            match self.inner.try_recv() {
+                Ok(message) => self.push(message),
+                Err(TryRecvError::Disconnected) => {
+                    self.closed = true;
+                    return self.pop().ok_or(TryRecvError::Disconnected);
+                }
+                Err(TryRecvError::Empty) => return Err(TryRecvError::Empty),
+            }
+        }
+    }
+
+    /// Closes the underlying receiver. Held messages can still be received.
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+
+    /// Returns the number of messages held back because an earlier message of their ID is missing.
+    pub fn held(&self) -> usize {
+        self.held.len()
+    }
+
+    fn push(&mut self, message: Sequenced<T>) {
+        let Some(seq) = message.seq else {
+            self.ready.push_back(message.message);
+            return;
+        };
+
+        let next_seq = self.next_seqs.entry(message.key).or_default();
+        if seq < *next_seq {
+            // Given up on earlier, so it can only be released late.
+            self.ready.push_back(message.message);
+            return;
+        }
+
+        self.held
+            .insert((message.key, seq), (self.arrivals, message.message));
+        self.arrivals += 1;
+        self.release(message.key);
+
+        while self.held.len() > self.window {
+            self.skip_gap();
+        }
+    }
+
+    /// Releases the held messages of `key` that are next in line.
+    fn release(&mut self, key: u64) {
+        let next_seq = self.next_seqs.entry(key).or_default();
+        while let Some((_, message)) = self.held.remove(&(key, *next_seq)) {
+            self.ready.push_back(message);
+            *next_seq += 1;
+        }
+    }
+
+    /// Gives up on the missing message of the ID whose held message arrived first.
+    fn skip_gap(&mut self) {
+        let Some(key) = self
+            .held
+            .iter()
+            .min_by_key(|(_, (arrival, _))| *arrival)
+            .map(|(&(key, _), _)| key)
+        else {
+            return;
+        };
+
+        if let Some((&(_, seq), _)) = self.held.range((key, 0)..=(key, u64::MAX)).next() {
+            self.next_seqs.insert(key, seq);
+            self.release(key);
+        }
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        if let Some(message) = self.ready.pop_front() {
+            return Some(message);
+        }
+
+        if self.closed {
+            return self.held.pop_first().map(|(_, (_, message))| message);
+        }
+
+        None
+    }
+}
+
+impl<R, T> StickyReceiver for SequencedReceiver<R, T>
+where
+    R: StickyReceiver<Item = Sequenced<T>>,
+{
+    type Item = T;
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        SequencedReceiver::poll_recv(self, cx)
+    }
+
+    fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        SequencedReceiver::try_recv(self)
+    }
+
+    fn close(&mut self) {
+        SequencedReceiver::close(self)
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    future::poll_fn,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Wake, Waker},
+};
+
+use crate::{StickyReceiver, TryRecvError};
+
+/// Cloneable receiver adapter that lets several workers compete for the messages of a single consumer.
+///
+/// Every message is received by exactly one of the clones. This scales the throughput of a partition with the number
+/// of workers, at the cost of per-ID ordering: messages of the same ID may be processed concurrently and finish out of
+/// order. Use it for partitions that do not need strict ordering and keep the plain receiver for the others.
+///
+/// Waiting clones are all woken up when a message arrives and race for it, so workers should be few enough that this
+/// is cheap compared to processing a message.
+pub struct SharedReceiver<R> {
+    shared: Arc<Mutex<Shared<R>>>,
+}
+
+struct Shared<R> {
+    inner: R,
+    waiters: Arc<Waiters>,
+    /// Wakes all waiters, registered with the underlying receiver in place of the waker of a single clone.
+    waker: Waker,
+}
+
+/// Clones waiting for a message.
+#[derive(Default)]
+struct Waiters(Mutex<Vec<Waker>>);
+
+impl Waiters {
+    fn register(&self, waker: &Waker) {
+        let mut waiters = self.0.lock().unwrap();
+        if !waiters.iter().any(|waiter| waiter.will_wake(waker)) {
+            waiters.push(waker.clone());
+        }
+    }
+
+    /// Wakes every waiting clone except the one with the waker `except`.
+    fn wake_others(&self, except: Option<&Waker>) {
+        let waiters = std::mem::take(&mut *self.0.lock().unwrap());
+        for waiter in waiters {
+            if !except.is_some_and(|except| waiter.will_wake(except)) {
+                waiter.wake();
+            }
+        }
+    }
+}
+
+impl Wake for Waiters {
+    fn wake(self: Arc<Self>) {
+        self.wake_others(None);
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wake_others(None);
+    }
+}
+
+impl<R> SharedReceiver<R>
+where
+    R: StickyReceiver,
+{
+    /// Wraps a receiver so that it can be cloned and shared between workers.
+    pub fn new(inner: R) -> Self {
+        let waiters = Arc::new(Waiters::default());
+        Self {
+            shared: Arc::new(Mutex::new(Shared {
+                inner,
+                waker: Waker::from(waiters.clone()),
+                waiters,
+            })),
+        }
+    }
+
+    /// Receives the next message not taken by another clone.
+    ///
+    /// This method returns `None` once the underlying receiver is closed and has no messages left.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe.
+    pub async fn recv(&self) -> Option<R::Item> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Polls to receive the next message not taken by another clone.
+    pub fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Option<R::Item>> {
+        let mut shared = self.shared.lock().unwrap();
+        shared.waiters.register(cx.waker());
+
+        let waker = shared.waker.clone();
+        let poll = shared.inner.poll_recv(&mut Context::from_waker(&waker));
+        if poll.is_ready() {
+            // Let the other clones check for further messages, or for the end of the channel.
+            shared.waiters.wake_others(Some(cx.waker()));
+        }
+        poll
+    }
+
+    /// Tries to receive the next message not taken by another clone without waiting.
+    pub fn try_recv(&self) -> Result<R::Item, TryRecvError> {
+        self.shared.lock().unwrap().inner.try_recv()
+    }
+
+    /// Closes the underlying receiver for all clones. Messages already queued can still be received.
+    pub fn close(&self) {
+        self.shared.lock().unwrap().inner.close();
+    }
+}
+
+impl<R> Clone for SharedReceiver<R> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<R> StickyReceiver for SharedReceiver<R>
+where
+    R: StickyReceiver,
+{
+    type Item = R::Item;
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<R::Item>> {
+        SharedReceiver::poll_recv(self, cx)
+    }
+
+    fn try_recv(&mut self) -> Result<R::Item, TryRecvError> {
+        SharedReceiver::try_recv(self)
+    }
+
+    fn close(&mut self) {
+        SharedReceiver::close(self)
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    pin::pin,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::Notify;
+
+use crate::error::BarrierError;
+
+/// Identifies a barrier sent with [`Sender::send_barrier`](crate::Sender::send_barrier).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BarrierId(pub u64);
+
+#[derive(Debug)]
+struct State {
+    remaining: usize,
+    abandoned: bool,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    changed: Notify,
+}
+
+/// Handle to a barrier marker sent to every consumer of a channel.
+///
+/// The marker is queued behind all messages sent before it, so a consumer that receives it has received every one of
+/// those messages. The barrier completes once every consumer has received its marker.
+#[derive(Debug, Clone)]
+pub struct Barrier {
+    id: BarrierId,
+    shared: Arc<Shared>,
+}
+
+impl Barrier {
+    pub(crate) fn new(id: BarrierId, consumers: usize) -> Self {
+        Self {
+            id,
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    remaining: consumers,
+                    abandoned: false,
+                }),
+                changed: Notify::new(),
+            }),
+        }
+    }
+
+    /// Creates the marker for one consumer.
+    pub(crate) fn marker(&self) -> BarrierMarker {
+        BarrierMarker {
+            id: self.id,
+            shared: Some(self.shared.clone()),
+        }
+    }
+
+    /// Returns the ID the barrier was sent with.
+    pub fn id(&self) -> BarrierId {
+        self.id
+    }
+
+    /// Returns `true` if every consumer has received the marker.
+    pub fn is_complete(&self) -> bool {
+        self.shared.state.lock().unwrap().remaining == 0
+    }
+
+    /// Waits until every consumer has received the marker.
+    ///
+    /// Fails if a consumer was closed or dropped before receiving its marker, in which case the barrier can never
+    /// complete.
+    pub async fn wait(&self) -> Result<(), BarrierError> {
+        loop {
+            let mut changed = pin!(self.shared.changed.notified());
+            changed.as_mut().enable();
+
+            {
+                let state = self.shared.state.lock().unwrap();
+                if state.remaining == 0 {
+                    return Ok(());
+                }
+                if state.abandoned {
+                    return Err(BarrierError(self.id));
+                }
+            }
+
+            changed.await;
+        }
+    }
+}
+
+/// A barrier marker queued for a single consumer.
+///
+/// Dropping a marker without [`arrive`](BarrierMarker::arrive) means its consumer will never receive it.
+#[derive(Debug)]
+pub(crate) struct BarrierMarker {
+    id: BarrierId,
+    shared: Option<Arc<Shared>>,
+}
+
+impl BarrierMarker {
+    /// Records that the consumer received the marker.
+    pub(crate) fn arrive(mut self) -> BarrierId {
+        if let Some(shared) = self.shared.take() {
+            let mut state = shared.state.lock().unwrap();
+            state.remaining -= 1;
+            if state.remaining == 0 {
+                shared.changed.notify_waiters();
+            }
+        }
+        self.id
+    }
+}
+
+impl Drop for BarrierMarker {
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.take() {
+            shared.state.lock().unwrap().abandoned = true;
+            shared.changed.notify_waiters();
+        }
+    }
+}
@@ -0,0 +1,597 @@
+use std::{
+    collections::VecDeque,
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+    num::NonZeroUsize,
+    panic::RefUnwindSafe,
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    ChannelAdmin, ConsumerQueue, ControlSender, DefaultBuildHasher, EventReceiver, QueuedReceiver,
+    SkewReport,
+    burst::Burst,
+    compact::{EvictionPolicy, LastValues},
+    control_channel,
+    health::{ConsumerHealth, Health},
+    hooks::Hooks,
+    offset::Offsets,
+    partition::PartitionTable,
+    queue::{Block, Coalescer},
+    sketch::FrequencySketch,
+    skew::SkewAlarm,
+    subscribe::Subscriptions,
+    tick::{TickStarter, ticker},
+    totals::{Counters, Tally},
+    util::next_producer,
+    validate::Validator,
+};
+
+use super::{Receiver, Sender, consumer::Consumer};
+
+/// Builder for bounded sticky channels.
+///
+/// [`sticky_channel`](crate::sticky_channel) and [`sticky_channel_with_hasher`](crate::sticky_channel_with_hasher)
+/// cover the common cases. The builder is needed to configure the less common options of a bounded channel.
+///
+/// ```rust
+/// use tokio_sticky_channel::StickyChannelBuilder;
+/// use std::num::NonZeroUsize;
+///
+/// let (sender, receivers) = StickyChannelBuilder::<&str, i32>::new(NonZeroUsize::new(3).unwrap(), 100)
+///     .reserved(4)
+///     .build();
+/// ```
+pub struct StickyChannelBuilder<ID, T, S = DefaultBuildHasher> {
+    num_consumers: NonZeroUsize,
+    capacity: usize,
+    reserved: usize,
+    burst: Option<(usize, Duration)>,
+    max_pending_per_key: Option<NonZeroUsize>,
+    block_size: Option<NonZeroUsize>,
+    wakeups: Option<Coalescer<T>>,
+    track_lag: bool,
+    watch_health: bool,
+    labels: Vec<Arc<str>>,
+    partitions: Option<NonZeroUsize>,
+    tick: Option<TickStarter<T>>,
+    skew: Option<SkewAlarm>,
+    frequencies: Option<(NonZeroUsize, NonZeroUsize)>,
+    last_values: Option<fn(&T) -> T>,
+    last_value_ttl: Option<Duration>,
+    max_tracked_keys: Option<(usize, EvictionPolicy)>,
+    validator: Option<Validator<ID, T>>,
+    hooks: Hooks<T>,
+    build_hasher: S,
+    _phantom: PhantomData<(ID, T)>,
+}
+
+impl<ID, T> StickyChannelBuilder<ID, T> {
+    /// Creates a builder for a bounded sticky channel with the specified number of consumers, capacity and default
+    /// hasher ([`DefaultBuildHasher`]).
+    ///
+    /// # Panics
+    ///
+    /// [`build`](StickyChannelBuilder::build) panics if `capacity` is zero.
+    pub fn new(num_consumers: NonZeroUsize, capacity: usize) -> Self {
+        Self {
+            num_consumers,
+            capacity,
+            reserved: 0,
+            burst: None,
+            max_pending_per_key: None,
+            block_size: None,
+            wakeups: None,
+            track_lag: false,
+            watch_health: false,
+            labels: Vec::new(),
+            partitions: None,
+            tick: None,
+            skew: None,
+            frequencies: None,
+            last_values: None,
+            last_value_ttl: None,
+            max_tracked_keys: None,
+            validator: None,
+            hooks: Hooks::default(),
+            build_hasher: DefaultBuildHasher::default(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<ID, T, S> StickyChannelBuilder<ID, T, S> {
+    /// Uses the given [`BuildHasher`] to route messages.
+    pub fn hasher<H>(self, build_hasher: H) -> StickyChannelBuilder<ID, T, H> {
+        StickyChannelBuilder {
+            num_consumers: self.num_consumers,
+            capacity: self.capacity,
+            reserved: self.reserved,
+            burst: self.burst,
+            max_pending_per_key: self.max_pending_per_key,
+            block_size: self.block_size,
+            wakeups: self.wakeups,
+            track_lag: self.track_lag,
+            watch_health: self.watch_health,
+            labels: self.labels,
+            partitions: self.partitions,
+            tick: self.tick,
+            skew: self.skew,
+            frequencies: self.frequencies,
+            last_values: self.last_values,
+            last_value_ttl: self.last_value_ttl,
+            max_tracked_keys: self.max_tracked_keys,
+            validator: self.validator,
+            hooks: self.hooks,
+            build_hasher,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Rounds the number of consumers up to the next power of two, so that every send routes with a bit mask instead
+    /// of a division.
+    ///
+    /// The channel is built with the rounded number of receivers. Counts that already are a power of two are kept.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rounded number of consumers overflows `usize`.
+    pub fn round_consumers_to_power_of_two(mut self) -> Self {
+        self.num_consumers = self
+            .num_consumers
+            .checked_next_power_of_two()
+            .expect("number of consumers overflows when rounded to a power of two");
+        self
+    }
+
+    /// Reserves `reserved` additional slots in each internal channel for high-priority messages.
+    ///
+    /// Reserved slots are only used by [`send_priority`](Sender::send_priority) and
+    /// [`try_send_priority`](Sender::try_send_priority) when all regular slots of the target channel are taken.
+    /// Regular sends never use them. Defaults to `0`.
+    pub fn reserved(mut self, reserved: usize) -> Self {
+        self.reserved = reserved;
+        self
+    }
+
+    /// Lets each consumer hold up to `ceiling` messages during short spikes, above its capacity.
+    ///
+    /// When all regular slots of a consumer are taken, a send takes one of the `ceiling - capacity` burst slots
+    /// instead of waiting or failing, as long as the consumer's burst allowance lasts. Every burst slot taken spends
+    /// one unit of the allowance, which refills from empty to `ceiling - capacity` over `refill`. Under sustained
+    /// overload the allowance runs dry and senders are held back by the regular capacity again. Burst usage is
+    /// reported by [`Sender::burst_report`]. A `ceiling` at or below the capacity disables bursting.
+    pub fn burst(mut self, ceiling: usize, refill: Duration) -> Self {
+        self.burst = Some((ceiling, refill));
+        self
+    }
+
+    /// Limits the number of queued-but-unreceived messages per ID.
+    ///
+    /// Without a limit, a single runaway ID can take up the whole capacity of its consumer and starve every other ID
+    /// routed to the same consumer. With a limit, [`send`](Sender::send) waits until one of the ID's messages has been
+    /// received and [`try_send`](Sender::try_send) fails with [`SendError::KeyBackpressure`](crate::SendError).
+    ///
+    /// Priority sends are counted towards the limit but never held back by it. IDs are told apart by their hash, so
+    /// IDs with colliding hashes share a limit.
+    pub fn max_pending_per_key(mut self, limit: NonZeroUsize) -> Self {
+        self.max_pending_per_key = Some(limit);
+        self
+    }
+
+    /// Packs messages into blocks of up to `size` messages per consumer before they are handed to the consumer's queue.
+    ///
+    /// Block framing cuts the per-message cost of the internal queue when many small messages are sent at high rates.
+    /// It is transparent to receivers: a consumer sees the same messages in the same order, and a partially filled block
+    /// is picked up as soon as its consumer asks for the next message, so no message waits for its block to fill up.
+    pub fn block_size(mut self, size: NonZeroUsize) -> Self {
+        self.block_size = Some(size);
+        self
+    }
+
+    /// Wakes every consumer at most once per `max_delay` for the messages sent to it in between.
+    ///
+    /// Messages are collected in a block per consumer like with [`block_size`](StickyChannelBuilder::block_size), but a consumer is
+    /// only told about its block by a task that runs once per `max_delay`, not by the first message of the block. Under
+    /// bursts, this cuts the wakeups of a busy consumer, and the context switches they cause, to one per period, at
+    /// the cost of delaying every message by up to `max_delay`. With a block size set as well, a full block is still
+    /// handed to its consumer right away. Messages left in a block when the last sender is dropped are received once
+    /// the rest of the channel has been drained.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_delay` is zero. [`build`](StickyChannelBuilder::build) panics if it is not called from within a Tokio runtime.
+    pub fn coalesce_wakeups(mut self, max_delay: Duration) -> Self
+    where
+        T: Send + 'static,
+    {
+        assert!(!max_delay.is_zero(), "max wakeup delay must be non-zero");
+        self.wakeups = Some(Coalescer::new(max_delay));
+        self
+    }
+
+    /// Labels the next unlabeled consumer, starting with the first one.
+    ///
+    /// Labels let callers refer to consumers by name instead of by index, see [`Sender::label_of`] and
+    /// [`Sender::send_to`], and are available from [`Receiver::label`]. Consumers without a label can only be referred
+    /// to by index.
+    ///
+    /// # Panics
+    ///
+    /// [`build`](StickyChannelBuilder::build) panics if there are more labels than consumers or if a label is used twice.
+    pub fn consumer(mut self, label: impl Into<Arc<str>>) -> Self {
+        self.labels.push(label.into());
+        self
+    }
+
+    /// Routes IDs through `partitions` partitions that can be moved between consumers at runtime.
+    ///
+    /// Every ID is routed to a partition by its hash, and the partitions are spread round-robin over the consumers.
+    /// The [`Admin`](crate::Admin) handle returned by [`ChannelAdmin::partitions`] reassigns partitions to other consumers
+    /// while the channel is in use, for channels created with [`build_with_admin`](Self::build_with_admin). Pick many
+    /// more partitions than consumers so that load can be moved in small steps.
+    pub fn partitions(mut self, partitions: NonZeroUsize) -> Self {
+        self.partitions = Some(partitions);
+        self
+    }
+
+    /// Records the time every message is sent, so that receivers can tell how long it spent in the queue.
+    ///
+    /// See [`recv_with_lag`](Receiver::recv_with_lag). Every consumer also keeps a histogram of the queueing delays of the
+    /// messages it received, available through `latency_report` on the sender and the receivers. Stamping costs a clock
+    /// read per message and is off by default.
+    pub fn track_lag(mut self) -> Self {
+        self.track_lag = true;
+        self
+    }
+
+    /// Tracks whether consumers are full or closed, so that supervisors can watch the health of the channel.
+    ///
+    /// See [`Sender::health`] and [`ChannelHealth`](crate::ChannelHealth). Off by default.
+    pub fn watch_health(mut self) -> Self {
+        self.watch_health = true;
+        self
+    }
+
+    /// Runs `validate` on every message before it is routed, rejecting the messages it returns an error for.
+    ///
+    /// Rejected messages fail with [`SendError::Rejected`](crate::SendError::Rejected), which carries the message and
+    /// the error returned by `validate`, so schema or business-rule checks live at the channel boundary instead of in
+    /// every producer. Messages sent with [`send`](Sender::send), [`try_send`](Sender::try_send) and the other
+    /// single-ID send methods are validated, including those sent through wrappers that forward messages unchanged.
+    /// Shared sends and wrappers that wrap messages in their own type are not.
+    pub fn validate<E, F>(mut self, validate: F) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+        F: Fn(&ID, &T) -> Result<(), E> + Send + Sync + RefUnwindSafe + 'static,
+    {
+        self.validator = Some(Validator::new(validate));
+        self
+    }
+
+    /// Calls `hook` with the consumer index and the message whenever a send finds a consumer without capacity.
+    ///
+    /// The hook runs on the sending task, before a [`try_send`](Sender::try_send) fails with
+    /// [`SendError::ChannelFull`](crate::SendError::ChannelFull) or before a [`send`](Sender::send) starts waiting, so it
+    /// should be quick.
+    pub fn on_full<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(usize, &T) + Send + Sync + RefUnwindSafe + 'static,
+    {
+        self.hooks.set_on_full(hook);
+        self
+    }
+
+    /// Calls `hook` with the consumer index when a receiver is closed or dropped, once per receiver.
+    pub fn on_closed<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(usize) + Send + Sync + RefUnwindSafe + 'static,
+    {
+        self.hooks.set_on_closed(hook);
+        self
+    }
+
+    /// Calls `hook` with the consumer index and the message whenever a message is dropped by policy instead of being
+    /// sent, which is what a [`SheddingSender`](crate::SheddingSender) does.
+    pub fn on_dropped<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(usize, &T) + Send + Sync + RefUnwindSafe + 'static,
+    {
+        self.hooks.set_on_dropped(hook);
+        self
+    }
+
+    /// Injects the message returned by `tick` into every consumer's queue once per `period`.
+    ///
+    /// Ticks let per-ID stateful consumers implement timeouts and periodic flushes without owning a timer each. They are
+    /// queued behind the messages already sent to the consumer, do not take up capacity and do not count towards
+    /// [`max_pending_per_key`](StickyChannelBuilder::max_pending_per_key). Use an enum as the message type to tell ticks apart from
+    /// regular messages. The first tick is injected one `period` after the channel is built.
+    ///
+    /// Ticks stop once every sender has been dropped, so they do not keep the channel open.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero. [`build`](StickyChannelBuilder::build) panics if it is not called from within a Tokio runtime.
+    pub fn tick<F>(mut self, period: Duration, tick: F) -> Self
+    where
+        T: Send + 'static,
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        assert!(!period.is_zero(), "tick period must be non-zero");
+        self.tick = Some(ticker(period, tick));
+        self
+    }
+
+    /// Calls `alarm` whenever the load of the consumers is skewed by more than `max_ratio` over a `window`.
+    ///
+    /// Once per `window`, the number of messages sent to the busiest consumer is compared with the number sent to the
+    /// least busy one, see [`SkewReport`](crate::SkewReport). The alarm fires for every window above the ratio, so hash
+    /// skew shows up before a consumer falls behind. Windows without messages are skipped.
+    ///
+    /// # Panics
+    ///
+    /// [`build`](StickyChannelBuilder::build) panics if it is not called from within a Tokio runtime.
+    pub fn skew_alarm<F>(mut self, max_ratio: f64, window: Duration, alarm: F) -> Self
+    where
+        F: Fn(&SkewReport) + Send + Sync + 'static,
+    {
+        self.skew = Some(SkewAlarm::new(max_ratio, window, alarm));
+        self
+    }
+
+    /// Estimates the number of messages sent per ID with a count-min sketch of `depth` rows of `width` counters.
+    ///
+    /// The estimates are read with [`approx_count`](Sender::approx_count). They never undercount and overcount by the
+    /// messages of IDs sharing counters with the ID, which wider sketches make less likely, while the memory used stays
+    /// at `width * depth` counters however many IDs are seen. Every queued message then costs `depth` more relaxed
+    /// atomic updates.
+    pub fn track_frequencies(mut self, width: NonZeroUsize, depth: NonZeroUsize) -> Self {
+        self.frequencies = Some((width, depth));
+        self
+    }
+
+    /// Keeps the most recent message of every ID, like a compacted log.
+    ///
+    /// Receivers read the latest messages of the IDs last sent to their consumer with [`Receiver::snapshot`], for example
+    /// to rebuild their state before consuming live traffic; single IDs are looked up with [`Sender::last_value`].
+    /// Every queued message is cloned into the cache, which keeps one message per ID until it is removed with
+    /// [`Sender::clear_last_value`].
+    pub fn last_values(mut self) -> Self
+    where
+        T: Clone,
+    {
+        self.last_values = Some(T::clone);
+        self
+    }
+
+    /// Forgets the cached messages of IDs that have not been sent for `ttl`, keeping the cache of
+    /// [`last_values`](Self::last_values) bounded over long uptimes.
+    ///
+    /// The message of an idle ID is no longer returned by [`Sender::last_value`] or snapshots, and is dropped by the
+    /// next send once `ttl` has passed since the previous sweep. Per-ID limits need no TTL, as they forget an ID as
+    /// soon as none of its messages is queued. The per-ID state of adapters, such as
+    /// [`HotKeySplitter`](crate::HotKeySplitter), [`Reassembler`](crate::Reassembler) and [`Demux`](crate::Demux),
+    /// lives outside the channel and is not affected.
+    pub fn last_value_ttl(mut self, ttl: Duration) -> Self {
+        self.last_value_ttl = Some(ttl);
+        self
+    }
+
+    /// Caps the number of IDs whose state the channel keeps at `limit`, however many distinct IDs are sent.
+    ///
+    /// This applies to the cache of [`last_values`](Self::last_values): sending a new ID when `limit` IDs are cached
+    /// forgets another one picked by `policy`, and counts it in [`Sender::last_value_evictions`]. The cached IDs are
+    /// kept in eviction order, so picking one takes logarithmic time, but every send then takes a lock shared by all
+    /// consumers. Combine with [`last_value_ttl`](Self::last_value_ttl) to also forget IDs that went quiet.
+    pub fn max_tracked_keys(mut self, limit: NonZeroUsize, policy: EvictionPolicy) -> Self {
+        self.max_tracked_keys = Some((limit.get(), policy));
+        self
+    }
+
+    /// Creates the bounded sticky channel.
+    ///
+    /// This function returns a tuple containing a [`Sender`] and a vector of [`Receiver`]s.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is zero, if there are more [`consumer`](StickyChannelBuilder::consumer) labels than
+    /// consumers or if a label is used twice.
+    pub fn build(self) -> (Sender<ID, T, S>, Vec<Receiver<T>>)
+    where
+        ID: Hash,
+        S: BuildHasher,
+    {
+        assert!(
+            self.capacity > 0,
+            "bounded sticky channel requires capacity > 0"
+        );
+
+        assert!(
+            self.labels.len() <= self.num_consumers.get(),
+            "more consumer labels than consumers"
+        );
+        for (index, label) in self.labels.iter().enumerate() {
+            assert!(
+                !self.labels[..index].contains(label),
+                "duplicate consumer label `{label}`"
+            );
+        }
+
+        let mut labels = self.labels.into_iter();
+        let partitions = self.partitions.map(|partitions| {
+            Arc::new(PartitionTable::new(
+                partitions.get(),
+                self.num_consumers.get(),
+            ))
+        });
+
+        let counters = Counters::new(
+            self.num_consumers.get(),
+            self.frequencies
+                .map(|(width, depth)| FrequencySketch::new(width.get(), depth.get())),
+        );
+        let hooks = self.hooks.finish();
+        let last_values = self.last_values.map(|clone| {
+            Arc::new(LastValues::new(
+                self.num_consumers.get(),
+                clone,
+                self.last_value_ttl,
+                self.max_tracked_keys,
+            ))
+        });
+        let health = self
+            .watch_health
+            .then(|| Health::new(self.num_consumers.get()));
+        let mut receivers = Vec::with_capacity(self.num_consumers.get());
+        let mut sender = Sender {
+            consumers: Vec::with_capacity(self.num_consumers.get()),
+            build_hasher: self.build_hasher,
+            partitions: partitions.clone(),
+            validator: self.validator,
+            health: health.clone(),
+            producer: next_producer(),
+            _phantom: PhantomData,
+        };
+
+        for index in 0..self.num_consumers.get() {
+            let (consumer, rx) = Consumer::new(
+                self.capacity,
+                self.reserved,
+                self.max_pending_per_key.map(NonZeroUsize::get),
+                Block::new(
+                    self.block_size,
+                    self.wakeups.as_ref().map(Coalescer::max_delay),
+                ),
+                self.track_lag,
+                labels.next(),
+                partitions.clone(),
+                Tally::new(counters.clone(), index),
+                health
+                    .as_ref()
+                    .map(|health| ConsumerHealth::new(health.clone(), index)),
+                hooks.clone(),
+                last_values.clone(),
+                self.burst
+                    .filter(|(ceiling, _)| *ceiling > self.capacity)
+                    .map(|(ceiling, refill)| Burst::new(ceiling - self.capacity, refill)),
+            );
+            receivers.push(Receiver {
+                receiver: rx,
+                slots: consumer.slots.clone(),
+                keys: consumer.keys.clone(),
+                buffer: Vec::new(),
+                unpacked: VecDeque::new(),
+                watermark: None,
+                finished: false,
+                latency: consumer.latency.clone(),
+                depth: consumer.depth.clone(),
+                offsets: Offsets::new(consumer.committed.clone()),
+                last_values: last_values.clone().map(|last_values| (last_values, index)),
+                block: consumer.sender.block.clone(),
+                label: consumer.label.clone(),
+                partitions: partitions.clone(),
+                subscriptions: Subscriptions::default(),
+                tally: consumer.tally.clone(),
+                on_closed: hooks.as_ref().and_then(|hooks| hooks.on_closed(index)),
+            });
+            sender.consumers.push(consumer);
+        }
+
+        if let Some(skew) = self.skew {
+            skew.start(&counters);
+        }
+        if let Some(start) = self.tick {
+            start(
+                sender
+                    .consumers
+                    .iter()
+                    .map(|consumer| consumer.sender.downgrade())
+                    .collect(),
+            );
+        }
+        if let Some(wakeups) = self.wakeups {
+            wakeups.start(
+                sender
+                    .consumers
+                    .iter()
+                    .map(|consumer| consumer.sender.downgrade())
+                    .collect(),
+            );
+        }
+
+        (sender, receivers)
+    }
+
+    /// Creates the channel together with a [`ChannelAdmin`] handle to its administrative operations.
+    ///
+    /// Operations that affect every producer, such as reassigning partitions, resizing consumers and closing the
+    /// channel, are only available through the handle, so code that only holds a sender cannot perform them. Keep the
+    /// handle with the code that operates the channel. Channels created with [`build`](Self::build) have no handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics like [`build`](Self::build).
+    #[allow(clippy::type_complexity)]
+    pub fn build_with_admin(self) -> (Sender<ID, T, S>, Vec<Receiver<T>>, ChannelAdmin<T>)
+    where
+        ID: Hash,
+        S: BuildHasher,
+    {
+        let (sender, receivers) = self.build();
+        let admin = sender.channel_admin();
+        (sender, receivers, admin)
+    }
+
+    /// Creates the channel with every receiver wrapped in a [`QueuedReceiver`] that hands out messages in the order
+    /// decided by a [`ConsumerQueue`] created with `queue`.
+    ///
+    /// Routing is unaffected, only the order in which a consumer's messages are handed out changes.
+    /// Each queue holds at most `capacity` messages taken from its consumer's channel, so backpressure still applies
+    /// once both are full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is zero.
+    #[allow(clippy::type_complexity)]
+    pub fn build_queued<Q, F>(
+        self,
+        mut queue: F,
+    ) -> (Sender<ID, T, S>, Vec<QueuedReceiver<Receiver<T>, Q>>)
+    where
+        ID: Hash,
+        S: BuildHasher,
+        Q: ConsumerQueue<T>,
+        F: FnMut() -> Q,
+    {
+        let capacity =
+            NonZeroUsize::new(self.capacity).expect("bounded sticky channel requires capacity > 0");
+        let (sender, receivers) = self.build();
+        let receivers = receivers
+            .into_iter()
+            .map(|receiver| QueuedReceiver::new(receiver, queue(), capacity))
+            .collect();
+        (sender, receivers)
+    }
+
+    /// Creates the bounded sticky channel together with a companion control channel.
+    ///
+    /// See [`control_channel`] for details.
+    #[allow(clippy::type_complexity)]
+    pub fn build_with_control<C>(
+        self,
+    ) -> (
+        Sender<ID, T, S>,
+        ControlSender<C>,
+        Vec<EventReceiver<Receiver<T>, C>>,
+    )
+    where
+        ID: Hash,
+        S: BuildHasher,
+    {
+        let (sender, receivers) = self.build();
+        let (control, receivers) = control_channel(receivers);
+        (sender, control, receivers)
+    }
+}
@@ -0,0 +1,593 @@
+use std::{
+    future::{Future, poll_fn},
+    pin::pin,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    task::Poll,
+};
+
+use tokio::{
+    sync::{Semaphore, TryAcquireError, mpsc::UnboundedReceiver as MpscReceiver},
+    time::Instant,
+};
+
+use crate::{
+    SendError,
+    burst::{Burst, BurstReport},
+    compact::LastValues,
+    envelope::{Envelope, Payload, Slot},
+    health::ConsumerHealth,
+    hooks::Hooks,
+    keys::{KeyLimiter, KeyPermit},
+    latency::LatencyHistogram,
+    partition::PartitionTable,
+    queue::{Block, Queue},
+    totals::Tally,
+    util::Route,
+};
+
+/// Capacity accounting for a single bounded consumer.
+///
+/// A permit is taken from one of the semaphores for every queued message and given back when the message is received.
+/// Closing the slots wakes up all senders waiting for capacity.
+pub(crate) struct Slots {
+    regular: Semaphore,
+    reserved: Semaphore,
+    /// Number of regular slots, taken or not. Lowered as soon as a shrink starts waiting for its slots.
+    capacity: AtomicUsize,
+    burst: Option<Burst>,
+    health: Option<ConsumerHealth>,
+}
+
+impl Slots {
+    pub(crate) fn new(
+        capacity: usize,
+        reserved: usize,
+        burst: Option<Burst>,
+        health: Option<ConsumerHealth>,
+    ) -> Self {
+        Self {
+            regular: Semaphore::new(capacity),
+            reserved: Semaphore::new(reserved),
+            capacity: AtomicUsize::new(capacity),
+            burst,
+            health,
+        }
+    }
+
+    pub(crate) fn burst_report(&self) -> Option<BurstReport> {
+        self.burst.as_ref().map(Burst::report)
+    }
+
+    /// Takes a burst slot if the consumer may burst right now.
+    fn try_burst(&self) -> Option<Slot> {
+        self.burst
+            .as_ref()
+            .filter(|burst| burst.try_acquire())
+            .map(|_| Slot::Burst)
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Acquire)
+    }
+
+    /// Adds `additional` regular slots, waking up senders waiting for capacity.
+    pub(crate) fn grow(&self, additional: usize) {
+        if additional == 0 || self.regular.is_closed() {
+            return;
+        }
+        self.capacity.fetch_add(additional, Ordering::AcqRel);
+        self.regular.add_permits(additional);
+        self.available();
+    }
+
+    /// Removes `by` regular slots, waiting until enough messages have been received to free them.
+    ///
+    /// Returns `false` without removing anything if that would leave no regular slot, or if the slots are closed
+    /// before they are free. Dropping the future before it completes leaves the capacity unchanged.
+    pub(crate) async fn shrink(&self, by: usize) -> bool {
+        let Ok(by_permits) = u32::try_from(by) else {
+            return false;
+        };
+        if by == 0 {
+            return true;
+        }
+        let lowered = self
+            .capacity
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |capacity| {
+                capacity.checked_sub(by).filter(|&capacity| capacity > 0)
+            });
+        if lowered.is_err() {
+            return false;
+        }
+
+        // Restores the capacity unless the slots were taken.
+        struct Pending<'a>(Option<&'a AtomicUsize>, usize);
+        impl Drop for Pending<'_> {
+            fn drop(&mut self) {
+                if let Some(capacity) = self.0 {
+                    capacity.fetch_add(self.1, Ordering::AcqRel);
+                }
+            }
+        }
+        let mut pending = Pending(Some(&self.capacity), by);
+
+        match self.regular.acquire_many(by_permits).await {
+            Ok(permits) => {
+                permits.forget();
+                pending.0 = None;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Returns `true` if no regular slot is left.
+    fn is_full(&self) -> bool {
+        self.regular.available_permits() == 0
+    }
+
+    /// Reports the consumer as full if no regular slot is left.
+    fn check_full(&self) {
+        if let Some(health) = &self.health
+            && self.is_full()
+        {
+            health.full();
+        }
+    }
+
+    /// Waits for a regular slot, unless a burst slot can be taken right away.
+    async fn acquire(&self) -> Option<Slot> {
+        self.check_full();
+        if self.is_full()
+            && !self.regular.is_closed()
+            && let Some(slot) = self.try_burst()
+        {
+            return Some(slot);
+        }
+        let permit = self.regular.acquire().await.ok()?;
+        permit.forget();
+        Some(Slot::Regular)
+    }
+
+    /// Waits for a regular slot, or a reserved one if no regular slot is available.
+    async fn acquire_priority(&self) -> Option<Slot> {
+        self.check_full();
+        let mut regular = pin!(self.regular.acquire());
+        let mut reserved = pin!(self.reserved.acquire());
+
+        poll_fn(|cx| {
+            if let Poll::Ready(result) = regular.as_mut().poll(cx) {
+                return Poll::Ready(result.ok().map(|permit| {
+                    permit.forget();
+                    Slot::Regular
+                }));
+            }
+
+            if let Poll::Ready(result) = reserved.as_mut().poll(cx) {
+                return Poll::Ready(result.ok().map(|permit| {
+                    permit.forget();
+                    Slot::Reserved
+                }));
+            }
+
+            Poll::Pending
+        })
+        .await
+    }
+
+    fn try_acquire(&self) -> Result<Slot, TryAcquireError> {
+        let slot = self.regular.try_acquire().map(|permit| {
+            permit.forget();
+            Slot::Regular
+        });
+        if let Err(TryAcquireError::NoPermits) = slot {
+            if let Some(slot) = self.try_burst() {
+                return Ok(slot);
+            }
+            if let Some(health) = &self.health {
+                health.full();
+            }
+        }
+        slot
+    }
+
+    fn try_acquire_priority(&self) -> Result<Slot, TryAcquireError> {
+        match self.try_acquire() {
+            Err(TryAcquireError::NoPermits) => self.reserved.try_acquire().map(|permit| {
+                permit.forget();
+                Slot::Reserved
+            }),
+            result => result,
+        }
+    }
+
+    pub(crate) fn release(&self, slot: Slot) {
+        match slot {
+            Slot::Regular => {
+                self.regular.add_permits(1);
+                self.available();
+            }
+            Slot::Reserved => self.reserved.add_permits(1),
+            Slot::Burst => self.release_burst(1),
+            Slot::Unbounded | Slot::Injected => {}
+        }
+    }
+
+    fn release_burst(&self, slots: usize) {
+        if let Some(burst) = &self.burst {
+            burst.release(slots);
+        }
+    }
+
+    pub(crate) fn release_many(&self, regular: usize, reserved: usize, burst: usize) {
+        if burst > 0 {
+            self.release_burst(burst);
+        }
+        if regular > 0 {
+            self.regular.add_permits(regular);
+            self.available();
+        }
+        if reserved > 0 {
+            self.reserved.add_permits(reserved);
+        }
+    }
+
+    pub(crate) fn close(&self) {
+        self.regular.close();
+        self.reserved.close();
+        if let Some(health) = &self.health {
+            health.closed();
+        }
+    }
+
+    fn available(&self) {
+        if let Some(health) = &self.health {
+            health.available();
+        }
+    }
+}
+
+/// Sending half of a single bounded consumer's queue.
+pub(crate) struct Consumer<T> {
+    pub(crate) sender: Queue<T>,
+    pub(crate) slots: Arc<Slots>,
+    pub(crate) keys: Option<Arc<KeyLimiter>>,
+    pub(crate) latency: Option<Arc<LatencyHistogram>>,
+    /// Number of messages sent to the consumer that it has not received yet.
+    pub(crate) depth: Arc<AtomicUsize>,
+    /// Committed offset of the consumer plus one, or zero if none was committed.
+    pub(crate) committed: Arc<AtomicU64>,
+    pub(crate) label: Option<Arc<str>>,
+    pub(crate) partitions: Option<Arc<PartitionTable>>,
+    pub(crate) tally: Tally,
+    pub(crate) hooks: Option<Arc<Hooks<T>>>,
+    pub(crate) last_values: Option<Arc<LastValues<T>>>,
+}
+
+impl<T> Consumer<T> {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        capacity: usize,
+        reserved: usize,
+        max_pending_per_key: Option<usize>,
+        block: Option<Block<T>>,
+        track_lag: bool,
+        label: Option<Arc<str>>,
+        partitions: Option<Arc<PartitionTable>>,
+        tally: Tally,
+        health: Option<ConsumerHealth>,
+        hooks: Option<Arc<Hooks<T>>>,
+        last_values: Option<Arc<LastValues<T>>>,
+        burst: Option<Burst>,
+    ) -> (Self, MpscReceiver<Envelope<T>>) {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        let consumer = Self {
+            sender: Queue::new(sender, block),
+            slots: Arc::new(Slots::new(capacity, reserved, burst, health)),
+            keys: max_pending_per_key.map(|limit| Arc::new(KeyLimiter::new(limit))),
+            latency: track_lag.then(|| Arc::new(LatencyHistogram::new())),
+            depth: Arc::new(AtomicUsize::new(0)),
+            committed: Arc::new(AtomicU64::new(0)),
+            label,
+            partitions,
+            tally,
+            hooks,
+            last_values,
+        };
+        (consumer, receiver)
+    }
+
+    /// Returns the number of messages sent to the consumer that it has not received yet.
+    pub(crate) fn depth(&self) -> usize {
+        self.depth.load(Ordering::Relaxed)
+    }
+
+    /// Calls the `on_full` hook for a message that is about to wait for capacity, if there is none.
+    fn report_full(&self, message: &T, route: Route) {
+        if let Some(hooks) = &self.hooks
+            && self.slots.is_full()
+        {
+            hooks.full(route.index, message);
+        }
+    }
+
+    pub(crate) async fn send(&self, message: T, route: Route) -> Result<(), SendError<T>> {
+        let key = match &self.keys {
+            Some(keys) => match keys.acquire(route.hash).await {
+                Some(permit) => Some(permit),
+                None => return Err(SendError::ChannelClosed(message, route.index)),
+            },
+            None => None,
+        };
+
+        self.report_full(&message, route);
+        match self.slots.acquire().await {
+            Some(slot) => self.enqueue(message, slot, key, route),
+            None => Err(SendError::ChannelClosed(message, route.index)),
+        }
+    }
+
+    pub(crate) async fn send_priority(&self, message: T, route: Route) -> Result<(), SendError<T>> {
+        let key = self
+            .keys
+            .as_ref()
+            .map(|keys| keys.acquire_unchecked(route.hash));
+
+        match self.slots.acquire_priority().await {
+            Some(slot) => self.enqueue(message, slot, key, route),
+            None => Err(SendError::ChannelClosed(message, route.index)),
+        }
+    }
+
+    pub(crate) fn try_send(&self, message: T, route: Route) -> Result<(), SendError<T>> {
+        let key = match &self.keys {
+            Some(keys) => match keys.try_acquire(route.hash) {
+                Ok(permit) => Some(permit),
+                Err(TryAcquireError::NoPermits) => {
+                    return Err(SendError::KeyBackpressure(message, route.index));
+                }
+                Err(TryAcquireError::Closed) => {
+                    return Err(SendError::ChannelClosed(message, route.index));
+                }
+            },
+            None => None,
+        };
+
+        let slot = self.slots.try_acquire();
+        self.try_enqueue(message, slot, key, route)
+    }
+
+    pub(crate) fn try_send_priority(&self, message: T, route: Route) -> Result<(), SendError<T>> {
+        let key = self
+            .keys
+            .as_ref()
+            .map(|keys| keys.acquire_unchecked(route.hash));
+        let slot = self.slots.try_acquire_priority();
+        self.try_enqueue(message, slot, key, route)
+    }
+
+    /// Takes the per-ID and capacity slots for a message without queueing it, waiting for capacity.
+    ///
+    /// The returned envelope owns both slots until it is received.
+    pub(crate) async fn reserve(
+        &self,
+        message: T,
+        route: Route,
+    ) -> Result<Envelope<T>, SendError<T>> {
+        let key = match &self.keys {
+            Some(keys) => match keys.acquire(route.hash).await {
+                Some(permit) => Some(permit),
+                None => return Err(SendError::ChannelClosed(message, route.index)),
+            },
+            None => None,
+        };
+
+        self.report_full(&message, route);
+        match self.slots.acquire().await {
+            Some(slot) => Ok(self.seal(message, slot, key, route)),
+            None => Err(SendError::ChannelClosed(message, route.index)),
+        }
+    }
+
+    /// Takes the per-ID and capacity slots for a message without queueing it or waiting.
+    pub(crate) fn try_reserve(
+        &self,
+        message: T,
+        route: Route,
+    ) -> Result<Envelope<T>, SendError<T>> {
+        let key = match &self.keys {
+            Some(keys) => match keys.try_acquire(route.hash) {
+                Ok(permit) => Some(permit),
+                Err(TryAcquireError::NoPermits) => {
+                    return Err(SendError::KeyBackpressure(message, route.index));
+                }
+                Err(TryAcquireError::Closed) => {
+                    return Err(SendError::ChannelClosed(message, route.index));
+                }
+            },
+            None => None,
+        };
+
+        match self.slots.try_acquire() {
+            Ok(slot) => Ok(self.seal(message, slot, key, route)),
+            Err(TryAcquireError::NoPermits) => {
+                if let Some(hooks) = &self.hooks {
+                    hooks.full(route.index, &message);
+                }
+                Err(SendError::ChannelFull(message, route.index))
+            }
+            Err(TryAcquireError::Closed) => Err(SendError::ChannelClosed(message, route.index)),
+        }
+    }
+
+    /// Takes the per-ID and capacity slots for a message that is only provided later, waiting for capacity.
+    ///
+    /// Returns `None` if the channel is closed.
+    pub(crate) async fn reserve_owned(self, route: Route) -> Option<OwnedPermit<T>> {
+        let keyed = match &self.keys {
+            Some(keys) => {
+                keys.acquire(route.hash).await?.forget();
+                true
+            }
+            None => false,
+        };
+
+        // Gives back the per-ID slot if no capacity slot can be taken.
+        let mut permit = OwnedPermit {
+            consumer: self,
+            slot: None,
+            keyed,
+            route,
+        };
+        permit.slot = Some(permit.consumer.slots.acquire().await?);
+        Some(permit)
+    }
+
+    fn try_enqueue(
+        &self,
+        message: T,
+        slot: Result<Slot, TryAcquireError>,
+        key: Option<KeyPermit<'_>>,
+        route: Route,
+    ) -> Result<(), SendError<T>> {
+        match slot {
+            Ok(slot) => self.enqueue(message, slot, key, route),
+            Err(TryAcquireError::NoPermits) => {
+                if let Some(hooks) = &self.hooks {
+                    hooks.full(route.index, &message);
+                }
+                Err(SendError::ChannelFull(message, route.index))
+            }
+            Err(TryAcquireError::Closed) => Err(SendError::ChannelClosed(message, route.index)),
+        }
+    }
+
+    fn enqueue(
+        &self,
+        message: T,
+        slot: Slot,
+        key: Option<KeyPermit<'_>>,
+        route: Route,
+    ) -> Result<(), SendError<T>> {
+        // The message is only cached once it has been queued, so a failed send leaves the cache untouched.
+        let copy = self
+            .last_values
+            .as_ref()
+            .map(|last_values| last_values.copy(&message));
+        match self.sender.send(self.envelope(message, slot, route)) {
+            Ok(()) => {
+                if let Some(key) = key {
+                    key.forget();
+                }
+                if let (Some(last_values), Some(copy)) = (&self.last_values, copy) {
+                    last_values.record(route.hash, route.index, copy);
+                }
+                Ok(())
+            }
+            Err(envelope) => {
+                self.slots.release(envelope.slot);
+                self.depth.fetch_sub(1, Ordering::Relaxed);
+                self.tally.unsent(envelope.hash);
+                if let Some(partitions) = &self.partitions {
+                    partitions.received([envelope.hash]);
+                }
+                Err(SendError::ChannelClosed(
+                    envelope.into_message(),
+                    route.index,
+                ))
+            }
+        }
+    }
+
+    /// Wraps a message whose slots have been taken into an envelope.
+    fn seal(
+        &self,
+        message: T,
+        slot: Slot,
+        key: Option<KeyPermit<'_>>,
+        route: Route,
+    ) -> Envelope<T> {
+        if let Some(key) = key {
+            key.forget();
+        }
+        if let Some(last_values) = &self.last_values {
+            last_values.record(route.hash, route.index, last_values.copy(&message));
+        }
+
+        self.envelope(message, slot, route)
+    }
+
+    /// Wraps a message into an envelope, stamping it with the current time if the channel tracks lag.
+    fn envelope(&self, message: T, slot: Slot, route: Route) -> Envelope<T> {
+        self.depth.fetch_add(1, Ordering::Relaxed);
+        self.tally.sent(route.hash);
+        if let Some(partitions) = &self.partitions {
+            partitions.enqueued(route.hash);
+        }
+        Envelope {
+            payload: Payload::Message(message),
+            hash: route.hash,
+            slot,
+            enqueued: self.latency.as_ref().map(|_| Instant::now()),
+            producer: route.producer,
+        }
+    }
+}
+
+impl<T> Clone for Consumer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            slots: self.slots.clone(),
+            keys: self.keys.clone(),
+            latency: self.latency.clone(),
+            depth: self.depth.clone(),
+            committed: self.committed.clone(),
+            label: self.label.clone(),
+            partitions: self.partitions.clone(),
+            tally: self.tally.clone(),
+            hooks: self.hooks.clone(),
+            last_values: self.last_values.clone(),
+        }
+    }
+}
+
+/// Slots taken for a message of a single route, given back on drop unless a message is sent with them.
+pub(crate) struct OwnedPermit<T> {
+    consumer: Consumer<T>,
+    slot: Option<Slot>,
+    /// Whether a per-ID slot was taken.
+    keyed: bool,
+    route: Route,
+}
+
+impl<T> OwnedPermit<T> {
+    pub(crate) fn route(&self) -> Route {
+        self.route
+    }
+
+    /// Queues a message using the taken slots.
+    pub(crate) fn send(mut self, message: T) -> Result<(), SendError<T>> {
+        let slot = self.slot.take().expect("permits always hold a slot");
+        let key = match (self.keyed, &self.consumer.keys) {
+            (true, Some(keys)) => Some(keys.adopt(self.route.hash)),
+            _ => None,
+        };
+        self.keyed = false;
+        self.consumer.enqueue(message, slot, key, self.route)
+    }
+}
+
+impl<T> Drop for OwnedPermit<T> {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot.take() {
+            self.consumer.slots.release(slot);
+        }
+        if let (true, Some(keys)) = (self.keyed, &self.consumer.keys) {
+            keys.release([self.route.hash]);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{
+    SendError,
+    util::Route,
+    validate::{BoundValidator, check_bound},
+};
+
+use super::consumer::{Consumer, OwnedPermit};
+
+/// Handle to a bounded channel that sends every message with the same ID.
+///
+/// Created with [`Sender::keyed`](crate::Sender::keyed). The route of the ID is computed once, so sending does not
+/// hash the ID again and callers do not have to repeat it, which suits per-session handles given to connection tasks.
+/// Messages are subject to capacity, per-ID limits and validation exactly like messages sent through the sender.
+///
+/// The route is fixed when the handle is created: if the ID's partition is reassigned later, see
+/// [`Admin::reassign`](crate::Admin::reassign), the handle keeps sending to the old consumer.
+pub struct KeyedSender<T> {
+    consumer: Consumer<T>,
+    route: Route,
+    validator: Option<BoundValidator<T>>,
+}
+
+impl<T> KeyedSender<T> {
+    pub(crate) fn new(
+        consumer: Consumer<T>,
+        route: Route,
+        validator: Option<BoundValidator<T>>,
+    ) -> Self {
+        Self {
+            consumer,
+            route,
+            validator,
+        }
+    }
+
+    /// Sends a message, waiting for capacity like [`Sender::send`](crate::Sender::send).
+    pub async fn send(&self, message: T) -> Result<(), SendError<T>> {
+        let message = check_bound(self.validator.as_ref(), message)?;
+        self.consumer.send(message, self.route).await
+    }
+
+    /// Attempts to send a message without waiting, like [`Sender::try_send`](crate::Sender::try_send).
+    pub fn try_send(&self, message: T) -> Result<(), SendError<T>> {
+        let message = check_bound(self.validator.as_ref(), message)?;
+        self.consumer.try_send(message, self.route)
+    }
+
+    /// Waits for capacity for one message and reserves it.
+    ///
+    /// The reserved slot is given back if the returned permit is dropped without sending. Returns an error if the
+    /// consumer is closed.
+    pub async fn reserve(&self) -> Result<KeyedPermit<T>, SendError<()>> {
+        match self.consumer.clone().reserve_owned(self.route).await {
+            Some(permit) => Ok(KeyedPermit {
+                permit,
+                validator: self.validator.clone(),
+            }),
+            None => Err(SendError::ChannelClosed((), self.route.index)),
+        }
+    }
+
+    /// Returns the index of the consumer the messages of this handle are delivered to.
+    pub fn consumer_index(&self) -> usize {
+        self.route.index
+    }
+}
+
+impl<T> Clone for KeyedSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            consumer: self.consumer.clone(),
+            route: self.route,
+            validator: self.validator.clone(),
+        }
+    }
+}
+
+/// Capacity for a single message reserved with [`KeyedSender::reserve`].
+pub struct KeyedPermit<T> {
+    permit: OwnedPermit<T>,
+    validator: Option<BoundValidator<T>>,
+}
+
+impl<T> KeyedPermit<T> {
+    /// Sends a message in the reserved slot without waiting.
+    ///
+    /// Fails if the message is rejected by the channel's validator, which gives back the slot, or if the consumer was
+    /// closed in the meantime.
+    pub fn send(self, message: T) -> Result<(), SendError<T>> {
+        let message = check_bound(self.validator.as_ref(), message)?;
+        self.permit.send(message)
+    }
+}
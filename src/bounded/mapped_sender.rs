@@ -0,0 +1,62 @@
+use std::{
+    hash::{BuildHasher, Hash},
+    sync::Arc,
+};
+
+use crate::{DefaultBuildHasher, SendError, Sender};
+
+type MapFn<U, T> = Arc<dyn Fn(U) -> T + Send + Sync>;
+
+/// Bounded [`Sender`] wrapper that accepts messages of another type and converts them to the channel's message type.
+///
+/// Created with [`Sender::with_map`]. Producers send their own type `U` and the conversion to `T` happens when a
+/// message is sent, so they do not depend on the type the consumers receive. Conversion happens before validation and
+/// routing, so errors carry the converted message.
+pub struct MappedSender<ID, U, T, S = DefaultBuildHasher> {
+    sender: Sender<ID, T, S>,
+    map: MapFn<U, T>,
+}
+
+impl<ID, U, T, S> MappedSender<ID, U, T, S> {
+    pub(crate) fn new(sender: Sender<ID, T, S>, map: MapFn<U, T>) -> Self {
+        Self { sender, map }
+    }
+
+    /// Returns a reference to the underlying sender.
+    pub fn get_ref(&self) -> &Sender<ID, T, S> {
+        &self.sender
+    }
+
+    /// Returns the underlying sender.
+    pub fn into_inner(self) -> Sender<ID, T, S> {
+        self.sender
+    }
+}
+
+impl<ID, U, T, S> MappedSender<ID, U, T, S>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Converts a message and sends it like [`Sender::send`].
+    pub async fn send(&self, id: ID, message: U) -> Result<(), SendError<T>> {
+        self.sender.send(id, (self.map)(message)).await
+    }
+
+    /// Converts a message and attempts to send it without waiting, like [`Sender::try_send`].
+    pub fn try_send(&self, id: ID, message: U) -> Result<(), SendError<T>> {
+        self.sender.try_send(id, (self.map)(message))
+    }
+}
+
+impl<ID, U, T, S> Clone for MappedSender<ID, U, T, S>
+where
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            map: self.map.clone(),
+        }
+    }
+}
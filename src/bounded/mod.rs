@@ -1,15 +1,36 @@
+mod builder;
+mod consumer;
+mod keyed_sender;
+mod mapped_sender;
+mod poll_sender;
 mod receiver;
 mod sender;
+mod timeout_sender;
 
-pub use self::{receiver::Receiver, sender::Sender};
+pub use self::{
+    builder::StickyChannelBuilder,
+    keyed_sender::{KeyedPermit, KeyedSender},
+    mapped_sender::MappedSender,
+    poll_sender::PollStickySender,
+    receiver::Receiver,
+    sender::Sender,
+    timeout_sender::TimeoutSender,
+};
+
+pub(crate) use self::consumer::Slots;
 
 use std::{
-    hash::{BuildHasher, Hash, RandomState},
+    hash::{BuildHasher, Hash},
     num::NonZeroUsize,
 };
 
+use crate::{DefaultBuildHasher, PriorityQueue, QueuedReceiver, SeededState, WithMeta};
+
+/// Receiver of a [`sticky_priority_channel`] that hands out messages in priority order.
+pub type PriorityReceiver<T> = QueuedReceiver<Receiver<T>, PriorityQueue<T>>;
+
 /// Creates a bounded sticky channel with the specified number of consumers, capacity and default hasher
-/// ([`RandomState`]).
+/// ([`DefaultBuildHasher`]).
 ///
 /// This function returns a tuple containing a [`Sender`] and a vector of [`Receiver`]s.
 ///
@@ -27,7 +48,7 @@ pub fn sticky_channel<ID, T>(
 where
     ID: Hash,
 {
-    sticky_channel_with_hasher(num_consumers, capacity, RandomState::new())
+    sticky_channel_with_hasher(num_consumers, capacity, DefaultBuildHasher::default())
 }
 
 /// Creates a bounded sticky channel with the specified number of consumers, capacity and a [`BuildHasher`].
@@ -50,18 +71,63 @@ where
     ID: Hash,
     S: BuildHasher,
 {
-    let mut receivers = Vec::with_capacity(num_consumers.get());
-    let mut sender = Sender {
-        consumers: Vec::with_capacity(num_consumers.get()),
-        build_hasher,
-        _phantom: std::marker::PhantomData,
-    };
+    StickyChannelBuilder::new(num_consumers, capacity)
+        .hasher(build_hasher)
+        .build()
+}
 
-    for _ in 0..num_consumers.get() {
-        let (tx, rx) = tokio::sync::mpsc::channel(capacity);
-        sender.consumers.push(tx);
-        receivers.push(Receiver { receiver: rx });
-    }
+/// Creates a bounded sticky channel with the specified number of consumers and capacity, hashing IDs with a
+/// [`SeededState`] keyed by `seed`.
+///
+/// Channels created with the same seed and number of consumers route every ID to the same consumer, in this process or
+/// any other, which lets independent processes agree on the owner of an ID or keep routes stable across restarts.
+pub fn sticky_channel_with_seed<ID, T>(
+    num_consumers: NonZeroUsize,
+    capacity: usize,
+    seed: u128,
+) -> (Sender<ID, T, SeededState>, Vec<Receiver<T>>)
+where
+    ID: Hash,
+{
+    sticky_channel_with_hasher(num_consumers, capacity, SeededState::new(seed))
+}
 
-    (sender, receivers)
+/// Creates a bounded sticky channel whose consumers receive their messages in priority order.
+///
+/// Messages are routed like with [`sticky_channel`], but each [`PriorityReceiver`] hands out the largest of its
+/// available messages first, according to their [`Ord`] implementation. Messages that compare equal are handed out in
+/// the order they were sent. This suits schedulers that route jobs by tenant but want to run urgent jobs first.
+///
+/// Only messages that have arrived by the time a message is received are ordered, and at most `capacity` of them are
+/// taken out of the channel at a time, see [`QueuedReceiver`].
+///
+/// # Panics
+///
+/// Panics if the capacity is zero.
+pub fn sticky_priority_channel<ID, T>(
+    num_consumers: NonZeroUsize,
+    capacity: usize,
+) -> (Sender<ID, T>, Vec<PriorityReceiver<T>>)
+where
+    ID: Hash,
+    T: Ord,
+{
+    StickyChannelBuilder::new(num_consumers, capacity).build_queued(PriorityQueue::new)
+}
+
+/// Creates a bounded sticky channel whose messages carry metadata of type `M`, with the default hasher
+/// ([`DefaultBuildHasher`]).
+///
+/// Messages are sent with [`Sender::send_with_meta`] and routed like with [`sticky_channel`]. Every [`Receiver`]
+/// receives them as [`WithMeta`], with the metadata attached at send, so correlation IDs, tenants or priorities do not
+/// have to be part of the payload type. Use [`StickyChannelBuilder`] with a `WithMeta` message type for other options.
+#[allow(clippy::type_complexity)]
+pub fn sticky_channel_with_meta<ID, T, M>(
+    num_consumers: NonZeroUsize,
+    capacity: usize,
+) -> (Sender<ID, WithMeta<T, M>>, Vec<Receiver<WithMeta<T, M>>>)
+where
+    ID: Hash,
+{
+    sticky_channel(num_consumers, capacity)
 }
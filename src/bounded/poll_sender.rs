@@ -0,0 +1,169 @@
+use std::{
+    future::Future,
+    hash::{BuildHasher, Hash},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{DefaultBuildHasher, SendError, Sender};
+
+use super::consumer::OwnedPermit;
+
+type Reserve<T> = Pin<Box<dyn Future<Output = Option<OwnedPermit<T>>> + Send>>;
+
+enum State<T> {
+    Idle,
+    Acquiring {
+        index: usize,
+        hash: u64,
+        reserve: Reserve<T>,
+    },
+    Ready(OwnedPermit<T>),
+    Closed,
+}
+
+/// Poll-based wrapper around a bounded [`Sender`], for code that cannot `.await`.
+///
+/// Like `tokio_util::sync::PollSender`, sending is split into two steps: [`poll_reserve`](PollStickySender::poll_reserve)
+/// waits for a slot in the queue of the consumer an ID is routed to, and [`send_item`](PollStickySender::send_item)
+/// sends a message with that ID in the reserved slot without waiting. This allows sending from manual `Future` or
+/// `Sink` implementations.
+///
+/// A reservation is made for a single ID, including its per-ID slot if the channel has a per-ID limit. Polling for
+/// another ID gives up the current reservation. Clones start without a reservation.
+pub struct PollStickySender<ID, T, S = DefaultBuildHasher> {
+    sender: Sender<ID, T, S>,
+    state: State<T>,
+}
+
+impl<ID, T, S> PollStickySender<ID, T, S>
+where
+    ID: Hash,
+    T: Send + 'static,
+    S: BuildHasher,
+{
+    /// Wraps a sender.
+    pub fn new(sender: Sender<ID, T, S>) -> Self {
+        Self {
+            sender,
+            state: State::Idle,
+        }
+    }
+
+    /// Polls to reserve a slot for a message with the given ID.
+    ///
+    /// Returns `Poll::Ready(Ok(()))` once a slot is reserved; the message must then be sent with
+    /// [`send_item`](PollStickySender::send_item) using the same ID. Polling again after that returns `Ready` right away
+    /// for the same ID. The reservation is kept until the message is sent, [`abort_send`](PollStickySender::abort_send)
+    /// is called or the wrapper is dropped.
+    ///
+    /// Returns an error if the route of the ID cannot be computed or its consumer is closed. After
+    /// [`close`](PollStickySender::close) is called, every poll returns an error.
+    pub fn poll_reserve(
+        &mut self,
+        cx: &mut Context<'_>,
+        id: &ID,
+    ) -> Poll<Result<(), SendError<()>>> {
+        let route = match self.sender.route_ref(id) {
+            Ok(route) => route,
+            Err(_) => return Poll::Ready(Err(SendError::FailedToComputeRouteID(()))),
+        };
+
+        loop {
+            match &mut self.state {
+                State::Closed => {
+                    return Poll::Ready(Err(SendError::ChannelClosed((), route.index)));
+                }
+                State::Ready(permit) if permit.route() == route => return Poll::Ready(Ok(())),
+                State::Acquiring {
+                    index,
+                    hash,
+                    reserve,
+                } if *index == route.index && *hash == route.hash => {
+                    match reserve.as_mut().poll(cx) {
+                        Poll::Ready(Some(permit)) => self.state = State::Ready(permit),
+                        Poll::Ready(None) => {
+                            self.state = State::Idle;
+                            return Poll::Ready(Err(SendError::ChannelClosed((), route.index)));
+                        }
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+                _ => {
+                    let Some(consumer) = self.sender.consumers.get(route.index) else {
+                        return Poll::Ready(Err(SendError::NoConsumer((), route.index)));
+                    };
+                    self.state = State::Acquiring {
+                        index: route.index,
+                        hash: route.hash,
+                        reserve: Box::pin(consumer.clone().reserve_owned(route)),
+                    };
+                }
+            }
+        }
+    }
+
+    /// Sends a message in the slot reserved with [`poll_reserve`](PollStickySender::poll_reserve), without waiting.
+    ///
+    /// Returns [`SendError::ChannelFull`] if no slot is reserved for `id`, and [`SendError::ChannelClosed`] if the
+    /// consumer was closed in the meantime. The error includes the message.
+    pub fn send_item(&mut self, id: ID, message: T) -> Result<(), SendError<T>> {
+        let message = self.sender.validate(&id, message)?;
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+
+        match std::mem::replace(&mut self.state, State::Idle) {
+            State::Ready(permit) if permit.route() == route => permit.send(message),
+            State::Closed => {
+                self.state = State::Closed;
+                Err(SendError::ChannelClosed(message, route.index))
+            }
+            state => {
+                self.state = state;
+                Err(SendError::ChannelFull(message, route.index))
+            }
+        }
+    }
+
+    /// Gives up the current reservation, or stops waiting for one. Returns `true` if there was one.
+    pub fn abort_send(&mut self) -> bool {
+        match self.state {
+            State::Acquiring { .. } | State::Ready(_) => {
+                self.state = State::Idle;
+                true
+            }
+            State::Idle | State::Closed => false,
+        }
+    }
+
+    /// Gives up the current reservation and fails all future reservations of this wrapper.
+    ///
+    /// Other senders of the channel, including clones of this wrapper, are not affected.
+    pub fn close(&mut self) {
+        self.state = State::Closed;
+    }
+
+    /// Returns `true` if [`close`](PollStickySender::close) has been called.
+    pub fn is_closed(&self) -> bool {
+        matches!(self.state, State::Closed)
+    }
+
+    /// Returns a reference to the underlying sender.
+    pub fn get_ref(&self) -> &Sender<ID, T, S> {
+        &self.sender
+    }
+}
+
+impl<ID, T, S> Clone for PollStickySender<ID, T, S>
+where
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            state: State::Idle,
+        }
+    }
+}
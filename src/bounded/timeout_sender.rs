@@ -0,0 +1,102 @@
+use std::{
+    hash::{BuildHasher, Hash},
+    time::Duration,
+};
+
+use tokio::time::timeout;
+
+use crate::{DefaultBuildHasher, SendError, Sender};
+
+/// Bounded [`Sender`] wrapper that gives up on a send once it has waited for capacity for a fixed time.
+///
+/// Application code can use the plain [`send`](TimeoutSender::send) API while the deadline is configured once, where
+/// the sender is created. A send that times out fails with [`SendError::Timeout`], which includes the message.
+pub struct TimeoutSender<ID, T, S = DefaultBuildHasher> {
+    sender: Sender<ID, T, S>,
+    timeout: Duration,
+}
+
+impl<ID, T, S> TimeoutSender<ID, T, S> {
+    /// Wraps a sender, limiting every send to `timeout`.
+    pub fn new(sender: Sender<ID, T, S>, timeout: Duration) -> Self {
+        Self { sender, timeout }
+    }
+
+    /// Returns the time a send may wait for capacity.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns a reference to the underlying sender.
+    pub fn get_ref(&self) -> &Sender<ID, T, S> {
+        &self.sender
+    }
+
+    /// Returns the underlying sender.
+    pub fn into_inner(self) -> Sender<ID, T, S> {
+        self.sender
+    }
+}
+
+impl<ID, T, S> TimeoutSender<ID, T, S>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a message to the consumer identified by `id`, waiting for capacity for at most the configured timeout.
+    ///
+    /// Fails like [`Sender::send`], and with [`SendError::Timeout`] if no capacity became available in time, or the ID
+    /// stayed at its per-ID limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is not called from within a Tokio runtime with the time driver enabled.
+    pub async fn send(&self, id: ID, message: T) -> Result<(), SendError<T>> {
+        self.send_timeout(id, message, self.timeout).await
+    }
+
+    /// Sends a message like [`send`](TimeoutSender::send), but with another timeout than the configured one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is not called from within a Tokio runtime with the time driver enabled.
+    pub async fn send_timeout(
+        &self,
+        id: ID,
+        message: T,
+        duration: Duration,
+    ) -> Result<(), SendError<T>> {
+        let message = self.sender.validate(&id, message)?;
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+        let Some(consumer) = self.sender.consumers.get(route.index) else {
+            return Err(SendError::NoConsumer(message, route.index));
+        };
+
+        // The message is only handed over once capacity is reserved, so it can be returned on timeout.
+        match timeout(duration, consumer.clone().reserve_owned(route)).await {
+            Ok(Some(permit)) => permit.send(message),
+            Ok(None) => Err(SendError::ChannelClosed(message, route.index)),
+            Err(_) => Err(SendError::Timeout(message, route.index)),
+        }
+    }
+
+    /// Attempts to send a message without waiting, like [`Sender::try_send`].
+    pub fn try_send(&self, id: ID, message: T) -> Result<(), SendError<T>> {
+        self.sender.try_send(id, message)
+    }
+}
+
+impl<ID, T, S> Clone for TimeoutSender<ID, T, S>
+where
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            timeout: self.timeout,
+        }
+    }
+}
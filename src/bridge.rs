@@ -0,0 +1,79 @@
+use std::{
+    future::poll_fn,
+    sync::mpsc::{self, SyncSender},
+    thread::{self, JoinHandle},
+};
+
+use crate::{StickyReceiver, util::block_on};
+
+/// Pump of a receiver mirrored into a [`std::sync::mpsc::Receiver`], returned by [`bridge_to_std`].
+pub struct StdBridge<R>
+where
+    R: StickyReceiver,
+{
+    thread: JoinHandle<(R, Option<R::Item>)>,
+}
+
+impl<R> StdBridge<R>
+where
+    R: StickyReceiver,
+{
+    /// Returns `true` once the pump has stopped.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Waits for the pump to stop and returns the receiver, together with the message that could not be handed off if
+    /// the stop was caused by the synchronous receiver being dropped.
+    ///
+    /// This blocks the calling thread; do not call it from asynchronous code.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the receiver panicked while it was polled.
+    pub fn join(self) -> (R, Option<R::Item>) {
+        match self.thread.join() {
+            Ok(stopped) => stopped,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+/// Mirrors `receiver` into a [`std::sync::mpsc::Receiver`] for synchronous components, such as legacy thread pools.
+///
+/// A dedicated thread moves messages from `receiver` into a [`sync_channel`](mpsc::sync_channel) of `bound` messages,
+/// in the order they are received. Once the handoff is full the thread waits, so `receiver` stops being drained and
+/// the channel's backpressure reaches its senders as usual. With a `bound` of zero every message is handed over
+/// directly.
+///
+/// The pump stops once `receiver` is closed and empty, after which the synchronous receiver reports disconnection, or
+/// when the synchronous receiver is dropped. Receivers that need a runtime to make progress, for example adapters with
+/// timers, cannot be bridged.
+///
+/// # Panics
+///
+/// Panics if the pump thread cannot be spawned.
+pub fn bridge_to_std<R>(receiver: R, bound: usize) -> (mpsc::Receiver<R::Item>, StdBridge<R>)
+where
+    R: StickyReceiver + Send + 'static,
+    R::Item: Send + 'static,
+{
+    let (handoff, std_receiver) = mpsc::sync_channel(bound);
+    let thread = thread::Builder::new()
+        .name("sticky-std-bridge".into())
+        .spawn(move || pump(receiver, handoff))
+        .expect("failed to spawn the bridge thread");
+    (std_receiver, StdBridge { thread })
+}
+
+fn pump<R>(mut receiver: R, handoff: SyncSender<R::Item>) -> (R, Option<R::Item>)
+where
+    R: StickyReceiver,
+{
+    while let Some(Some(message)) = block_on(poll_fn(|cx| receiver.poll_recv(cx)), None) {
+        if let Err(mpsc::SendError(message)) = handoff.send(message) {
+            return (receiver, Some(message));
+        }
+    }
+    (receiver, None)
+}
@@ -0,0 +1,100 @@
+use std::{
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{sync::Semaphore, time::Instant};
+
+/// Burst usage of a single bounded consumer, as returned by [`Sender::burst_report`](crate::Sender::burst_report) and
+/// [`ChannelAdmin::burst_report`](crate::ChannelAdmin::burst_report) for channels built with
+/// [`burst`](crate::StickyChannelBuilder::burst).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BurstReport {
+    /// Number of slots the consumer may use beyond its capacity, the ceiling minus the capacity it was built with.
+    pub burst_slots: usize,
+    /// Burst slots taken by queued messages.
+    pub in_use: usize,
+    /// Burst slots that may still be taken before the allowance has to refill.
+    pub allowance: usize,
+    /// Messages queued in a burst slot since the channel was built.
+    pub bursts: u64,
+}
+
+/// Slots a bounded consumer may take beyond its capacity, limited by an allowance that refills over time.
+///
+/// Taking a burst slot spends one unit of the allowance, which refills linearly from empty to `size` within `refill`.
+/// Short spikes are absorbed by the slots, while under sustained overload the allowance runs dry and senders wait for
+/// regular slots again.
+pub(crate) struct Burst {
+    slots: Semaphore,
+    size: usize,
+    refill: Duration,
+    allowance: Mutex<Allowance>,
+    bursts: AtomicU64,
+}
+
+struct Allowance {
+    units: f64,
+    refilled: Instant,
+}
+
+impl Burst {
+    pub(crate) fn new(size: usize, refill: Duration) -> Self {
+        Self {
+            slots: Semaphore::new(size),
+            size,
+            refill,
+            allowance: Mutex::new(Allowance {
+                units: size as f64,
+                refilled: Instant::now(),
+            }),
+            bursts: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the allowance after refilling it for the time passed since the last refill.
+    fn allowance(&self) -> std::sync::MutexGuard<'_, Allowance> {
+        let mut allowance = self.allowance.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(allowance.refilled);
+        allowance.refilled = now;
+        allowance.units = if self.refill.is_zero() {
+            self.size as f64
+        } else {
+            let refilled = self.size as f64 * elapsed.as_secs_f64() / self.refill.as_secs_f64();
+            (allowance.units + refilled).min(self.size as f64)
+        };
+        allowance
+    }
+
+    /// Takes a burst slot if one is free and the allowance is not spent.
+    pub(crate) fn try_acquire(&self) -> bool {
+        let Ok(permit) = self.slots.try_acquire() else {
+            return false;
+        };
+        let mut allowance = self.allowance();
+        if allowance.units < 1.0 {
+            return false;
+        }
+        allowance.units -= 1.0;
+        permit.forget();
+        self.bursts.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    pub(crate) fn release(&self, slots: usize) {
+        self.slots.add_permits(slots);
+    }
+
+    pub(crate) fn report(&self) -> BurstReport {
+        BurstReport {
+            burst_slots: self.size,
+            in_use: self.size - self.slots.available_permits(),
+            allowance: self.allowance().units as usize,
+            bursts: self.bursts.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    future::poll_fn,
+    hash::{BuildHasher, Hash},
+    num::NonZeroUsize,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use tokio::sync::{Semaphore, TryAcquireError};
+
+use crate::{
+    DefaultBuildHasher, SendError, StickyReceiver, TryRecvError, UnboundedReceiver,
+    UnboundedSender, unbounded_sticky_channel_with_hasher,
+};
+
+/// Byte budget of a single consumer.
+struct Budget {
+    bytes: Semaphore,
+    capacity: usize,
+}
+
+impl Budget {
+    fn new(capacity: usize) -> Self {
+        Self {
+            bytes: Semaphore::new(capacity),
+            capacity,
+        }
+    }
+
+    /// Number of budget bytes a message takes.
+    ///
+    /// Messages larger than the whole budget take all of it, so that they can still be sent into an empty channel.
+    fn cost(&self, message: &Bytes) -> u32 {
+        u32::try_from(message.len().min(self.capacity)).unwrap_or(u32::MAX)
+    }
+
+    fn release(&self, bytes: u32) {
+        if bytes > 0 {
+            self.bytes.add_permits(bytes as usize);
+        }
+    }
+}
+
+/// Creates a sticky channel for [`Bytes`] payloads whose capacity is a byte budget per consumer, with the default
+/// hasher ([`DefaultBuildHasher`]).
+///
+/// See [`sticky_bytes_channel_with_hasher`] for details.
+pub fn sticky_bytes_channel<ID>(
+    num_consumers: NonZeroUsize,
+    budget: usize,
+) -> (BytesSender<ID>, Vec<BytesReceiver>)
+where
+    ID: Hash,
+{
+    sticky_bytes_channel_with_hasher(num_consumers, budget, DefaultBuildHasher::default())
+}
+
+/// Creates a sticky channel for [`Bytes`] payloads whose capacity is a byte budget per consumer.
+///
+/// Each consumer can hold up to `budget` bytes of queued payloads. Payloads are moved through the channel without
+/// being copied, and [`BytesReceiver::recv_many`] collects them into a `Vec<Bytes>` that can be handed to a vectored
+/// write as is.
+///
+/// # Panics
+///
+/// Panics if `budget` is zero.
+pub fn sticky_bytes_channel_with_hasher<ID, S>(
+    num_consumers: NonZeroUsize,
+    budget: usize,
+    build_hasher: S,
+) -> (BytesSender<ID, S>, Vec<BytesReceiver>)
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    assert!(budget > 0, "bytes sticky channel requires budget > 0");
+
+    let (sender, receivers) = unbounded_sticky_channel_with_hasher(num_consumers, build_hasher);
+    let budgets: Vec<_> = receivers
+        .iter()
+        .map(|_| Arc::new(Budget::new(budget)))
+        .collect();
+
+    let receivers = receivers
+        .into_iter()
+        .zip(&budgets)
+        .map(|(receiver, budget)| BytesReceiver {
+            receiver,
+            budget: budget.clone(),
+        })
+        .collect();
+
+    (BytesSender { sender, budgets }, receivers)
+}
+
+/// Send [`Bytes`] payloads to the associated [`BytesReceiver`]s.
+pub struct BytesSender<ID, S = DefaultBuildHasher> {
+    sender: UnboundedSender<ID, Bytes, S>,
+    budgets: Vec<Arc<Budget>>,
+}
+
+impl<ID, S> BytesSender<ID, S>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a payload to the consumer identified by `id`, waiting until its byte budget has room for it.
+    ///
+    /// If the receive half of the channel is closed, this function returns an error. The error includes the payload
+    /// passed to `send`.
+    pub async fn send(&self, id: ID, message: Bytes) -> Result<(), SendError<Bytes>> {
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+        let Some(budget) = self.budgets.get(route.index) else {
+            return Err(SendError::NoConsumer(message, route.index));
+        };
+
+        let cost = budget.cost(&message);
+        match budget.bytes.acquire_many(cost).await {
+            Ok(permit) => permit.forget(),
+            Err(_) => return Err(SendError::ChannelClosed(message, route.index)),
+        }
+
+        self.sender
+            .send_route(message, route)
+            .inspect_err(|_| budget.release(cost))
+    }
+
+    /// Sends a payload to the consumer identified by `id` without waiting.
+    ///
+    /// This method returns [`SendError::ChannelFull`] if the byte budget of the target consumer has no room for it.
+    pub fn try_send(&self, id: ID, message: Bytes) -> Result<(), SendError<Bytes>> {
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+        let Some(budget) = self.budgets.get(route.index) else {
+            return Err(SendError::NoConsumer(message, route.index));
+        };
+
+        let cost = budget.cost(&message);
+        match budget.bytes.try_acquire_many(cost) {
+            Ok(permit) => permit.forget(),
+            Err(TryAcquireError::NoPermits) => {
+                return Err(SendError::ChannelFull(message, route.index));
+            }
+            Err(TryAcquireError::Closed) => {
+                return Err(SendError::ChannelClosed(message, route.index));
+            }
+        }
+
+        self.sender
+            .send_route(message, route)
+            .inspect_err(|_| budget.release(cost))
+    }
+}
+
+impl<ID, S> Clone for BytesSender<ID, S>
+where
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            budgets: self.budgets.clone(),
+        }
+    }
+}
+
+/// Receive [`Bytes`] payloads from the associated [`BytesSender`].
+pub struct BytesReceiver {
+    receiver: UnboundedReceiver<Bytes>,
+    budget: Arc<Budget>,
+}
+
+impl BytesReceiver {
+    /// Receives the next payload for this receiver.
+    ///
+    /// See [`Receiver::recv`](crate::Receiver::recv) for details.
+    pub async fn recv(&mut self) -> Option<Bytes> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Receives the next payloads for this receiver and extends `buffer`.
+    ///
+    /// The payloads can be passed to a vectored write without copying them. See
+    /// [`Receiver::recv_many`](crate::Receiver::recv_many) for details.
+    pub async fn recv_many(&mut self, buffer: &mut Vec<Bytes>, limit: usize) -> usize {
+        let start = buffer.len();
+        let count = self.receiver.recv_many(buffer, limit).await;
+
+        let cost = buffer[start..].iter().fold(0u32, |cost, message| {
+            cost.saturating_add(self.budget.cost(message))
+        });
+        self.budget.release(cost);
+
+        count
+    }
+
+    /// Polls to receive the next payload for this receiver.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<Bytes>> {
+        self.receiver.poll_recv(cx).map(|message| {
+            message.inspect(|message| self.budget.release(self.budget.cost(message)))
+        })
+    }
+
+    /// Tries to receive the next payload for this receiver.
+    pub fn try_recv(&mut self) -> Result<Bytes, TryRecvError> {
+        self.receiver
+            .try_recv()
+            .inspect(|message| self.budget.release(self.budget.cost(message)))
+    }
+
+    /// Returns the number of bytes the byte budget of this receiver currently has room for.
+    pub fn available_bytes(&self) -> usize {
+        self.budget.bytes.available_permits()
+    }
+
+    /// Closes the receiver without dropping it, so that buffered payloads can still be drained.
+    pub fn close(&mut self) {
+        self.receiver.close();
+        self.budget.bytes.close();
+    }
+}
+
+impl Drop for BytesReceiver {
+    fn drop(&mut self) {
+        self.budget.bytes.close();
+    }
+}
+
+impl StickyReceiver for BytesReceiver {
+    type Item = Bytes;
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<Bytes>> {
+        BytesReceiver::poll_recv(self, cx)
+    }
+
+    fn try_recv(&mut self) -> Result<Bytes, TryRecvError> {
+        BytesReceiver::try_recv(self)
+    }
+
+    fn close(&mut self) {
+        BytesReceiver::close(self)
+    }
+}
@@ -0,0 +1,225 @@
+use std::sync::{Arc, atomic::AtomicUsize};
+
+use tokio::sync::watch;
+
+use crate::{
+    Admin, BurstReport, ChannelHealth, DepthSnapshot, LatencyReport, Reconciliation, Totals,
+    bounded::Slots,
+    envelope::{Payload, inject},
+    health::Health,
+    latency::LatencyHistogram,
+    partition::PartitionTable,
+    queue::WeakQueue,
+    totals::Counters,
+};
+
+/// Handle to the administrative operations of a channel, returned by
+/// [`StickyChannelBuilder::build_with_admin`](crate::StickyChannelBuilder::build_with_admin) and
+/// [`UnboundedStickyChannelBuilder::build_with_admin`](crate::UnboundedStickyChannelBuilder::build_with_admin).
+///
+/// The partition [`Admin`], capacity changes and [`close_all`](ChannelAdmin::close_all) are only available through this
+/// handle, so code that only holds a sender clone cannot move IDs between consumers or shut the channel down. The
+/// handle does not keep the channel open: once every sender is dropped, [`finish`](ChannelAdmin::finish) and
+/// [`close_all`](ChannelAdmin::close_all) do nothing and the counters stop changing.
+pub struct ChannelAdmin<T> {
+    queues: Vec<WeakQueue<T>>,
+    depths: Vec<Arc<AtomicUsize>>,
+    latencies: Vec<Option<Arc<LatencyHistogram>>>,
+    /// Capacity accounting of every consumer, for bounded channels.
+    slots: Option<Vec<Arc<Slots>>>,
+    counters: Arc<Counters>,
+    partitions: Option<Arc<PartitionTable>>,
+    health: Option<Arc<Health>>,
+}
+
+/// Handles to the parts of a single consumer that a [`ChannelAdmin`] operates on.
+pub(crate) struct ConsumerHandles<T> {
+    pub(crate) queue: WeakQueue<T>,
+    pub(crate) depth: Arc<AtomicUsize>,
+    pub(crate) latency: Option<Arc<LatencyHistogram>>,
+    pub(crate) slots: Option<Arc<Slots>>,
+}
+
+impl<T> ChannelAdmin<T> {
+    pub(crate) fn new(
+        consumers: impl Iterator<Item = ConsumerHandles<T>>,
+        counters: Arc<Counters>,
+        partitions: Option<Arc<PartitionTable>>,
+        health: Option<Arc<Health>>,
+    ) -> Self {
+        let mut queues = Vec::new();
+        let mut depths = Vec::new();
+        let mut latencies = Vec::new();
+        let mut slots = Some(Vec::new());
+        for consumer in consumers {
+            queues.push(consumer.queue);
+            depths.push(consumer.depth);
+            latencies.push(consumer.latency);
+            slots = slots.zip(consumer.slots).map(|(mut slots, consumer)| {
+                slots.push(consumer);
+                slots
+            });
+        }
+
+        Self {
+            queues,
+            depths,
+            latencies,
+            slots,
+            counters,
+            partitions,
+            health,
+        }
+    }
+
+    /// Returns the number of consumers of the channel.
+    pub fn consumers(&self) -> usize {
+        self.queues.len()
+    }
+
+    /// Returns a handle to reassign partitions at runtime, for channels built with partitions.
+    pub fn partitions(&self) -> Option<Admin> {
+        self.partitions.clone().map(Admin::new)
+    }
+
+    /// Returns the number of regular slots of consumer `index`, or `None` for unbounded channels.
+    ///
+    /// Starts at the capacity the channel was built with and follows [`grow_capacity`](Self::grow_capacity) and
+    /// [`shrink_capacity`](Self::shrink_capacity). Reserved slots are not included.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of range.
+    pub fn capacity(&self, index: usize) -> Option<usize> {
+        Some(self.consumer_slots(index)?.capacity())
+    }
+
+    /// Adds `additional` regular slots to consumer `index` at once, waking up senders waiting for capacity.
+    ///
+    /// Returns `false` for unbounded channels, which have no capacity to grow.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of range.
+    pub fn grow_capacity(&self, index: usize, additional: usize) -> bool {
+        match self.consumer_slots(index) {
+            Some(slots) => {
+                slots.grow(additional);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes `by` regular slots from consumer `index`, waiting until its receiver has drained enough messages to
+    /// free them.
+    ///
+    /// Messages already queued are never dropped: the slots are taken once they are free, and senders that start
+    /// waiting for capacity meanwhile queue up behind the shrink. [`capacity`](Self::capacity) reports the lowered
+    /// capacity while the shrink waits.
+    ///
+    /// Returns `false` without changing the capacity for unbounded channels, if the consumer would be left without
+    /// regular slots, or if its receiver is closed before the slots are free. Cancelling the shrink also leaves the
+    /// capacity unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of range.
+    pub async fn shrink_capacity(&self, index: usize, by: usize) -> bool {
+        match self.consumer_slots(index) {
+            Some(slots) => slots.shrink(by).await,
+            None => false,
+        }
+    }
+
+    fn consumer_slots(&self, index: usize) -> Option<&Slots> {
+        assert!(
+            index < self.queues.len(),
+            "consumer index {index} out of range"
+        );
+        Some(&self.slots.as_ref()?[index])
+    }
+
+    /// Queues a terminal sentinel to every consumer, like [`Sender::finish`](crate::Sender::finish).
+    ///
+    /// Returns `false` if every sender has been dropped, in which case the receivers see the channel closing instead.
+    pub fn finish(&self) -> bool {
+        let mut finished = false;
+        for queue in &self.queues {
+            if let Some(queue) = queue.upgrade() {
+                finished |= inject(&queue, Payload::Finish);
+            }
+        }
+        finished
+    }
+
+    /// Closes every consumer, as if [`close`](crate::Receiver::close) was called on all receivers.
+    ///
+    /// Sends fail with [`ChannelClosed`](crate::SendError::ChannelClosed) from then on, and senders waiting for
+    /// capacity are woken up with the same error. Receivers still receive the messages sent before the call and then
+    /// return `None`, even while senders are alive.
+    ///
+    /// Returns `false` if the channel was already closed or every sender has been dropped.
+    pub fn close_all(&self) -> bool {
+        let mut closed = false;
+        for (index, queue) in self.queues.iter().enumerate() {
+            if let Some(queue) = queue.upgrade() {
+                closed |= queue.close();
+            }
+            if let Some(slots) = &self.slots {
+                slots[index].close();
+            }
+        }
+        closed
+    }
+
+    /// Samples the queue depth of every consumer at once, see [`Sender::queue_depths`](crate::Sender::queue_depths).
+    pub fn queue_depths(&self) -> DepthSnapshot {
+        DepthSnapshot::sample(self.depths.iter().map(|depth| &**depth))
+    }
+
+    /// Returns the message counts of the whole channel, see [`Totals`].
+    pub fn totals(&self) -> Totals {
+        self.counters.totals()
+    }
+
+    /// Returns how the messages sent to every consumer were accounted for, see
+    /// [`Sender::reconcile`](crate::Sender::reconcile).
+    pub fn reconcile(&self) -> Vec<Reconciliation> {
+        self.counters.reconcile()
+    }
+
+    /// Returns a snapshot of the queueing delays of every consumer, for channels built with `track_lag`.
+    pub fn latency_report(&self) -> Option<Vec<LatencyReport>> {
+        self.latencies
+            .iter()
+            .map(|latency| latency.as_ref().map(|latency| latency.report()))
+            .collect()
+    }
+
+    /// Returns the burst usage of every consumer, for bounded channels built with
+    /// [`burst`](crate::StickyChannelBuilder::burst).
+    pub fn burst_report(&self) -> Option<Vec<BurstReport>> {
+        self.slots
+            .as_ref()?
+            .iter()
+            .map(|slots| slots.burst_report())
+            .collect()
+    }
+
+    /// Returns a watch of the channel's health, for channels built with `watch_health`.
+    pub fn health(&self) -> Option<watch::Receiver<ChannelHealth>> {
+        self.health.as_ref().map(|health| health.subscribe())
+    }
+
+    /// Returns a handle to the channel's metrics, to publish them through a
+    /// [`PrometheusRegistry`](crate::PrometheusRegistry).
+    #[cfg(feature = "prometheus")]
+    pub fn metrics(&self) -> crate::ChannelMetrics {
+        crate::ChannelMetrics::new(
+            self.counters.clone(),
+            self.depths.clone(),
+            self.latencies.clone(),
+        )
+    }
+}
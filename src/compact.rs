@@ -0,0 +1,236 @@
+use std::{
+    collections::{BTreeSet, HashMap, hash_map},
+    sync::{
+        Mutex, MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+type Shard<T> = Mutex<HashMap<u64, Entry<T>>>;
+
+/// Position of an ID in the eviction order: the policy's rank, a tie-breaking tick and the ID hash.
+type Rank = (u64, u64, u64);
+
+/// Which ID a channel built with [`max_tracked_keys`](crate::StickyChannelBuilder::max_tracked_keys) forgets when
+/// a new ID would exceed the limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Forgets the ID sent least recently.
+    Lru,
+    /// Forgets the ID sent least often since it was last tracked, and among those the one sent least recently.
+    Lfu,
+}
+
+/// Most recent message of every ID hash sent on a channel, for channels built with `last_values`.
+///
+/// Messages are cloned with a function pointer taken when the channel is built, so that consumers do not require
+/// `T: Clone`. The cache is sharded by hash to keep senders of different IDs from contending on a single lock.
+///
+/// With a TTL, messages of IDs that have not been sent for that long are treated as gone and swept out of the cache by
+/// the next message recorded after at least one TTL since the previous sweep. With a limit, the IDs are also kept in
+/// eviction order, so recording a new ID beyond the limit evicts another ID in logarithmic time. The order is locked
+/// before any shard.
+pub(crate) struct LastValues<T> {
+    shards: Box<[Shard<T>]>,
+    clone: fn(&T) -> T,
+    ttl: Option<Duration>,
+    swept: Mutex<Instant>,
+    limit: Option<(usize, EvictionPolicy)>,
+    /// IDs ordered by their eviction rank, lowest first. Only kept with a limit.
+    order: Option<Mutex<BTreeSet<Rank>>>,
+    ticks: AtomicU64,
+    evictions: AtomicU64,
+}
+
+struct Entry<T> {
+    /// Index of the consumer the message was sent to.
+    index: usize,
+    sent: Instant,
+    /// Number of messages recorded for the ID since it was added to the cache.
+    sends: u64,
+    /// Order in which the message was recorded among all messages of the cache.
+    tick: u64,
+    message: T,
+}
+
+impl<T> LastValues<T> {
+    pub(crate) fn new(
+        shards: usize,
+        clone: fn(&T) -> T,
+        ttl: Option<Duration>,
+        limit: Option<(usize, EvictionPolicy)>,
+    ) -> Self {
+        Self {
+            shards: (0..shards).map(|_| Mutex::default()).collect(),
+            clone,
+            ttl,
+            swept: Mutex::new(Instant::now()),
+            limit,
+            order: limit.map(|_| Mutex::default()),
+            ticks: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    fn lock_order(&self) -> Option<MutexGuard<'_, BTreeSet<Rank>>> {
+        self.order.as_ref().map(|order| order.lock().unwrap())
+    }
+
+    fn rank(&self, hash: u64, entry: &Entry<T>) -> Rank {
+        match self.limit {
+            Some((_, EvictionPolicy::Lfu)) => (entry.sends, entry.tick, hash),
+            _ => (0, entry.tick, hash),
+        }
+    }
+
+    fn is_live(&self, entry: &Entry<T>, now: Instant) -> bool {
+        self.ttl
+            .is_none_or(|ttl| now.duration_since(entry.sent) < ttl)
+    }
+
+    /// Drops the messages of idle IDs if a TTL has passed since the last sweep. Skipped while another sender sweeps.
+    fn sweep(&self, now: Instant) {
+        let Some(ttl) = self.ttl else {
+            return;
+        };
+        let Ok(mut swept) = self.swept.try_lock() else {
+            return;
+        };
+        if now.duration_since(*swept) < ttl {
+            return;
+        }
+        *swept = now;
+
+        let mut order = self.lock_order();
+        for shard in &self.shards {
+            shard.lock().unwrap().retain(|&hash, entry| {
+                let live = self.is_live(entry, now);
+                if let (false, Some(order)) = (live, &mut order) {
+                    order.remove(&self.rank(hash, entry));
+                }
+                live
+            });
+        }
+    }
+
+    fn shard(&self, hash: u64) -> &Shard<T> {
+        &self.shards[(hash % self.shards.len() as u64) as usize]
+    }
+
+    /// Clones a message with the function the cache was built with, to [`record`](Self::record) it once it was sent.
+    pub(crate) fn copy(&self, message: &T) -> T {
+        (self.clone)(message)
+    }
+
+    /// Replaces the cached message of the ID with `hash` by `message`, sent to consumer `index`.
+    pub(crate) fn record(&self, hash: u64, index: usize, message: T) {
+        let sent = Instant::now();
+        let mut order = self.lock_order();
+        let tick = self.ticks.fetch_add(1, Ordering::Relaxed);
+        let rank = match self.shard(hash).lock().unwrap().entry(hash) {
+            hash_map::Entry::Occupied(mut entry) => {
+                let entry = entry.get_mut();
+                if let Some(order) = &mut order {
+                    order.remove(&self.rank(hash, entry));
+                }
+                entry.sends = if self.is_live(entry, sent) {
+                    entry.sends + 1
+                } else {
+                    1
+                };
+                entry.index = index;
+                entry.sent = sent;
+                entry.tick = tick;
+                entry.message = message;
+                self.rank(hash, entry)
+            }
+            hash_map::Entry::Vacant(entry) => {
+                let entry = entry.insert(Entry {
+                    index,
+                    sent,
+                    sends: 1,
+                    tick,
+                    message,
+                });
+                self.rank(hash, entry)
+            }
+        };
+
+        if let Some(mut order) = order {
+            order.insert(rank);
+            self.evict(&mut order, hash);
+        }
+        self.sweep(sent);
+    }
+
+    /// Evicts the lowest ranked IDs other than `added` while the cache holds more IDs than its limit.
+    fn evict(&self, order: &mut BTreeSet<Rank>, added: u64) {
+        let Some((limit, _)) = self.limit else {
+            return;
+        };
+
+        while order.len() > limit {
+            // Ranks are unique, so at most the added ID is skipped.
+            let Some(&victim) = order.iter().find(|&&(_, _, hash)| hash != added) else {
+                return;
+            };
+            order.remove(&victim);
+            self.shard(victim.2).lock().unwrap().remove(&victim.2);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the number of IDs evicted because of the limit.
+    pub(crate) fn evictions(&self) -> u64 {
+        self.evictions.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn get(&self, hash: u64) -> Option<T> {
+        let now = Instant::now();
+        self.shard(hash)
+            .lock()
+            .unwrap()
+            .get(&hash)
+            .filter(|entry| self.is_live(entry, now))
+            .map(|entry| (self.clone)(&entry.message))
+    }
+
+    pub(crate) fn remove(&self, hash: u64) -> Option<T> {
+        let now = Instant::now();
+        let mut order = self.lock_order();
+        let entry = self.shard(hash).lock().unwrap().remove(&hash)?;
+        if let Some(order) = &mut order {
+            order.remove(&self.rank(hash, &entry));
+        }
+        Some(entry)
+            .filter(|entry| self.is_live(entry, now))
+            .map(|entry| entry.message)
+    }
+
+    /// Returns the number of IDs in the cache, including idle ones that have not been swept yet.
+    pub(crate) fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+
+    /// Returns clones of the cached messages last sent to consumer `index`, in no particular order.
+    pub(crate) fn snapshot(&self, index: usize) -> Vec<T> {
+        let now = Instant::now();
+        let mut messages = Vec::new();
+        for shard in &self.shards {
+            let shard = shard.lock().unwrap();
+            messages.extend(
+                shard
+                    .values()
+                    .filter(|entry| entry.index == index && self.is_live(entry, now))
+                    .map(|entry| (self.clone)(&entry.message)),
+            );
+        }
+        messages
+    }
+}
@@ -0,0 +1,478 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt,
+    future::{Future, poll_fn},
+    num::{NonZeroU32, NonZeroUsize},
+    pin::pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    task::Poll,
+    time::Duration,
+};
+
+use tokio::{
+    sync::Notify,
+    task::{self, JoinError, JoinHandle, JoinSet},
+};
+
+use crate::{
+    Admin, Backoff, PartitionMove, StickyReceiver,
+    util::{spawn, spawn_in},
+};
+
+type RestartHook = Arc<dyn Fn(&Restart) + Send + Sync>;
+type PanicHook<T> = (fn(&T) -> T, Arc<dyn Fn(HandlerPanic<T>) + Send + Sync>);
+
+/// Progress of a single consumer driven by [`consume_with`], as returned by [`ConsumeHandle::progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConsumeProgress {
+    /// Number of messages received and passed to the handler.
+    pub received: u64,
+    /// Number of handler calls that completed.
+    pub completed: u64,
+    /// Number of handler calls that panicked.
+    pub panicked: u64,
+}
+
+impl ConsumeProgress {
+    /// Returns the number of handler calls still running.
+    pub fn in_flight(&self) -> u64 {
+        self.received - self.completed - self.panicked
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    received: AtomicU64,
+    completed: AtomicU64,
+    panicked: AtomicU64,
+}
+
+impl Counters {
+    fn load(&self) -> ConsumeProgress {
+        // Read in reverse order of updating, so that `in_flight` never underflows.
+        let completed = self.completed.load(Ordering::Acquire);
+        let panicked = self.panicked.load(Ordering::Acquire);
+        ConsumeProgress {
+            received: self.received.load(Ordering::Acquire),
+            completed,
+            panicked,
+        }
+    }
+}
+
+struct Stop {
+    stopped: AtomicBool,
+    notify: Notify,
+}
+
+/// Handle to the consumers driven by [`consume_with`].
+///
+/// Dropping the handle does not stop the consumers; they keep running until their receivers are closed and empty.
+pub struct ConsumeHandle<R> {
+    counters: Vec<Arc<Counters>>,
+    stop: Arc<Stop>,
+    tasks: Vec<JoinHandle<R>>,
+}
+
+impl<R> ConsumeHandle<R> {
+    /// Returns the progress of every consumer, indexed like the receivers.
+    pub fn progress(&self) -> Vec<ConsumeProgress> {
+        self.counters
+            .iter()
+            .map(|counters| counters.load())
+            .collect()
+    }
+
+    /// Stops receiving new messages and waits for the handler calls in flight to finish.
+    ///
+    /// Returns the receivers in their original order, with the messages that were not received yet still queued, so
+    /// they can be drained with [`drain_all`](crate::drain_all) or consumed again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a receiver panicked while it was polled.
+    pub async fn stop(self) -> Vec<R> {
+        self.stop.stopped.store(true, Ordering::Release);
+        self.stop.notify.notify_waiters();
+        self.join().await
+    }
+
+    /// Waits until every receiver is closed and empty and all handler calls have finished.
+    ///
+    /// Returns the receivers in their original order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a receiver panicked while it was polled.
+    pub async fn join(self) -> Vec<R> {
+        let mut receivers = Vec::with_capacity(self.tasks.len());
+        for task in self.tasks {
+            match task.await {
+                Ok(receiver) => receivers.push(receiver),
+                Err(err) => std::panic::resume_unwind(err.into_panic()),
+            }
+        }
+        receivers
+    }
+}
+
+/// Restart of a consumer after a panic in its handler, as reported to [`Supervision::on_restart`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Restart {
+    /// Index of the consumer.
+    pub consumer: usize,
+    /// Number of handler calls of the consumer that panicked in a row, including this one. A handler call that
+    /// completes resets it.
+    pub consecutive_panics: u32,
+    /// Time the consumer pauses before it receives its next message.
+    pub backoff: Duration,
+    /// Partitions moved to another consumer because of this restart, see [`Supervision::reassign_after`].
+    pub reassigned: Vec<PartitionMove>,
+}
+
+/// Handler call that panicked, as given to [`Supervision::on_panic`].
+#[derive(Debug)]
+pub struct HandlerPanic<T> {
+    /// Index of the consumer that received the message.
+    pub consumer: usize,
+    /// Copy of the message the handler panicked on, taken before the handler was called.
+    pub message: T,
+    /// Payload the handler panicked with.
+    pub payload: Box<dyn Any + Send>,
+}
+
+impl<T> HandlerPanic<T> {
+    /// Returns the panic message, if the handler panicked with a string.
+    pub fn panic_message(&self) -> Option<&str> {
+        self.payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| self.payload.downcast_ref::<String>().map(String::as_str))
+    }
+}
+
+/// How [`consume_supervised`] reacts to panics in the handler.
+///
+/// A panic never stops a consumer: it restarts right away, attached to the same receiver. By default it carries on
+/// with its next message without pausing.
+pub struct Supervision<T> {
+    backoff: Option<Backoff>,
+    reassign: Option<(u32, Admin)>,
+    on_restart: Option<RestartHook>,
+    on_panic: Option<PanicHook<T>>,
+}
+
+impl<T> Supervision<T> {
+    /// Creates a supervision that restarts consumers without pausing.
+    pub fn new() -> Self {
+        Self {
+            backoff: None,
+            reassign: None,
+            on_restart: None,
+            on_panic: None,
+        }
+    }
+
+    /// Pauses a consumer after a panic according to `backoff`, counting the panics in a row.
+    ///
+    /// Messages keep queueing for the consumer while it pauses, so a handler that fails on every message does not spin.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = Some(backoff);
+        self
+    }
+
+    /// Moves the partitions of a consumer to the next consumer once `panics` handler calls in a row have panicked.
+    ///
+    /// Only applies to channels built with partitions, whose [`Admin`] is given. Partitions are moved like
+    /// [`Admin::reassign`]: messages already queued are still received by the failing consumer.
+    pub fn reassign_after(mut self, panics: NonZeroU32, admin: Admin) -> Self {
+        self.reassign = Some((panics.get(), admin));
+        self
+    }
+
+    /// Calls `hook` whenever a consumer restarts after a panic, before it pauses.
+    pub fn on_restart<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Restart) + Send + Sync + 'static,
+    {
+        self.on_restart = Some(Arc::new(hook));
+        self
+    }
+
+    /// Feeds `panic` to the hook set by [`on_panic`](Self::on_panic).
+    fn report(&self, panic: HandlerPanic<T>) {
+        if let Some((_, hook)) = &self.on_panic {
+            hook(panic);
+        }
+    }
+
+    /// Handles a panic of consumer `index`, returning how long it pauses.
+    fn restart(&self, consumer: usize, consecutive_panics: u32) -> Duration {
+        let backoff = self.backoff.map_or(Duration::ZERO, |backoff| {
+            backoff.delay(consecutive_panics as usize - 1)
+        });
+
+        let reassigned = match &self.reassign {
+            Some((panics, admin)) if consecutive_panics == *panics && admin.consumers() > 1 => {
+                let to = (consumer + 1) % admin.consumers();
+                (0..admin.partitions())
+                    .filter(|&partition| admin.consumer_of(partition) == consumer)
+                    .map(|partition| PartitionMove {
+                        partition,
+                        from: admin.reassign(partition, to),
+                        to,
+                        drained: 0,
+                        left_behind: admin.backlog(partition),
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+
+        if let Some(hook) = &self.on_restart {
+            hook(&Restart {
+                consumer,
+                consecutive_panics,
+                backoff,
+                reassigned,
+            });
+        }
+        backoff
+    }
+}
+
+impl<T: Clone> Supervision<T> {
+    /// Calls `hook` with the message and panic payload of every handler call that panics, for example to forward the
+    /// message to a dead-letter queue.
+    ///
+    /// Each message is cloned before it is handed to the handler, and the copy is dropped once the call completes.
+    /// `hook` runs on the consumer task, before [`on_restart`](Self::on_restart).
+    pub fn on_panic<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(HandlerPanic<T>) + Send + Sync + 'static,
+    {
+        self.on_panic = Some((T::clone, Arc::new(hook)));
+        self
+    }
+}
+
+impl<T> Default for Supervision<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for Supervision<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Supervision")
+            .field("backoff", &self.backoff)
+            .field(
+                "reassign_after",
+                &self.reassign.as_ref().map(|(panics, _)| panics),
+            )
+            .field("on_panic", &self.on_panic.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Consumes every receiver of a channel on its own task, calling `handler` for each message.
+///
+/// Up to `concurrency_per_consumer` handler calls of a single consumer run at the same time, each on its own task.
+/// With a concurrency of `1` a consumer handles its messages one after another in the order they were received, which
+/// keeps the per-ID ordering of the channel; with more, messages of the same ID may be handled concurrently and finish
+/// out of order.
+///
+/// A panic in `handler` is caught and counted in [`ConsumeProgress::panicked`]; the consumer moves on to its next
+/// message. Works for the receivers of both channel flavours as well as for receiver adapters. See
+/// [`consume_supervised`] to pause or report consumers after panics.
+///
+/// # Panics
+///
+/// Panics if it is not called from within a Tokio runtime.
+pub fn consume_with<R, F, Fut>(
+    receivers: Vec<R>,
+    concurrency_per_consumer: NonZeroUsize,
+    handler: F,
+) -> ConsumeHandle<R>
+where
+    R: StickyReceiver + Send + 'static,
+    R::Item: Send + 'static,
+    F: Fn(R::Item) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    consume_supervised(
+        receivers,
+        concurrency_per_consumer,
+        Supervision::new(),
+        handler,
+    )
+}
+
+/// Like [`consume_with`], but reacts to panics in `handler` as configured by `supervision`.
+///
+/// After a handler call panics, its consumer is restarted: `supervision` is told about it, may move the consumer's
+/// partitions elsewhere, and decides how long the consumer pauses before it receives its next message. Handler calls
+/// already running are not affected, and the consumer keeps receiving the messages of its partitions. The message a
+/// handler call panicked on can be recovered with [`Supervision::on_panic`].
+///
+/// # Panics
+///
+/// Panics if it is not called from within a Tokio runtime.
+pub fn consume_supervised<R, F, Fut>(
+    receivers: Vec<R>,
+    concurrency_per_consumer: NonZeroUsize,
+    supervision: Supervision<R::Item>,
+    handler: F,
+) -> ConsumeHandle<R>
+where
+    R: StickyReceiver + Send + 'static,
+    R::Item: Send + 'static,
+    F: Fn(R::Item) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let stop = Arc::new(Stop {
+        stopped: AtomicBool::new(false),
+        notify: Notify::new(),
+    });
+    let supervision = Arc::new(supervision);
+    let mut counters = Vec::with_capacity(receivers.len());
+    let tasks = receivers
+        .into_iter()
+        .enumerate()
+        .map(|(index, receiver)| {
+            let consumer = Consumer {
+                index,
+                counters: Arc::new(Counters::default()),
+                stop: stop.clone(),
+                supervision: supervision.clone(),
+            };
+            counters.push(consumer.counters.clone());
+            spawn(
+                "sticky-channel consumer",
+                consumer.run(receiver, concurrency_per_consumer.get(), handler.clone()),
+            )
+        })
+        .collect();
+
+    ConsumeHandle {
+        counters,
+        stop,
+        tasks,
+    }
+}
+
+/// State of a single consumer task of [`consume_supervised`].
+struct Consumer<T> {
+    index: usize,
+    counters: Arc<Counters>,
+    stop: Arc<Stop>,
+    supervision: Arc<Supervision<T>>,
+}
+
+impl<T> Consumer<T> {
+    async fn run<R, F, Fut>(self, mut receiver: R, concurrency: usize, handler: F) -> R
+    where
+        R: StickyReceiver<Item = T>,
+        F: Fn(T) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut stopped = pin!(self.stop.notify.notified());
+        stopped.as_mut().enable();
+
+        let mut calls = JoinSet::new();
+        let mut copies = HashMap::new();
+        let mut consecutive_panics = 0;
+        while !self.stop.stopped.load(Ordering::Acquire) {
+            let mut backoff = Duration::ZERO;
+            let finished = match calls.try_join_next_with_id() {
+                Some(result) => Some(result),
+                None if calls.len() >= concurrency => calls.join_next_with_id().await,
+                None => None,
+            };
+            let reaped = finished.is_some();
+            if let Some(result) = finished {
+                if self.finish(result, &mut copies) {
+                    consecutive_panics += 1;
+                    backoff = self.supervision.restart(self.index, consecutive_panics);
+                } else {
+                    consecutive_panics = 0;
+                }
+            }
+
+            if !backoff.is_zero() {
+                let mut pause = pin!(tokio::time::sleep(backoff));
+                poll_fn(|cx| {
+                    if stopped.as_mut().poll(cx).is_ready() {
+                        return Poll::Ready(());
+                    }
+                    pause.as_mut().poll(cx)
+                })
+                .await;
+            }
+            if reaped {
+                continue;
+            }
+
+            let message = poll_fn(|cx| {
+                if stopped.as_mut().poll(cx).is_ready() {
+                    return Poll::Ready(None);
+                }
+                receiver.poll_recv(cx)
+            })
+            .await;
+            let Some(message) = message else {
+                break;
+            };
+
+            self.counters.received.fetch_add(1, Ordering::AcqRel);
+            let copy = self
+                .supervision
+                .on_panic
+                .as_ref()
+                .map(|(clone, _)| clone(&message));
+            let call = spawn_in(&mut calls, "sticky-channel handler", handler(message));
+            if let Some(copy) = copy {
+                copies.insert(call.id(), copy);
+            }
+        }
+
+        while let Some(result) = calls.join_next_with_id().await {
+            self.finish(result, &mut copies);
+        }
+        receiver
+    }
+
+    /// Counts a finished handler call and reports it if it panicked, returning `true` if it did.
+    fn finish(
+        &self,
+        result: Result<(task::Id, ()), JoinError>,
+        copies: &mut HashMap<task::Id, T>,
+    ) -> bool {
+        match result {
+            Ok((id, ())) => {
+                copies.remove(&id);
+                self.counters.completed.fetch_add(1, Ordering::AcqRel);
+                false
+            }
+            Err(err) if err.is_panic() => {
+                self.counters.panicked.fetch_add(1, Ordering::AcqRel);
+                if let Some(message) = copies.remove(&err.id()) {
+                    self.supervision.report(HandlerPanic {
+                        consumer: self.index,
+                        message,
+                        payload: err.into_panic(),
+                    });
+                }
+                true
+            }
+            Err(err) => {
+                copies.remove(&err.id());
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::{
+    fmt,
+    future::poll_fn,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use tokio::sync::{
+    mpsc::{UnboundedReceiver as MpscReceiver, UnboundedSender as MpscSender, unbounded_channel},
+    oneshot,
+};
+
+use crate::{CommandError, Event, StickyReceiver, TryRecvError};
+
+type CommandFn<W, E> = Arc<dyn Fn(&mut W) -> Result<(), E> + Send + Sync>;
+
+/// Broadcasts control messages to every [`EventReceiver`] of a channel.
+///
+/// Created with [`control_channel`] or the `build_with_control` method of the channel builders.
+pub struct ControlSender<C> {
+    receivers: Vec<MpscSender<C>>,
+}
+
+impl<C> ControlSender<C>
+where
+    C: Clone,
+{
+    /// Sends a control message to every receiver that is still open.
+    ///
+    /// Returns the number of receivers the message was delivered to, or the message itself if every receiver has been
+    /// closed or dropped.
+    pub fn send(&self, message: C) -> Result<usize, C> {
+        let delivered = self
+            .receivers
+            .iter()
+            .filter(|receiver| receiver.send(message.clone()).is_ok())
+            .count();
+
+        if delivered == 0 {
+            Err(message)
+        } else {
+            Ok(delivered)
+        }
+    }
+}
+
+impl<W, E> ControlSender<Command<W, E>> {
+    /// Runs `command` on every consumer and waits for each of them to acknowledge it.
+    ///
+    /// Every open receiver gets a [`Command`] through the control channel, which the consumer runs on its own state
+    /// with [`Command::run`]. Returns one result per receiver, in the order the receivers were returned, with
+    /// [`CommandError::NotRun`] for consumers whose receiver was closed or that dropped the command without running it.
+    /// Waits as long as a consumer takes to get to the command; bound the wait with a timeout if needed.
+    pub async fn broadcast_fn<F>(&self, command: F) -> Vec<Result<(), CommandError<E>>>
+    where
+        F: Fn(&mut W) -> Result<(), E> + Send + Sync + 'static,
+    {
+        let command: CommandFn<W, E> = Arc::new(command);
+        let acks: Vec<_> = self
+            .receivers
+            .iter()
+            .map(|receiver| {
+                let (ack, acked) = oneshot::channel();
+                let command = Command {
+                    command: command.clone(),
+                    ack,
+                };
+                receiver.send(command).ok().map(|_| acked)
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(acks.len());
+        for acked in acks {
+            let result = match acked {
+                Some(acked) => match acked.await {
+                    Ok(result) => result.map_err(CommandError::Failed),
+                    Err(_) => Err(CommandError::NotRun),
+                },
+                None => Err(CommandError::NotRun),
+            };
+            results.push(result);
+        }
+        results
+    }
+}
+
+/// A command sent to every consumer with [`ControlSender::broadcast_fn`], received as [`Event::Control`].
+pub struct Command<W, E> {
+    command: CommandFn<W, E>,
+    ack: oneshot::Sender<Result<(), E>>,
+}
+
+impl<W, E> Command<W, E> {
+    /// Runs the command on the consumer's `state` and acknowledges it with the outcome.
+    ///
+    /// Returns `true` if the command succeeded.
+    pub fn run(self, state: &mut W) -> bool {
+        let result = (self.command)(state);
+        let succeeded = result.is_ok();
+        // The broadcaster may have stopped waiting, in which case there is nobody left to tell.
+        let _ = self.ack.send(result);
+        succeeded
+    }
+}
+
+impl<W, E> fmt::Debug for Command<W, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Command").finish_non_exhaustive()
+    }
+}
+
+impl<C> Clone for ControlSender<C> {
+    fn clone(&self) -> Self {
+        Self {
+            receivers: self.receivers.clone(),
+        }
+    }
+}
+
+/// Receiver that combines a sticky receiver with the companion control channel.
+///
+/// Control messages are low-volume and do not wait behind queued data: whenever both are available, the control
+/// message is returned first. Control messages are therefore not ordered with respect to data messages, unlike markers
+/// such as [`Event::Barrier`], which are passed through from the underlying receiver.
+pub struct EventReceiver<R, C> {
+    inner: R,
+    control: MpscReceiver<C>,
+}
+
+impl<R, C> EventReceiver<R, C>
+where
+    R: StickyReceiver,
+{
+    /// Receives the next data message, control message or marker.
+    ///
+    /// This method returns `None` once the data channel is closed and all buffered data and control messages have
+    /// been received. Dropping all [`ControlSender`]s does not close the receiver.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe.
+    pub async fn recv_event(&mut self) -> Option<Event<R::Item, C>> {
+        poll_fn(|cx| self.poll_recv_event(cx)).await
+    }
+
+    /// Polls to receive the next data message, control message or marker.
+    pub fn poll_recv_event(&mut self, cx: &mut Context<'_>) -> Poll<Option<Event<R::Item, C>>> {
+        if let Poll::Ready(Some(message)) = self.control.poll_recv(cx) {
+            return Poll::Ready(Some(Event::Control(message)));
+        }
+
+        match self.inner.poll_recv_event(cx) {
+            Poll::Ready(Some(event)) => Poll::Ready(Some(event.with_control())),
+            Poll::Ready(None) => Poll::Ready(self.control.try_recv().ok().map(Event::Control)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    /// Tries to receive the next data message, control message or marker without waiting.
+    pub fn try_recv_event(&mut self) -> Result<Event<R::Item, C>, TryRecvError> {
+        if let Ok(message) = self.control.try_recv() {
+            return Ok(Event::Control(message));
+        }
+
+        self.inner.try_recv_event().map(Event::with_control)
+    }
+
+    /// Closes both the data and the control side, so that buffered messages can still be drained.
+    pub fn close(&mut self) {
+        self.inner.close();
+        self.control.close();
+    }
+
+    /// Returns a mutable reference to the underlying data receiver.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Consumes the event receiver, returning the underlying data receiver.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// Adds a companion control channel to the receivers of a sticky channel.
+///
+/// Every message sent through the returned [`ControlSender`] is delivered to all of the returned
+/// [`EventReceiver`]s, which yield it next to the data messages of the wrapped receivers. This is meant for
+/// configuration pushes and coordination messages that concern every consumer rather than a single ID.
+///
+/// ```rust
+/// use tokio_sticky_channel::{Event, control_channel, unbounded_sticky_channel};
+/// use std::num::NonZeroUsize;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let (sender, receivers) = unbounded_sticky_channel::<&str, i32>(NonZeroUsize::new(2).unwrap());
+/// let (control, mut receivers) = control_channel::<_, &str>(receivers);
+///
+/// control.send("reload").unwrap();
+/// sender.send("user-123", 42).unwrap();
+///
+/// for receiver in &mut receivers {
+///     assert_eq!(receiver.recv_event().await, Some(Event::Control("reload")));
+/// }
+/// # }
+/// ```
+pub fn control_channel<R, C>(receivers: Vec<R>) -> (ControlSender<C>, Vec<EventReceiver<R, C>>) {
+    let mut control_senders = Vec::with_capacity(receivers.len());
+    let receivers = receivers
+        .into_iter()
+        .map(|inner| {
+            let (sender, control) = unbounded_channel();
+            control_senders.push(sender);
+            EventReceiver { inner, control }
+        })
+        .collect();
+
+    (
+        ControlSender {
+            receivers: control_senders,
+        },
+        receivers,
+    )
+}
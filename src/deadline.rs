@@ -0,0 +1,115 @@
+use std::{
+    hash::{BuildHasher, Hash},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+use crate::{Receiver, SendError, Sender, UnboundedReceiver, UnboundedSender};
+
+/// A message that should be processed before a deadline.
+///
+/// Sent with [`Sender::send_with_deadline`] and its siblings and received with
+/// [`Receiver::recv_with_deadline`], which tells consumers how much time is left, so they can skip or down-prioritize
+/// work that can no longer meet its deadline. The deadline is not enforced by the channel: expired messages are still
+/// delivered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WithDeadline<T> {
+    /// The message itself.
+    pub message: T,
+    /// Time by which the message should be processed.
+    pub deadline: Instant,
+}
+
+impl<T> WithDeadline<T> {
+    /// Returns the message together with the time left until its deadline.
+    pub fn check(self) -> (T, Remaining) {
+        let now = Instant::now();
+        let remaining = match self.deadline.checked_duration_since(now) {
+            Some(left) if !left.is_zero() => Remaining::Left(left),
+            _ => Remaining::Expired(now.duration_since(self.deadline)),
+        };
+        (self.message, remaining)
+    }
+}
+
+/// Time left until the deadline of a received [`WithDeadline`] message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Remaining {
+    /// The deadline has not passed yet, and this much time is left.
+    Left(Duration),
+    /// The deadline has passed this long ago.
+    Expired(Duration),
+}
+
+impl Remaining {
+    /// Returns `true` if the deadline has passed.
+    pub fn is_expired(&self) -> bool {
+        matches!(self, Self::Expired(_))
+    }
+
+    /// Returns the time left until the deadline, or `None` if it has passed.
+    pub fn left(&self) -> Option<Duration> {
+        match self {
+            Self::Left(left) => Some(*left),
+            Self::Expired(_) => None,
+        }
+    }
+}
+
+impl<ID, T, S> Sender<ID, WithDeadline<T>, S>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a message that should be processed before `deadline`, waiting for capacity like [`send`](Sender::send).
+    pub async fn send_with_deadline(
+        &self,
+        id: ID,
+        message: T,
+        deadline: Instant,
+    ) -> Result<(), SendError<WithDeadline<T>>> {
+        self.send(id, WithDeadline { message, deadline }).await
+    }
+
+    /// Sends a message that should be processed before `deadline` without waiting, like [`try_send`](Sender::try_send).
+    pub fn try_send_with_deadline(
+        &self,
+        id: ID,
+        message: T,
+        deadline: Instant,
+    ) -> Result<(), SendError<WithDeadline<T>>> {
+        self.try_send(id, WithDeadline { message, deadline })
+    }
+}
+
+impl<ID, T, S> UnboundedSender<ID, WithDeadline<T>, S>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a message that should be processed before `deadline`, like [`send`](UnboundedSender::send).
+    pub fn send_with_deadline(
+        &self,
+        id: ID,
+        message: T,
+        deadline: Instant,
+    ) -> Result<(), SendError<WithDeadline<T>>> {
+        self.send(id, WithDeadline { message, deadline })
+    }
+}
+
+impl<T> Receiver<WithDeadline<T>> {
+    /// Receives the next message like [`recv`](Receiver::recv), together with the time left until its deadline.
+    pub async fn recv_with_deadline(&mut self) -> Option<(T, Remaining)> {
+        self.recv().await.map(WithDeadline::check)
+    }
+}
+
+impl<T> UnboundedReceiver<WithDeadline<T>> {
+    /// Receives the next message like [`recv`](UnboundedReceiver::recv), together with the time left until its
+    /// deadline.
+    pub async fn recv_with_deadline(&mut self) -> Option<(T, Remaining)> {
+        self.recv().await.map(WithDeadline::check)
+    }
+}
@@ -0,0 +1,49 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+/// Queue depths of all consumers of a channel sampled together, as returned by
+/// [`Sender::queue_depths`](crate::Sender::queue_depths) and
+/// [`UnboundedSender::queue_depths`](crate::UnboundedSender::queue_depths).
+///
+/// The depths are read in a single pass without allocating or locking in between, so they are as close to a snapshot
+/// of the same instant as the channel allows. [`spread`](DepthSnapshot::spread) bounds how far apart the reads were.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepthSnapshot {
+    /// Number of messages sent to every consumer that it has not received yet, indexed like the receivers.
+    pub depths: Vec<usize>,
+    /// Time the first depth was read.
+    pub taken_at: Instant,
+    /// Time between reading the first and the last depth.
+    pub spread: Duration,
+}
+
+impl DepthSnapshot {
+    pub(crate) fn sample<'a>(depths: impl ExactSizeIterator<Item = &'a AtomicUsize>) -> Self {
+        let mut sampled = Vec::with_capacity(depths.len());
+        let taken_at = Instant::now();
+        sampled.extend(depths.map(|depth| depth.load(Ordering::Relaxed)));
+        Self {
+            depths: sampled,
+            taken_at,
+            spread: taken_at.elapsed(),
+        }
+    }
+
+    /// Returns the total number of messages queued across all consumers.
+    pub fn total(&self) -> usize {
+        self.depths.iter().sum()
+    }
+
+    /// Returns the index of the consumer with the deepest queue, the first one on ties.
+    pub fn deepest(&self) -> Option<usize> {
+        self.depths
+            .iter()
+            .enumerate()
+            .max_by(|(a_index, a), (b_index, b)| a.cmp(b).then(b_index.cmp(a_index)))
+            .map(|(index, _)| index)
+    }
+}
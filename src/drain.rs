@@ -0,0 +1,35 @@
+use crate::StickyReceiver;
+
+/// Messages left in the queue of a single consumer at shutdown, as returned by [`drain_all`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrainReport<T> {
+    /// Index of the consumer, i.e. the position of its receiver in the vector returned when the channel was built.
+    pub consumer_index: usize,
+    /// Messages sent to the consumer that it never received, in the order they would have been received.
+    pub messages: Vec<T>,
+}
+
+/// Closes all receivers of a channel and collects the messages they have not received yet.
+///
+/// Returns one report per receiver in the order given, including receivers with nothing left, so `receivers` should be
+/// the receivers of a channel in the order they were returned when the channel was built. Works for the receivers of
+/// both channel flavours as well as for receiver adapters. See [`Receiver::take_remaining`](crate::Receiver::take_remaining)
+/// for messages whose send is in progress while draining.
+pub fn drain_all<R>(receivers: &mut [R]) -> Vec<DrainReport<R::Item>>
+where
+    R: StickyReceiver,
+{
+    // Close every receiver first, so that messages cannot move on to a receiver that was already drained.
+    for receiver in receivers.iter_mut() {
+        receiver.close();
+    }
+
+    receivers
+        .iter_mut()
+        .enumerate()
+        .map(|(consumer_index, receiver)| DrainReport {
+            consumer_index,
+            messages: receiver.take_remaining(),
+        })
+        .collect()
+}
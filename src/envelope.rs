@@ -0,0 +1,94 @@
+use tokio::time::Instant;
+
+use crate::{barrier::BarrierMarker, queue::Queue};
+
+/// Pool a message's slot was taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Slot {
+    /// Messages of unbounded channels do not occupy a slot.
+    Unbounded,
+    /// One of the regular slots every send can use.
+    Regular,
+    /// One of the slots reserved for priority sends.
+    Reserved,
+    /// One of the slots a consumer may take beyond its capacity while its burst allowance lasts.
+    Burst,
+    /// Messages injected by the channel itself, such as ticks, occupy neither a slot nor a place in an ID's pending
+    /// count.
+    Injected,
+}
+
+/// What an envelope carries.
+pub(crate) enum Payload<T> {
+    /// A message that is handed to the user as is.
+    Message(T),
+    /// A barrier marker, acknowledged when it is received.
+    Barrier(BarrierMarker),
+    /// An event-time watermark.
+    Watermark(u64),
+    /// Envelopes pushed as a single block, unpacked by the receiver.
+    Batch(Vec<Envelope<T>>),
+    /// The terminal sentinel queued by `finish`.
+    Finish,
+    /// Closes the receiver once it is reached, queued by `ChannelAdmin::close_all`.
+    Close,
+    /// Signals that the consumer's open block of the given generation has envelopes.
+    Block(u64),
+}
+
+/// A message as it travels through an internal channel.
+pub(crate) struct Envelope<T> {
+    pub(crate) payload: Payload<T>,
+    /// Hash of the ID the message was sent with.
+    pub(crate) hash: u64,
+    pub(crate) slot: Slot,
+    /// When the message was sent, if the channel tracks lag.
+    pub(crate) enqueued: Option<Instant>,
+    /// Producer ID of the sender clone the message was sent from, `0` for injected envelopes.
+    pub(crate) producer: u64,
+}
+
+impl<T> Envelope<T> {
+    /// Returns the message of an envelope a sender failed to enqueue.
+    ///
+    /// Senders only get back the envelopes they created for their own messages.
+    pub(crate) fn into_message(self) -> T {
+        match self.payload {
+            Payload::Message(message) => message,
+            Payload::Barrier(_)
+            | Payload::Watermark(_)
+            | Payload::Finish
+            | Payload::Close
+            | Payload::Batch(_)
+            | Payload::Block(_) => {
+                unreachable!("only single messages are handed back to senders")
+            }
+        }
+    }
+}
+
+/// Enqueues a payload that bypasses capacity and per-ID limits.
+///
+/// Returns `false` if the receiver has been closed or dropped.
+pub(crate) fn inject<T>(queue: &Queue<T>, payload: Payload<T>) -> bool {
+    queue
+        .send(Envelope {
+            payload,
+            hash: 0,
+            slot: Slot::Injected,
+            enqueued: None,
+            producer: 0,
+        })
+        .is_ok()
+}
+
+/// Moves a receiver's watermark forward to `timestamp`.
+///
+/// Returns `false` if the watermark is already at or past `timestamp`.
+pub(crate) fn advance_watermark(watermark: &mut Option<u64>, timestamp: u64) -> bool {
+    if watermark.is_some_and(|current| current >= timestamp) {
+        return false;
+    }
+    *watermark = Some(timestamp);
+    true
+}
@@ -1,5 +1,11 @@
+use std::{error::Error, fmt, sync::Arc};
+
+use tokio::sync::mpsc::error as mpsc;
+
+use crate::BarrierId;
+
 /// Error type for receiving messages through [`UnboundedReceiver::try_recv`](crate::UnboundedReceiver::try_recv) and [`Receiver::try_recv`](crate::Receiver::try_recv).
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
 pub enum TryRecvError {
     /// The channel is empty.
     #[error("channel is empty")]
@@ -11,22 +17,322 @@ pub enum TryRecvError {
 }
 
 /// Error type for sending messages through the [`UnboundedSender::send`](crate::UnboundedSender::send) and [`Sender::send`](crate::Sender::send).
-#[derive(Debug, thiserror::Error)]
+///
+/// Every variant carries the message that could not be sent. Variants that occur after the target consumer has been
+/// resolved also carry the index of that consumer.
+///
+/// For interoperability with code written against raw tokio channels, a `SendError` can be converted into tokio's
+/// [`SendError`](tokio::sync::mpsc::error::SendError) and [`TrySendError`](tokio::sync::mpsc::error::TrySendError)
+/// with [`TryFrom`]. The conversion fails, returning the original error, for variants tokio has no equivalent for.
+/// There is no conversion in the other direction because tokio's errors do not know which consumer was targeted.
+///
+/// New variants may be added in future releases. Use [`into_inner`](SendError::into_inner) and the other accessors
+/// instead of matching every variant.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
 #[error("channel closed")]
+#[non_exhaustive]
 pub enum SendError<T> {
     /// The message could not be send because there is no receiver for given ID.
     #[error("no receiver for ID")]
-    NoConsumer(T),
+    NoConsumer(T, usize),
 
     /// The channel was closed before the message could be sent.
     #[error("channel closed")]
-    ChannelClosed(T),
+    ChannelClosed(T, usize),
 
     /// The channel is full and cannot accept more messages (bounded channels only).
     #[error("channel is full")]
-    ChannelFull(T),
+    ChannelFull(T, usize),
+
+    /// The ID already has the maximum number of pending messages allowed for a single ID.
+    ///
+    /// Only returned by channels configured with a per-ID limit, e.g. with
+    /// [`StickyChannelBuilder::max_pending_per_key`](crate::StickyChannelBuilder::max_pending_per_key).
+    #[error("too many pending messages for ID")]
+    KeyBackpressure(T, usize),
 
     /// Failed to compute route ID from the given ID.
     #[error("failed to compute route ID")]
     FailedToComputeRouteID(T),
+
+    /// No consumer has the label the message was sent to.
+    #[error("no consumer with that label")]
+    UnknownLabel(T),
+
+    /// The message was rejected by the channel's validator before it was routed.
+    ///
+    /// Only returned by channels built with a validator, e.g. with
+    /// [`StickyChannelBuilder::validate`](crate::StickyChannelBuilder::validate).
+    #[error("message rejected: {1}")]
+    Rejected(T, Rejection),
+
+    /// No capacity became available before the deadline of the send.
+    ///
+    /// Only returned by sends with a deadline, e.g. through a [`TimeoutSender`](crate::TimeoutSender).
+    #[error("timed out waiting for capacity")]
+    Timeout(T, usize),
+}
+
+impl<T> SendError<T> {
+    /// Consumes the error, returning the message that failed to send.
+    pub fn into_inner(self) -> T {
+        match self {
+            SendError::NoConsumer(message, _)
+            | SendError::ChannelClosed(message, _)
+            | SendError::ChannelFull(message, _)
+            | SendError::KeyBackpressure(message, _)
+            | SendError::FailedToComputeRouteID(message)
+            | SendError::UnknownLabel(message)
+            | SendError::Rejected(message, _)
+            | SendError::Timeout(message, _) => message,
+        }
+    }
+
+    /// Maps the message carried by the error, keeping the reason and consumer index.
+    pub(crate) fn map<U>(self, f: impl FnOnce(T) -> U) -> SendError<U> {
+        match self {
+            SendError::NoConsumer(message, index) => SendError::NoConsumer(f(message), index),
+            SendError::ChannelClosed(message, index) => SendError::ChannelClosed(f(message), index),
+            SendError::ChannelFull(message, index) => SendError::ChannelFull(f(message), index),
+            SendError::KeyBackpressure(message, index) => {
+                SendError::KeyBackpressure(f(message), index)
+            }
+            SendError::FailedToComputeRouteID(message) => {
+                SendError::FailedToComputeRouteID(f(message))
+            }
+            SendError::UnknownLabel(message) => SendError::UnknownLabel(f(message)),
+            SendError::Rejected(message, rejection) => SendError::Rejected(f(message), rejection),
+            SendError::Timeout(message, index) => SendError::Timeout(f(message), index),
+        }
+    }
+
+    /// Returns `true` if the message could not be sent because the target channel is full.
+    ///
+    /// Sending the same message again later may succeed.
+    pub fn is_full(&self) -> bool {
+        matches!(self, SendError::ChannelFull(..))
+    }
+
+    /// Returns `true` if the message could not be sent because the target channel is closed.
+    ///
+    /// Sending to the same consumer again will never succeed.
+    pub fn is_closed(&self) -> bool {
+        matches!(self, SendError::ChannelClosed(..))
+    }
+
+    /// Returns the index of the consumer the message was routed to, if routing got that far.
+    ///
+    /// The label of a labeled consumer can be looked up with [`Sender::label`](crate::Sender::label) and
+    /// [`UnboundedSender::label`](crate::UnboundedSender::label).
+    pub fn consumer_index(&self) -> Option<usize> {
+        match self {
+            SendError::NoConsumer(_, index)
+            | SendError::ChannelClosed(_, index)
+            | SendError::ChannelFull(_, index)
+            | SendError::KeyBackpressure(_, index)
+            | SendError::Timeout(_, index) => Some(*index),
+            SendError::FailedToComputeRouteID(_)
+            | SendError::UnknownLabel(_)
+            | SendError::Rejected(..) => None,
+        }
+    }
+
+    /// Returns the reason the message was rejected, if the channel's validator rejected it.
+    pub fn rejection(&self) -> Option<&Rejection> {
+        match self {
+            SendError::Rejected(_, rejection) => Some(rejection),
+            _ => None,
+        }
+    }
+
+    /// Attaches the ID the message was sent with, so callers sending many messages can tell which one failed.
+    pub fn with_id<ID>(self, id: ID) -> KeyedSendError<ID, T> {
+        KeyedSendError { id, error: self }
+    }
+}
+
+/// A [`SendError`] together with the ID the failed message was sent with.
+///
+/// Created with [`SendError::with_id`] and returned by operations that send many messages at once, where the consumer
+/// index alone does not tell which ID was affected.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{error}")]
+pub struct KeyedSendError<ID, T> {
+    /// ID the message was sent with.
+    pub id: ID,
+    /// Reason the message could not be sent, including the message itself.
+    pub error: SendError<T>,
+}
+
+impl<ID, T> KeyedSendError<ID, T> {
+    /// Consumes the error, returning the ID and the message that failed to send.
+    pub fn into_parts(self) -> (ID, T) {
+        (self.id, self.error.into_inner())
+    }
+}
+
+/// Outcome of [`Sender::try_send_batch`](crate::Sender::try_send_batch).
+#[derive(Debug, PartialEq, Eq)]
+pub struct BatchSendResult<ID, T> {
+    /// Number of messages that were queued.
+    pub sent: usize,
+    /// Messages that could not be queued, in the order they were given, together with their IDs and the reason.
+    pub failed: Vec<KeyedSendError<ID, T>>,
+}
+
+impl<ID, T> BatchSendResult<ID, T> {
+    /// Returns `true` if every message of the batch was queued.
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Reason a message was rejected by the validator of a channel.
+///
+/// Wraps the error returned by the validation function, which can be recovered with
+/// [`downcast_ref`](Rejection::downcast_ref). Rejections are cheap to clone; a rejection is only equal to itself and
+/// its clones.
+#[derive(Clone)]
+pub struct Rejection(Arc<dyn Error + Send + Sync>);
+
+impl Rejection {
+    pub(crate) fn new<E>(error: E) -> Self
+    where
+        E: Error + Send + Sync + 'static,
+    {
+        Self(Arc::new(error))
+    }
+
+    /// Returns the error of the validation function if it is of type `E`.
+    pub fn downcast_ref<E>(&self) -> Option<&E>
+    where
+        E: Error + 'static,
+    {
+        self.0.downcast_ref()
+    }
+
+    /// Returns `true` if the error of the validation function is of type `E`.
+    pub fn is<E>(&self) -> bool
+    where
+        E: Error + 'static,
+    {
+        self.0.is::<E>()
+    }
+}
+
+impl fmt::Debug for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Error for Rejection {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl PartialEq for Rejection {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Rejection {}
+
+/// Error returned by quorum sends of a [`ReplicatedSender`](crate::ReplicatedSender) when too few replicas accepted
+/// the message.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+#[error("message accepted by {accepted} replicas, {quorum} required")]
+pub struct QuorumError<T> {
+    /// Number of replicas that had to accept the message.
+    pub quorum: usize,
+    /// Number of replicas that accepted the message.
+    pub accepted: usize,
+    /// Outcome for every replica, starting with the primary.
+    ///
+    /// Accepted replicas hold the index of the consumer they were delivered to. Replicas that were accepted stay
+    /// queued even though the quorum send failed.
+    pub results: Vec<Result<usize, SendError<T>>>,
+}
+
+/// Error returned by [`Barrier::wait`](crate::Barrier::wait) when a consumer was closed before receiving the barrier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("consumer closed before receiving barrier {0:?}")]
+pub struct BarrierError(pub BarrierId);
+
+impl From<mpsc::TryRecvError> for TryRecvError {
+    fn from(err: mpsc::TryRecvError) -> Self {
+        match err {
+            mpsc::TryRecvError::Empty => TryRecvError::Empty,
+            mpsc::TryRecvError::Disconnected => TryRecvError::Disconnected,
+        }
+    }
+}
+
+impl From<TryRecvError> for mpsc::TryRecvError {
+    fn from(err: TryRecvError) -> Self {
+        match err {
+            TryRecvError::Empty => mpsc::TryRecvError::Empty,
+            TryRecvError::Disconnected => mpsc::TryRecvError::Disconnected,
+        }
+    }
+}
+
+impl<T> TryFrom<SendError<T>> for mpsc::SendError<T> {
+    type Error = SendError<T>;
+
+    fn try_from(err: SendError<T>) -> Result<Self, Self::Error> {
+        match err {
+            SendError::ChannelClosed(message, _) => Ok(mpsc::SendError(message)),
+            err => Err(err),
+        }
+    }
+}
+
+impl<T> TryFrom<SendError<T>> for mpsc::TrySendError<T> {
+    type Error = SendError<T>;
+
+    fn try_from(err: SendError<T>) -> Result<Self, Self::Error> {
+        match err {
+            SendError::ChannelClosed(message, _) => Ok(mpsc::TrySendError::Closed(message)),
+            SendError::ChannelFull(message, _) | SendError::KeyBackpressure(message, _) => {
+                Ok(mpsc::TrySendError::Full(message))
+            }
+            err => Err(err),
+        }
+    }
+}
+
+/// Error type for the acknowledgment of a single consumer to
+/// [`ControlSender::broadcast_fn`](crate::ControlSender::broadcast_fn).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CommandError<E> {
+    /// The consumer's receiver was closed, or the command was dropped without being run.
+    #[error("command was not run")]
+    NotRun,
+    /// The command ran and failed.
+    #[error("command failed: {0}")]
+    Failed(E),
+}
+
+/// Error type for encoding and decoding [`WireEnvelope`](crate::WireEnvelope)s.
+#[cfg(feature = "serde")]
+#[derive(Debug, thiserror::Error)]
+pub enum WireError {
+    /// The envelope was written in a format version this crate cannot read.
+    #[error("unsupported wire format version {0}")]
+    UnsupportedVersion(u16),
+    /// The envelope could not be encoded.
+    #[error("failed to encode envelope: {0}")]
+    Encode(#[source] Box<dyn Error + Send + Sync>),
+    /// The bytes are not a valid envelope.
+    #[error("failed to decode envelope: {0}")]
+    Decode(#[source] Box<dyn Error + Send + Sync>),
 }
@@ -0,0 +1,38 @@
+use std::convert::Infallible;
+
+use crate::BarrierId;
+
+/// An item received with `recv_event`.
+///
+/// Besides the messages themselves, receivers can observe the markers the channel delivers in order with them. `C` is
+/// the message type of the companion control channel, see [`control_channel`](crate::control_channel); receivers
+/// without one never yield [`Control`](Event::Control).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<T, C = Infallible> {
+    /// A message sent through the sticky data channel.
+    Data(T),
+    /// A message broadcast through the companion control channel.
+    Control(C),
+    /// A barrier marker sent with [`Sender::send_barrier`](crate::Sender::send_barrier). Every message sent before the
+    /// barrier has been received.
+    Barrier(BarrierId),
+    /// An event-time watermark sent with [`Sender::advance_watermark`](crate::Sender::advance_watermark). No more
+    /// messages with an event time at or before the timestamp are expected.
+    Watermark(u64),
+    /// The terminal sentinel sent with [`Sender::finish`](crate::Sender::finish). Every message sent before it has been
+    /// received.
+    Finished,
+}
+
+impl<T> Event<T> {
+    /// Converts an event without control messages into one of any control message type.
+    pub(crate) fn with_control<C>(self) -> Event<T, C> {
+        match self {
+            Event::Data(message) => Event::Data(message),
+            Event::Control(never) => match never {},
+            Event::Barrier(id) => Event::Barrier(id),
+            Event::Watermark(timestamp) => Event::Watermark(timestamp),
+            Event::Finished => Event::Finished,
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    future::{Future, poll_fn},
+    hash::{BuildHasher, Hash},
+    num::NonZeroUsize,
+};
+
+use tokio::task::JoinSet;
+
+use crate::{SendError, Sender, StickyReceiver, UnboundedSender, util::spawn_in};
+
+/// Number of messages [`rekey`] moves from a source at a time.
+const DEFAULT_BATCH_SIZE: usize = 64;
+
+/// Common interface of the sending halves of sticky channels, used to forward messages into them.
+///
+/// This trait is implemented by [`Sender`] and [`UnboundedSender`].
+pub trait StickySender<ID, T>: Clone + Send + Sync + 'static {
+    /// Sends a message to the consumer of `id`, waiting while the channel cannot take it.
+    ///
+    /// [`Sender`] waits for capacity like [`Sender::send`]. [`UnboundedSender`] only waits while the ID has reached its
+    /// per-ID limit, like [`UnboundedSender::send_wait`].
+    fn forward(&self, id: ID, message: T) -> impl Future<Output = Result<(), SendError<T>>> + Send;
+}
+
+impl<ID, T, S> StickySender<ID, T> for Sender<ID, T, S>
+where
+    ID: Hash + Send + Sync + 'static,
+    T: Send + 'static,
+    S: BuildHasher + Clone + Send + Sync + 'static,
+{
+    fn forward(&self, id: ID, message: T) -> impl Future<Output = Result<(), SendError<T>>> + Send {
+        self.send(id, message)
+    }
+}
+
+impl<ID, T, S> StickySender<ID, T> for UnboundedSender<ID, T, S>
+where
+    ID: Hash + Send + Sync + 'static,
+    T: Send + 'static,
+    S: BuildHasher + Clone + Send + Sync + 'static,
+{
+    fn forward(&self, id: ID, message: T) -> impl Future<Output = Result<(), SendError<T>>> + Send {
+        self.send_wait(id, message)
+    }
+}
+
+/// Merges several upstream sources into the consumers of a single sticky channel.
+///
+/// Every source is forwarded by its own task through a clone of the same sender, so the ID of a message decides its
+/// consumer no matter which source it came from. This is not the case when the sources are separate sticky channels,
+/// even with the same number of consumers, because every channel gets its own randomly seeded hasher by default.
+///
+/// Sources are any [`StickyReceiver`], for example the receivers of upstream sticky channels. Messages of a single
+/// source keep their order; messages of different sources are interleaved as they arrive. A forwarding task stops once
+/// its source is closed and empty, or when a message cannot be sent.
+pub struct FanIn<X, T> {
+    sender: X,
+    batch_size: usize,
+    tasks: JoinSet<Result<(), SendError<T>>>,
+}
+
+impl<X, T> FanIn<X, T> {
+    /// Creates a fan-in that forwards its sources through `sender`, a [`Sender`] or an [`UnboundedSender`].
+    pub fn new(sender: X) -> Self {
+        Self {
+            sender,
+            batch_size: 1,
+            tasks: JoinSet::new(),
+        }
+    }
+
+    /// Sets how many messages are moved from a source at a time, for sources added afterwards. Defaults to `1`.
+    ///
+    /// A forwarding task waits for one message and then takes up to `batch_size - 1` more that are already available
+    /// before sending them one after another. Larger batches take fewer wakeups under load, but a batch is held by the
+    /// task while it waits for capacity downstream.
+    pub fn with_batch_size(mut self, batch_size: NonZeroUsize) -> Self {
+        self.batch_size = batch_size.get();
+        self
+    }
+}
+
+impl<X, T> FanIn<X, T>
+where
+    X: Clone,
+{
+    /// Returns a clone of the shared sender, for sources that push messages themselves.
+    pub fn sender(&self) -> X {
+        self.sender.clone()
+    }
+
+    /// Returns the number of sources still being forwarded.
+    pub fn sources(&self) -> usize {
+        self.tasks.len()
+    }
+}
+
+impl<X, T> FanIn<X, T>
+where
+    T: 'static,
+{
+    /// Waits until every source has been forwarded completely.
+    ///
+    /// Returns the first error of a forwarding task, including the message it could not send. Tasks that are still
+    /// running when an error is returned keep running until the `FanIn` is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a forwarding task panicked, for example in the key function of its source.
+    pub async fn join(&mut self) -> Result<(), SendError<T>> {
+        while let Some(result) = self.tasks.join_next().await {
+            match result {
+                Ok(result) => result?,
+                Err(err) => std::panic::resume_unwind(err.into_panic()),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<X, T> FanIn<X, T>
+where
+    T: Send + 'static,
+{
+    /// Forwards every message of `source` to the consumer of the ID returned by `key`.
+    ///
+    /// Messages are sent with [`StickySender::forward`], so a full bounded channel holds up the source, which in turn
+    /// holds up its own producers if it is bounded. If a message cannot be sent, the rest of its batch is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is not called from within a Tokio runtime.
+    pub fn add_source<ID, R, F>(&mut self, mut source: R, key: F)
+    where
+        X: StickySender<ID, T>,
+        R: StickyReceiver<Item = T> + Send + 'static,
+        F: Fn(&T) -> ID + Send + 'static,
+    {
+        let sender = self.sender.clone();
+        let batch_size = self.batch_size;
+        spawn_in(&mut self.tasks, "sticky-channel fan-in", async move {
+            let mut batch = Vec::with_capacity(batch_size);
+            while let Some(message) = poll_fn(|cx| source.poll_recv(cx)).await {
+                batch.push(message);
+                while batch.len() < batch_size {
+                    match source.try_recv() {
+                        Ok(message) => batch.push(message),
+                        Err(_) => break,
+                    }
+                }
+
+                for message in batch.drain(..) {
+                    sender.forward(key(&message), message).await?;
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Re-partitions the messages of one sticky channel into another by a new key.
+///
+/// Every receiver of the upstream channel is forwarded to the consumer of the ID that `key` returns for each message,
+/// in batches of up to 64 messages. This is the shuffle step between two stages of a keyed pipeline: the first stage is
+/// partitioned by one key and the second by another. Backpressure carries over: while `sender` is bounded and full,
+/// the upstream receivers are not drained, so a bounded upstream channel holds up its producers.
+///
+/// Wait for the forwarding to finish with [`FanIn::join`], after all upstream senders have been dropped. Use
+/// [`FanIn::new`] and [`FanIn::add_source`] directly to choose another batch size.
+///
+/// # Panics
+///
+/// Panics if it is not called from within a Tokio runtime.
+pub fn rekey<I, F, X, ID, T>(receivers: I, key: F, sender: X) -> FanIn<X, T>
+where
+    I: IntoIterator,
+    I::Item: StickyReceiver<Item = T> + Send + 'static,
+    F: Fn(&T) -> ID + Clone + Send + 'static,
+    X: StickySender<ID, T>,
+    T: Send + 'static,
+{
+    let mut fan_in =
+        FanIn::new(sender).with_batch_size(NonZeroUsize::new(DEFAULT_BATCH_SIZE).unwrap());
+    for receiver in receivers {
+        fan_in.add_source(receiver, key.clone());
+    }
+    fan_in
+}
@@ -0,0 +1,198 @@
+use core::hash::{BuildHasher, Hasher};
+
+/// Hasher used by channels created without an explicit [`BuildHasher`].
+///
+/// This is [`RandomState`](std::hash::RandomState) by default. SipHash is a measurable part of the send path for
+/// short IDs, so faster hashers can be picked with cargo features:
+///
+/// - `ahash`: `ahash::RandomState`, randomly keyed like the standard hasher.
+/// - `fxhash`: `BuildHasherDefault<fxhash::FxHasher>`, the fastest but unkeyed, so IDs chosen by an adversary can all
+///   be routed to the same consumer.
+/// - `stable-routing`: [`SeededState`] with a zero seed, whose routes never change, see below.
+///
+/// If several features are enabled, `stable-routing` takes precedence over `ahash`, which takes precedence over
+/// `fxhash`.
+///
+/// # Stable routing
+///
+/// The algorithm of the standard hasher is not guaranteed to stay the same across Rust releases, so neither are the
+/// routes of its fixed-key variants. With `stable-routing`, IDs are hashed with SipHash-2-4 keyed with zeros, as
+/// implemented by this crate, and routed with [`consumer_index`](crate::routing::consumer_index). Both are part of
+/// the crate's API and only change in a major release, so persisted partition assignments and the routes of other
+/// deployments stay valid across compiler upgrades. The `Hash` implementations of the ID types must stay stable as
+/// well, which holds for integers, strings and byte slices.
+#[cfg(all(
+    feature = "std",
+    not(any(feature = "ahash", feature = "fxhash", feature = "stable-routing"))
+))]
+pub type DefaultBuildHasher = std::hash::RandomState;
+
+/// Hasher used by channels created without an explicit [`BuildHasher`].
+///
+/// This is `ahash::RandomState`, as selected by the `ahash` feature.
+#[cfg(all(feature = "ahash", not(feature = "stable-routing")))]
+pub type DefaultBuildHasher = ahash::RandomState;
+
+/// Hasher used by channels created without an explicit [`BuildHasher`].
+///
+/// This is `BuildHasherDefault<fxhash::FxHasher>`, as selected by the `fxhash` feature. It is not keyed, so IDs chosen
+/// by an adversary can all be routed to the same consumer.
+#[cfg(all(
+    feature = "fxhash",
+    not(any(feature = "ahash", feature = "stable-routing"))
+))]
+pub type DefaultBuildHasher = std::hash::BuildHasherDefault<fxhash::FxHasher>;
+
+/// Hasher used by channels created without an explicit [`BuildHasher`].
+///
+/// This is [`SeededState`] with a zero seed, as selected by the `stable-routing` feature: SipHash-2-4 keyed with zeros.
+/// The algorithm is part of the crate's API and only changes in a major release, so routes stay the same across
+/// compiler upgrades and deployments. It is not keyed with a secret, so IDs chosen by an adversary can all be routed
+/// to the same consumer.
+#[cfg(all(feature = "std", feature = "stable-routing"))]
+pub type DefaultBuildHasher = SeededState;
+
+/// [`BuildHasher`] of SipHash-2-4 keyed with an explicit 128-bit seed.
+///
+/// Unlike [`RandomState`](std::hash::RandomState), whose keys are random, every `SeededState` created from the same
+/// seed hashes the same way: independent processes, or a process before and after a restart, route the same IDs to the
+/// same consumers. Integers are hashed as little-endian bytes and `usize` as a `u64`, so routes also agree between
+/// 32-bit and 64-bit or big-endian and little-endian targets, as long as the IDs' [`Hash`](std::hash::Hash)
+/// implementations write the same values.
+///
+/// Keep the seed secret if IDs may be chosen by an adversary, who could otherwise route them all to one consumer.
+///
+/// ```rust
+/// use std::num::NonZeroUsize;
+/// use tokio_sticky_channel::{SeededState, unbounded_sticky_channel_with_seed};
+///
+/// let seed = 0x5eed_u128;
+/// let (first, _receivers) = unbounded_sticky_channel_with_seed::<&str, u32>(NonZeroUsize::new(4).unwrap(), seed);
+/// let (second, _receivers) = unbounded_sticky_channel_with_seed::<&str, u32>(NonZeroUsize::new(4).unwrap(), seed);
+/// assert_eq!(first.route_of("user-123"), second.route_of("user-123"));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SeededState {
+    k0: u64,
+    k1: u64,
+}
+
+impl SeededState {
+    /// Creates a hasher keyed with `seed`, whose low 64 bits are the first SipHash key and high 64 bits the second.
+    pub const fn new(seed: u128) -> Self {
+        Self {
+            k0: seed as u64,
+            k1: (seed >> 64) as u64,
+        }
+    }
+}
+
+impl BuildHasher for SeededState {
+    type Hasher = SeededHasher;
+
+    fn build_hasher(&self) -> SeededHasher {
+        SeededHasher {
+            v0: self.k0 ^ 0x736f_6d65_7073_6575,
+            v1: self.k1 ^ 0x646f_7261_6e64_6f6d,
+            v2: self.k0 ^ 0x6c79_6765_6e65_7261,
+            v3: self.k1 ^ 0x7465_6462_7974_6573,
+            tail: 0,
+            tail_len: 0,
+            len: 0,
+        }
+    }
+}
+
+/// The [`Hasher`] built by [`SeededState`].
+#[derive(Debug, Clone)]
+pub struct SeededHasher {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    /// Bytes written since the last full 8-byte word, in the low bytes.
+    tail: u64,
+    tail_len: usize,
+    len: u64,
+}
+
+impl SeededHasher {
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13) ^ self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16) ^ self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21) ^ self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17) ^ self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+
+    fn compress(&mut self, word: u64) {
+        self.v3 ^= word;
+        self.round();
+        self.round();
+        self.v0 ^= word;
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.tail |= u64::from(byte) << (8 * self.tail_len);
+        self.tail_len += 1;
+        if self.tail_len == 8 {
+            self.compress(self.tail);
+            self.tail = 0;
+            self.tail_len = 0;
+        }
+    }
+}
+
+impl Hasher for SeededHasher {
+    fn write(&mut self, mut bytes: &[u8]) {
+        self.len = self.len.wrapping_add(bytes.len() as u64);
+        while self.tail_len != 0 && !bytes.is_empty() {
+            self.push(bytes[0]);
+            bytes = &bytes[1..];
+        }
+
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.compress(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        for &byte in words.remainder() {
+            self.push(byte);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        let mut state = self.clone();
+        let last = ((self.len & 0xff) << 56) | self.tail;
+        state.compress(last);
+        state.v2 ^= 0xff;
+        for _ in 0..4 {
+            state.round();
+        }
+        state.v0 ^ state.v1 ^ state.v2 ^ state.v3
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use tokio::sync::watch;
+
+/// Health of a sticky channel, as watched through [`Sender::health`](crate::Sender::health).
+///
+/// Only tracked for channels built with [`watch_health`](crate::StickyChannelBuilder::watch_health). Closed consumers
+/// take precedence over full ones, since their messages cannot be delivered at all.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ChannelHealth {
+    /// Every consumer is open and has capacity.
+    #[default]
+    Healthy,
+    /// Every consumer is open, but the listed consumers ran out of capacity and have not received a message since.
+    ///
+    /// Unbounded channels never report full consumers.
+    Degraded {
+        /// Indices of the full consumers, in ascending order.
+        full_consumers: Vec<usize>,
+    },
+    /// The receivers of the listed consumers were closed or dropped, while other consumers are still open.
+    ConsumersClosed {
+        /// Indices of the closed consumers, in ascending order.
+        indices: Vec<usize>,
+    },
+    /// The receivers of all consumers were closed or dropped.
+    Closed,
+}
+
+#[derive(Default)]
+struct ConsumerState {
+    full: AtomicBool,
+    closed: AtomicBool,
+}
+
+/// Health of a channel, shared by its senders, consumers and receivers.
+pub(crate) struct Health {
+    sender: watch::Sender<ChannelHealth>,
+    consumers: Box<[ConsumerState]>,
+}
+
+impl Health {
+    pub(crate) fn new(num_consumers: usize) -> Arc<Self> {
+        Arc::new(Self {
+            sender: watch::Sender::new(ChannelHealth::Healthy),
+            consumers: (0..num_consumers)
+                .map(|_| ConsumerState::default())
+                .collect(),
+        })
+    }
+
+    pub(crate) fn subscribe(&self) -> watch::Receiver<ChannelHealth> {
+        self.sender.subscribe()
+    }
+
+    /// Publishes the health derived from the consumer states, if it changed.
+    fn update(&self) {
+        // Deriving the health while holding the watch's lock keeps concurrent updates from publishing stale states.
+        self.sender.send_if_modified(|current| {
+            let health = self.derive();
+            let modified = *current != health;
+            *current = health;
+            modified
+        });
+    }
+
+    fn derive(&self) -> ChannelHealth {
+        let closed = self.indices(|state| state.closed.load(Ordering::Relaxed));
+        if closed.len() == self.consumers.len() {
+            return ChannelHealth::Closed;
+        }
+        if !closed.is_empty() {
+            return ChannelHealth::ConsumersClosed { indices: closed };
+        }
+        let full = self.indices(|state| state.full.load(Ordering::Relaxed));
+        if !full.is_empty() {
+            return ChannelHealth::Degraded {
+                full_consumers: full,
+            };
+        }
+        ChannelHealth::Healthy
+    }
+
+    fn indices(&self, predicate: impl Fn(&ConsumerState) -> bool) -> Vec<usize> {
+        self.consumers
+            .iter()
+            .enumerate()
+            .filter(|(_, state)| predicate(state))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+/// Handle to the health of a single consumer.
+#[derive(Clone)]
+pub(crate) struct ConsumerHealth {
+    health: Arc<Health>,
+    index: usize,
+}
+
+impl ConsumerHealth {
+    pub(crate) fn new(health: Arc<Health>, index: usize) -> Self {
+        Self { health, index }
+    }
+
+    fn state(&self) -> &ConsumerState {
+        &self.health.consumers[self.index]
+    }
+
+    /// Marks the consumer as out of capacity.
+    pub(crate) fn full(&self) {
+        if !self.state().full.swap(true, Ordering::Relaxed) {
+            self.health.update();
+        }
+    }
+
+    /// Marks the consumer as having capacity again. Cheap if it was not full.
+    pub(crate) fn available(&self) {
+        let full = &self.state().full;
+        if full.load(Ordering::Relaxed) && full.swap(false, Ordering::Relaxed) {
+            self.health.update();
+        }
+    }
+
+    /// Marks the consumer as closed.
+    pub(crate) fn closed(&self) {
+        if !self.state().closed.swap(true, Ordering::Relaxed) {
+            self.health.update();
+        }
+    }
+}
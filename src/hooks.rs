@@ -0,0 +1,127 @@
+use std::{
+    any::Any,
+    panic::RefUnwindSafe,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+type ClosedHook = Arc<dyn Fn(usize) + Send + Sync + RefUnwindSafe>;
+
+/// Callbacks registered on a channel's builder, shared by its consumers.
+pub(crate) struct Hooks<T> {
+    on_full: Option<MessageHook<T>>,
+    on_closed: Option<ClosedHook>,
+    on_dropped: Option<MessageHook<T>>,
+}
+
+impl<T> Hooks<T> {
+    pub(crate) fn set_on_full<F>(&mut self, hook: F)
+    where
+        F: Fn(usize, &T) + Send + Sync + RefUnwindSafe + 'static,
+    {
+        self.on_full = Some(MessageHook::new(hook));
+    }
+
+    pub(crate) fn set_on_closed<F>(&mut self, hook: F)
+    where
+        F: Fn(usize) + Send + Sync + RefUnwindSafe + 'static,
+    {
+        self.on_closed = Some(Arc::new(hook));
+    }
+
+    pub(crate) fn set_on_dropped<F>(&mut self, hook: F)
+    where
+        F: Fn(usize, &T) + Send + Sync + RefUnwindSafe + 'static,
+    {
+        self.on_dropped = Some(MessageHook::new(hook));
+    }
+
+    /// Returns the hooks to share with the consumers, or `None` if no hook was registered.
+    pub(crate) fn finish(self) -> Option<Arc<Self>> {
+        (self.on_full.is_some() || self.on_closed.is_some() || self.on_dropped.is_some())
+            .then(|| Arc::new(self))
+    }
+
+    /// Returns the hook that reports the receiver of consumer `index` closing.
+    pub(crate) fn on_closed(&self, index: usize) -> Option<OnClosed> {
+        self.on_closed.clone().map(|hook| OnClosed {
+            hook,
+            index,
+            called: AtomicBool::new(false),
+        })
+    }
+
+    /// Reports a message that found consumer `index` without capacity.
+    pub(crate) fn full(&self, index: usize, message: &T) {
+        if let Some(hook) = &self.on_full {
+            hook.call(index, message);
+        }
+    }
+
+    /// Reports a message for consumer `index` that was dropped by policy.
+    pub(crate) fn dropped(&self, index: usize, message: &T) {
+        if let Some(hook) = &self.on_dropped {
+            hook.call(index, message);
+        }
+    }
+}
+
+impl<T> Default for Hooks<T> {
+    fn default() -> Self {
+        Self {
+            on_full: None,
+            on_closed: None,
+            on_dropped: None,
+        }
+    }
+}
+
+/// Hook taking a message, stored without its type like [`Validator`](crate::validate::Validator), so that the
+/// consumers do not own a closure over `T`.
+struct MessageHook<T> {
+    hook: Arc<dyn Any + Send + Sync + RefUnwindSafe>,
+    call: fn(&(dyn Any + Send + Sync), usize, &T),
+}
+
+impl<T> MessageHook<T> {
+    fn new<F>(hook: F) -> Self
+    where
+        F: Fn(usize, &T) + Send + Sync + RefUnwindSafe + 'static,
+    {
+        Self {
+            hook: Arc::new(hook),
+            call: call_with::<T, F>,
+        }
+    }
+
+    fn call(&self, index: usize, message: &T) {
+        (self.call)(&*self.hook, index, message);
+    }
+}
+
+fn call_with<T, F>(hook: &(dyn Any + Send + Sync), index: usize, message: &T)
+where
+    F: Fn(usize, &T) + 'static,
+{
+    let hook = hook
+        .downcast_ref::<F>()
+        .expect("hooks are called with the call of their own type");
+    hook(index, message);
+}
+
+/// Closing hook of a single receiver, called at most once.
+pub(crate) struct OnClosed {
+    hook: ClosedHook,
+    index: usize,
+    called: AtomicBool,
+}
+
+impl OnClosed {
+    pub(crate) fn call(&self) {
+        if !self.called.swap(true, Ordering::Relaxed) {
+            (self.hook)(self.index);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::hash::{BuildHasher, Hash};
+
+use tracing::{Instrument, Span};
+
+use crate::{SendError, Sender, UnboundedSender};
+
+/// A message carrying the [`Span`] that was current when it was sent.
+///
+/// Sent with [`Sender::send_instrumented`] and its siblings, or created with [`Instrumented::new`]. Consumers process
+/// the message with [`in_scope`](Instrumented::in_scope) or [`instrument`](Instrumented::instrument), so that the
+/// events they emit are attributed to the request that sent the message rather than to the consumer's own task.
+#[derive(Debug, Clone)]
+pub struct Instrumented<T> {
+    message: T,
+    span: Span,
+}
+
+impl<T> Instrumented<T> {
+    /// Wraps a message with the current span.
+    pub fn new(message: T) -> Self {
+        Self::with_span(message, Span::current())
+    }
+
+    /// Wraps a message with the given span.
+    pub fn with_span(message: T, span: Span) -> Self {
+        Self { message, span }
+    }
+
+    /// Returns the span captured when the message was sent.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Returns a reference to the message.
+    pub fn get_ref(&self) -> &T {
+        &self.message
+    }
+
+    /// Returns the message, dropping its span.
+    pub fn into_inner(self) -> T {
+        self.message
+    }
+
+    /// Returns the message and its span.
+    pub fn into_parts(self) -> (T, Span) {
+        (self.message, self.span)
+    }
+
+    /// Processes the message with `f` inside its span.
+    pub fn in_scope<F, R>(self, f: F) -> R
+    where
+        F: FnOnce(T) -> R,
+    {
+        let Self { message, span } = self;
+        span.in_scope(|| f(message))
+    }
+
+    /// Processes the message with the future returned by `f`, entering its span every time the future is polled.
+    pub async fn instrument<F, Fut>(self, f: F) -> Fut::Output
+    where
+        F: FnOnce(T) -> Fut,
+        Fut: Future,
+    {
+        let Self { message, span } = self;
+        let future = span.in_scope(|| f(message));
+        future.instrument(span).await
+    }
+}
+
+impl<ID, T, S> Sender<ID, Instrumented<T>, S>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a message with the current span, waiting for capacity like [`send`](Sender::send).
+    pub async fn send_instrumented(
+        &self,
+        id: ID,
+        message: T,
+    ) -> Result<(), SendError<Instrumented<T>>> {
+        self.send(id, Instrumented::new(message)).await
+    }
+
+    /// Sends a message with the current span without waiting, like [`try_send`](Sender::try_send).
+    pub fn try_send_instrumented(
+        &self,
+        id: ID,
+        message: T,
+    ) -> Result<(), SendError<Instrumented<T>>> {
+        self.try_send(id, Instrumented::new(message))
+    }
+}
+
+impl<ID, T, S> UnboundedSender<ID, Instrumented<T>, S>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a message with the current span, like [`send`](UnboundedSender::send).
+    pub fn send_instrumented(&self, id: ID, message: T) -> Result<(), SendError<Instrumented<T>>> {
+        self.send(id, Instrumented::new(message))
+    }
+}
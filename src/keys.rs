@@ -0,0 +1,125 @@
+use std::{
+    collections::HashMap,
+    pin::pin,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use tokio::sync::{Notify, TryAcquireError};
+
+/// Tracks the number of queued-but-unreceived messages per ID hash for a single consumer.
+pub(crate) struct KeyLimiter {
+    limit: usize,
+    pending: Mutex<HashMap<u64, usize>>,
+    released: Notify,
+    closed: AtomicBool,
+}
+
+impl KeyLimiter {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            pending: Mutex::new(HashMap::new()),
+            released: Notify::new(),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    /// Takes a pending slot for `hash` if it is below the limit.
+    pub(crate) fn try_acquire(&self, hash: u64) -> Result<KeyPermit<'_>, TryAcquireError> {
+        if self.closed.load(Ordering::Acquire) {
+            return Err(TryAcquireError::Closed);
+        }
+
+        let mut pending = self.pending.lock().unwrap();
+        let count = pending.entry(hash).or_default();
+        if *count >= self.limit {
+            return Err(TryAcquireError::NoPermits);
+        }
+        *count += 1;
+
+        Ok(KeyPermit {
+            limiter: self,
+            hash,
+        })
+    }
+
+    /// Waits until `hash` is below the limit and takes a pending slot. Returns `None` if the limiter is closed.
+    pub(crate) async fn acquire(&self, hash: u64) -> Option<KeyPermit<'_>> {
+        loop {
+            let mut released = pin!(self.released.notified());
+            released.as_mut().enable();
+
+            match self.try_acquire(hash) {
+                Ok(permit) => return Some(permit),
+                Err(TryAcquireError::Closed) => return None,
+                Err(TryAcquireError::NoPermits) => released.await,
+            }
+        }
+    }
+
+    /// Takes a pending slot for `hash` regardless of the limit.
+    pub(crate) fn acquire_unchecked(&self, hash: u64) -> KeyPermit<'_> {
+        *self.pending.lock().unwrap().entry(hash).or_default() += 1;
+        KeyPermit {
+            limiter: self,
+            hash,
+        }
+    }
+
+    /// Takes back a pending slot for `hash` that was kept with [`KeyPermit::forget`] before its message was queued.
+    pub(crate) fn adopt(&self, hash: u64) -> KeyPermit<'_> {
+        KeyPermit {
+            limiter: self,
+            hash,
+        }
+    }
+
+    /// Gives back the pending slots of received messages.
+    pub(crate) fn release(&self, hashes: impl IntoIterator<Item = u64>) {
+        let mut released = false;
+        {
+            let mut pending = self.pending.lock().unwrap();
+            for hash in hashes {
+                if let Some(count) = pending.get_mut(&hash) {
+                    *count -= 1;
+                    if *count == 0 {
+                        pending.remove(&hash);
+                    }
+                    released = true;
+                }
+            }
+        }
+
+        if released {
+            self.released.notify_waiters();
+        }
+    }
+
+    /// Fails all current and future acquisitions.
+    pub(crate) fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.released.notify_waiters();
+    }
+}
+
+/// A pending slot for an ID hash, given back on drop unless the message was queued.
+pub(crate) struct KeyPermit<'a> {
+    limiter: &'a KeyLimiter,
+    hash: u64,
+}
+
+impl KeyPermit<'_> {
+    /// Keeps the slot taken until the message is received.
+    pub(crate) fn forget(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for KeyPermit<'_> {
+    fn drop(&mut self) {
+        self.limiter.release([self.hash]);
+    }
+}
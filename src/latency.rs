@@ -0,0 +1,180 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crate::envelope::Envelope;
+
+/// Number of bits below the leading one that select a bucket, which bounds the relative error to 1/32.
+const SUB_BUCKET_BITS: u32 = 5;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+/// Values below `SUB_BUCKETS` get a bucket each, every further power of two is split into `SUB_BUCKETS` buckets.
+const BUCKETS: usize = SUB_BUCKETS + (64 - SUB_BUCKET_BITS as usize) * SUB_BUCKETS;
+
+/// Lock-free histogram of the time messages spent in a single consumer's queue, in nanoseconds.
+///
+/// Buckets are log-linear like in HDR histograms: every power of two is split into 32 equally sized buckets, so a
+/// recorded value is reported with a relative error of at most about 3%.
+pub(crate) struct LatencyHistogram {
+    buckets: Box<[AtomicU64]>,
+    count: AtomicU64,
+    sum: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+}
+
+impl LatencyHistogram {
+    pub(crate) fn new() -> Self {
+        Self {
+            buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    /// Records the queueing delay of a received envelope, if it was stamped when it was sent.
+    pub(crate) fn record<T>(&self, envelope: &Envelope<T>) {
+        if let Some(enqueued) = envelope.enqueued {
+            let nanos = u64::try_from(enqueued.elapsed().as_nanos()).unwrap_or(u64::MAX);
+            self.buckets[bucket(nanos)].fetch_add(1, Ordering::Relaxed);
+            self.count.fetch_add(1, Ordering::Relaxed);
+            self.sum.fetch_add(nanos, Ordering::Relaxed);
+            self.min.fetch_min(nanos, Ordering::Relaxed);
+            self.max.fetch_max(nanos, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn report(&self) -> LatencyReport {
+        LatencyReport {
+            buckets: self
+                .buckets
+                .iter()
+                .map(|bucket| bucket.load(Ordering::Relaxed))
+                .collect(),
+            count: self.count.load(Ordering::Relaxed),
+            sum: self.sum.load(Ordering::Relaxed),
+            min: self.min.load(Ordering::Relaxed),
+            max: self.max.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn bucket(nanos: u64) -> usize {
+    if nanos < SUB_BUCKETS as u64 {
+        return nanos as usize;
+    }
+
+    let exponent = 63 - nanos.leading_zeros();
+    let shift = exponent - SUB_BUCKET_BITS;
+    let sub_bucket = (nanos >> shift) as usize - SUB_BUCKETS;
+    SUB_BUCKETS + shift as usize * SUB_BUCKETS + sub_bucket
+}
+
+/// Returns the largest value that falls into `bucket`.
+fn bucket_upper_bound(bucket: usize) -> u64 {
+    if bucket < SUB_BUCKETS {
+        return bucket as u64;
+    }
+
+    let shift = ((bucket - SUB_BUCKETS) / SUB_BUCKETS) as u32;
+    let sub_bucket = ((bucket - SUB_BUCKETS) % SUB_BUCKETS) as u64;
+    let lower = (SUB_BUCKETS as u64 + sub_bucket) << shift;
+    lower.saturating_add((1 << shift) - 1)
+}
+
+/// A snapshot of the queueing delays of a single consumer.
+///
+/// Returned by [`Sender::latency_report`](crate::Sender::latency_report),
+/// [`Receiver::latency_report`](crate::Receiver::latency_report) and their unbounded counterparts for channels built with
+/// [`track_lag`](crate::StickyChannelBuilder::track_lag). The delay of a message is the time from the moment it was sent
+/// until it was received. Percentiles are accurate to about 3%.
+///
+/// Reports are cumulative since the channel was built. Snapshots are taken without stopping senders and receivers, so
+/// a message received while the report is taken may only be partially reflected in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyReport {
+    buckets: Vec<u64>,
+    count: u64,
+    sum: u64,
+    min: u64,
+    max: u64,
+}
+
+impl LatencyReport {
+    /// Returns the number of messages whose delay has been recorded.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the smallest recorded delay, or zero if nothing has been recorded.
+    pub fn min(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos(self.min)
+    }
+
+    /// Returns the largest recorded delay.
+    pub fn max(&self) -> Duration {
+        Duration::from_nanos(self.max)
+    }
+
+    /// Returns the mean recorded delay, or zero if nothing has been recorded.
+    pub fn mean(&self) -> Duration {
+        match self.count {
+            0 => Duration::ZERO,
+            count => Duration::from_nanos(self.sum / count),
+        }
+    }
+
+    /// Returns the delay that `percentile` percent of the recorded messages did not exceed.
+    ///
+    /// `percentile` is clamped to the range `0.0..=100.0`. Returns zero if nothing has been recorded.
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+
+        let rank =
+            ((percentile.clamp(0.0, 100.0) / 100.0 * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let nanos = bucket_upper_bound(bucket).min(self.max);
+                return Duration::from_nanos(nanos);
+            }
+        }
+        self.max()
+    }
+
+    /// Returns the number of recorded delays not longer than `delay`, up to the bucket resolution of about 3%.
+    #[cfg(feature = "prometheus")]
+    pub(crate) fn count_at_most(&self, delay: Duration) -> u64 {
+        let nanos = u64::try_from(delay.as_nanos()).unwrap_or(u64::MAX);
+        self.buckets
+            .iter()
+            .enumerate()
+            .take_while(|&(bucket, _)| bucket_upper_bound(bucket) <= nanos)
+            .map(|(_, &count)| count)
+            .sum()
+    }
+
+    /// Returns the sum of all recorded delays.
+    #[cfg(feature = "prometheus")]
+    pub(crate) fn sum(&self) -> Duration {
+        Duration::from_nanos(self.sum)
+    }
+
+    /// Returns the median delay.
+    pub fn p50(&self) -> Duration {
+        self.percentile(50.0)
+    }
+
+    /// Returns the 99th percentile delay.
+    pub fn p99(&self) -> Duration {
+        self.percentile(99.0)
+    }
+}
@@ -90,22 +90,231 @@
 //!
 //! - **Unbounded channels**: Memory usage can grow if consumers can't keep up
 //! - **Bounded channels**: Provide backpressure but may block senders when full
-//! - **Hashing overhead**: Each send operation computes a hash of the ID
+//! - **Hashing overhead**: Each send operation computes a hash of the ID; the `ahash` and `fxhash` features switch the
+//!   [`DefaultBuildHasher`] to faster hashers
 //! - **Load distribution**: Hash distribution may not be perfectly even across consumers
+//!
+//! # Runtime Support
+//!
+//! The consumer queues are built on Tokio's `sync` primitives, which do not need a Tokio runtime. Sending and
+//! receiving therefore work on any executor, such as async-std or smol, without a separate backend. The features that
+//! spawn tasks or use Tokio's timers must run inside a Tokio runtime: ticks, skew alarms, [`BatchingSender`],
+//! [`TimeoutSender`], [`Sender::send_with_retry`], [`FanIn`], [`consume_with`] and [`consume_supervised`].
+//!
+//! # Platform Support
+//!
+//! `wasm32-unknown-unknown` is not supported: `std` cannot tell the time on that target, and reading the clock panics
+//! there. The channels read it to track lag, refill bursts, expire last values, take depth snapshots and time
+//! partition rebalancing, and the blocking receive methods park the calling thread, which a browser does not allow.
+//!
+//! The default hasher, [`RandomState`](std::hash::RandomState), seeds its keys from the platform's randomness source
+//! (see [`DefaultBuildHasher`] for the `ahash` and `fxhash` features that replace it). Where that source is
+//! missing or should not be relied upon, create the channel with a [`BuildHasher`](std::hash::BuildHasher) of fixed
+//! keys through [`sticky_channel_with_hasher`] or the builders' `hasher` method.
+//!
+//! ## `no_std`
+//!
+//! The channels need `std` and Tokio, which the default `std` feature enables. Without it, the crate is `no_std` and
+//! only provides the [`routing`] functions and [`SeededState`], so that embedded executors with their own channel
+//! primitives route IDs exactly like the channels do. The other features need `std` and enable it.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+mod adapters;
+#[cfg(feature = "std")]
+mod barrier;
+#[cfg(feature = "std")]
+mod batch;
+#[cfg(feature = "std")]
 mod bounded;
+#[cfg(feature = "std")]
+mod bridge;
+#[cfg(feature = "std")]
+mod burst;
+#[cfg(feature = "bytes")]
+mod bytes_channel;
+#[cfg(feature = "std")]
+mod channel_admin;
+#[cfg(feature = "std")]
+mod compact;
+#[cfg(feature = "std")]
+mod consume;
+#[cfg(feature = "std")]
+mod control;
+#[cfg(feature = "std")]
+mod deadline;
+#[cfg(feature = "std")]
+mod depths;
+#[cfg(feature = "std")]
+mod drain;
+#[cfg(feature = "std")]
+mod envelope;
+#[cfg(feature = "std")]
 mod error;
+#[cfg(feature = "std")]
+mod event;
+#[cfg(feature = "std")]
+mod fan_in;
+mod hasher;
+#[cfg(feature = "std")]
+mod health;
+#[cfg(feature = "std")]
+mod hooks;
+#[cfg(feature = "tracing")]
+mod instrument;
+#[cfg(feature = "std")]
+mod keys;
+#[cfg(feature = "std")]
+mod latency;
+#[cfg(feature = "std")]
+mod meta;
+#[cfg(feature = "std")]
+mod offset;
+#[cfg(feature = "std")]
+mod partition;
+#[cfg(feature = "std")]
+mod prehashed;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+#[cfg(feature = "std")]
+mod queue;
+#[cfg(feature = "std")]
+mod receiver;
+#[cfg(feature = "std")]
+mod replica;
+#[cfg(feature = "std")]
+mod request;
+#[cfg(feature = "std")]
+mod retry;
+#[cfg(feature = "std")]
+mod route;
+pub mod routing;
+#[cfg(feature = "std")]
+mod select;
+#[cfg(feature = "std")]
+mod sequence;
+#[cfg(feature = "std")]
+mod shed;
+#[cfg(feature = "io")]
+mod sink;
+#[cfg(feature = "std")]
+mod sketch;
+#[cfg(feature = "std")]
+mod skew;
+#[cfg(feature = "std")]
+mod split;
+#[cfg(feature = "stream")]
+mod stream;
+#[cfg(feature = "std")]
+mod subscribe;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(feature = "std")]
+mod tick;
+#[cfg(feature = "std")]
+mod totals;
+#[cfg(feature = "std")]
 mod unbounded;
+#[cfg(feature = "std")]
 mod util;
+#[cfg(feature = "std")]
+mod validate;
+#[cfg(feature = "serde")]
+mod wire;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
+#[allow(
+    clippy::needless_borrow,
+    clippy::redundant_pattern_matching,
+    clippy::unnecessary_to_owned,
+    clippy::useless_vec
+)]
 mod tests;
 
+#[cfg(feature = "bytes")]
+pub use self::bytes_channel::{
+    BytesReceiver, BytesSender, sticky_bytes_channel, sticky_bytes_channel_with_hasher,
+};
+
+#[cfg(feature = "stream")]
+pub use self::stream::{MergedStream, StickyReceiverStream, UnboundedStickyReceiverStream, merge};
+
+#[cfg(feature = "sled")]
+pub use self::adapters::SledQueue;
+#[cfg(feature = "tracing")]
+pub use self::instrument::Instrumented;
+#[cfg(feature = "prometheus")]
+pub use self::prometheus::{ChannelMetrics, PrometheusRegistry};
+#[cfg(feature = "io")]
+pub use self::sink::{FlushPolicy, write_to};
+#[cfg(feature = "bincode")]
+pub use self::wire::Bincode;
+#[cfg(feature = "cbor")]
+pub use self::wire::Cbor;
+#[cfg(feature = "json")]
+pub use self::wire::Json;
+#[cfg(feature = "postcard")]
+pub use self::wire::Postcard;
+#[cfg(feature = "serde")]
+pub use self::{
+    error::WireError,
+    wire::{WIRE_VERSION, WireCodec, WireEnvelope, WireMeta},
+};
+
+pub use self::hasher::{SeededHasher, SeededState};
+#[cfg(feature = "std")]
 pub use self::{
-    bounded::{Receiver, Sender, sticky_channel, sticky_channel_with_hasher},
-    error::{SendError, TryRecvError},
+    adapters::{
+        ConsumerQueue, Delivery, Demux, Eviction, FairReceiver, FilterReceiver, PriorityQueue,
+        QueuedReceiver, RedeliveryReceiver, ReorderReceiver, SequencedReceiver, SharedReceiver,
+    },
+    barrier::{Barrier, BarrierId},
+    batch::{BatchingSender, BufferedSender, UnboundedBatchingSender, UnboundedBufferedSender},
+    bounded::{
+        KeyedPermit, KeyedSender, MappedSender, PollStickySender, PriorityReceiver, Receiver,
+        Sender, StickyChannelBuilder, TimeoutSender, sticky_channel, sticky_channel_with_hasher,
+        sticky_channel_with_meta, sticky_channel_with_seed, sticky_priority_channel,
+    },
+    bridge::{StdBridge, bridge_to_std},
+    burst::BurstReport,
+    channel_admin::ChannelAdmin,
+    compact::EvictionPolicy,
+    consume::{
+        ConsumeHandle, ConsumeProgress, HandlerPanic, Restart, Supervision, consume_supervised,
+        consume_with,
+    },
+    control::{Command, ControlSender, EventReceiver, control_channel},
+    deadline::{Remaining, WithDeadline},
+    depths::DepthSnapshot,
+    drain::{DrainReport, drain_all},
+    error::{
+        BarrierError, BatchSendResult, CommandError, KeyedSendError, QuorumError, Rejection,
+        SendError, TryRecvError,
+    },
+    event::Event,
+    fan_in::{FanIn, StickySender, rekey},
+    hasher::DefaultBuildHasher,
+    health::ChannelHealth,
+    latency::LatencyReport,
+    meta::{MessageMeta, WithMeta},
+    partition::{Admin, PartitionMove, RebalanceReport},
+    prehashed::PreHashed,
+    receiver::StickyReceiver,
+    replica::{Replica, ReplicatedSender},
+    request::{Gathered, Request, Responder},
+    retry::{Backoff, RetryPolicy},
+    route::{RoutedMessage, StickyRoute},
+    select::recv_first,
+    sequence::SequencedSender,
+    shed::{SamplingPolicy, ShedCounts, ShedReason, SheddingSender},
+    skew::SkewReport,
+    split::{HotKeySplitter, Reassembler, Sequenced},
+    subscribe::KeyReceiver,
+    totals::{Reconciliation, Totals},
     unbounded::{
-        UnboundedReceiver, UnboundedSender, unbounded_sticky_channel,
-        unbounded_sticky_channel_with_hasher,
+        UnboundedReceiver, UnboundedSender, UnboundedStickyChannelBuilder,
+        unbounded_sticky_channel, unbounded_sticky_channel_with_hasher,
+        unbounded_sticky_channel_with_seed,
     },
 };
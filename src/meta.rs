@@ -0,0 +1,88 @@
+use std::hash::{BuildHasher, Hash};
+
+use tokio::time::Instant;
+
+use crate::{SendError, Sender, UnboundedSender};
+
+/// Queueing information of a received message, as returned by
+/// [`Receiver::recv_with_meta`](crate::Receiver::recv_with_meta) and
+/// [`UnboundedReceiver::recv_with_meta`](crate::UnboundedReceiver::recv_with_meta).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageMeta {
+    /// When the message was sent, if the channel was built with
+    /// [`track_lag`](crate::StickyChannelBuilder::track_lag).
+    pub enqueued_at: Option<Instant>,
+    /// [`producer_id`](crate::Sender::producer_id) of the sender clone the message was sent from, or `0` for messages
+    /// injected by the channel itself, such as ticks.
+    pub producer: u64,
+    /// Delivery attempt of the message, starting at `1`.
+    ///
+    /// Receivers deliver every message once, so this is always `1`. A
+    /// [`RedeliveryReceiver`](crate::RedeliveryReceiver) counts redeliveries with
+    /// [`Delivery::attempt`](crate::Delivery::attempt).
+    pub attempt: u32,
+}
+
+/// A message delivered together with metadata attached when it was sent.
+///
+/// Channels of `WithMeta` messages, such as the one created by
+/// [`sticky_channel_with_meta`](crate::sticky_channel_with_meta), carry cross-cutting data like correlation IDs or
+/// tenants next to the payload instead of inside it. Send them with [`Sender::send_with_meta`] and its siblings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WithMeta<T, M> {
+    /// The message itself.
+    pub message: T,
+    /// The metadata attached to the message.
+    pub meta: M,
+}
+
+impl<T, M> WithMeta<T, M> {
+    /// Returns the message and its metadata.
+    pub fn into_parts(self) -> (T, M) {
+        (self.message, self.meta)
+    }
+}
+
+impl<ID, T, M, S> Sender<ID, WithMeta<T, M>, S>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a message with its metadata, waiting for capacity like [`send`](Sender::send).
+    ///
+    /// Errors carry both the message and the metadata.
+    pub async fn send_with_meta(
+        &self,
+        id: ID,
+        message: T,
+        meta: M,
+    ) -> Result<(), SendError<WithMeta<T, M>>> {
+        self.send(id, WithMeta { message, meta }).await
+    }
+
+    /// Sends a message with its metadata without waiting, like [`try_send`](Sender::try_send).
+    pub fn try_send_with_meta(
+        &self,
+        id: ID,
+        message: T,
+        meta: M,
+    ) -> Result<(), SendError<WithMeta<T, M>>> {
+        self.try_send(id, WithMeta { message, meta })
+    }
+}
+
+impl<ID, T, M, S> UnboundedSender<ID, WithMeta<T, M>, S>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a message with its metadata, like [`send`](UnboundedSender::send).
+    pub fn send_with_meta(
+        &self,
+        id: ID,
+        message: T,
+        meta: M,
+    ) -> Result<(), SendError<WithMeta<T, M>>> {
+        self.send(id, WithMeta { message, meta })
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+/// Delivery offsets of a single consumer.
+///
+/// Every message delivered by a receiver gets the next offset of its consumer, starting at zero. Committed offsets are
+/// shared with the senders, so they can be queried for the whole channel.
+pub(crate) struct Offsets {
+    delivered: u64,
+    committed: Arc<AtomicU64>,
+}
+
+impl Offsets {
+    pub(crate) fn new(committed: Arc<AtomicU64>) -> Self {
+        Self {
+            delivered: 0,
+            committed,
+        }
+    }
+
+    /// Returns the offset of the next message to be delivered.
+    pub(crate) fn next(&self) -> u64 {
+        self.delivered
+    }
+
+    /// Counts `count` messages as delivered.
+    pub(crate) fn delivered(&mut self, count: usize) {
+        self.delivered += count as u64;
+    }
+
+    pub(crate) fn commit(&self, offset: u64) {
+        assert!(
+            offset < self.delivered,
+            "offset {offset} has not been delivered yet"
+        );
+        // Stored one higher, so that zero means nothing was committed.
+        self.committed.fetch_max(offset + 1, Ordering::AcqRel);
+    }
+
+    pub(crate) fn committed(&self) -> Option<u64> {
+        committed(&self.committed)
+    }
+}
+
+/// Reads the committed offset shared by a consumer and its receiver.
+pub(crate) fn committed(offset: &AtomicU64) -> Option<u64> {
+    offset.load(Ordering::Acquire).checked_sub(1)
+}
@@ -0,0 +1,290 @@
+use std::{
+    pin::pin,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{sync::Notify, time::Instant};
+
+use crate::util::Route;
+
+/// Maps ID hashes to partitions and partitions to consumers, for channels built with a fixed number of partitions.
+pub(crate) struct PartitionTable {
+    /// Index of the consumer every partition is assigned to.
+    assignment: Box<[AtomicUsize]>,
+    /// Number of queued-but-unreceived messages of every partition.
+    pending: Box<[AtomicUsize]>,
+    num_consumers: usize,
+    drained: Notify,
+}
+
+impl PartitionTable {
+    /// Creates a table that spreads `partitions` partitions round-robin over `num_consumers` consumers.
+    pub(crate) fn new(partitions: usize, num_consumers: usize) -> Self {
+        Self {
+            assignment: (0..partitions)
+                .map(|partition| AtomicUsize::new(partition % num_consumers))
+                .collect(),
+            pending: (0..partitions).map(|_| AtomicUsize::new(0)).collect(),
+            num_consumers,
+            drained: Notify::new(),
+        }
+    }
+
+    pub(crate) fn partition(&self, hash: u64) -> usize {
+        (hash % self.assignment.len() as u64) as usize
+    }
+
+    /// Redirects a route to the consumer its partition is currently assigned to.
+    pub(crate) fn route(&self, route: Route) -> Route {
+        let index = self.assignment[self.partition(route.hash)].load(Ordering::Acquire);
+        Route { index, ..route }
+    }
+
+    /// Counts a message of the ID with `hash` as queued.
+    pub(crate) fn enqueued(&self, hash: u64) {
+        self.pending[self.partition(hash)].fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Counts messages of the IDs with the given hashes as received, or as no longer queued.
+    pub(crate) fn received(&self, hashes: impl IntoIterator<Item = u64>) {
+        let mut drained = false;
+        for hash in hashes {
+            drained |= self.pending[self.partition(hash)].fetch_sub(1, Ordering::AcqRel) == 1;
+        }
+
+        if drained {
+            self.drained.notify_waiters();
+        }
+    }
+}
+
+/// Move of a single partition, as listed in a [`RebalanceReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionMove {
+    /// Index of the partition.
+    pub partition: usize,
+    /// Index of the consumer the partition was assigned to before.
+    pub from: usize,
+    /// Index of the consumer the partition is assigned to now.
+    pub to: usize,
+    /// Messages of the partition the old consumer received before the move. Always `0` for
+    /// [`rebalance`](Admin::rebalance).
+    pub drained: usize,
+    /// Messages of the partition still queued for the old consumer when it moved. The old consumer still receives them.
+    pub left_behind: usize,
+}
+
+/// Outcome of a rebalancing, as returned by [`Admin::rebalance`] and [`Admin::rebalance_drained`].
+///
+/// Queued messages are never moved between consumers, so the impact of a rebalancing is described by the messages
+/// drained before moving a partition and the messages left behind with its old consumer.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RebalanceReport {
+    /// Partitions that were assigned to another consumer, in ascending order. Partitions that kept their consumer are
+    /// not listed.
+    pub moves: Vec<PartitionMove>,
+    /// Time taken by the rebalancing, including waiting for backlogs to drain.
+    pub elapsed: Duration,
+}
+
+impl RebalanceReport {
+    /// Returns the number of messages drained before their partitions moved.
+    pub fn drained(&self) -> usize {
+        self.moves.iter().map(|moved| moved.drained).sum()
+    }
+
+    /// Returns the number of messages left behind with the old consumers of their partitions.
+    pub fn left_behind(&self) -> usize {
+        self.moves.iter().map(|moved| moved.left_behind).sum()
+    }
+}
+
+/// Runtime administration of the partitions of a channel built with a fixed number of partitions.
+///
+/// A partitioned channel routes every ID to one of a fixed number of partitions by its hash, and every partition to a
+/// consumer. Reassigning a partition moves all of its IDs to another consumer at once, which allows manual load
+/// balancing at runtime without changing which IDs are kept together. Get a handle from
+/// [`ChannelAdmin::partitions`](crate::ChannelAdmin::partitions) for a channel built with
+/// [`StickyChannelBuilder::partitions`](crate::StickyChannelBuilder::partitions) or
+/// [`UnboundedStickyChannelBuilder::partitions`](crate::UnboundedStickyChannelBuilder::partitions).
+///
+/// Messages queued before a reassignment stay with the old consumer, so for a while the messages of a partition may be
+/// processed by two consumers at once. [`reassign_drained`](Admin::reassign_drained) waits for the old consumer to
+/// receive them first.
+#[derive(Clone)]
+pub struct Admin {
+    table: Arc<PartitionTable>,
+}
+
+impl Admin {
+    pub(crate) fn new(table: Arc<PartitionTable>) -> Self {
+        Self { table }
+    }
+
+    /// Returns the number of partitions.
+    pub fn partitions(&self) -> usize {
+        self.table.assignment.len()
+    }
+
+    /// Returns the number of consumers partitions can be assigned to.
+    pub(crate) fn consumers(&self) -> usize {
+        self.table.num_consumers
+    }
+
+    /// Returns the index of the consumer `partition` is assigned to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `partition` is out of range.
+    pub fn consumer_of(&self, partition: usize) -> usize {
+        self.table.assignment[partition].load(Ordering::Acquire)
+    }
+
+    /// Returns the number of messages of `partition` that have been sent but not received yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `partition` is out of range.
+    pub fn backlog(&self, partition: usize) -> usize {
+        self.table.pending[partition].load(Ordering::Acquire)
+    }
+
+    /// Assigns `partition` to the consumer at index `consumer`, returning the consumer it was assigned to before.
+    ///
+    /// Messages sent from now on are delivered to the new consumer. Messages already queued are still received by the
+    /// old one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `partition` or `consumer` is out of range.
+    pub fn reassign(&self, partition: usize, consumer: usize) -> usize {
+        assert!(
+            consumer < self.table.num_consumers,
+            "consumer index {consumer} out of range"
+        );
+        self.table.assignment[partition].swap(consumer, Ordering::AcqRel)
+    }
+
+    /// Waits until every message of `partition` sent so far has been received.
+    ///
+    /// Under continuous traffic for the partition, or if a receiver holding some of its messages was dropped, this may
+    /// never finish; pause the partition's producers first, or bound the wait with a timeout.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `partition` is out of range.
+    pub async fn drain(&self, partition: usize) {
+        loop {
+            let mut drained = pin!(self.table.drained.notified());
+            drained.as_mut().enable();
+
+            if self.backlog(partition) == 0 {
+                return;
+            }
+            drained.await;
+        }
+    }
+
+    /// Waits for the backlog of `partition` to be received and then assigns it to the consumer at index `consumer`.
+    ///
+    /// Returns the consumer the partition was assigned to before. Messages sent while waiting still go to the old
+    /// consumer, see [`drain`](Admin::drain). If the partition's producers are paused until this method returns, the
+    /// messages of the partition are never queued for two consumers at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `partition` or `consumer` is out of range.
+    pub async fn reassign_drained(&self, partition: usize, consumer: usize) -> usize {
+        self.drain(partition).await;
+        self.reassign(partition, consumer)
+    }
+
+    /// Assigns every partition to the consumer at the same position in `assignment` and reports the partitions that
+    /// moved.
+    ///
+    /// Like [`reassign`](Admin::reassign), messages already queued are still received by the old consumers; they are
+    /// reported as [`left_behind`](PartitionMove::left_behind).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `assignment` does not have one entry per partition or if a consumer index is out of range.
+    pub fn rebalance(&self, assignment: &[usize]) -> RebalanceReport {
+        let start = Instant::now();
+        self.check_assignment(assignment);
+
+        let moves = assignment
+            .iter()
+            .enumerate()
+            .filter_map(|(partition, &to)| {
+                let from = self.reassign(partition, to);
+                (from != to).then(|| PartitionMove {
+                    partition,
+                    from,
+                    to,
+                    drained: 0,
+                    left_behind: self.backlog(partition),
+                })
+            })
+            .collect();
+
+        RebalanceReport {
+            moves,
+            elapsed: start.elapsed(),
+        }
+    }
+
+    /// Like [`rebalance`](Admin::rebalance), but moves every partition like
+    /// [`reassign_drained`](Admin::reassign_drained), one after the other.
+    ///
+    /// The backlog of a partition when its wait started is reported as [`drained`](PartitionMove::drained). See
+    /// [`drain`](Admin::drain) for backlogs that may never drain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `assignment` does not have one entry per partition or if a consumer index is out of range.
+    pub async fn rebalance_drained(&self, assignment: &[usize]) -> RebalanceReport {
+        let start = Instant::now();
+        self.check_assignment(assignment);
+
+        let mut moves = Vec::new();
+        for (partition, &to) in assignment.iter().enumerate() {
+            if self.consumer_of(partition) == to {
+                continue;
+            }
+            let drained = self.backlog(partition);
+            let from = self.reassign_drained(partition, to).await;
+            if from != to {
+                moves.push(PartitionMove {
+                    partition,
+                    from,
+                    to,
+                    drained,
+                    left_behind: self.backlog(partition),
+                });
+            }
+        }
+
+        RebalanceReport {
+            moves,
+            elapsed: start.elapsed(),
+        }
+    }
+
+    fn check_assignment(&self, assignment: &[usize]) {
+        assert_eq!(
+            assignment.len(),
+            self.partitions(),
+            "assignment must have one entry per partition"
+        );
+        if let Some(consumer) = assignment
+            .iter()
+            .find(|&&consumer| consumer >= self.table.num_consumers)
+        {
+            panic!("consumer index {consumer} out of range");
+        }
+    }
+}
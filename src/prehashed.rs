@@ -0,0 +1,68 @@
+use std::{
+    hash::{BuildHasher, Hash, Hasher},
+    ops::Deref,
+};
+
+use crate::routing::hash_id;
+
+/// An ID whose hash is computed once, when it is created.
+///
+/// Create it with [`Sender::prehash`](crate::Sender::prehash) or
+/// [`UnboundedSender::prehash`](crate::UnboundedSender::prehash) and send with `send_prehashed`: the cached hash is
+/// routed as is, so a large composite ID that is sent many times is only hashed once. The hash is computed with the
+/// channel's own hasher, so a `PreHashed` ID is routed to the same consumer as the plain ID and keeps the HashDoS
+/// protection of a randomly keyed hasher. With the `stable-routing` feature, the channel's default hasher is keyed
+/// with zeros and the cached hash is the same in every build, like the routes of plain IDs.
+///
+/// A `PreHashed` ID is only meaningful for channels whose hasher it was created with. Hashing it, for example as the
+/// key of a map, only feeds the cached hash to the hasher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PreHashed<ID> {
+    id: ID,
+    hash: u64,
+}
+
+impl<ID> PreHashed<ID>
+where
+    ID: Hash,
+{
+    /// Hashes an ID with `build_hasher` and caches the hash.
+    ///
+    /// `build_hasher` must be the hasher of the channel the ID is sent on, otherwise the ID is routed as if it were a
+    /// different one.
+    pub fn new<S>(id: ID, build_hasher: &S) -> Self
+    where
+        S: BuildHasher,
+    {
+        Self {
+            hash: hash_id(&id, build_hasher),
+            id,
+        }
+    }
+}
+
+impl<ID> PreHashed<ID> {
+    /// Returns the cached hash.
+    pub fn hash_value(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns the ID.
+    pub fn into_inner(self) -> ID {
+        self.id
+    }
+}
+
+impl<ID> Hash for PreHashed<ID> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+impl<ID> Deref for PreHashed<ID> {
+    type Target = ID;
+
+    fn deref(&self) -> &ID {
+        &self.id
+    }
+}
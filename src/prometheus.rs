@@ -0,0 +1,258 @@
+use std::{
+    fmt::Write,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::{latency::LatencyHistogram, totals::Counters};
+
+/// Upper bounds of the buckets of the queueing delay histogram, in seconds.
+const DELAY_BUCKETS: [f64; 8] = [1e-6, 1e-5, 1e-4, 1e-3, 1e-2, 1e-1, 1.0, 10.0];
+
+/// Handle to the metrics of a single channel, as returned by [`Sender::metrics`](crate::Sender::metrics) and
+/// [`UnboundedSender::metrics`](crate::UnboundedSender::metrics).
+///
+/// The handle only shares the channel's counters, so holding it does not keep the channel open.
+#[derive(Clone)]
+pub struct ChannelMetrics {
+    counters: Arc<Counters>,
+    depths: Vec<Arc<AtomicUsize>>,
+    latencies: Vec<Option<Arc<LatencyHistogram>>>,
+}
+
+impl ChannelMetrics {
+    pub(crate) fn new(
+        counters: Arc<Counters>,
+        depths: Vec<Arc<AtomicUsize>>,
+        latencies: Vec<Option<Arc<LatencyHistogram>>>,
+    ) -> Self {
+        Self {
+            counters,
+            depths,
+            latencies,
+        }
+    }
+
+    /// Returns the metrics of this channel alone in the Prometheus text exposition format, labelled with
+    /// `channel="<name>"`.
+    pub fn gather(&self, name: &str) -> String {
+        let mut out = String::new();
+        encode(&mut out, [(name, self)]);
+        out
+    }
+}
+
+impl std::fmt::Debug for ChannelMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChannelMetrics")
+            .field("consumers", &self.depths.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Collection of named channels whose metrics are published together in the Prometheus text exposition format.
+///
+/// Every consumer of a registered channel is reported with the labels `channel` and `consumer`, the index of its
+/// receiver:
+///
+/// - `sticky_channel_queue_depth`: gauge of the messages queued for the consumer.
+/// - `sticky_channel_messages_sent_total`: counter of the messages queued for the consumer, whose rate is the
+///   consumer's throughput.
+/// - `sticky_channel_messages_received_total`: counter of the messages received by the consumer.
+/// - `sticky_channel_messages_dropped_total`: counter of the messages lost in the consumer's dropped receiver.
+/// - `sticky_channel_queue_delay_seconds`: histogram of the time messages spent queued, for channels built with
+///   `track_lag`. Bucket counts are accurate to about 3% of the bucket bounds.
+///
+/// Serve the output of [`gather`](Self::gather) from the scrape endpoint of any HTTP server. Clones share the
+/// registered channels.
+///
+/// ```rust
+/// use std::num::NonZeroUsize;
+/// use tokio_sticky_channel::{PrometheusRegistry, unbounded_sticky_channel};
+///
+/// let (sender, _receivers) = unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(2).unwrap());
+/// let registry = PrometheusRegistry::new();
+/// registry.register("orders", sender.metrics());
+///
+/// sender.send(7, 1).unwrap();
+/// assert!(registry.gather().contains("sticky_channel_messages_sent_total{channel=\"orders\""));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PrometheusRegistry {
+    channels: Arc<Mutex<Vec<(String, ChannelMetrics)>>>,
+}
+
+impl PrometheusRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the metrics of a channel under `name`, replacing a channel registered under the same name before.
+    pub fn register(&self, name: impl Into<String>, metrics: ChannelMetrics) {
+        let name = name.into();
+        let mut channels = self.channels.lock().unwrap();
+        match channels
+            .iter_mut()
+            .find(|(registered, _)| *registered == name)
+        {
+            Some((_, registered)) => *registered = metrics,
+            None => channels.push((name, metrics)),
+        }
+    }
+
+    /// Removes the channel registered under `name`, returning whether there was one.
+    pub fn unregister(&self, name: &str) -> bool {
+        let mut channels = self.channels.lock().unwrap();
+        let len = channels.len();
+        channels.retain(|(registered, _)| registered != name);
+        channels.len() != len
+    }
+
+    /// Returns the current metrics of all registered channels in the Prometheus text exposition format.
+    pub fn gather(&self) -> String {
+        let channels = self.channels.lock().unwrap();
+        let mut out = String::new();
+        encode(
+            &mut out,
+            channels
+                .iter()
+                .map(|(name, metrics)| (name.as_str(), metrics)),
+        );
+        out
+    }
+}
+
+/// Writes the metric families of `channels`, each family once with the samples of every channel.
+fn encode<'a>(
+    out: &mut String,
+    channels: impl IntoIterator<Item = (&'a str, &'a ChannelMetrics)> + Clone,
+) {
+    family(
+        out,
+        "sticky_channel_queue_depth",
+        "gauge",
+        "Messages queued for a consumer.",
+    );
+    for (name, metrics) in channels.clone() {
+        for (consumer, depth) in metrics.depths.iter().enumerate() {
+            sample(
+                out,
+                "sticky_channel_queue_depth",
+                name,
+                consumer,
+                "",
+                depth.load(Ordering::Relaxed),
+            );
+        }
+    }
+
+    let counts = [
+        (
+            "sticky_channel_messages_sent_total",
+            "Messages queued for a consumer.",
+        ),
+        (
+            "sticky_channel_messages_received_total",
+            "Messages received by a consumer.",
+        ),
+        (
+            "sticky_channel_messages_dropped_total",
+            "Messages lost in the dropped receiver of a consumer.",
+        ),
+    ];
+    for (metric, help) in counts {
+        family(out, metric, "counter", help);
+        for (name, metrics) in channels.clone() {
+            for reconciliation in metrics.counters.reconcile() {
+                let value = match metric {
+                    "sticky_channel_messages_sent_total" => reconciliation.accepted,
+                    "sticky_channel_messages_received_total" => reconciliation.consumed,
+                    _ => reconciliation.dropped,
+                };
+                sample(out, metric, name, reconciliation.consumer_index, "", value);
+            }
+        }
+    }
+
+    family(
+        out,
+        "sticky_channel_queue_delay_seconds",
+        "histogram",
+        "Time messages spent queued for a consumer.",
+    );
+    for (name, metrics) in channels {
+        for (consumer, latency) in metrics.latencies.iter().enumerate() {
+            let Some(latency) = latency else {
+                continue;
+            };
+            let report = latency.report();
+            for bound in DELAY_BUCKETS {
+                let count = report.count_at_most(Duration::from_secs_f64(bound));
+                let le = format!(",le=\"{bound}\"");
+                sample(
+                    out,
+                    "sticky_channel_queue_delay_seconds_bucket",
+                    name,
+                    consumer,
+                    &le,
+                    count,
+                );
+            }
+            let total = report.count();
+            sample(
+                out,
+                "sticky_channel_queue_delay_seconds_bucket",
+                name,
+                consumer,
+                ",le=\"+Inf\"",
+                total,
+            );
+            let sum = report.sum().as_secs_f64();
+            sample(
+                out,
+                "sticky_channel_queue_delay_seconds_sum",
+                name,
+                consumer,
+                "",
+                sum,
+            );
+            sample(
+                out,
+                "sticky_channel_queue_delay_seconds_count",
+                name,
+                consumer,
+                "",
+                total,
+            );
+        }
+    }
+}
+
+fn family(out: &mut String, metric: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {metric} {help}");
+    let _ = writeln!(out, "# TYPE {metric} {kind}");
+}
+
+fn sample(
+    out: &mut String,
+    metric: &str,
+    channel: &str,
+    consumer: usize,
+    extra: &str,
+    value: impl std::fmt::Display,
+) {
+    let _ = write!(out, "{metric}{{channel=\"");
+    for c in channel.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    let _ = writeln!(out, "\",consumer=\"{consumer}\"{extra}}} {value}");
+}
@@ -0,0 +1,304 @@
+use std::{
+    mem,
+    num::NonZeroUsize,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{
+    sync::mpsc::{UnboundedSender as MpscSender, WeakUnboundedSender as WeakMpscSender},
+    time::{Instant, MissedTickBehavior, interval_at},
+};
+
+use crate::envelope::{Envelope, Payload, Slot};
+
+/// Sending end of a single consumer's internal queue.
+///
+/// Without block framing, envelopes go straight into the underlying channel. With block framing, they are collected in
+/// the consumer's open [`Block`] instead, see [`Block::push`]. Everything sent to a consumer, including markers, goes
+/// through its queue so that nothing can overtake the open block.
+pub(crate) struct Queue<T> {
+    pub(crate) sender: MpscSender<Envelope<T>>,
+    pub(crate) block: Option<Arc<Block<T>>>,
+    /// Set by [`close`](Queue::close), after which nothing but the closing marker is sent.
+    closed: Arc<AtomicBool>,
+}
+
+impl<T> Queue<T> {
+    pub(crate) fn new(sender: MpscSender<Envelope<T>>, block: Option<Block<T>>) -> Self {
+        Self {
+            sender,
+            block: block.map(Arc::new),
+            closed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Sends an envelope, handing it back if the queue or the receiver has been closed, or the receiver dropped.
+    pub(crate) fn send(&self, envelope: Envelope<T>) -> Result<(), Envelope<T>> {
+        if self.closed.load(Ordering::Acquire) {
+            return Err(envelope);
+        }
+        self.push(envelope)
+    }
+
+    fn push(&self, envelope: Envelope<T>) -> Result<(), Envelope<T>> {
+        match &self.block {
+            Some(block) => block.push(&self.sender, envelope),
+            None => self.sender.send(envelope).map_err(|err| err.0),
+        }
+    }
+
+    /// Fails every further send and queues a [`Payload::Close`] marker, which closes the receiver once it is reached.
+    ///
+    /// A send that raced with the call may still be queued behind the marker. The receiver drains it after closing,
+    /// like any message that was buffered when it closed. Returns `false` if the queue was already closed or the
+    /// receiver has been closed or dropped.
+    pub(crate) fn close(&self) -> bool {
+        !self.closed.swap(true, Ordering::AcqRel)
+            && self
+                .push(Envelope {
+                    payload: Payload::Close,
+                    hash: 0,
+                    slot: Slot::Injected,
+                    enqueued: None,
+                    producer: 0,
+                })
+                .is_ok()
+    }
+
+    /// Signals the open block to the receiver if it holds envelopes that have not been signalled yet.
+    ///
+    /// Returns `false` if the receiver has been closed or dropped.
+    fn signal(&self) -> bool {
+        match &self.block {
+            Some(block) => block.signal(&self.sender),
+            None => !self.sender.is_closed(),
+        }
+    }
+
+    /// Returns a handle that does not keep the channel open.
+    pub(crate) fn downgrade(&self) -> WeakQueue<T> {
+        WeakQueue {
+            sender: self.sender.downgrade(),
+            block: self.block.clone(),
+            closed: self.closed.clone(),
+        }
+    }
+}
+
+impl<T> Clone for Queue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            block: self.block.clone(),
+            closed: self.closed.clone(),
+        }
+    }
+}
+
+/// A [`Queue`] handle that does not keep the channel open.
+pub(crate) struct WeakQueue<T> {
+    sender: WeakMpscSender<Envelope<T>>,
+    block: Option<Arc<Block<T>>>,
+    closed: Arc<AtomicBool>,
+}
+
+impl<T> WeakQueue<T> {
+    /// Returns the queue if the channel still has senders.
+    pub(crate) fn upgrade(&self) -> Option<Queue<T>> {
+        Some(Queue {
+            sender: self.sender.upgrade()?,
+            block: self.block.clone(),
+            closed: self.closed.clone(),
+        })
+    }
+}
+
+/// Envelopes collected for a consumer but not pushed into its channel yet.
+///
+/// The first envelope added to an empty block pushes a [`Payload::Block`] signal carrying the block's generation.
+/// Further envelopes join the block without touching the channel, until either the block is full and the sender pushes
+/// it as a single [`Payload::Batch`], or the receiver reaches the signal and takes the block. Both start a new
+/// generation, so a signal whose block has already been pushed as a batch is ignored by the receiver.
+///
+/// A coalesced block does not push its signal when the first envelope is added. The signal is pushed by the task
+/// started by a [`Coalescer`] instead, so the receiver is woken at most once per period for all envelopes added in
+/// between. Envelopes that are still unsignalled when the last sender is dropped are taken by the receiver once its
+/// channel is empty, see [`take_all`](Block::take_all).
+pub(crate) struct Block<T> {
+    state: Mutex<BlockState<T>>,
+    size: usize,
+    coalesced: bool,
+}
+
+struct BlockState<T> {
+    envelopes: Vec<Envelope<T>>,
+    generation: u64,
+    signalled: bool,
+}
+
+impl<T> Block<T> {
+    /// Creates the block of a consumer if block framing or coalesced wakeups are enabled.
+    ///
+    /// Coalesced blocks without a size are only pushed as a batch once they hold `usize::MAX` envelopes, so in practice
+    /// they are only ever handed out through their signal.
+    pub(crate) fn new(size: Option<NonZeroUsize>, max_delay: Option<Duration>) -> Option<Self> {
+        if size.is_none() && max_delay.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            state: Mutex::new(BlockState {
+                envelopes: Vec::new(),
+                generation: 0,
+                signalled: false,
+            }),
+            size: size.map_or(usize::MAX, NonZeroUsize::get),
+            coalesced: max_delay.is_some(),
+        })
+    }
+
+    fn push(
+        &self,
+        sender: &MpscSender<Envelope<T>>,
+        envelope: Envelope<T>,
+    ) -> Result<(), Envelope<T>> {
+        let mut state = self.state.lock().unwrap();
+        if sender.is_closed() {
+            return Err(envelope);
+        }
+
+        state.envelopes.push(envelope);
+
+        // If the receiver closes in between, the envelope is handed back. The rest of a batch is dropped with it, just
+        // like queued messages.
+        if state.envelopes.len() >= self.size {
+            let envelopes = state.take();
+            if let Err(err) = sender.send(signal(Payload::Batch(envelopes))) {
+                let Payload::Batch(mut envelopes) = err.0.payload else {
+                    unreachable!("a batch is handed back as sent")
+                };
+                return Err(envelopes.pop().expect("a batch is never empty"));
+            }
+        } else if state.envelopes.len() == 1
+            && !self.coalesced
+            && sender
+                .send(signal(Payload::Block(state.generation)))
+                .is_err()
+        {
+            return Err(state.envelopes.pop().expect("the envelope was just pushed"));
+        }
+
+        Ok(())
+    }
+
+    /// Pushes the signal of a coalesced block that holds unsignalled envelopes.
+    ///
+    /// Returns `false` if the receiver has been closed or dropped.
+    fn signal(&self, sender: &MpscSender<Envelope<T>>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.envelopes.is_empty() || state.signalled {
+            return !sender.is_closed();
+        }
+
+        state.signalled = true;
+        sender
+            .send(signal(Payload::Block(state.generation)))
+            .is_ok()
+    }
+
+    /// Takes the envelopes of the block with the given generation, if it has not been pushed as a batch.
+    pub(crate) fn take(&self, generation: u64) -> Vec<Envelope<T>> {
+        let mut state = self.state.lock().unwrap();
+        if state.generation != generation {
+            return Vec::new();
+        }
+
+        state.take()
+    }
+
+    /// Takes the envelopes of the open block, whatever its generation.
+    ///
+    /// The receiver takes them once its channel is empty and every sender is gone, so that envelopes of a coalesced
+    /// block whose signal was never pushed are not lost.
+    pub(crate) fn take_all(&self) -> Vec<Envelope<T>> {
+        self.state.lock().unwrap().take()
+    }
+
+    /// Drops the envelopes of the open block once its receiver is gone.
+    ///
+    /// The block is shared with every sender, so without this its envelopes, including markers waiting to be
+    /// acknowledged, would live until the last sender is dropped. The receiver must be closed first, so that nothing
+    /// joins the block afterwards.
+    pub(crate) fn clear(&self) {
+        drop(self.take_all());
+    }
+}
+
+impl<T> BlockState<T> {
+    /// Takes the envelopes and starts a new generation.
+    fn take(&mut self) -> Vec<Envelope<T>> {
+        self.generation += 1;
+        self.signalled = false;
+        mem::take(&mut self.envelopes)
+    }
+}
+
+/// Starts the task that signals the coalesced blocks of a channel once its internal queues exist.
+pub(crate) struct Coalescer<T> {
+    max_delay: Duration,
+    start: Box<dyn FnOnce(Vec<WeakQueue<T>>) + Send>,
+}
+
+impl<T> Coalescer<T> {
+    pub(crate) fn new(max_delay: Duration) -> Self
+    where
+        T: Send + 'static,
+    {
+        Self {
+            max_delay,
+            start: Box::new(move |queues| coalesce(queues, max_delay)),
+        }
+    }
+
+    pub(crate) fn max_delay(&self) -> Duration {
+        self.max_delay
+    }
+
+    pub(crate) fn start(self, queues: Vec<WeakQueue<T>>) {
+        (self.start)(queues);
+    }
+}
+
+/// Spawns a task that pushes the signals of the coalesced blocks of `queues` once per `max_delay`.
+///
+/// The task only holds weak handles to the queues, so it does not keep the channel open. It stops once every sender
+/// has been dropped or every receiver has been closed.
+fn coalesce<T>(mut queues: Vec<WeakQueue<T>>, max_delay: Duration)
+where
+    T: Send + 'static,
+{
+    crate::util::spawn("sticky-channel wakeups", async move {
+        let mut interval = interval_at(Instant::now() + max_delay, max_delay);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        while !queues.is_empty() {
+            interval.tick().await;
+
+            queues.retain(|queue| queue.upgrade().is_some_and(|queue| queue.signal()));
+        }
+    });
+}
+
+fn signal<T>(payload: Payload<T>) -> Envelope<T> {
+    Envelope {
+        payload,
+        hash: 0,
+        slot: Slot::Injected,
+        enqueued: None,
+        producer: 0,
+    }
+}
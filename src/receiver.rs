@@ -0,0 +1,95 @@
+use std::task::{Context, Poll};
+
+use crate::{Event, Receiver, TryRecvError, UnboundedReceiver};
+
+/// Common interface of the receiving halves of sticky channels.
+///
+/// This trait is implemented by [`Receiver`] and [`UnboundedReceiver`] as well as by the receiver adapters of this
+/// crate, so adapters work with both channel flavours and can be stacked on top of each other.
+pub trait StickyReceiver {
+    /// The type of messages yielded by the receiver.
+    type Item;
+
+    /// Polls to receive the next message.
+    ///
+    /// See [`Receiver::poll_recv`] for the meaning of the returned value.
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<Self::Item>>;
+
+    /// Tries to receive the next message without waiting.
+    fn try_recv(&mut self) -> Result<Self::Item, TryRecvError>;
+
+    /// Closes the receiver without dropping it, so that buffered messages can still be drained.
+    fn close(&mut self);
+
+    /// Closes the receiver and returns the messages still buffered, without waiting.
+    ///
+    /// See [`Receiver::take_remaining`].
+    fn take_remaining(&mut self) -> Vec<Self::Item> {
+        self.close();
+        let mut messages = Vec::new();
+        while let Ok(message) = self.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    /// Polls to receive the next message or marker.
+    ///
+    /// Receivers that do not deliver markers only yield [`Event::Data`], which is what the default implementation does.
+    fn poll_recv_event(&mut self, cx: &mut Context<'_>) -> Poll<Option<Event<Self::Item>>> {
+        self.poll_recv(cx).map(|message| message.map(Event::Data))
+    }
+
+    /// Tries to receive the next message or marker without waiting.
+    fn try_recv_event(&mut self) -> Result<Event<Self::Item>, TryRecvError> {
+        self.try_recv().map(Event::Data)
+    }
+}
+
+impl<T> StickyReceiver for Receiver<T> {
+    type Item = T;
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        Receiver::poll_recv(self, cx)
+    }
+
+    fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        Receiver::try_recv(self)
+    }
+
+    fn close(&mut self) {
+        Receiver::close(self)
+    }
+
+    fn poll_recv_event(&mut self, cx: &mut Context<'_>) -> Poll<Option<Event<T>>> {
+        Receiver::poll_recv_event(self, cx)
+    }
+
+    fn try_recv_event(&mut self) -> Result<Event<T>, TryRecvError> {
+        Receiver::try_recv_event(self)
+    }
+}
+
+impl<T> StickyReceiver for UnboundedReceiver<T> {
+    type Item = T;
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        UnboundedReceiver::poll_recv(self, cx)
+    }
+
+    fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        UnboundedReceiver::try_recv(self)
+    }
+
+    fn close(&mut self) {
+        UnboundedReceiver::close(self)
+    }
+
+    fn poll_recv_event(&mut self, cx: &mut Context<'_>) -> Poll<Option<Event<T>>> {
+        UnboundedReceiver::poll_recv_event(self, cx)
+    }
+
+    fn try_recv_event(&mut self) -> Result<Event<T>, TryRecvError> {
+        UnboundedReceiver::try_recv_event(self)
+    }
+}
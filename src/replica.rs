@@ -0,0 +1,277 @@
+use std::{
+    hash::{BuildHasher, Hash},
+    num::NonZeroUsize,
+};
+
+use crate::{QuorumError, SendError, Sender, UnboundedSender, util::Route};
+
+/// A copy of a message sent through a [`ReplicatedSender`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replica<T> {
+    /// Which copy this is. `0` is the primary, every other value a backup.
+    pub replica: usize,
+    /// The message itself.
+    pub message: T,
+}
+
+impl<T> Replica<T> {
+    /// Returns `true` if this copy was delivered to the primary consumer of its ID.
+    pub fn is_primary(&self) -> bool {
+        self.replica == 0
+    }
+}
+
+/// Sender wrapper that delivers every message to its primary consumer and to a number of backup consumers.
+///
+/// Replica `k` of a message is delivered to the `k`-th consumer after the ID's primary consumer, wrapping around. The
+/// backup consumers of an ID are therefore as stable as its primary, which lets a standby worker keep warm per-ID state
+/// and take over quickly if the primary fails. Each copy is wrapped in a [`Replica`] so that consumers can tell primary
+/// work from backup traffic.
+///
+/// The replication factor counts the primary and is capped at the number of consumers.
+///
+/// Besides the all-or-error `send`, the quorum sends (`send_quorum` and `try_send_quorum`) try every replica and
+/// succeed as long as enough of them accepted the message, reporting the outcome of each replica otherwise.
+pub struct ReplicatedSender<X> {
+    sender: X,
+    replicas: usize,
+}
+
+impl<X> ReplicatedSender<X> {
+    /// Wraps a bounded or unbounded sender, delivering every message to `replicas` consumers.
+    pub fn new(sender: X, replicas: NonZeroUsize) -> Self {
+        Self {
+            sender,
+            replicas: replicas.get(),
+        }
+    }
+
+    /// Returns the routes of all replicas of a message, starting with the primary.
+    fn replica_routes(&self, route: Route, num_consumers: usize) -> impl Iterator<Item = Route> {
+        (0..self.replicas.min(num_consumers)).map(move |replica| Route {
+            index: (route.index + replica) % num_consumers,
+            ..route
+        })
+    }
+}
+
+impl<ID, T, S> ReplicatedSender<Sender<ID, Replica<T>, S>>
+where
+    ID: Hash,
+    T: Clone,
+    S: BuildHasher,
+{
+    /// Sends a message to the primary and backup consumers of `id`, waiting for capacity in each of them.
+    ///
+    /// Replicas are sent one after another, starting with the primary. If one of them fails, the error for that replica
+    /// is returned and the remaining replicas are not sent; replicas sent before it stay queued.
+    pub async fn send(&self, id: ID, message: T) -> Result<(), SendError<T>> {
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+
+        for (replica, route) in self
+            .replica_routes(route, self.sender.consumers.len())
+            .enumerate()
+        {
+            let message = Replica {
+                replica,
+                message: message.clone(),
+            };
+            self.sender
+                .send_route(message, route)
+                .await
+                .map_err(|err| err.map(|replica| replica.message))?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends a message to the primary and backup consumers of `id` without waiting.
+    ///
+    /// Fails like [`send`](ReplicatedSender::send), and additionally if one of the target channels is at capacity.
+    pub fn try_send(&self, id: ID, message: T) -> Result<(), SendError<T>> {
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+
+        for (replica, route) in self
+            .replica_routes(route, self.sender.consumers.len())
+            .enumerate()
+        {
+            let message = Replica {
+                replica,
+                message: message.clone(),
+            };
+            self.sender
+                .try_send_route(message, route)
+                .map_err(|err| err.map(|replica| replica.message))?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends a message to every replica of `id`, succeeding if at least `quorum` of them accepted it.
+    ///
+    /// Unlike [`send`](ReplicatedSender::send), a failing replica does not stop the remaining ones from being sent.
+    /// Replicas are sent one after another, waiting for capacity in each of them.
+    pub async fn send_quorum(
+        &self,
+        id: ID,
+        message: T,
+        quorum: usize,
+    ) -> Result<(), QuorumError<T>> {
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => {
+                return quorum_result(
+                    quorum,
+                    vec![Err(SendError::FailedToComputeRouteID(message))],
+                );
+            }
+        };
+
+        let mut results = Vec::new();
+        for (replica, route) in self
+            .replica_routes(route, self.sender.consumers.len())
+            .enumerate()
+        {
+            let message = Replica {
+                replica,
+                message: message.clone(),
+            };
+            let result = self.sender.send_route(message, route).await;
+            results.push(replica_result(result, route));
+        }
+
+        quorum_result(quorum, results)
+    }
+
+    /// Sends a message to every replica of `id` without waiting, succeeding if at least `quorum` of them accepted it.
+    pub fn try_send_quorum(&self, id: ID, message: T, quorum: usize) -> Result<(), QuorumError<T>> {
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => {
+                return quorum_result(
+                    quorum,
+                    vec![Err(SendError::FailedToComputeRouteID(message))],
+                );
+            }
+        };
+
+        let results = self
+            .replica_routes(route, self.sender.consumers.len())
+            .enumerate()
+            .map(|(replica, route)| {
+                let message = Replica {
+                    replica,
+                    message: message.clone(),
+                };
+                replica_result(self.sender.try_send_route(message, route), route)
+            })
+            .collect();
+
+        quorum_result(quorum, results)
+    }
+}
+
+impl<ID, T, S> ReplicatedSender<UnboundedSender<ID, Replica<T>, S>>
+where
+    ID: Hash,
+    T: Clone,
+    S: BuildHasher,
+{
+    /// Sends a message to the primary and backup consumers of `id`.
+    ///
+    /// Replicas are sent one after another, starting with the primary. If one of them fails, the error for that replica
+    /// is returned and the remaining replicas are not sent; replicas sent before it stay queued.
+    pub fn send(&self, id: ID, message: T) -> Result<(), SendError<T>> {
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+
+        for (replica, route) in self
+            .replica_routes(route, self.sender.consumers.len())
+            .enumerate()
+        {
+            let message = Replica {
+                replica,
+                message: message.clone(),
+            };
+            self.sender
+                .send_route(message, route)
+                .map_err(|err| err.map(|replica| replica.message))?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends a message to every replica of `id`, succeeding if at least `quorum` of them accepted it.
+    ///
+    /// Unlike [`send`](ReplicatedSender::send), a failing replica does not stop the remaining ones from being sent.
+    pub fn send_quorum(&self, id: ID, message: T, quorum: usize) -> Result<(), QuorumError<T>> {
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => {
+                return quorum_result(
+                    quorum,
+                    vec![Err(SendError::FailedToComputeRouteID(message))],
+                );
+            }
+        };
+
+        let results = self
+            .replica_routes(route, self.sender.consumers.len())
+            .enumerate()
+            .map(|(replica, route)| {
+                let message = Replica {
+                    replica,
+                    message: message.clone(),
+                };
+                replica_result(self.sender.send_route(message, route), route)
+            })
+            .collect();
+
+        quorum_result(quorum, results)
+    }
+}
+
+impl<X> Clone for ReplicatedSender<X>
+where
+    X: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            replicas: self.replicas,
+        }
+    }
+}
+
+fn replica_result<T>(
+    result: Result<(), SendError<Replica<T>>>,
+    route: Route,
+) -> Result<usize, SendError<T>> {
+    result
+        .map(|()| route.index)
+        .map_err(|err| err.map(|replica| replica.message))
+}
+
+fn quorum_result<T>(
+    quorum: usize,
+    results: Vec<Result<usize, SendError<T>>>,
+) -> Result<(), QuorumError<T>> {
+    let accepted = results.iter().filter(|result| result.is_ok()).count();
+    if accepted >= quorum {
+        Ok(())
+    } else {
+        Err(QuorumError {
+            quorum,
+            accepted,
+            results,
+        })
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash},
+    time::Duration,
+};
+
+use tokio::{
+    sync::oneshot,
+    time::{Instant, timeout_at},
+};
+
+use crate::{SendError, Sender, UnboundedSender};
+
+/// A message that expects a response, as sent with `send_request` or [`gather`](Sender::gather).
+///
+/// The consumer handles the request and answers it with [`respond`](Request::respond), or splits it with
+/// [`into_parts`](Request::into_parts) to answer later. Dropping a request without answering it tells the requester
+/// that no response will come.
+#[derive(Debug)]
+pub struct Request<Q, A> {
+    request: Q,
+    responder: Responder<A>,
+}
+
+impl<Q, A> Request<Q, A> {
+    /// Creates a request together with the receiver its response is delivered to.
+    pub fn new(request: Q) -> (Self, oneshot::Receiver<A>) {
+        let (reply, response) = oneshot::channel();
+        let request = Self {
+            request,
+            responder: Responder { reply },
+        };
+        (request, response)
+    }
+
+    /// Returns a reference to the request.
+    pub fn get_ref(&self) -> &Q {
+        &self.request
+    }
+
+    /// Consumes the request, returning it together with the handle to answer it.
+    pub fn into_parts(self) -> (Q, Responder<A>) {
+        (self.request, self.responder)
+    }
+
+    /// Answers the request, returning the response back if the requester is no longer waiting for it.
+    pub fn respond(self, response: A) -> Result<(), A> {
+        self.responder.respond(response)
+    }
+}
+
+/// Handle to answer a [`Request`].
+#[derive(Debug)]
+pub struct Responder<A> {
+    reply: oneshot::Sender<A>,
+}
+
+impl<A> Responder<A> {
+    /// Answers the request, returning the response back if the requester is no longer waiting for it.
+    pub fn respond(self, response: A) -> Result<(), A> {
+        self.reply.send(response)
+    }
+
+    /// Returns `true` if the requester is no longer waiting for the response.
+    pub fn is_closed(&self) -> bool {
+        self.reply.is_closed()
+    }
+}
+
+/// Responses collected by [`gather`](Sender::gather).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gathered<ID, A>
+where
+    ID: Hash + Eq,
+{
+    /// Responses that arrived before the deadline, keyed by the ID of their request.
+    pub responses: HashMap<ID, A>,
+    /// IDs of the requests that were not answered in time, in the order they were given. This includes requests that
+    /// could not be sent and requests that were dropped without a response.
+    pub missing: Vec<ID>,
+}
+
+impl<ID, A> Gathered<ID, A>
+where
+    ID: Hash + Eq,
+{
+    /// Returns `true` if every request was answered before the deadline.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+impl<ID, Q, A, S> Sender<ID, Request<Q, A>, S>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a request to the consumer of `id`, waiting for capacity like [`send`](Sender::send), and returns the
+    /// receiver of its response.
+    pub async fn send_request(
+        &self,
+        id: ID,
+        request: Q,
+    ) -> Result<oneshot::Receiver<A>, SendError<Request<Q, A>>> {
+        let (request, response) = Request::new(request);
+        self.send(id, request).await?;
+        Ok(response)
+    }
+
+    /// Sends every request to the consumer of its ID and waits for the responses until `timeout` has passed.
+    ///
+    /// Returns the responses that arrived in time keyed by ID, and the IDs of the requests that were not answered, so
+    /// a timeout yields partial results rather than an error. Waiting for capacity to send a request counts towards
+    /// the timeout. The IDs of the requests should be distinct; of several responses with the same ID only one is kept.
+    pub async fn gather<I>(&self, requests: I, timeout: Duration) -> Gathered<ID, A>
+    where
+        ID: Eq + Clone,
+        I: IntoIterator<Item = (ID, Q)>,
+    {
+        let deadline = Instant::now() + timeout;
+        let mut pending = Vec::new();
+        for (id, request) in requests {
+            let (request, response) = Request::new(request);
+            let sent = matches!(
+                timeout_at(deadline, self.send(id.clone(), request)).await,
+                Ok(Ok(()))
+            );
+            pending.push((id, sent.then_some(response)));
+        }
+        collect(pending, deadline).await
+    }
+}
+
+impl<ID, Q, A, S> UnboundedSender<ID, Request<Q, A>, S>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a request to the consumer of `id` like [`send`](UnboundedSender::send) and returns the receiver of its
+    /// response.
+    pub fn send_request(
+        &self,
+        id: ID,
+        request: Q,
+    ) -> Result<oneshot::Receiver<A>, SendError<Request<Q, A>>> {
+        let (request, response) = Request::new(request);
+        self.send(id, request)?;
+        Ok(response)
+    }
+
+    /// Sends every request to the consumer of its ID and waits for the responses until `timeout` has passed.
+    ///
+    /// See [`Sender::gather`].
+    pub async fn gather<I>(&self, requests: I, timeout: Duration) -> Gathered<ID, A>
+    where
+        ID: Eq + Clone,
+        I: IntoIterator<Item = (ID, Q)>,
+    {
+        let deadline = Instant::now() + timeout;
+        let pending = requests
+            .into_iter()
+            .map(|(id, request)| {
+                let (request, response) = Request::new(request);
+                let sent = self.send(id.clone(), request).is_ok();
+                (id, sent.then_some(response))
+            })
+            .collect();
+        collect(pending, deadline).await
+    }
+}
+
+/// Waits for the responses of sent requests until `deadline`.
+async fn collect<ID, A>(
+    pending: Vec<(ID, Option<oneshot::Receiver<A>>)>,
+    deadline: Instant,
+) -> Gathered<ID, A>
+where
+    ID: Hash + Eq,
+{
+    let mut gathered = Gathered {
+        responses: HashMap::with_capacity(pending.len()),
+        missing: Vec::new(),
+    };
+    for (id, response) in pending {
+        let response = match response {
+            Some(response) => timeout_at(deadline, response).await,
+            None => {
+                gathered.missing.push(id);
+                continue;
+            }
+        };
+        match response {
+            Ok(Ok(response)) => {
+                gathered.responses.insert(id, response);
+            }
+            Ok(Err(_)) | Err(_) => gathered.missing.push(id),
+        }
+    }
+    gathered
+}
@@ -0,0 +1,88 @@
+use std::{
+    hash::{BuildHasher, RandomState},
+    num::NonZeroUsize,
+    time::Duration,
+};
+
+use crate::util::Rng;
+
+/// How long [`Sender::send_with_retry`](crate::Sender::send_with_retry) waits between attempts, and how long
+/// [`consume_supervised`](crate::consume_supervised) pauses a consumer after a panic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    /// Waits the same time before every retry.
+    Fixed(Duration),
+    /// Waits `initial` before the first retry and twice as long before every further retry, but never more than `max`.
+    Exponential {
+        /// Wait before the first retry.
+        initial: Duration,
+        /// Upper bound of the wait before a retry.
+        max: Duration,
+    },
+}
+
+impl Backoff {
+    /// Returns the wait before retry number `retry`, starting at `0`.
+    pub(crate) fn delay(&self, retry: usize) -> Duration {
+        match *self {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => {
+                let factor = 1u32.checked_shl(retry.min(31) as u32).unwrap_or(u32::MAX);
+                initial.saturating_mul(factor).min(max)
+            }
+        }
+    }
+}
+
+/// Number of attempts and backoff of [`Sender::send_with_retry`](crate::Sender::send_with_retry).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_attempts: NonZeroUsize,
+    backoff: Backoff,
+    jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Creates a policy that tries to send a message up to `max_attempts` times, waiting according to `backoff` in
+    /// between.
+    pub fn new(max_attempts: NonZeroUsize, backoff: Backoff) -> Self {
+        Self {
+            max_attempts,
+            backoff,
+            jitter: false,
+        }
+    }
+
+    /// Waits a random time between half and all of the backoff before every retry.
+    ///
+    /// Jitter keeps producers that hit a full channel at the same time from retrying in lockstep.
+    pub fn with_jitter(mut self) -> Self {
+        self.jitter = true;
+        self
+    }
+
+    /// Returns the maximum number of attempts, including the first one.
+    pub fn max_attempts(&self) -> usize {
+        self.max_attempts.get()
+    }
+
+    /// Returns the backoff before retry number `retry`, starting at `0`, without jitter.
+    pub fn backoff(&self, retry: usize) -> Duration {
+        self.backoff.delay(retry)
+    }
+
+    /// Returns a source of jitter for a series of retries, if the policy uses jitter.
+    pub(crate) fn jitter(&self) -> Option<Rng> {
+        self.jitter
+            .then(|| Rng::new(RandomState::new().hash_one(0u64)))
+    }
+
+    /// Returns the wait before retry number `retry`, applying jitter if `rng` is given.
+    pub(crate) fn delay(&self, retry: usize, rng: Option<&Rng>) -> Duration {
+        let backoff = self.backoff(retry);
+        match rng {
+            Some(rng) => backoff.mul_f64(0.5 + rng.next_f64() / 2.0),
+            None => backoff,
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::hash::Hash;
+
+use crate::util::Route;
+
+/// A message that carries its own routing ID.
+///
+/// Implementing this trait lets a message be sent without passing its ID separately, using
+/// [`Sender::send_by_key`](crate::Sender::send_by_key), [`Sender::try_send_by_key`](crate::Sender::try_send_by_key) or
+/// [`UnboundedSender::send_by_key`](crate::UnboundedSender::send_by_key). The key is extracted from the message
+/// before it is sent, so messages that return equal keys are always delivered to the same receiver.
+///
+/// This is most useful for enum messages where every variant belongs to the same entity:
+///
+/// ```rust
+/// use tokio_sticky_channel::{StickyRoute, unbounded_sticky_channel};
+/// use std::num::NonZeroUsize;
+///
+/// #[derive(Debug)]
+/// enum OrderEvent {
+///     Created { order_id: u64 },
+///     Paid { order_id: u64, amount: u64 },
+///     Shipped { order_id: u64 },
+/// }
+///
+/// impl StickyRoute for OrderEvent {
+///     type Key = u64;
+///
+///     fn route_key(&self) -> u64 {
+///         match self {
+///             OrderEvent::Created { order_id }
+///             | OrderEvent::Paid { order_id, .. }
+///             | OrderEvent::Shipped { order_id } => *order_id,
+///         }
+///     }
+/// }
+///
+/// let (sender, _receivers) = unbounded_sticky_channel::<u64, OrderEvent>(NonZeroUsize::new(4).unwrap());
+///
+/// // All events of order 7 are delivered to the same receiver.
+/// sender.send_by_key(OrderEvent::Created { order_id: 7 }).unwrap();
+/// sender.send_by_key(OrderEvent::Paid { order_id: 7, amount: 100 }).unwrap();
+/// sender.send_by_key(OrderEvent::Shipped { order_id: 7 }).unwrap();
+/// ```
+pub trait StickyRoute {
+    /// The type of ID used to route the message.
+    type Key: Hash;
+
+    /// Returns the ID the message should be routed by.
+    fn route_key(&self) -> Self::Key;
+}
+
+/// A message whose route has been decided but that has not been sent yet.
+///
+/// Created with [`Sender::prepare`](crate::Sender::prepare) or
+/// [`UnboundedSender::prepare`](crate::UnboundedSender::prepare), which validate the message and compute its route,
+/// and sent with `send_routed` on the same channel. In between, the message can be inspected and enriched, for
+/// example with data that depends on the consumer it will be delivered to, without routing it again.
+///
+/// The route is kept as it was when the message was prepared, so a partition reassigned in between does not affect it.
+/// A message prepared for another channel is delivered to the consumer with the same index, if there is one.
+#[derive(Debug, Clone)]
+pub struct RoutedMessage<T> {
+    message: T,
+    route: Route,
+}
+
+impl<T> RoutedMessage<T> {
+    pub(crate) fn new(message: T, route: Route) -> Self {
+        Self { message, route }
+    }
+
+    pub(crate) fn into_parts(self) -> (T, Route) {
+        (self.message, self.route)
+    }
+
+    /// Returns the index of the receiver the message will be delivered to.
+    pub fn consumer_index(&self) -> usize {
+        self.route.index
+    }
+
+    /// Returns a reference to the message.
+    pub fn get_ref(&self) -> &T {
+        &self.message
+    }
+
+    /// Returns a mutable reference to the message.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.message
+    }
+
+    /// Returns the message, giving up its route.
+    pub fn into_inner(self) -> T {
+        self.message
+    }
+
+    /// Converts the message, keeping its route.
+    pub fn map<U, F>(self, f: F) -> RoutedMessage<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        RoutedMessage {
+            message: f(self.message),
+            route: self.route,
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Routing math shared by all channel flavours.
+//!
+//! This module only depends on `core` and is available without the `std` feature, so that the same IDs map to the same
+//! consumers in other environments, such as embedded executors with their own channel primitives. The channels of this
+//! crate route every message with these functions:
+//!
+//! ```rust
+//! use tokio_sticky_channel::routing::{consumer_index, hash_id};
+//! use std::{hash::RandomState, num::NonZeroUsize};
+//!
+//! let build_hasher = RandomState::new();
+//! let (sender, _receivers) = tokio_sticky_channel::sticky_channel_with_hasher::<&str, u32, _>(
+//!     NonZeroUsize::new(4).unwrap(),
+//!     16,
+//!     build_hasher.clone(),
+//! );
+//!
+//! let hash = hash_id(&"user-123", &build_hasher);
+//! assert_eq!(sender.route_of("user-123"), Some(consumer_index(hash, NonZeroUsize::new(4).unwrap())));
+//! ```
+
+use core::{
+    hash::{BuildHasher, Hash},
+    num::NonZeroUsize,
+};
+
+/// Hashes an ID the way the channels do before routing it.
+pub fn hash_id<ID, S>(id: &ID, build_hasher: &S) -> u64
+where
+    ID: Hash + ?Sized,
+    S: BuildHasher,
+{
+    build_hasher.hash_one(id)
+}
+
+/// Returns the index of the consumer the ID with `hash` is routed to, out of `num_consumers`.
+///
+/// Computed on the full 64-bit hash, so that routing is the same on 32-bit and 64-bit targets. Power-of-two consumer
+/// counts are routed with a bit mask, which gives the same index as the remainder without a division.
+pub fn consumer_index(hash: u64, num_consumers: NonZeroUsize) -> usize {
+    let num_consumers = num_consumers.get() as u64;
+    if num_consumers.is_power_of_two() {
+        (hash & (num_consumers - 1)) as usize
+    } else {
+        (hash % num_consumers) as usize
+    }
+}
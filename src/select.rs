@@ -0,0 +1,56 @@
+use std::{
+    future::poll_fn,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll},
+};
+
+use crate::StickyReceiver;
+
+/// Receives the next message from whichever of `receivers` has one first, together with the receiver's index.
+///
+/// Returns `None` once every receiver is closed and drained, or if `receivers` is empty. Receivers are polled starting
+/// at a different one on every call, so a busy receiver cannot starve the others. Works for the receivers of both
+/// channel flavours as well as for receiver adapters.
+///
+/// # Cancel safety
+///
+/// This function is cancel safe if the receivers' `poll_recv` is, which is the case for the receivers of this crate.
+/// If it is used in a `tokio::select!` statement and another branch completes first, no message has been received.
+pub async fn recv_first<R>(receivers: &mut [R]) -> Option<(usize, R::Item)>
+where
+    R: StickyReceiver,
+{
+    static NEXT_START: AtomicUsize = AtomicUsize::new(0);
+
+    let start = NEXT_START.fetch_add(1, Ordering::Relaxed);
+    poll_fn(|cx| poll_recv_first(receivers, start, cx)).await
+}
+
+/// Polls `receivers` in order starting at index `start` modulo their number, returning the first message.
+///
+/// Ready with `None` once every receiver is closed and drained.
+pub(crate) fn poll_recv_first<R>(
+    receivers: &mut [R],
+    start: usize,
+    cx: &mut Context<'_>,
+) -> Poll<Option<(usize, R::Item)>>
+where
+    R: StickyReceiver,
+{
+    let len = receivers.len();
+    let mut open = false;
+    for offset in 0..len {
+        let index = (start + offset) % len;
+        match receivers[index].poll_recv(cx) {
+            Poll::Ready(Some(message)) => return Poll::Ready(Some((index, message))),
+            Poll::Ready(None) => {}
+            Poll::Pending => open = true,
+        }
+    }
+
+    if open {
+        Poll::Pending
+    } else {
+        Poll::Ready(None)
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash},
+    sync::{Arc, Mutex},
+};
+
+use crate::{SendError, Sender, Sequenced, UnboundedSender, util::Route};
+
+/// Sender wrapper that numbers the messages of every ID in the order they are sent, across all of its clones.
+///
+/// When clones of a sender are used from different tasks, a message that was sent first can still be queued after a
+/// message sent later, for example because its task was preempted between picking the message and queueing it. A
+/// `SequencedSender` assigns every message a per-ID sequence number at the start of the send, so that a
+/// [`SequencedReceiver`](crate::SequencedReceiver) can restore the order in which the sends were started.
+///
+/// Clones share their sequence numbers. Separate wrappers around clones of the same sender do not, so every producer
+/// must use a clone of the same `SequencedSender`. Sequence numbers are kept for every ID sent so far.
+///
+/// If a send fails after its sequence number was assigned, later messages of the same ID may be held back by the
+/// receiver until its window fills up, see [`SequencedReceiver::with_window`](crate::SequencedReceiver::with_window).
+pub struct SequencedSender<X> {
+    sender: X,
+    next_seqs: Arc<Mutex<HashMap<u64, u64>>>,
+}
+
+impl<X> SequencedSender<X> {
+    /// Wraps a bounded or unbounded sender of [`Sequenced`] messages.
+    pub fn new(sender: X) -> Self {
+        Self {
+            sender,
+            next_seqs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Assigns the next sequence number of the ID routed by `route`.
+    fn sequence<T>(&self, route: Route, message: T) -> Sequenced<T> {
+        let mut next_seqs = self.next_seqs.lock().unwrap();
+        let next_seq = next_seqs.entry(route.hash).or_default();
+        let seq = *next_seq;
+        *next_seq += 1;
+
+        Sequenced {
+            key: route.hash,
+            seq: Some(seq),
+            message,
+        }
+    }
+
+    /// Takes back the sequence number of a message that could not be sent, unless a later one has been assigned since.
+    fn unsequence<T>(&self, err: SendError<Sequenced<T>>) -> SendError<T> {
+        let mut next_seqs = self.next_seqs.lock().unwrap();
+        err.map(|sequenced| {
+            if let Some(seq) = sequenced.seq
+                && let Some(next_seq) = next_seqs.get_mut(&sequenced.key)
+                && *next_seq == seq + 1
+            {
+                *next_seq = seq;
+            }
+            sequenced.message
+        })
+    }
+}
+
+impl<ID, T, S> SequencedSender<Sender<ID, Sequenced<T>, S>>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a sequenced message to the consumer identified by `id`, waiting for capacity like [`Sender::send`].
+    pub async fn send(&self, id: ID, message: T) -> Result<(), SendError<T>> {
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+
+        let message = self.sequence(route, message);
+        self.sender
+            .send_route(message, route)
+            .await
+            .map_err(|err| self.unsequence(err))
+    }
+
+    /// Sends a sequenced message to the consumer identified by `id` without waiting, like [`Sender::try_send`].
+    pub fn try_send(&self, id: ID, message: T) -> Result<(), SendError<T>> {
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+
+        let message = self.sequence(route, message);
+        self.sender
+            .try_send_route(message, route)
+            .map_err(|err| self.unsequence(err))
+    }
+}
+
+impl<ID, T, S> SequencedSender<UnboundedSender<ID, Sequenced<T>, S>>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a sequenced message to the consumer identified by `id`, like [`UnboundedSender::send`].
+    pub fn send(&self, id: ID, message: T) -> Result<(), SendError<T>> {
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+
+        let message = self.sequence(route, message);
+        self.sender
+            .send_route(message, route)
+            .map_err(|err| self.unsequence(err))
+    }
+}
+
+impl<X> Clone for SequencedSender<X>
+where
+    X: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            next_seqs: self.next_seqs.clone(),
+        }
+    }
+}
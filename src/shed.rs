@@ -0,0 +1,352 @@
+use std::{
+    hash::{BuildHasher, Hash, RandomState},
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use crate::{
+    Reconciliation, SendError, Sender, UnboundedSender,
+    hooks::Hooks,
+    util::{Rng, Route},
+};
+
+/// Why a [`SheddingSender`] dropped a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShedReason {
+    /// The consumer had at least the configured number of messages queued.
+    Depth,
+    /// The message was not picked by the [`SamplingPolicy`].
+    Sampled,
+    /// The consumer's bounded channel was at capacity.
+    Full,
+    /// The ID had reached its [`max_pending_per_key`](crate::StickyChannelBuilder::max_pending_per_key) limit.
+    KeyBackpressure,
+}
+
+/// Number of messages a [`SheddingSender`] dropped for a single consumer, per reason.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShedCounts {
+    /// Messages dropped because the consumer's queue had reached the depth threshold.
+    pub depth: u64,
+    /// Messages dropped by the sampling policy.
+    pub sampled: u64,
+    /// Messages dropped because the consumer's bounded channel was at capacity.
+    pub full: u64,
+    /// Messages dropped because their ID had reached its pending limit.
+    pub key_backpressure: u64,
+}
+
+impl ShedCounts {
+    /// Returns the number of messages dropped for the given reason.
+    pub fn get(&self, reason: ShedReason) -> u64 {
+        match reason {
+            ShedReason::Depth => self.depth,
+            ShedReason::Sampled => self.sampled,
+            ShedReason::Full => self.full,
+            ShedReason::KeyBackpressure => self.key_backpressure,
+        }
+    }
+
+    /// Returns the number of messages dropped for any reason.
+    pub fn total(&self) -> u64 {
+        self.depth + self.sampled + self.full + self.key_backpressure
+    }
+}
+
+/// Drop counters of a single consumer.
+#[derive(Default)]
+struct Counters {
+    depth: AtomicU64,
+    sampled: AtomicU64,
+    full: AtomicU64,
+    key_backpressure: AtomicU64,
+}
+
+impl Counters {
+    fn add(&self, reason: ShedReason) {
+        let counter = match reason {
+            ShedReason::Depth => &self.depth,
+            ShedReason::Sampled => &self.sampled,
+            ShedReason::Full => &self.full,
+            ShedReason::KeyBackpressure => &self.key_backpressure,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn load(&self) -> ShedCounts {
+        ShedCounts {
+            depth: self.depth.load(Ordering::Relaxed),
+            sampled: self.sampled.load(Ordering::Relaxed),
+            full: self.full.load(Ordering::Relaxed),
+            key_backpressure: self.key_backpressure.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Adaptive policy of a [`SheddingSender`] that drops a growing fraction of messages as a consumer fills up.
+///
+/// A consumer's fullness is its number of queued messages relative to the shedding threshold. Below `start`, every
+/// message is kept. From there, the fraction of messages kept falls linearly until only `min_keep` of them are kept
+/// when the consumer is full. Which messages are kept is decided at random.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplingPolicy {
+    start: f64,
+    min_keep: f64,
+}
+
+impl SamplingPolicy {
+    /// Creates a policy that starts sampling at fullness `start` and keeps `min_keep` of the messages when full.
+    ///
+    /// Both values are fractions and clamped to `0.0..=1.0`.
+    pub fn new(start: f64, min_keep: f64) -> Self {
+        Self {
+            start: start.clamp(0.0, 1.0),
+            min_keep: min_keep.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Returns the fraction of messages kept for a consumer with `depth` of `threshold` messages queued.
+    fn keep(&self, depth: usize, threshold: usize) -> f64 {
+        let fullness = depth as f64 / threshold.max(1) as f64;
+        if fullness < self.start {
+            return 1.0;
+        }
+        let progress = ((fullness - self.start) / (1.0 - self.start)).min(1.0);
+        1.0 - (1.0 - self.min_keep) * progress
+    }
+}
+
+impl Default for SamplingPolicy {
+    /// Keeps every message below half full, falling to one in ten when full.
+    fn default() -> Self {
+        Self::new(0.5, 0.1)
+    }
+}
+
+/// Random sampling decisions shared by the clones of a [`SheddingSender`].
+struct Sampler {
+    policy: SamplingPolicy,
+    rng: Rng,
+}
+
+impl Sampler {
+    fn new(policy: SamplingPolicy) -> Self {
+        Self {
+            policy,
+            rng: Rng::new(RandomState::new().hash_one(0u64)),
+        }
+    }
+
+    /// Decides at random whether to keep a message for a consumer with `depth` of `threshold` messages queued.
+    fn keep(&self, depth: usize, threshold: usize) -> bool {
+        let keep = self.policy.keep(depth, threshold);
+        keep >= 1.0 || self.rng.next_f64() < keep
+    }
+}
+
+/// Sender wrapper that drops messages instead of waiting when their consumer falls behind.
+///
+/// Once a consumer has `threshold` messages queued, further messages for it are dropped until it catches up. Messages
+/// that would otherwise fail because the bounded channel is at capacity or their ID has reached its pending limit are
+/// dropped as well, so sending never waits. This suits soft-real-time systems that prefer losing data over adding
+/// latency.
+///
+/// With a [`SamplingPolicy`], messages are dropped at random well before the threshold is reached, at a rate that grows
+/// with the consumer's queue, see [`with_sampling`](SheddingSender::with_sampling).
+///
+/// Dropped messages are counted per consumer and per [`ShedReason`], see [`dropped`](SheddingSender::dropped). Clones
+/// share their counters.
+pub struct SheddingSender<X> {
+    sender: X,
+    threshold: usize,
+    sampler: Option<Arc<Sampler>>,
+    /// Created on first use, once the number of consumers is known.
+    counters: Arc<OnceLock<Box<[Counters]>>>,
+}
+
+impl<X> SheddingSender<X> {
+    /// Wraps a bounded or unbounded sender, dropping messages for consumers with at least `threshold` messages queued.
+    pub fn new(sender: X, threshold: usize) -> Self {
+        Self {
+            sender,
+            threshold,
+            sampler: None,
+            counters: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// Drops messages at random according to `policy` as consumers fill up towards the threshold.
+    pub fn with_sampling(mut self, policy: SamplingPolicy) -> Self {
+        self.sampler = Some(Arc::new(Sampler::new(policy)));
+        self
+    }
+
+    /// Decides whether a message for a consumer with `depth` messages queued is dropped before it is sent.
+    fn shed_reason(&self, depth: usize) -> Option<ShedReason> {
+        if depth >= self.threshold {
+            return Some(ShedReason::Depth);
+        }
+        match &self.sampler {
+            Some(sampler) if !sampler.keep(depth, self.threshold) => Some(ShedReason::Sampled),
+            _ => None,
+        }
+    }
+
+    fn counters(&self, num_consumers: usize) -> &[Counters] {
+        self.counters
+            .get_or_init(|| (0..num_consumers).map(|_| Counters::default()).collect())
+    }
+
+    /// Adds the messages shed for every consumer to the reconciliation of the underlying sender.
+    fn reconcile_with(&self, mut reconciliations: Vec<Reconciliation>) -> Vec<Reconciliation> {
+        let counters = self.counters(reconciliations.len());
+        for (reconciliation, counters) in reconciliations.iter_mut().zip(counters) {
+            reconciliation.shed = counters.load().total();
+        }
+        reconciliations
+    }
+
+    /// Counts a dropped message and reports it to the channel's `on_dropped` hook, returning `Ok(false)` for the
+    /// caller to pass on.
+    fn shed<T>(
+        &self,
+        message: T,
+        route: Route,
+        reason: ShedReason,
+        num_consumers: usize,
+        hooks: Option<&Hooks<T>>,
+    ) -> Result<bool, SendError<T>> {
+        self.counters(num_consumers)[route.index].add(reason);
+        if let Some(hooks) = hooks {
+            hooks.dropped(route.index, &message);
+        }
+        Ok(false)
+    }
+}
+
+impl<ID, T, S> SheddingSender<Sender<ID, T, S>>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a message to the consumer identified by `id` unless it has to be dropped. Never waits.
+    ///
+    /// Returns `Ok(true)` if the message was queued and `Ok(false)` if it was dropped. Errors other than a full channel
+    /// or key backpressure are returned as by [`Sender::try_send`].
+    pub fn send(&self, id: ID, message: T) -> Result<bool, SendError<T>> {
+        let message = self.sender.validate(&id, message)?;
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+        let num_consumers = self.sender.consumers.len();
+        let consumer = self.sender.consumers.get(route.index);
+        let hooks = consumer.and_then(|consumer| consumer.hooks.as_deref());
+        if let Some(consumer) = consumer
+            && let Some(reason) = self.shed_reason(consumer.depth())
+        {
+            return self.shed(message, route, reason, num_consumers, hooks);
+        }
+
+        match self.sender.try_send_route(message, route) {
+            Ok(()) => Ok(true),
+            Err(SendError::ChannelFull(message, _)) => {
+                self.shed(message, route, ShedReason::Full, num_consumers, hooks)
+            }
+            Err(SendError::KeyBackpressure(message, _)) => self.shed(
+                message,
+                route,
+                ShedReason::KeyBackpressure,
+                num_consumers,
+                hooks,
+            ),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the number of messages dropped so far for every consumer, indexed like the receivers.
+    pub fn dropped(&self) -> Vec<ShedCounts> {
+        self.counters(self.sender.consumers.len())
+            .iter()
+            .map(Counters::load)
+            .collect()
+    }
+
+    /// Returns how the messages sent to every consumer were accounted for, including the ones shed by this sender and
+    /// its clones.
+    ///
+    /// See [`Sender::reconcile`].
+    pub fn reconcile(&self) -> Vec<Reconciliation> {
+        self.reconcile_with(self.sender.reconcile())
+    }
+}
+
+impl<ID, T, S> SheddingSender<UnboundedSender<ID, T, S>>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a message to the consumer identified by `id` unless it has to be dropped.
+    ///
+    /// Returns `Ok(true)` if the message was queued and `Ok(false)` if it was dropped. Errors other than key
+    /// backpressure are returned as by [`UnboundedSender::send`].
+    pub fn send(&self, id: ID, message: T) -> Result<bool, SendError<T>> {
+        let message = self.sender.validate(&id, message)?;
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+        let num_consumers = self.sender.consumers.len();
+        let consumer = self.sender.consumers.get(route.index);
+        let hooks = consumer.and_then(|consumer| consumer.hooks.as_deref());
+        if let Some(consumer) = consumer
+            && let Some(reason) = self.shed_reason(consumer.depth())
+        {
+            return self.shed(message, route, reason, num_consumers, hooks);
+        }
+
+        match self.sender.send_route(message, route) {
+            Ok(()) => Ok(true),
+            Err(SendError::KeyBackpressure(message, _)) => self.shed(
+                message,
+                route,
+                ShedReason::KeyBackpressure,
+                num_consumers,
+                hooks,
+            ),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the number of messages dropped so far for every consumer, indexed like the receivers.
+    pub fn dropped(&self) -> Vec<ShedCounts> {
+        self.counters(self.sender.consumers.len())
+            .iter()
+            .map(Counters::load)
+            .collect()
+    }
+
+    /// Returns how the messages sent to every consumer were accounted for, including the ones shed by this sender and
+    /// its clones.
+    ///
+    /// See [`UnboundedSender::reconcile`].
+    pub fn reconcile(&self) -> Vec<Reconciliation> {
+        self.reconcile_with(self.sender.reconcile())
+    }
+}
+
+impl<X> Clone for SheddingSender<X>
+where
+    X: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            threshold: self.threshold,
+            sampler: self.sampler.clone(),
+            counters: self.counters.clone(),
+        }
+    }
+}
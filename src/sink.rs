@@ -0,0 +1,86 @@
+use std::{future::poll_fn, io, num::NonZeroUsize};
+
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::StickyReceiver;
+
+/// When [`write_to`] flushes its writer.
+///
+/// The writer is always flushed once more before [`write_to`] returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlushPolicy {
+    /// Flushes after every batch.
+    EveryBatch,
+    /// Flushes before waiting for the next message, i.e. whenever the receiver has nothing left to receive.
+    #[default]
+    WhenIdle,
+    /// Only flushes when the receiver is closed and empty, leaving it to the writer to decide in between.
+    AtEnd,
+}
+
+/// Receives every message of `receiver` and writes it into `writer`, encoded by `encode`.
+///
+/// Messages are written in batches: after waiting for a message, up to `max_batch - 1` more that are already available
+/// are received, encoded one after another into a single buffer and written at once. `encode` appends a message to the
+/// buffer and is responsible for any framing the reader needs to split the messages again, for example a length
+/// prefix. `flush` decides when the writer is flushed.
+///
+/// Returns the number of messages written once `receiver` is closed and empty. Fails with the first error of `encode`
+/// or `writer`; the messages of the batch that failed are lost, and messages not received yet stay with `receiver`.
+///
+/// # Cancel safety
+///
+/// This function is not cancel safe. Messages received for a batch that has not been written yet are lost.
+pub async fn write_to<R, W, F>(
+    receiver: &mut R,
+    mut writer: W,
+    max_batch: NonZeroUsize,
+    flush: FlushPolicy,
+    mut encode: F,
+) -> io::Result<u64>
+where
+    R: StickyReceiver,
+    W: AsyncWrite + Unpin,
+    F: FnMut(R::Item, &mut Vec<u8>) -> io::Result<()>,
+{
+    let mut buffer = Vec::new();
+    let mut written = 0;
+    loop {
+        let message = match receiver.try_recv() {
+            Ok(message) => message,
+            Err(_) => {
+                // Every batch is followed by a wait or another batch, so only the first wait has nothing to flush.
+                if written > 0 && flush == FlushPolicy::WhenIdle {
+                    writer.flush().await?;
+                }
+                match poll_fn(|cx| receiver.poll_recv(cx)).await {
+                    Some(message) => message,
+                    None => break,
+                }
+            }
+        };
+
+        encode(message, &mut buffer)?;
+        let mut batch = 1;
+        while batch < max_batch.get() {
+            match receiver.try_recv() {
+                Ok(message) => {
+                    encode(message, &mut buffer)?;
+                    batch += 1;
+                }
+                Err(_) => break,
+            }
+        }
+
+        writer.write_all(&buffer).await?;
+        buffer.clear();
+        written += batch as u64;
+
+        if flush == FlushPolicy::EveryBatch {
+            writer.flush().await?;
+        }
+    }
+
+    writer.flush().await?;
+    Ok(written)
+}
@@ -0,0 +1,57 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Count-min sketch of the number of messages queued per ID hash.
+///
+/// Every row counts the messages of an ID in one of `width` counters, picked by a hash that differs per row. An ID's
+/// estimate is the smallest of its counters, which never undercounts and only overcounts by the messages of the IDs
+/// colliding with it in every row.
+pub(crate) struct FrequencySketch {
+    width: usize,
+    counters: Box<[AtomicU64]>,
+}
+
+impl FrequencySketch {
+    pub(crate) fn new(width: usize, depth: usize) -> Self {
+        Self {
+            width,
+            counters: (0..width * depth).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    fn cells(&self, hash: u64) -> impl Iterator<Item = &AtomicU64> {
+        self.counters
+            .chunks_exact(self.width)
+            .enumerate()
+            .map(move |(row, counters)| {
+                &counters[(mix(hash, row as u64) % self.width as u64) as usize]
+            })
+    }
+
+    pub(crate) fn increment(&self, hash: u64) {
+        for cell in self.cells(hash) {
+            cell.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Takes back a message counted with [`increment`](FrequencySketch::increment) that could not be queued.
+    pub(crate) fn decrement(&self, hash: u64) {
+        for cell in self.cells(hash) {
+            cell.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn estimate(&self, hash: u64) -> u64 {
+        self.cells(hash)
+            .map(|cell| cell.load(Ordering::Relaxed))
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+/// Derives the hash of `row` from the hash of an ID, using the finalizer of SplitMix64.
+fn mix(hash: u64, row: u64) -> u64 {
+    let mut x = hash.wrapping_add((row + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
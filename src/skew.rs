@@ -0,0 +1,84 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::time::{Instant, MissedTickBehavior, interval_at};
+
+use crate::totals::Counters;
+
+/// Load of every consumer over one window, passed to the alarm registered with
+/// [`skew_alarm`](crate::StickyChannelBuilder::skew_alarm).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkewReport {
+    /// Messages sent to every consumer during the window, indexed like the receivers.
+    pub loads: Vec<u64>,
+    /// Load of the busiest consumer divided by the load of the least busy one, counted as at least `1`.
+    pub ratio: f64,
+}
+
+impl SkewReport {
+    fn new(loads: Vec<u64>) -> Option<Self> {
+        let max = *loads.iter().max()?;
+        let min = *loads.iter().min()?;
+        if max == 0 {
+            return None;
+        }
+        Some(Self {
+            ratio: max as f64 / min.max(1) as f64,
+            loads,
+        })
+    }
+}
+
+/// Skew alarm of a channel, started once the channel is built.
+#[derive(Clone)]
+pub(crate) struct SkewAlarm {
+    max_ratio: f64,
+    window: Duration,
+    alarm: Arc<dyn Fn(&SkewReport) + Send + Sync>,
+}
+
+impl SkewAlarm {
+    pub(crate) fn new<F>(max_ratio: f64, window: Duration, alarm: F) -> Self
+    where
+        F: Fn(&SkewReport) + Send + Sync + 'static,
+    {
+        Self {
+            max_ratio,
+            window,
+            alarm: Arc::new(alarm),
+        }
+    }
+
+    /// Spawns a task comparing the load of the consumers once per window.
+    ///
+    /// The task only holds a weak handle to the counters, so it stops once every sender and receiver has been dropped.
+    pub(crate) fn start(self, counters: &Arc<Counters>) {
+        // Taken before spawning, so that messages sent before the task first runs count towards the first window.
+        let mut previous = counters.accepted();
+        let counters = Arc::downgrade(counters);
+        crate::util::spawn("sticky-channel skew alarm", async move {
+            let mut interval = interval_at(Instant::now() + self.window, self.window);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                interval.tick().await;
+                let Some(counters) = counters.upgrade() else {
+                    return;
+                };
+                let current = counters.accepted();
+                drop(counters);
+
+                let loads = current
+                    .iter()
+                    .zip(&previous)
+                    .map(|(current, previous)| current.saturating_sub(*previous))
+                    .collect();
+                previous = current;
+                if let Some(report) = SkewReport::new(loads)
+                    && report.ratio > self.max_ratio
+                {
+                    (self.alarm)(&report);
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,335 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::{BuildHasher, Hash},
+    mem,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+};
+
+use crate::{SendError, Sender, UnboundedSender, util::Route};
+
+/// A message sent through a [`HotKeySplitter`].
+///
+/// Messages of hot IDs carry a per-ID sequence number so that their order can be restored with a [`Reassembler`]
+/// after they have been processed by different consumers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sequenced<T> {
+    /// Hash of the ID the message was sent with.
+    pub key: u64,
+    /// Position of the message among the messages of its ID, or `None` if the ID was not hot when it was sent.
+    pub seq: Option<u64>,
+    /// The message itself.
+    pub message: T,
+}
+
+/// Per-ID state of a hot ID.
+#[derive(Debug, Default)]
+struct HotKey {
+    hot: bool,
+    next_seq: u64,
+    next_offset: usize,
+}
+
+/// Sender wrapper that spreads the messages of hot IDs over several consumers.
+///
+/// A sticky channel caps the throughput of a single ID at what one consumer can process. IDs marked with
+/// [`mark_hot`](HotKeySplitter::mark_hot) are instead sent round-robin to `fanout` consecutive consumers starting at
+/// the ID's regular consumer. Messages of all other IDs are routed as usual.
+///
+/// IDs are not detected as hot automatically: the caller decides which IDs to mark, for example from its own load
+/// metrics, and unmarks them with [`unmark_hot`](HotKeySplitter::unmark_hot) once they cool down.
+///
+/// This trades per-ID ordering for throughput: messages of a hot ID may be processed concurrently and out of order.
+/// Every message of a hot ID carries a sequence number, so consumers that need the original order downstream can
+/// restore it with a [`Reassembler`].
+///
+/// Clones of a splitter share the set of hot IDs and their sequence numbers.
+pub struct HotKeySplitter<X> {
+    sender: X,
+    fanout: usize,
+    keys: Arc<Mutex<HashMap<u64, HotKey>>>,
+}
+
+impl<X> HotKeySplitter<X> {
+    /// Wraps a bounded or unbounded sender, splitting each hot ID over up to `fanout` consumers.
+    pub fn new(sender: X, fanout: NonZeroUsize) -> Self {
+        Self {
+            sender,
+            fanout: fanout.get(),
+            keys: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn set_hot(&self, route: Route, hot: bool) {
+        let mut keys = self.keys.lock().unwrap();
+        if hot {
+            keys.entry(route.hash).or_default().hot = true;
+        } else if let Some(key) = keys.get_mut(&route.hash) {
+            key.hot = false;
+        }
+    }
+
+    fn restart(&self, route: Route) {
+        if let Some(key) = self.keys.lock().unwrap().get_mut(&route.hash) {
+            key.next_seq = 0;
+            key.next_offset = 0;
+        }
+    }
+
+    /// Picks the consumer for the next message of the ID routed by `route`.
+    fn pick(&self, route: Route, num_consumers: usize) -> Route {
+        let mut keys = self.keys.lock().unwrap();
+        match keys.get_mut(&route.hash) {
+            Some(key) if key.hot => {
+                let offset = key.next_offset;
+                key.next_offset = (offset + 1) % self.fanout.min(num_consumers);
+
+                let index = (route.index + offset) % num_consumers;
+                Route { index, ..route }
+            }
+            _ => route,
+        }
+    }
+
+    /// Numbers the next message of the ID routed by `route` and sends it with `send`.
+    ///
+    /// `send` must not wait. The sequence number is only taken if the message was sent, so a failed send leaves no gap
+    /// that a [`Reassembler`] would wait for.
+    fn sequence<T>(
+        &self,
+        route: Route,
+        message: T,
+        send: impl FnOnce(Sequenced<T>) -> Result<(), SendError<Sequenced<T>>>,
+    ) -> Result<(), SendError<T>> {
+        let mut keys = self.keys.lock().unwrap();
+        // IDs that are no longer hot keep numbering where they left off in case they become hot again.
+        let key = keys.get_mut(&route.hash);
+        let seq = key.as_ref().map(|key| key.next_seq);
+        let message = Sequenced {
+            key: route.hash,
+            seq,
+            message,
+        };
+
+        send(message).map_err(|err| err.map(|sequenced| sequenced.message))?;
+        if let Some(key) = key {
+            key.next_seq += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<ID, T, S> HotKeySplitter<Sender<ID, Sequenced<T>, S>>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Starts splitting the messages of `id` over several consumers.
+    pub fn mark_hot(&self, id: ID) {
+        if let Ok(route) = self.sender.route(id) {
+            self.set_hot(route, true);
+        }
+    }
+
+    /// Stops splitting the messages of `id`. Its messages keep their sequence numbers.
+    pub fn unmark_hot(&self, id: ID) {
+        if let Ok(route) = self.sender.route(id) {
+            self.set_hot(route, false);
+        }
+    }
+
+    /// Restarts the sequence numbers of `id` at `0`, for example after its [`Reassembler`] position was
+    /// [`reset`](Reassembler::reset).
+    pub fn reset(&self, id: ID) {
+        if let Ok(route) = self.sender.route(id) {
+            self.restart(route);
+        }
+    }
+
+    /// Sends a message, spreading it over several consumers if `id` is hot.
+    ///
+    /// This method waits for capacity like [`Sender::send`]. The sequence number is only assigned once capacity has
+    /// been reserved, so cancelling the returned future or a failed send does not leave a gap in the numbering.
+    /// Messages that are lost after they were sent, for example because their consumer was dropped, still do: skip
+    /// them with [`Reassembler::skip_to`] or the [`Reassembler`] holds back every later message of the ID.
+    pub async fn send(&self, id: ID, message: T) -> Result<(), SendError<T>> {
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+
+        let route = self.pick(route, self.sender.consumers.len());
+        let Some(consumer) = self.sender.consumers.get(route.index) else {
+            return Err(SendError::NoConsumer(message, route.index));
+        };
+        let Some(permit) = consumer.clone().reserve_owned(route).await else {
+            return Err(SendError::ChannelClosed(message, route.index));
+        };
+
+        self.sequence(route, message, |message| permit.send(message))
+    }
+}
+
+impl<ID, T, S> HotKeySplitter<UnboundedSender<ID, Sequenced<T>, S>>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Starts splitting the messages of `id` over several consumers.
+    pub fn mark_hot(&self, id: ID) {
+        if let Ok(route) = self.sender.route(id) {
+            self.set_hot(route, true);
+        }
+    }
+
+    /// Stops splitting the messages of `id`. Its messages keep their sequence numbers.
+    pub fn unmark_hot(&self, id: ID) {
+        if let Ok(route) = self.sender.route(id) {
+            self.set_hot(route, false);
+        }
+    }
+
+    /// Restarts the sequence numbers of `id` at `0`, for example after its [`Reassembler`] position was
+    /// [`reset`](Reassembler::reset).
+    pub fn reset(&self, id: ID) {
+        if let Ok(route) = self.sender.route(id) {
+            self.restart(route);
+        }
+    }
+
+    /// Sends a message, spreading it over several consumers if `id` is hot.
+    ///
+    /// A failed send does not use up a sequence number. Messages that are lost after they were sent, for example
+    /// because their consumer was dropped, leave a gap: skip it with [`Reassembler::skip_to`].
+    pub fn send(&self, id: ID, message: T) -> Result<(), SendError<T>> {
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+
+        let route = self.pick(route, self.sender.consumers.len());
+        self.sequence(route, message, |message| {
+            self.sender.send_route(message, route)
+        })
+    }
+}
+
+impl<X> Clone for HotKeySplitter<X>
+where
+    X: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            fanout: self.fanout,
+            keys: self.keys.clone(),
+        }
+    }
+}
+
+/// Restores the per-ID order of [`Sequenced`] messages.
+///
+/// Messages are pushed in whatever order they arrive, for example from several consumers that processed the
+/// messages of a hot ID concurrently. [`push`](Reassembler::push) returns the messages that are next in line for
+/// their ID and holds back messages that arrived too early. Messages without a sequence number are returned right away.
+#[derive(Debug)]
+pub struct Reassembler<T> {
+    keys: HashMap<u64, Pending<T>>,
+}
+
+#[derive(Debug)]
+struct Pending<T> {
+    next_seq: u64,
+    early: BTreeMap<u64, T>,
+}
+
+impl<T> Reassembler<T> {
+    /// Creates an empty reassembler.
+    pub fn new() -> Self {
+        Self {
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Adds a message and returns all messages that can now be delivered in order.
+    ///
+    /// Sequence numbers of each ID are expected to start at `0`.
+    pub fn push(&mut self, message: Sequenced<T>) -> Vec<T> {
+        let Some(seq) = message.seq else {
+            return vec![message.message];
+        };
+
+        let pending = self.keys.entry(message.key).or_insert_with(|| Pending {
+            next_seq: 0,
+            early: BTreeMap::new(),
+        });
+
+        if seq < pending.next_seq {
+            // Duplicate of a message that was already delivered.
+            return Vec::new();
+        }
+
+        pending.early.insert(seq, message.message);
+
+        let mut ready = Vec::new();
+        while let Some(message) = pending.early.remove(&pending.next_seq) {
+            ready.push(message);
+            pending.next_seq += 1;
+        }
+
+        if pending.early.is_empty() {
+            // Keep only the position so that memory is proportional to the number of IDs.
+            pending.early = BTreeMap::new();
+        }
+
+        ready
+    }
+
+    /// Gives up on the missing messages of the ID with hash `key` before sequence number `seq`.
+    ///
+    /// Returns the held back messages before `seq` followed by the messages that are now next in line. Use this when
+    /// messages are known to be lost, for example after a consumer was dropped, since a gap otherwise holds back every
+    /// later message of the ID.
+    pub fn skip_to(&mut self, key: u64, seq: u64) -> Vec<T> {
+        let pending = self.keys.entry(key).or_insert_with(|| Pending {
+            next_seq: 0,
+            early: BTreeMap::new(),
+        });
+        if seq <= pending.next_seq {
+            return Vec::new();
+        }
+
+        let later = pending.early.split_off(&seq);
+        let mut ready: Vec<T> = mem::replace(&mut pending.early, later)
+            .into_values()
+            .collect();
+        pending.next_seq = seq;
+        while let Some(message) = pending.early.remove(&pending.next_seq) {
+            ready.push(message);
+            pending.next_seq += 1;
+        }
+
+        ready
+    }
+
+    /// Forgets the ID with hash `key`, returning its held back messages in order.
+    ///
+    /// The next message of the ID is expected to have sequence number `0` again, see [`HotKeySplitter::reset`].
+    pub fn reset(&mut self, key: u64) -> Vec<T> {
+        self.keys
+            .remove(&key)
+            .map(|pending| pending.early.into_values().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of messages held back because an earlier message of their ID is missing.
+    pub fn pending(&self) -> usize {
+        self.keys.values().map(|pending| pending.early.len()).sum()
+    }
+}
+
+impl<T> Default for Reassembler<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
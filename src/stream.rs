@@ -0,0 +1,167 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+
+use crate::{Receiver, StickyReceiver, UnboundedReceiver, select::poll_recv_first};
+
+/// A wrapper around a [`Receiver`] that implements [`Stream`].
+///
+/// Like `tokio_stream::wrappers::ReceiverStream`, the stream ends once the channel is closed and all of its messages
+/// have been received.
+pub struct StickyReceiverStream<T> {
+    inner: Receiver<T>,
+}
+
+impl<T> StickyReceiverStream<T> {
+    /// Creates a new `StickyReceiverStream`.
+    pub fn new(receiver: Receiver<T>) -> Self {
+        Self { inner: receiver }
+    }
+
+    /// Get back the inner [`Receiver`].
+    pub fn into_inner(self) -> Receiver<T> {
+        self.inner
+    }
+
+    /// Closes the receiving half of the channel without dropping it.
+    ///
+    /// See [`Receiver::close`]. Messages sent before closing are still yielded by the stream.
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+}
+
+// The receiver is never pinned, so the stream can be moved freely whatever `T` is.
+impl<T> Unpin for StickyReceiverStream<T> {}
+
+impl<T> Stream for StickyReceiverStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().inner.poll_recv(cx)
+    }
+}
+
+impl<T> AsRef<Receiver<T>> for StickyReceiverStream<T> {
+    fn as_ref(&self) -> &Receiver<T> {
+        &self.inner
+    }
+}
+
+impl<T> AsMut<Receiver<T>> for StickyReceiverStream<T> {
+    fn as_mut(&mut self) -> &mut Receiver<T> {
+        &mut self.inner
+    }
+}
+
+impl<T> From<Receiver<T>> for StickyReceiverStream<T> {
+    fn from(receiver: Receiver<T>) -> Self {
+        Self::new(receiver)
+    }
+}
+
+/// A wrapper around an [`UnboundedReceiver`] that implements [`Stream`].
+///
+/// Like `tokio_stream::wrappers::UnboundedReceiverStream`, the stream ends once the channel is closed and all of its
+/// messages have been received.
+pub struct UnboundedStickyReceiverStream<T> {
+    inner: UnboundedReceiver<T>,
+}
+
+impl<T> UnboundedStickyReceiverStream<T> {
+    /// Creates a new `UnboundedStickyReceiverStream`.
+    pub fn new(receiver: UnboundedReceiver<T>) -> Self {
+        Self { inner: receiver }
+    }
+
+    /// Get back the inner [`UnboundedReceiver`].
+    pub fn into_inner(self) -> UnboundedReceiver<T> {
+        self.inner
+    }
+
+    /// Closes the receiving half of the channel without dropping it.
+    ///
+    /// See [`UnboundedReceiver::close`]. Messages sent before closing are still yielded by the stream.
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+}
+
+// The receiver is never pinned, so the stream can be moved freely whatever `T` is.
+impl<T> Unpin for UnboundedStickyReceiverStream<T> {}
+
+impl<T> Stream for UnboundedStickyReceiverStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().inner.poll_recv(cx)
+    }
+}
+
+impl<T> AsRef<UnboundedReceiver<T>> for UnboundedStickyReceiverStream<T> {
+    fn as_ref(&self) -> &UnboundedReceiver<T> {
+        &self.inner
+    }
+}
+
+impl<T> AsMut<UnboundedReceiver<T>> for UnboundedStickyReceiverStream<T> {
+    fn as_mut(&mut self) -> &mut UnboundedReceiver<T> {
+        &mut self.inner
+    }
+}
+
+impl<T> From<UnboundedReceiver<T>> for UnboundedStickyReceiverStream<T> {
+    fn from(receiver: UnboundedReceiver<T>) -> Self {
+        Self::new(receiver)
+    }
+}
+
+/// A single [`Stream`] over all receivers of a channel, yielding every message with the index of its receiver.
+///
+/// Created by [`merge`]. Messages of a single receiver keep their order, so the per-ID ordering of the channel holds
+/// for the merged stream as well. The stream ends once every receiver is closed and all messages have been received.
+pub struct MergedStream<R> {
+    receivers: Vec<R>,
+    next: usize,
+}
+
+impl<R> MergedStream<R> {
+    /// Get back the inner receivers, in their original order.
+    pub fn into_inner(self) -> Vec<R> {
+        self.receivers
+    }
+}
+
+// The receivers are never pinned, so the stream can be moved freely whatever `R` is.
+impl<R> Unpin for MergedStream<R> {}
+
+impl<R> Stream for MergedStream<R>
+where
+    R: StickyReceiver,
+{
+    type Item = (usize, R::Item);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let polled = poll_recv_first(&mut this.receivers, this.next, cx);
+        if let Poll::Ready(Some((index, _))) = &polled {
+            this.next = index + 1;
+        }
+        polled
+    }
+}
+
+/// Merges `receivers` into a single [`Stream`] of `(index, message)` pairs, where `index` is the position of the
+/// receiver the message came from.
+///
+/// Receivers are polled round-robin, starting after the one that yielded last, so a busy receiver cannot starve the
+/// others. Works for the receivers of both channel flavours as well as for receiver adapters.
+pub fn merge<R>(receivers: Vec<R>) -> MergedStream<R>
+where
+    R: StickyReceiver,
+{
+    MergedStream { receivers, next: 0 }
+}
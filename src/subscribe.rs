@@ -0,0 +1,131 @@
+use std::{
+    collections::HashMap,
+    task::{Context, Poll},
+};
+
+use tokio::sync::mpsc::{
+    UnboundedReceiver as MpscReceiver, UnboundedSender as MpscSender, unbounded_channel,
+};
+
+use crate::{StickyReceiver, TryRecvError};
+
+/// Receives the messages of a single ID that were diverted from a receiver.
+///
+/// Created by [`Receiver::subscribe`](crate::Receiver::subscribe) or
+/// [`UnboundedReceiver::subscribe`](crate::UnboundedReceiver::subscribe), and handed out by
+/// [`Demux::accept`](crate::Demux::accept) for the messages of a secondary key. Messages are moved to the `KeyReceiver` as
+/// the receiver it was created from receives them, so that receiver has to keep receiving for the subscription to make
+/// progress. Diverted messages no longer count towards the channel's capacity or per-ID limit.
+///
+/// Dropping the `KeyReceiver` of a subscription ends it: later messages of the ID are received by the main receiver again.
+/// Once the main receiver is dropped, `recv` returns `None` after the diverted messages have been received.
+#[derive(Debug)]
+pub struct KeyReceiver<T> {
+    receiver: MpscReceiver<T>,
+}
+
+impl<T> KeyReceiver<T> {
+    /// Receives the next message of the subscribed ID.
+    ///
+    /// Returns `None` once the receiver the subscription was created from has been dropped and all diverted messages
+    /// have been received.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe.
+    pub async fn recv(&mut self) -> Option<T> {
+        self.receiver.recv().await
+    }
+
+    /// Tries to receive the next message of the subscribed ID without waiting.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        Ok(self.receiver.try_recv()?)
+    }
+
+    /// Polls to receive the next message of the subscribed ID.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.receiver.poll_recv(cx)
+    }
+
+    /// Closes the subscription without dropping it.
+    ///
+    /// Messages that were already diverted can still be received, later messages of the ID are received by the main
+    /// receiver.
+    pub fn close(&mut self) {
+        self.receiver.close();
+    }
+
+    /// Returns the number of diverted messages that have not been received yet.
+    pub fn len(&self) -> usize {
+        self.receiver.len()
+    }
+
+    /// Returns `true` if there are no diverted messages waiting to be received.
+    pub fn is_empty(&self) -> bool {
+        self.receiver.is_empty()
+    }
+}
+
+impl<T> StickyReceiver for KeyReceiver<T> {
+    type Item = T;
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        KeyReceiver::poll_recv(self, cx)
+    }
+
+    fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        KeyReceiver::try_recv(self)
+    }
+
+    fn close(&mut self) {
+        KeyReceiver::close(self)
+    }
+}
+
+/// Creates a [`KeyReceiver`] together with the sender that feeds it.
+pub(crate) fn key_channel<T>() -> (MpscSender<T>, KeyReceiver<T>) {
+    let (sender, receiver) = unbounded_channel();
+    (sender, KeyReceiver { receiver })
+}
+
+/// Subscriptions of a receiver, keyed by the hash of the subscribed ID.
+pub(crate) struct Subscriptions<T> {
+    keys: HashMap<u64, MpscSender<T>>,
+}
+
+impl<T> Subscriptions<T> {
+    /// Subscribes to the messages with the given hash, replacing an earlier subscription to it.
+    pub(crate) fn subscribe(&mut self, hash: u64) -> KeyReceiver<T> {
+        let (sender, receiver) = key_channel();
+        self.keys.insert(hash, sender);
+        receiver
+    }
+
+    /// Moves a received message to the subscription of its hash.
+    ///
+    /// Returns the message if nobody is subscribed to it, so that the receiver delivers it itself.
+    pub(crate) fn divert(&mut self, hash: u64, message: T) -> Option<T> {
+        if self.keys.is_empty() {
+            return Some(message);
+        }
+        let Some(sender) = self.keys.get(&hash) else {
+            return Some(message);
+        };
+        match sender.send(message) {
+            Ok(()) => None,
+            Err(error) => {
+                // The key receiver is gone, so the ID goes back to the main receiver.
+                self.keys.remove(&hash);
+                Some(error.0)
+            }
+        }
+    }
+}
+
+impl<T> Default for Subscriptions<T> {
+    fn default() -> Self {
+        Self {
+            keys: HashMap::new(),
+        }
+    }
+}
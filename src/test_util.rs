@@ -0,0 +1,436 @@
+//! Utilities for testing code that uses sticky channels.
+//!
+//! This module is only available with the `test-util` feature. It provides:
+//!
+//! - [`FaultySender`], a sender wrapper that delays sends and fails some of them with
+//!   [`SendError::ChannelFull`] according to a [`FaultConfig`],
+//! - [`StallableReceiver`], a receiver adapter whose consumer can be stalled and resumed through a [`Stall`] handle,
+//! - [`FixedState`], a deterministic hasher that makes routes predictable, and [`assert_routes_to`] to pin them.
+//!
+//! Faults are driven by a seeded generator and explicit handles, so tests that use them are deterministic.
+
+use std::{
+    fmt::Debug,
+    future::poll_fn,
+    hash::{BuildHasher, Hash, Hasher},
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use crate::{SendError, Sender, StickyReceiver, TryRecvError, UnboundedSender, util::Rng};
+
+/// Faults a [`FaultySender`] injects into the sends passing through it.
+///
+/// By default no fault is injected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FaultConfig {
+    send_delay: Option<Duration>,
+    full_probability: f64,
+    seed: u64,
+}
+
+impl FaultConfig {
+    /// Creates a configuration that injects no faults.
+    pub fn new() -> Self {
+        Self {
+            send_delay: None,
+            full_probability: 0.0,
+            seed: 0,
+        }
+    }
+
+    /// Delays every waiting send by `delay` before it is passed on.
+    pub fn with_send_delay(mut self, delay: Duration) -> Self {
+        self.send_delay = Some(delay);
+        self
+    }
+
+    /// Fails sends with [`SendError::ChannelFull`] with the given probability, clamped to `0.0..=1.0`.
+    ///
+    /// The failed message is handed back without having been sent.
+    pub fn with_full_probability(mut self, probability: f64) -> Self {
+        self.full_probability = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Seeds the generator that decides which sends fail. Defaults to `0`.
+    ///
+    /// The same seed fails the same sends, as long as they are made in the same order.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sender wrapper that injects artificial delays and [`SendError::ChannelFull`] results, see [`FaultConfig`].
+///
+/// Use it in place of a [`Sender`] or [`UnboundedSender`] to exercise the backpressure and failover handling of the
+/// code under test. Clones share their generator.
+pub struct FaultySender<X> {
+    sender: X,
+    config: FaultConfig,
+    rng: Arc<Rng>,
+}
+
+impl<X> FaultySender<X> {
+    /// Wraps a bounded or unbounded sender, injecting the faults described by `config`.
+    pub fn new(sender: X, config: FaultConfig) -> Self {
+        Self {
+            sender,
+            config,
+            rng: Arc::new(Rng::new(config.seed)),
+        }
+    }
+
+    /// Returns the wrapped sender.
+    pub fn get_ref(&self) -> &X {
+        &self.sender
+    }
+
+    async fn delay(&self) {
+        if let Some(delay) = self.config.send_delay {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Decides whether the next send fails with a full channel.
+    fn fail(&self) -> bool {
+        self.config.full_probability > 0.0 && self.rng.next_f64() < self.config.full_probability
+    }
+}
+
+impl<ID, T, S> FaultySender<Sender<ID, T, S>>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a message after the configured delay, unless the send is picked to fail.
+    ///
+    /// See [`Sender::send`].
+    pub async fn send(&self, id: ID, message: T) -> Result<(), SendError<T>> {
+        self.delay().await;
+        let message = self.sender.validate(&id, message)?;
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+        if self.fail() {
+            return Err(SendError::ChannelFull(message, route.index));
+        }
+        self.sender.send_route(message, route).await
+    }
+
+    /// Sends a message without waiting, unless the send is picked to fail. No delay is applied.
+    ///
+    /// See [`Sender::try_send`].
+    pub fn try_send(&self, id: ID, message: T) -> Result<(), SendError<T>> {
+        let message = self.sender.validate(&id, message)?;
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+        if self.fail() {
+            return Err(SendError::ChannelFull(message, route.index));
+        }
+        self.sender.try_send_route(message, route)
+    }
+}
+
+impl<ID, T, S> FaultySender<UnboundedSender<ID, T, S>>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a message after the configured delay, unless the send is picked to fail.
+    ///
+    /// See [`UnboundedSender::send`]. Unlike the wrapped sender, this method is async so that it can be delayed.
+    pub async fn send(&self, id: ID, message: T) -> Result<(), SendError<T>> {
+        self.delay().await;
+        let message = self.sender.validate(&id, message)?;
+        let route = match self.sender.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+        if self.fail() {
+            return Err(SendError::ChannelFull(message, route.index));
+        }
+        self.sender.send_route(message, route)
+    }
+}
+
+impl<X> Clone for FaultySender<X>
+where
+    X: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            config: self.config,
+            rng: self.rng.clone(),
+        }
+    }
+}
+
+/// Handle that stalls and resumes the consumer of a [`StallableReceiver`].
+///
+/// Cloning the handle controls the same receiver.
+#[derive(Clone)]
+pub struct Stall {
+    state: Arc<Mutex<StallState>>,
+}
+
+struct StallState {
+    stalled: bool,
+    waker: Option<Waker>,
+}
+
+impl Stall {
+    /// Stalls the consumer: its receiver behaves as if no message were queued until [`resume`](Stall::resume) is
+    /// called. Messages keep queueing up in the meantime.
+    pub fn stall(&self) {
+        self.state.lock().unwrap().stalled = true;
+    }
+
+    /// Resumes a stalled consumer, waking it up if it is waiting for a message.
+    pub fn resume(&self) {
+        let waker = {
+            let mut state = self.state.lock().unwrap();
+            state.stalled = false;
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Stalls the consumer for `duration`, then resumes it.
+    pub async fn stall_for(&self, duration: Duration) {
+        self.stall();
+        tokio::time::sleep(duration).await;
+        self.resume();
+    }
+
+    /// Returns `true` while the consumer is stalled.
+    pub fn is_stalled(&self) -> bool {
+        self.state.lock().unwrap().stalled
+    }
+
+    /// Returns `true` if the receiver is stalled, registering the waker to be woken on resume.
+    fn poll_stalled(&self, cx: &mut Context<'_>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.stalled {
+            state.waker = Some(cx.waker().clone());
+        }
+        state.stalled
+    }
+}
+
+/// Receiver adapter that simulates a consumer stall, controlled through a [`Stall`] handle.
+///
+/// While stalled, the adapter does not take any message from the underlying receiver: waiting receives wait until the
+/// consumer is resumed and [`try_recv`](StallableReceiver::try_recv) reports an empty channel. This lets tests fill up a
+/// consumer's queue on demand.
+pub struct StallableReceiver<R> {
+    inner: R,
+    stall: Stall,
+}
+
+impl<R> StallableReceiver<R>
+where
+    R: StickyReceiver,
+{
+    /// Wraps a receiver. The consumer starts out running.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            stall: Stall {
+                state: Arc::new(Mutex::new(StallState {
+                    stalled: false,
+                    waker: None,
+                })),
+            },
+        }
+    }
+
+    /// Returns a handle that stalls and resumes this receiver.
+    pub fn stall_handle(&self) -> Stall {
+        self.stall.clone()
+    }
+
+    /// Receives the next message once the consumer is not stalled.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe.
+    pub async fn recv(&mut self) -> Option<R::Item> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Polls to receive the next message once the consumer is not stalled.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<R::Item>> {
+        if self.stall.poll_stalled(cx) {
+            return Poll::Pending;
+        }
+        self.inner.poll_recv(cx)
+    }
+
+    /// Tries to receive the next message without waiting. Fails with [`TryRecvError::Empty`] while stalled.
+    pub fn try_recv(&mut self) -> Result<R::Item, TryRecvError> {
+        if self.stall.is_stalled() {
+            return Err(TryRecvError::Empty);
+        }
+        self.inner.try_recv()
+    }
+
+    /// Closes the underlying receiver.
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+
+    /// Returns the underlying receiver.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R> StickyReceiver for StallableReceiver<R>
+where
+    R: StickyReceiver,
+{
+    type Item = R::Item;
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<R::Item>> {
+        StallableReceiver::poll_recv(self, cx)
+    }
+
+    fn try_recv(&mut self) -> Result<R::Item, TryRecvError> {
+        StallableReceiver::try_recv(self)
+    }
+
+    fn close(&mut self) {
+        StallableReceiver::close(self)
+    }
+}
+
+/// Deterministic [`BuildHasher`] for tests.
+///
+/// Unlike [`RandomState`](std::hash::RandomState), every `FixedState` hashes the same way in every process. Integer
+/// IDs hash to themselves, so with `n` consumers the ID `k` is routed to consumer `k % n`:
+///
+/// ```rust
+/// use std::num::NonZeroUsize;
+/// use tokio_sticky_channel::{sticky_channel_with_hasher, test_util::{FixedState, assert_routes_to}};
+///
+/// let (sender, _receivers) =
+///     sticky_channel_with_hasher::<u64, &str, _>(NonZeroUsize::new(4).unwrap(), 8, FixedState);
+/// assert_routes_to(&sender, 6, 2);
+/// ```
+///
+/// Other IDs are hashed by folding the integers and bytes they write into the hasher. The result is stable, but not
+/// meant to be well distributed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FixedState;
+
+impl BuildHasher for FixedState {
+    type Hasher = FixedHasher;
+
+    fn build_hasher(&self) -> FixedHasher {
+        FixedHasher { hash: 0 }
+    }
+}
+
+/// The [`Hasher`] built by [`FixedState`].
+#[derive(Debug, Clone)]
+pub struct FixedHasher {
+    hash: u64,
+}
+
+impl FixedHasher {
+    fn fold(&mut self, value: u64) {
+        self.hash = self.hash.wrapping_mul(31).wrapping_add(value);
+    }
+}
+
+impl Hasher for FixedHasher {
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.fold(u64::from(byte));
+        }
+    }
+
+    fn write_u8(&mut self, value: u8) {
+        self.fold(u64::from(value));
+    }
+
+    fn write_u16(&mut self, value: u16) {
+        self.fold(u64::from(value));
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.fold(u64::from(value));
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.fold(value);
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.fold(value as u64);
+    }
+}
+
+/// Senders whose routes can be checked with [`assert_routes_to`].
+pub trait RouteOf<ID> {
+    /// Returns the index of the receiver that messages with the given ID are delivered to.
+    fn route_of(&self, id: ID) -> Option<usize>;
+}
+
+impl<ID, T, S> RouteOf<ID> for Sender<ID, T, S>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    fn route_of(&self, id: ID) -> Option<usize> {
+        Sender::route_of(self, id)
+    }
+}
+
+impl<ID, T, S> RouteOf<ID> for UnboundedSender<ID, T, S>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    fn route_of(&self, id: ID) -> Option<usize> {
+        UnboundedSender::route_of(self, id)
+    }
+}
+
+/// Asserts that messages with the given ID are delivered to receiver `index`.
+///
+/// # Panics
+///
+/// Panics with a message naming the ID and both indices if the ID is routed elsewhere.
+#[track_caller]
+pub fn assert_routes_to<X, ID>(sender: &X, id: ID, index: usize)
+where
+    X: RouteOf<ID>,
+    ID: Debug + Clone,
+{
+    let actual = sender.route_of(id.clone());
+    assert_eq!(
+        actual,
+        Some(index),
+        "expected ID {id:?} to be routed to receiver {index}, but it is routed to {actual:?}"
+    );
+}
@@ -187,8 +187,8 @@ async fn test_send_after_receiver_dropped() {
     drop(receivers);
 
     let result = sender.send(42, 100);
-    assert!(matches!(result, Err(SendError::ChannelClosed(_))));
-    if let Err(SendError::ChannelClosed(value)) = result {
+    assert!(matches!(result, Err(SendError::ChannelClosed(..))));
+    if let Err(SendError::ChannelClosed(value, _)) = result {
         assert_eq!(value, 100);
     }
 }
@@ -225,7 +225,7 @@ async fn test_send_after_all_receivers_dropped() {
     drop(receivers);
 
     let result = sender.send(&"test".to_string(), "message2".to_string());
-    assert!(matches!(result, Err(SendError::ChannelClosed(_))));
+    assert!(matches!(result, Err(SendError::ChannelClosed(..))));
 }
 
 #[tokio::test]
@@ -292,7 +292,7 @@ async fn test_receiver_close_method() {
     receivers[0].close();
 
     let result1 = sender.send(42, 300);
-    assert!(matches!(result1, Err(SendError::ChannelClosed(_))));
+    assert!(matches!(result1, Err(SendError::ChannelClosed(..))));
 
     let msg1 = receivers[0].recv().await;
     assert_eq!(msg1, Some(100));
@@ -537,8 +537,8 @@ async fn test_bounded_capacity_full() {
     sender.try_send(0, 2).unwrap();
 
     let result = sender.try_send(0, 3);
-    assert!(matches!(result, Err(SendError::ChannelFull(_))));
-    if let Err(SendError::ChannelFull(value)) = result {
+    assert!(matches!(result, Err(SendError::ChannelFull(..))));
+    if let Err(SendError::ChannelFull(value, _)) = result {
         assert_eq!(value, 3);
     }
 
@@ -588,8 +588,8 @@ async fn test_bounded_send_after_receiver_dropped() {
     drop(receivers);
 
     let result = sender.send(42, 100).await;
-    assert!(matches!(result, Err(SendError::ChannelClosed(_))));
-    if let Err(SendError::ChannelClosed(value)) = result {
+    assert!(matches!(result, Err(SendError::ChannelClosed(..))));
+    if let Err(SendError::ChannelClosed(value, _)) = result {
         assert_eq!(value, 100);
     }
 }
@@ -601,8 +601,8 @@ async fn test_bounded_try_send_after_receiver_dropped() {
     drop(receivers);
 
     let result = sender.try_send(42, 100);
-    assert!(matches!(result, Err(SendError::ChannelClosed(_))));
-    if let Err(SendError::ChannelClosed(value)) = result {
+    assert!(matches!(result, Err(SendError::ChannelClosed(..))));
+    if let Err(SendError::ChannelClosed(value, _)) = result {
         assert_eq!(value, 100);
     }
 }
@@ -636,7 +636,7 @@ async fn test_bounded_receiver_close() {
     receivers[0].close();
 
     let result = sender.send(42, 300).await;
-    assert!(matches!(result, Err(SendError::ChannelClosed(_))));
+    assert!(matches!(result, Err(SendError::ChannelClosed(..))));
 
     let msg1 = receivers[0].recv().await;
     assert_eq!(msg1, Some(100));
@@ -697,3 +697,4059 @@ async fn test_bounded_concurrent_senders() {
 
     assert_eq!(total_received, 150);
 }
+
+#[test]
+fn test_channels_run_without_a_tokio_runtime() {
+    smol::block_on(async {
+        let (sender, mut receivers) = sticky_channel::<u32, u32>(NonZeroUsize::new(2).unwrap(), 1);
+        let index = sender.route_of(7).unwrap();
+        let producer = smol::spawn(async move {
+            for message in 0..4 {
+                sender.send(7, message).await.unwrap();
+            }
+        });
+        let mut received = Vec::new();
+        while let Some(message) = receivers[index].recv().await {
+            received.push(message);
+        }
+        producer.await;
+        assert_eq!(received, vec![0, 1, 2, 3]);
+
+        let (sender, mut receivers) =
+            unbounded_sticky_channel::<u32, u32>(NonZeroUsize::new(2).unwrap());
+        let index = sender.route_of(7).unwrap();
+        sender.send(7, 1).unwrap();
+        assert_eq!(receivers[index].recv().await, Some(1));
+    });
+}
+
+#[tokio::test]
+async fn test_unbounded_sender_strong_count() {
+    let (sender, receivers) = unbounded_sticky_channel::<i32, i32>(NonZeroUsize::new(2).unwrap());
+
+    for receiver in &receivers {
+        assert_eq!(receiver.sender_strong_count(), 1);
+        assert_eq!(receiver.sender_weak_count(), 0);
+    }
+
+    let sender2 = sender.clone();
+    for receiver in &receivers {
+        assert_eq!(receiver.sender_strong_count(), 2);
+    }
+
+    drop(sender);
+    drop(sender2);
+    for receiver in &receivers {
+        assert_eq!(receiver.sender_strong_count(), 0);
+    }
+}
+
+#[tokio::test]
+async fn test_bounded_sender_strong_count() {
+    let (sender, receivers) = sticky_channel::<i32, i32>(NonZeroUsize::new(2).unwrap(), 5);
+
+    let sender2 = sender.clone();
+    for receiver in &receivers {
+        assert_eq!(receiver.sender_strong_count(), 2);
+        assert_eq!(receiver.sender_weak_count(), 0);
+    }
+
+    drop(sender);
+    drop(sender2);
+    for receiver in &receivers {
+        assert_eq!(receiver.sender_strong_count(), 0);
+    }
+}
+
+#[tokio::test]
+async fn test_send_error_helpers_on_full_channel() {
+    let (sender, _receivers) = sticky_channel::<i32, i32>(NonZeroUsize::new(1).unwrap(), 1);
+
+    sender.try_send(0, 1).unwrap();
+
+    let err = sender.try_send(0, 2).unwrap_err();
+    assert!(err.is_full());
+    assert!(!err.is_closed());
+    assert_eq!(err.consumer_index(), Some(0));
+    assert_eq!(err.into_inner(), 2);
+}
+
+#[tokio::test]
+async fn test_send_error_helpers_on_closed_channel() {
+    let (sender, receivers) = unbounded_sticky_channel::<i32, i32>(NonZeroUsize::new(3).unwrap());
+
+    drop(receivers);
+
+    let err = sender.send(7, 42).unwrap_err();
+    assert!(err.is_closed());
+    assert!(!err.is_full());
+    assert!(err.consumer_index().unwrap() < 3);
+    assert_eq!(err.into_inner(), 42);
+}
+
+#[tokio::test]
+async fn test_send_error_with_id() {
+    let (sender, _receivers) = sticky_channel::<&str, i32>(NonZeroUsize::new(2).unwrap(), 1);
+
+    sender.try_send("a", 1).unwrap();
+
+    let err = sender.try_send("a", 2).unwrap_err().with_id("a");
+    assert_eq!(err.id, "a");
+    assert!(err.error.is_full());
+    assert_eq!(err.error.consumer_index(), sender.route_of("a"));
+    assert_eq!(err.to_string(), "channel is full");
+    assert_eq!(err.into_parts(), ("a", 2));
+}
+
+#[tokio::test]
+async fn test_try_send_batch_reports_failed_messages() {
+    let (sender, mut receivers) = sticky_channel::<&str, i32>(NonZeroUsize::new(2).unwrap(), 2);
+
+    let result = sender.try_send_batch([("a", 1), ("a", 2), ("a", 3), ("a", 4)]);
+    assert_eq!(result.sent, 2);
+    assert!(!result.is_complete());
+    let failed: Vec<_> = result
+        .failed
+        .into_iter()
+        .map(|err| {
+            assert!(err.error.is_full());
+            err.into_parts()
+        })
+        .collect();
+    assert_eq!(failed, vec![("a", 3), ("a", 4)]);
+
+    let receiver = &mut receivers[sender.route_of("a").unwrap()];
+    assert_eq!(receiver.recv().await, Some(1));
+    assert_eq!(receiver.recv().await, Some(2));
+}
+
+#[test]
+fn test_try_recv_error_tokio_conversion() {
+    use tokio::sync::mpsc::error::TryRecvError as MpscTryRecvError;
+
+    assert_eq!(
+        TryRecvError::from(MpscTryRecvError::Empty),
+        TryRecvError::Empty
+    );
+    assert_eq!(
+        TryRecvError::from(MpscTryRecvError::Disconnected),
+        TryRecvError::Disconnected
+    );
+    assert_eq!(
+        MpscTryRecvError::from(TryRecvError::Disconnected),
+        MpscTryRecvError::Disconnected
+    );
+}
+
+#[test]
+fn test_send_error_tokio_conversion() {
+    use tokio::sync::mpsc::error::{SendError as MpscSendError, TrySendError};
+
+    let closed = MpscSendError::try_from(SendError::ChannelClosed(1, 0)).unwrap();
+    assert_eq!(closed.0, 1);
+
+    let full = TrySendError::try_from(SendError::ChannelFull(2, 0)).unwrap();
+    assert!(matches!(full, TrySendError::Full(2)));
+
+    let err = MpscSendError::try_from(SendError::ChannelFull(3, 1)).unwrap_err();
+    assert_eq!(err, SendError::ChannelFull(3, 1));
+
+    let err = TrySendError::try_from(SendError::FailedToComputeRouteID(4)).unwrap_err();
+    assert_eq!(err, SendError::FailedToComputeRouteID(4));
+}
+
+#[derive(Debug, PartialEq)]
+enum OrderEvent {
+    Created { order_id: u64 },
+    Paid { order_id: u64, amount: u64 },
+    Shipped { order_id: u64 },
+}
+
+impl crate::StickyRoute for OrderEvent {
+    type Key = u64;
+
+    fn route_key(&self) -> u64 {
+        match self {
+            OrderEvent::Created { order_id }
+            | OrderEvent::Paid { order_id, .. }
+            | OrderEvent::Shipped { order_id } => *order_id,
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_unbounded_send_by_key_routes_variants_together() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u64, OrderEvent>(NonZeroUsize::new(4).unwrap());
+
+    for order_id in 0..20 {
+        sender
+            .send_by_key(OrderEvent::Created { order_id })
+            .unwrap();
+        sender
+            .send_by_key(OrderEvent::Paid {
+                order_id,
+                amount: 10,
+            })
+            .unwrap();
+        sender
+            .send_by_key(OrderEvent::Shipped { order_id })
+            .unwrap();
+    }
+    drop(sender);
+
+    let mut routing: HashMap<u64, usize> = HashMap::new();
+    let mut total = 0;
+    for (receiver_idx, receiver) in receivers.iter_mut().enumerate() {
+        while let Some(event) = receiver.recv().await {
+            let order_id = crate::StickyRoute::route_key(&event);
+            assert_eq!(
+                *routing.entry(order_id).or_insert(receiver_idx),
+                receiver_idx
+            );
+            total += 1;
+        }
+    }
+
+    assert_eq!(total, 60);
+}
+
+#[tokio::test]
+async fn test_bounded_send_by_key() {
+    let (sender, mut receivers) =
+        sticky_channel::<u64, OrderEvent>(NonZeroUsize::new(1).unwrap(), 2);
+
+    sender
+        .send_by_key(OrderEvent::Created { order_id: 1 })
+        .await
+        .unwrap();
+    sender
+        .try_send_by_key(OrderEvent::Shipped { order_id: 1 })
+        .unwrap();
+
+    let err = sender
+        .try_send_by_key(OrderEvent::Created { order_id: 2 })
+        .unwrap_err();
+    assert_eq!(err.into_inner(), OrderEvent::Created { order_id: 2 });
+
+    assert_eq!(
+        receivers[0].recv().await,
+        Some(OrderEvent::Created { order_id: 1 })
+    );
+    assert_eq!(
+        receivers[0].recv().await,
+        Some(OrderEvent::Shipped { order_id: 1 })
+    );
+}
+
+#[tokio::test]
+async fn test_bounded_priority_uses_reserved_slots() {
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<i32, i32>::new(NonZeroUsize::new(1).unwrap(), 2)
+            .reserved(1)
+            .build();
+
+    sender.try_send(0, 1).unwrap();
+    sender.try_send(0, 2).unwrap();
+    assert!(sender.try_send(0, 3).unwrap_err().is_full());
+
+    sender.try_send_priority(0, 100).unwrap();
+    assert!(sender.try_send_priority(0, 101).unwrap_err().is_full());
+
+    assert_eq!(receivers[0].recv().await, Some(1));
+    assert_eq!(receivers[0].recv().await, Some(2));
+    assert_eq!(receivers[0].recv().await, Some(100));
+
+    // Regular slots are preferred, freed reserved slots are usable by priority sends only.
+    sender.try_send(0, 4).unwrap();
+    sender.try_send(0, 5).unwrap();
+    assert!(sender.try_send(0, 6).unwrap_err().is_full());
+    sender.try_send_priority(0, 102).unwrap();
+}
+
+#[tokio::test]
+async fn test_bounded_priority_send_not_blocked_by_bulk_traffic() {
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<i32, i32>::new(NonZeroUsize::new(1).unwrap(), 1)
+            .reserved(1)
+            .build();
+
+    sender.send(0, 1).await.unwrap();
+
+    tokio::time::timeout(Duration::from_secs(1), sender.send_priority(0, 2))
+        .await
+        .expect("priority send should use the reserved slot")
+        .unwrap();
+
+    let blocked = tokio::time::timeout(Duration::from_millis(10), sender.send(0, 3)).await;
+    assert!(blocked.is_err());
+
+    let mut buffer = Vec::new();
+    assert_eq!(receivers[0].recv_many(&mut buffer, 10).await, 2);
+    assert_eq!(buffer, vec![1, 2]);
+
+    sender.send(0, 3).await.unwrap();
+    sender.send_priority(0, 4).await.unwrap();
+    assert!(sender.try_send_priority(0, 5).unwrap_err().is_full());
+}
+
+#[tokio::test]
+async fn test_bounded_blocked_send_fails_when_receiver_dropped() {
+    let (sender, receivers) = sticky_channel::<i32, i32>(NonZeroUsize::new(1).unwrap(), 1);
+
+    sender.send(0, 1).await.unwrap();
+
+    let task = tokio::spawn(async move { sender.send(0, 2).await });
+    tokio::task::yield_now().await;
+    drop(receivers);
+
+    let result = task.await.unwrap();
+    assert!(matches!(result, Err(SendError::ChannelClosed(2, 0))));
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct KeyedMessage {
+    key: &'static str,
+    seq: u32,
+}
+
+impl crate::StickyRoute for KeyedMessage {
+    type Key = &'static str;
+
+    fn route_key(&self) -> &'static str {
+        self.key
+    }
+}
+
+fn keyed(key: &'static str, seq: u32) -> KeyedMessage {
+    KeyedMessage { key, seq }
+}
+
+#[tokio::test]
+async fn test_fair_receiver_interleaves_keys() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<&str, KeyedMessage>(NonZeroUsize::new(1).unwrap());
+
+    for seq in 0..4 {
+        sender.send_by_key(keyed("chatty", seq)).unwrap();
+    }
+    sender.send_by_key(keyed("quiet", 0)).unwrap();
+    sender.send_by_key(keyed("quiet", 1)).unwrap();
+    drop(sender);
+
+    let mut receiver = crate::FairReceiver::new(receivers.remove(0));
+    let mut received = Vec::new();
+    while let Some(message) = receiver.recv().await {
+        received.push(message);
+    }
+
+    assert_eq!(
+        received,
+        vec![
+            keyed("chatty", 0),
+            keyed("quiet", 0),
+            keyed("chatty", 1),
+            keyed("quiet", 1),
+            keyed("chatty", 2),
+            keyed("chatty", 3),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_fair_receiver_weights() {
+    let (sender, mut receivers) =
+        sticky_channel::<&str, KeyedMessage>(NonZeroUsize::new(1).unwrap(), 16);
+
+    for seq in 0..4 {
+        sender.send_by_key(keyed("heavy", seq)).await.unwrap();
+    }
+    for seq in 0..2 {
+        sender.send_by_key(keyed("light", seq)).await.unwrap();
+    }
+
+    let mut receiver = crate::FairReceiver::new(receivers.remove(0))
+        .with_weights(|key: &&str| if *key == "heavy" { 2 } else { 1 });
+
+    let mut received = Vec::new();
+    while let Ok(message) = receiver.try_recv() {
+        received.push((message.key, message.seq));
+    }
+
+    assert_eq!(
+        received,
+        vec![
+            ("heavy", 0),
+            ("heavy", 1),
+            ("light", 0),
+            ("heavy", 2),
+            ("heavy", 3),
+            ("light", 1),
+        ]
+    );
+    assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+}
+
+#[tokio::test]
+async fn test_fair_receiver_waits_for_messages() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<&str, KeyedMessage>(NonZeroUsize::new(1).unwrap());
+
+    let mut receiver = crate::FairReceiver::new(receivers.remove(0)).with_lookahead(1);
+    let task = tokio::spawn(async move { receiver.recv().await });
+
+    tokio::task::yield_now().await;
+    sender.send_by_key(keyed("late", 0)).unwrap();
+
+    assert_eq!(task.await.unwrap(), Some(keyed("late", 0)));
+}
+
+#[tokio::test]
+async fn test_unbounded_max_pending_per_key() {
+    let (sender, mut receivers) =
+        crate::UnboundedStickyChannelBuilder::<&str, i32>::new(NonZeroUsize::new(1).unwrap())
+            .max_pending_per_key(NonZeroUsize::new(2).unwrap())
+            .build();
+
+    sender.send("runaway", 1).unwrap();
+    sender.send("runaway", 2).unwrap();
+    let err = sender.send("runaway", 3).unwrap_err();
+    assert!(matches!(err, SendError::KeyBackpressure(3, 0)));
+
+    sender.send("other", 10).unwrap();
+
+    assert_eq!(receivers[0].recv().await, Some(1));
+    sender.send("runaway", 4).unwrap();
+
+    let mut buffer = Vec::new();
+    receivers[0].recv_many(&mut buffer, 10).await;
+    assert_eq!(buffer, vec![2, 10, 4]);
+
+    sender.send("runaway", 5).unwrap();
+    sender.send("runaway", 6).unwrap();
+}
+
+#[tokio::test]
+async fn test_bounded_max_pending_per_key() {
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<&str, i32>::new(NonZeroUsize::new(1).unwrap(), 10)
+            .max_pending_per_key(NonZeroUsize::new(1).unwrap())
+            .build();
+
+    sender.send("runaway", 1).await.unwrap();
+    assert!(matches!(
+        sender.try_send("runaway", 2),
+        Err(SendError::KeyBackpressure(2, 0))
+    ));
+    sender.try_send("other", 10).unwrap();
+
+    let blocked = tokio::time::timeout(Duration::from_millis(10), sender.send("runaway", 2)).await;
+    assert!(blocked.is_err());
+
+    let sender2 = sender.clone();
+    let task = tokio::spawn(async move { sender2.send("runaway", 3).await });
+    tokio::task::yield_now().await;
+
+    assert_eq!(receivers[0].recv().await, Some(1));
+    task.await.unwrap().unwrap();
+
+    assert_eq!(receivers[0].recv().await, Some(10));
+    assert_eq!(receivers[0].recv().await, Some(3));
+}
+
+#[tokio::test]
+async fn test_bounded_per_key_wait_fails_when_receiver_dropped() {
+    let (sender, receivers) =
+        crate::StickyChannelBuilder::<&str, i32>::new(NonZeroUsize::new(1).unwrap(), 10)
+            .max_pending_per_key(NonZeroUsize::new(1).unwrap())
+            .build();
+
+    sender.send("key", 1).await.unwrap();
+
+    let task = tokio::spawn(async move { sender.send("key", 2).await });
+    tokio::task::yield_now().await;
+    drop(receivers);
+
+    assert!(matches!(
+        task.await.unwrap(),
+        Err(SendError::ChannelClosed(2, 0))
+    ));
+}
+
+#[tokio::test]
+async fn test_hot_key_splitter_spreads_hot_ids() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<&str, crate::Sequenced<i32>>(NonZeroUsize::new(4).unwrap());
+    let splitter = crate::HotKeySplitter::new(sender, NonZeroUsize::new(3).unwrap());
+
+    splitter.send("cold", 0).unwrap();
+    splitter.mark_hot("hot");
+    for i in 1..=6 {
+        splitter.send("hot", i).unwrap();
+    }
+    drop(splitter);
+
+    let mut hot_consumers = Vec::new();
+    let mut hot_messages = Vec::new();
+    for (index, receiver) in receivers.iter_mut().enumerate() {
+        while let Some(message) = receiver.recv().await {
+            if message.message == 0 {
+                assert_eq!(message.seq, None);
+            } else {
+                hot_consumers.push(index);
+                hot_messages.push(message);
+            }
+        }
+    }
+
+    hot_consumers.dedup();
+    assert_eq!(hot_consumers.len(), 3);
+
+    let mut reassembler = crate::Reassembler::new();
+    let mut restored = Vec::new();
+    for message in hot_messages.into_iter().rev() {
+        restored.extend(reassembler.push(message));
+    }
+    assert_eq!(restored, vec![1, 2, 3, 4, 5, 6]);
+    assert_eq!(reassembler.pending(), 0);
+}
+
+#[test]
+fn test_reassembler_holds_back_early_messages() {
+    let mut reassembler = crate::Reassembler::new();
+    let message = |key, seq, message| crate::Sequenced {
+        key,
+        seq: Some(seq),
+        message,
+    };
+
+    assert!(reassembler.push(message(1, 1, "b")).is_empty());
+    assert!(reassembler.push(message(1, 2, "c")).is_empty());
+    assert_eq!(reassembler.push(message(2, 0, "x")), vec!["x"]);
+    assert_eq!(reassembler.pending(), 2);
+
+    assert_eq!(reassembler.push(message(1, 0, "a")), vec!["a", "b", "c"]);
+    assert!(reassembler.push(message(1, 0, "a")).is_empty());
+    assert_eq!(reassembler.pending(), 0);
+}
+
+#[tokio::test]
+async fn test_hot_key_splitter_keeps_numbering_of_unsent_messages() {
+    let (sender, mut receivers) =
+        sticky_channel::<&str, crate::Sequenced<i32>>(NonZeroUsize::new(1).unwrap(), 1);
+    let splitter = crate::HotKeySplitter::new(sender, NonZeroUsize::new(2).unwrap());
+    splitter.mark_hot("hot");
+
+    splitter.send("hot", 1).await.unwrap();
+    let cancelled = tokio::time::timeout(Duration::from_millis(10), splitter.send("hot", 2)).await;
+    assert!(cancelled.is_err());
+    assert_eq!(receivers[0].recv().await.unwrap().seq, Some(0));
+
+    splitter.send("hot", 3).await.unwrap();
+    assert_eq!(receivers[0].recv().await.unwrap().seq, Some(1));
+
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<&str, crate::Sequenced<i32>>(NonZeroUsize::new(2).unwrap());
+    let index = sender.route("hot").unwrap().index;
+    let splitter = crate::HotKeySplitter::new(sender, NonZeroUsize::new(2).unwrap());
+    splitter.mark_hot("hot");
+    receivers[1 - index].close();
+
+    splitter.send("hot", 1).unwrap();
+    assert!(splitter.send("hot", 2).is_err());
+    splitter.send("hot", 3).unwrap();
+    assert_eq!(receivers[index].recv().await.unwrap().seq, Some(0));
+    assert_eq!(receivers[index].recv().await.unwrap().seq, Some(1));
+}
+
+#[test]
+fn test_reassembler_skips_gaps_and_resets_ids() {
+    let mut reassembler = crate::Reassembler::new();
+    let message = |seq, message| crate::Sequenced {
+        key: 1,
+        seq: Some(seq),
+        message,
+    };
+
+    assert!(reassembler.push(message(1, "b")).is_empty());
+    assert!(reassembler.push(message(3, "d")).is_empty());
+    assert!(reassembler.push(message(4, "e")).is_empty());
+    assert_eq!(reassembler.skip_to(1, 3), vec!["b", "d", "e"]);
+    assert_eq!(reassembler.push(message(5, "f")), vec!["f"]);
+    assert!(reassembler.skip_to(1, 2).is_empty());
+
+    assert!(reassembler.push(message(8, "i")).is_empty());
+    assert_eq!(reassembler.reset(1), vec!["i"]);
+    assert_eq!(reassembler.pending(), 0);
+    assert_eq!(reassembler.push(message(0, "a")), vec!["a"]);
+}
+
+#[tokio::test]
+async fn test_replicated_sender_delivers_to_backup() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u32, crate::Replica<u32>>(NonZeroUsize::new(3).unwrap());
+    let sender = crate::ReplicatedSender::new(sender, NonZeroUsize::new(2).unwrap());
+
+    for id in 0..20 {
+        sender.send(id, id).unwrap();
+    }
+    drop(sender);
+
+    let mut primaries = HashMap::new();
+    let mut backups = HashMap::new();
+    for (index, receiver) in receivers.iter_mut().enumerate() {
+        while let Some(replica) = receiver.recv().await {
+            let seen = if replica.is_primary() {
+                &mut primaries
+            } else {
+                &mut backups
+            };
+            assert!(seen.insert(replica.message, index).is_none());
+        }
+    }
+
+    assert_eq!(primaries.len(), 20);
+    assert_eq!(backups.len(), 20);
+    for (id, primary) in primaries {
+        assert_eq!(backups[&id], (primary + 1) % 3);
+    }
+}
+
+#[tokio::test]
+async fn test_replicated_sender_reports_failed_replica() {
+    let (sender, mut receivers) =
+        sticky_channel::<u32, crate::Replica<u32>>(NonZeroUsize::new(2).unwrap(), 1);
+    let sender = crate::ReplicatedSender::new(sender, NonZeroUsize::new(5).unwrap());
+
+    sender.try_send(7, 7).unwrap();
+    receivers[0].close();
+    receivers[1].close();
+
+    let err = sender.send(7, 8).await.unwrap_err();
+    assert!(matches!(err, SendError::ChannelClosed(8, _)));
+    assert_eq!(receivers[0].recv().await.unwrap().message, 7);
+    assert_eq!(receivers[1].recv().await.unwrap().message, 7);
+}
+
+#[tokio::test]
+async fn test_replicated_sender_quorum() {
+    let (sender, mut receivers) =
+        sticky_channel::<u32, crate::Replica<u32>>(NonZeroUsize::new(3).unwrap(), 1);
+    let sender = crate::ReplicatedSender::new(sender, NonZeroUsize::new(3).unwrap());
+
+    receivers[1].close();
+    sender.try_send_quorum(1, 10, 2).unwrap();
+
+    let err = sender.try_send_quorum(1, 11, 2).unwrap_err();
+    assert_eq!(err.quorum, 2);
+    assert_eq!(err.accepted, 0);
+    assert_eq!(err.results.len(), 3);
+    assert!(
+        err.results
+            .iter()
+            .any(|result| matches!(result, Err(SendError::ChannelClosed(11, 1))))
+    );
+    assert_eq!(
+        err.results
+            .iter()
+            .filter(|result| matches!(result, Err(SendError::ChannelFull(11, _))))
+            .count(),
+        2
+    );
+
+    assert_eq!(receivers[0].recv().await.unwrap().message, 10);
+    assert_eq!(receivers[2].recv().await.unwrap().message, 10);
+
+    let err = sender.send_quorum(1, 12, 3).await.unwrap_err();
+    assert_eq!(err.accepted, 2);
+    assert_eq!(
+        err.results
+            .iter()
+            .filter(|result| matches!(result, Err(SendError::ChannelClosed(12, 1))))
+            .count(),
+        1
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_tick_injected_into_every_consumer() {
+    let (sender, mut receivers) =
+        crate::UnboundedStickyChannelBuilder::<&str, i32>::new(NonZeroUsize::new(3).unwrap())
+            .max_pending_per_key(NonZeroUsize::new(1).unwrap())
+            .tick(Duration::from_secs(1), || -1)
+            .build();
+
+    sender.send("key", 1).unwrap();
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+
+    for receiver in &mut receivers {
+        let mut buffer = Vec::new();
+        receiver.recv_many(&mut buffer, 10).await;
+        assert_eq!(buffer.last(), Some(&-1));
+        assert!(buffer.len() <= 2);
+    }
+
+    // Ticks do not count towards the per-ID limit.
+    sender.send("key", 2).unwrap();
+
+    drop(sender);
+    for receiver in &mut receivers {
+        while receiver.recv().await.is_some() {}
+    }
+}
+
+#[test]
+#[should_panic(expected = "tick period must be non-zero")]
+fn test_zero_tick_period_panics() {
+    let _ = crate::StickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(1).unwrap(), 1)
+        .tick(Duration::ZERO, || 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_bounded_tick_bypasses_capacity() {
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<&str, i32>::new(NonZeroUsize::new(1).unwrap(), 1)
+            .tick(Duration::from_secs(1), || -1)
+            .build();
+
+    sender.send("key", 1).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+
+    assert_eq!(receivers[0].recv().await, Some(1));
+    assert_eq!(receivers[0].recv().await, Some(-1));
+    assert_eq!(receivers[0].recv().await, Some(-1));
+    assert!(matches!(receivers[0].try_recv(), Err(TryRecvError::Empty)));
+}
+
+#[tokio::test]
+async fn test_control_channel_reaches_every_receiver() {
+    let (sender, control, mut receivers) =
+        crate::StickyChannelBuilder::<&str, i32>::new(NonZeroUsize::new(3).unwrap(), 10)
+            .build_with_control::<&str>();
+
+    sender.send("key", 1).await.unwrap();
+    assert_eq!(control.send("pause"), Ok(3));
+
+    for receiver in &mut receivers {
+        assert_eq!(
+            receiver.recv_event().await,
+            Some(crate::Event::Control("pause"))
+        );
+    }
+
+    drop(sender);
+    let mut data = Vec::new();
+    for receiver in &mut receivers {
+        while let Some(event) = receiver.recv_event().await {
+            data.push(event);
+        }
+    }
+    assert_eq!(data, vec![crate::Event::Data(1)]);
+}
+
+#[tokio::test]
+async fn test_control_channel_send_fails_without_receivers() {
+    let (_sender, receivers) = unbounded_sticky_channel::<&str, i32>(NonZeroUsize::new(2).unwrap());
+    let (control, mut receivers) = crate::control_channel::<_, u8>(receivers);
+
+    receivers[0].close();
+    assert_eq!(control.send(1), Ok(1));
+    assert_eq!(receivers[1].try_recv_event(), Ok(crate::Event::Control(1)));
+    assert_eq!(receivers[1].try_recv_event(), Err(TryRecvError::Empty));
+
+    drop(receivers);
+    assert_eq!(control.send(2), Err(2));
+}
+
+#[tokio::test]
+async fn test_barrier_completes_after_every_consumer_received_it() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u32, u32>(NonZeroUsize::new(3).unwrap());
+
+    sender.send(1, 1).unwrap();
+    let barrier = sender.send_barrier(crate::BarrierId(7));
+    sender.send(1, 2).unwrap();
+    assert!(!barrier.is_complete());
+
+    let marker = crate::Event::Barrier(crate::BarrierId(7));
+    for receiver in &mut receivers {
+        let mut events = Vec::new();
+        while let Ok(event) = receiver.try_recv_event() {
+            events.push(event);
+        }
+        if events.len() > 1 {
+            assert_eq!(
+                events,
+                vec![crate::Event::Data(1), marker.clone(), crate::Event::Data(2)]
+            );
+        } else {
+            assert_eq!(events, vec![marker.clone()]);
+        }
+    }
+    barrier.wait().await.unwrap();
+    assert!(barrier.is_complete());
+}
+
+#[tokio::test]
+async fn test_barrier_is_acknowledged_by_recv_and_abandoned_by_dropped_consumer() {
+    let (sender, mut receivers) = sticky_channel::<u32, u32>(NonZeroUsize::new(2).unwrap(), 4);
+
+    let barrier = sender.send_barrier(crate::BarrierId(1));
+    sender.send(0, 0).await.unwrap();
+    let index = sender.route(0).unwrap().index;
+
+    let mut buffer = Vec::new();
+    assert_eq!(receivers[index].recv_many(&mut buffer, 10).await, 1);
+    assert_eq!(buffer, vec![0]);
+
+    let waiter = tokio::spawn({
+        let barrier = barrier.clone();
+        async move { barrier.wait().await }
+    });
+    tokio::task::yield_now().await;
+    receivers.remove(1 - index);
+
+    assert_eq!(
+        waiter.await.unwrap(),
+        Err(crate::BarrierError(crate::BarrierId(1)))
+    );
+}
+
+#[tokio::test]
+async fn test_watermark_delivered_in_order() {
+    let (sender, mut receivers) = sticky_channel::<u32, u32>(NonZeroUsize::new(2).unwrap(), 4);
+    let index = sender.route(0).unwrap().index;
+
+    sender.send(0, 1).await.unwrap();
+    sender.advance_watermark(10);
+    sender.send(0, 2).await.unwrap();
+    sender.advance_watermark(5);
+    sender.advance_watermark(20);
+
+    let receiver = &mut receivers[index];
+    assert_eq!(receiver.recv_event().await, Some(crate::Event::Data(1)));
+    assert_eq!(
+        receiver.recv_event().await,
+        Some(crate::Event::Watermark(10))
+    );
+    assert_eq!(receiver.recv_event().await, Some(crate::Event::Data(2)));
+    assert_eq!(
+        receiver.recv_event().await,
+        Some(crate::Event::Watermark(20))
+    );
+    assert_eq!(receiver.watermark(), Some(20));
+
+    let other = &mut receivers[1 - index];
+    assert_eq!(other.try_recv(), Err(TryRecvError::Empty));
+    assert_eq!(other.watermark(), Some(20));
+}
+
+#[tokio::test]
+async fn test_reorder_receiver_releases_on_watermark() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<(), (u64, &str)>(NonZeroUsize::new(1).unwrap());
+    let mut receiver =
+        crate::ReorderReceiver::new(receivers.remove(0), |message: &(u64, &str)| message.0);
+
+    sender.send((), (3, "c")).unwrap();
+    sender.send((), (1, "a")).unwrap();
+    sender.send((), (5, "e")).unwrap();
+    sender.send((), (2, "b")).unwrap();
+    assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+
+    sender.advance_watermark(3);
+    assert_eq!(receiver.recv().await, Some((1, "a")));
+    assert_eq!(receiver.recv().await, Some((2, "b")));
+    assert_eq!(receiver.recv().await, Some((3, "c")));
+    assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+
+    sender.send((), (4, "d")).unwrap();
+    drop(sender);
+    assert_eq!(receiver.recv().await, Some((4, "d")));
+    assert_eq!(receiver.recv().await, Some((5, "e")));
+    assert_eq!(receiver.recv().await, None);
+}
+
+#[tokio::test]
+async fn test_reorder_receiver_lateness_and_capacity() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<(), u64>(NonZeroUsize::new(1).unwrap());
+    let mut receiver =
+        crate::ReorderReceiver::new(receivers.remove(0), |ts: &u64| *ts).with_lateness(10);
+    for ts in [12, 5, 20, 30] {
+        sender.send((), ts).unwrap();
+    }
+    let mut released = Vec::new();
+    while let Ok(ts) = receiver.try_recv() {
+        released.push(ts);
+    }
+    assert_eq!(released, vec![5, 12, 20]);
+
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<(), u64>(NonZeroUsize::new(1).unwrap());
+    let mut receiver =
+        crate::ReorderReceiver::new(receivers.remove(0), |ts: &u64| *ts).with_capacity(2);
+    for ts in [12, 5, 20, 30] {
+        sender.send((), ts).unwrap();
+    }
+    let mut released = Vec::new();
+    while let Ok(ts) = receiver.try_recv() {
+        released.push(ts);
+    }
+    assert_eq!(released, vec![5, 12]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_batching_sender_pushes_full_batches_and_lingers() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<(), i32>(NonZeroUsize::new(1).unwrap());
+    let sender = crate::UnboundedBatchingSender::new(
+        sender,
+        Duration::from_millis(5),
+        NonZeroUsize::new(3).unwrap(),
+    );
+
+    for i in 1..=5 {
+        sender.send((), i).unwrap();
+    }
+
+    let mut buffer = Vec::new();
+    assert_eq!(receivers[0].recv_many(&mut buffer, 2).await, 2);
+    assert_eq!(receivers[0].try_recv(), Ok(3));
+    assert_eq!(receivers[0].try_recv(), Err(TryRecvError::Empty));
+
+    tokio::time::sleep(Duration::from_millis(6)).await;
+    assert_eq!(receivers[0].recv_many(&mut buffer, 10).await, 2);
+    assert_eq!(buffer, vec![1, 2, 4, 5]);
+
+    sender.send((), 6).unwrap();
+    drop(sender);
+    assert_eq!(receivers[0].recv().await, Some(6));
+    assert_eq!(receivers[0].recv().await, None);
+}
+
+#[tokio::test]
+async fn test_bounded_batching_sender_takes_capacity_on_send() {
+    let (sender, mut receivers) = sticky_channel::<(), i32>(NonZeroUsize::new(1).unwrap(), 2);
+    let sender = crate::BatchingSender::new(
+        sender,
+        Duration::from_secs(60),
+        NonZeroUsize::new(10).unwrap(),
+    );
+
+    sender.try_send((), 1).unwrap();
+    sender.send((), 2).await.unwrap();
+    assert!(matches!(
+        sender.try_send((), 3),
+        Err(SendError::ChannelFull(3, 0))
+    ));
+    assert_eq!(receivers[0].try_recv(), Err(TryRecvError::Empty));
+
+    sender.flush();
+    assert_eq!(receivers[0].recv().await, Some(1));
+    sender.try_send((), 3).unwrap();
+}
+
+#[cfg(feature = "bytes")]
+#[tokio::test]
+async fn test_bytes_channel_byte_budget() {
+    use bytes::Bytes;
+
+    let (sender, mut receivers) =
+        crate::sticky_bytes_channel::<&str>(NonZeroUsize::new(1).unwrap(), 10);
+
+    sender
+        .try_send("key", Bytes::from_static(b"hello"))
+        .unwrap();
+    sender
+        .send("key", Bytes::from_static(b"world"))
+        .await
+        .unwrap();
+    assert!(matches!(
+        sender.try_send("key", Bytes::from_static(b"!")),
+        Err(SendError::ChannelFull(_, 0))
+    ));
+    assert_eq!(receivers[0].available_bytes(), 0);
+
+    let mut buffer = Vec::new();
+    assert_eq!(receivers[0].recv_many(&mut buffer, 10).await, 2);
+    assert_eq!(
+        buffer,
+        vec![Bytes::from_static(b"hello"), Bytes::from_static(b"world")]
+    );
+    assert_eq!(receivers[0].available_bytes(), 10);
+
+    // Payloads larger than the budget take all of it.
+    sender.try_send("key", Bytes::from(vec![0; 64])).unwrap();
+    assert_eq!(receivers[0].available_bytes(), 0);
+    assert_eq!(
+        receivers[0].recv().await.map(|message| message.len()),
+        Some(64)
+    );
+    assert_eq!(receivers[0].available_bytes(), 10);
+}
+
+#[tokio::test]
+async fn test_send_shared_delivers_once_per_consumer() {
+    let (sender, mut receivers) =
+        sticky_channel::<u32, Arc<Vec<u8>>>(NonZeroUsize::new(3).unwrap(), 10);
+    let payload = Arc::new(vec![7; 1024]);
+
+    let ids: Vec<u32> = (0..30).collect();
+    sender
+        .send_shared(ids.clone(), payload.clone())
+        .await
+        .unwrap();
+    sender
+        .try_send_shared([ids[0], ids[0]], payload.clone())
+        .unwrap();
+
+    let mut received = 0;
+    for receiver in &mut receivers {
+        while let Ok(message) = receiver.try_recv() {
+            assert!(Arc::ptr_eq(&message, &payload));
+            received += 1;
+        }
+    }
+    assert_eq!(received, 4);
+}
+
+#[tokio::test]
+async fn test_recv_owned_unwraps_last_reference() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u32, Arc<String>>(NonZeroUsize::new(2).unwrap());
+
+    sender
+        .send_shared([1, 2, 3, 4], Arc::new("shared".to_string()))
+        .unwrap();
+    drop(sender);
+
+    for receiver in &mut receivers {
+        while let Some(message) = receiver.recv_owned().await {
+            assert_eq!(message, "shared");
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_block_framing_preserves_order() {
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<u32, u32>::new(NonZeroUsize::new(1).unwrap(), 100)
+            .block_size(NonZeroUsize::new(4).unwrap())
+            .build();
+    let receiver = &mut receivers[0];
+
+    for message in 0..10 {
+        sender.send(message, message).await.unwrap();
+    }
+    assert_eq!(receiver.try_recv(), Ok(0));
+    assert_eq!(receiver.recv().await, Some(1));
+
+    let mut buffer = Vec::new();
+    assert_eq!(receiver.recv_many(&mut buffer, 5).await, 5);
+    assert_eq!(buffer, vec![2, 3, 4, 5, 6]);
+
+    sender.send(10, 10).await.unwrap();
+    drop(sender);
+    buffer.clear();
+    assert_eq!(receiver.recv_many(&mut buffer, 10).await, 4);
+    assert_eq!(buffer, vec![7, 8, 9, 10]);
+    assert_eq!(receiver.recv().await, None);
+}
+
+#[tokio::test]
+async fn test_block_framing_drops_open_block_with_receiver() {
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<u32, u32>::new(NonZeroUsize::new(1).unwrap(), 8)
+            .block_size(NonZeroUsize::new(4).unwrap())
+            .build();
+
+    sender.send(0, 1).await.unwrap();
+    let barrier = sender.send_barrier(crate::BarrierId(7));
+    drop(receivers.pop());
+
+    let result = tokio::time::timeout(Duration::from_secs(1), barrier.wait()).await;
+    assert_eq!(result, Ok(Err(crate::BarrierError(crate::BarrierId(7)))));
+    assert!(sender.send(0, 2).await.is_err());
+}
+
+#[tokio::test]
+async fn test_block_framing_keeps_markers_in_place() {
+    let (sender, mut receivers) =
+        crate::UnboundedStickyChannelBuilder::<u32, u32>::new(NonZeroUsize::new(1).unwrap())
+            .block_size(NonZeroUsize::new(3).unwrap())
+            .build();
+
+    sender.send(0, 1).unwrap();
+    let barrier = sender.send_barrier(crate::BarrierId(3));
+    sender.send(0, 2).unwrap();
+    sender.advance_watermark(5);
+
+    let receiver = &mut receivers[0];
+    assert_eq!(receiver.recv_event().await, Some(crate::Event::Data(1)));
+    assert!(!barrier.is_complete());
+    assert_eq!(
+        receiver.recv_event().await,
+        Some(crate::Event::Barrier(crate::BarrierId(3)))
+    );
+    assert_eq!(receiver.recv_event().await, Some(crate::Event::Data(2)));
+    assert_eq!(
+        receiver.recv_event().await,
+        Some(crate::Event::Watermark(5))
+    );
+    barrier.wait().await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_coalesced_wakeups_hand_over_blocks_once_per_delay() {
+    let (sender, mut receivers) =
+        crate::UnboundedStickyChannelBuilder::<u32, u32>::new(NonZeroUsize::new(1).unwrap())
+            .coalesce_wakeups(Duration::from_millis(10))
+            .build();
+    let receiver = &mut receivers[0];
+
+    for message in 0..3 {
+        sender.send(message, message).unwrap();
+    }
+    assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let mut buffer = Vec::new();
+    assert_eq!(receiver.recv_many(&mut buffer, 10).await, 3);
+    assert_eq!(buffer, vec![0, 1, 2]);
+
+    sender.send(3, 3).unwrap();
+    drop(sender);
+    assert_eq!(receiver.recv().await, Some(3));
+    assert_eq!(receiver.recv().await, None);
+
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<u32, u32>::new(NonZeroUsize::new(1).unwrap(), 8)
+            .block_size(NonZeroUsize::new(2).unwrap())
+            .coalesce_wakeups(Duration::from_secs(60))
+            .build();
+    let receiver = &mut receivers[0];
+
+    for message in 0..3 {
+        sender.send(message, message).await.unwrap();
+    }
+    assert_eq!(receiver.try_recv(), Ok(0));
+    assert_eq!(receiver.try_recv(), Ok(1));
+    assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+    drop(sender);
+    assert_eq!(receiver.try_recv(), Ok(2));
+    assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+}
+
+#[test]
+#[should_panic(expected = "max wakeup delay must be non-zero")]
+fn test_zero_max_wakeup_delay_panics() {
+    let _ = crate::UnboundedStickyChannelBuilder::<u32, u32>::new(NonZeroUsize::new(1).unwrap())
+        .coalesce_wakeups(Duration::ZERO);
+}
+
+#[tokio::test]
+async fn test_redelivery_receiver_requeues_dropped_deliveries_in_order() {
+    let (sender, receivers) = unbounded_sticky_channel::<u32, u32>(NonZeroUsize::new(1).unwrap());
+    let mut receiver = crate::RedeliveryReceiver::new(receivers.into_iter().next().unwrap());
+
+    for message in 1..=3 {
+        sender.send(0, message).unwrap();
+    }
+    drop(sender);
+
+    let first = receiver.recv().await.unwrap();
+    let second = receiver.recv().await.unwrap();
+    assert_eq!(*first, 1);
+    drop(second);
+    drop(first);
+
+    assert_eq!(receiver.recv().await.unwrap().complete(), 1);
+    assert_eq!(receiver.recv().await.unwrap().complete(), 2);
+    assert_eq!(receiver.recv().await.unwrap().complete(), 3);
+    assert!(receiver.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn test_redelivery_receiver_waits_for_outstanding_delivery() {
+    let (sender, receivers) = sticky_channel::<u32, u32>(NonZeroUsize::new(1).unwrap(), 4);
+    let mut receiver = crate::RedeliveryReceiver::new(receivers.into_iter().next().unwrap());
+
+    sender.send(0, 7).await.unwrap();
+    drop(sender);
+
+    let delivery = receiver.recv().await.unwrap();
+    assert_eq!(receiver.try_recv().unwrap_err(), TryRecvError::Empty);
+
+    let worker = tokio::spawn(async move {
+        let _delivery = delivery;
+        panic!("worker failed");
+    });
+    assert!(worker.await.unwrap_err().is_panic());
+
+    assert_eq!(receiver.recv().await.unwrap().complete(), 7);
+    assert!(receiver.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn test_finish_sentinel_follows_backlog() {
+    let (sender, mut receivers) = sticky_channel::<u32, u32>(NonZeroUsize::new(2).unwrap(), 8);
+    let other = sender.clone();
+    let index = sender.route(0).unwrap().index;
+
+    sender.send(0, 1).await.unwrap();
+    sender.send(0, 2).await.unwrap();
+    sender.finish();
+    other.send(0, 3).await.unwrap();
+
+    let receiver = &mut receivers[index];
+    let mut backlog = Vec::new();
+    while let Some(event) = receiver.recv_event().await {
+        match event {
+            crate::Event::Data(message) => backlog.push(message),
+            crate::Event::Finished => break,
+            event => panic!("unexpected event {event:?}"),
+        }
+    }
+    assert_eq!(backlog, vec![1, 2]);
+    assert!(receiver.is_finished());
+    assert_eq!(receiver.try_recv(), Ok(3));
+
+    let idle = &mut receivers[1 - index];
+    assert!(!idle.is_finished());
+    assert_eq!(idle.try_recv(), Err(TryRecvError::Empty));
+    assert!(idle.is_finished());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_drain_with_timeout() {
+    let (sender, mut receivers) = sticky_channel::<u32, u32>(NonZeroUsize::new(1).unwrap(), 8);
+    sender.send(0, 1).await.unwrap();
+    sender.send(0, 2).await.unwrap();
+
+    let receiver = &mut receivers[0];
+    assert_eq!(
+        receiver.drain_with_timeout(Duration::from_secs(1)).await,
+        (vec![1, 2], false)
+    );
+
+    sender.send(0, 3).await.unwrap();
+    receiver.close();
+    assert_eq!(
+        receiver.drain_with_timeout(Duration::from_secs(1)).await,
+        (vec![3], true)
+    );
+    assert!(sender.send(0, 4).await.is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_recv_with_lag() {
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<u32, u32>::new(NonZeroUsize::new(1).unwrap(), 8)
+            .track_lag()
+            .build();
+
+    sender.send(0, 1).await.unwrap();
+    tokio::time::advance(Duration::from_millis(30)).await;
+    sender.send(0, 2).await.unwrap();
+    tokio::time::advance(Duration::from_millis(10)).await;
+
+    let receiver = &mut receivers[0];
+    assert_eq!(
+        receiver.recv_with_lag().await,
+        Some((1, Duration::from_millis(40)))
+    );
+    assert_eq!(
+        receiver.recv_with_lag().await,
+        Some((2, Duration::from_millis(10)))
+    );
+
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u32, u32>(NonZeroUsize::new(1).unwrap());
+    sender.send(0, 3).unwrap();
+    tokio::time::advance(Duration::from_millis(10)).await;
+    assert_eq!(
+        receivers[0].recv_with_lag().await,
+        Some((3, Duration::ZERO))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_latency_report_per_consumer() {
+    let (sender, mut receivers) =
+        crate::UnboundedStickyChannelBuilder::<u32, u32>::new(NonZeroUsize::new(2).unwrap())
+            .track_lag()
+            .build();
+    let index = sender.route(0).unwrap().index;
+
+    for message in 0..100 {
+        sender.send(0, message).unwrap();
+        tokio::time::advance(Duration::from_millis(1)).await;
+    }
+    let mut buffer = Vec::new();
+    receivers[index].recv_many(&mut buffer, 50).await;
+    receivers[index].recv_many(&mut buffer, 50).await;
+
+    let report = receivers[index].latency_report().unwrap();
+    assert_eq!(report.count(), 100);
+    assert_eq!(report.min(), Duration::from_millis(1));
+    assert_eq!(report.max(), Duration::from_millis(100));
+    let p50 = report.p50().as_secs_f64();
+    assert!((0.049..=0.052).contains(&p50), "p50 was {p50}");
+    let p99 = report.p99().as_secs_f64();
+    assert!((0.098..=0.1).contains(&p99), "p99 was {p99}");
+
+    let reports = sender.latency_report().unwrap();
+    assert_eq!(reports[index], report);
+    assert_eq!(reports[1 - index].count(), 0);
+    assert_eq!(reports[1 - index].p99(), Duration::ZERO);
+
+    let (sender, receivers) = unbounded_sticky_channel::<u32, u32>(NonZeroUsize::new(1).unwrap());
+    assert!(sender.latency_report().is_none());
+    assert!(receivers[0].latency_report().is_none());
+}
+
+#[tokio::test]
+async fn test_shedding_sender_drops_beyond_threshold() {
+    let (sender, mut receivers) = sticky_channel::<u32, u32>(NonZeroUsize::new(2).unwrap(), 3);
+    let index = sender.route(0).unwrap().index;
+    let shedder = crate::SheddingSender::new(sender, 2);
+
+    assert_eq!(shedder.send(0, 1), Ok(true));
+    assert_eq!(shedder.send(0, 2), Ok(true));
+    assert_eq!(shedder.send(0, 3), Ok(false));
+    assert_eq!(receivers[index].recv().await, Some(1));
+    assert_eq!(shedder.send(0, 4), Ok(true));
+
+    let dropped = shedder.dropped();
+    assert_eq!(dropped[index].get(crate::ShedReason::Depth), 1);
+    assert_eq!(dropped[index].total(), 1);
+    assert_eq!(dropped[1 - index], crate::ShedCounts::default());
+
+    let (sender, _receivers) =
+        crate::StickyChannelBuilder::<u32, u32>::new(NonZeroUsize::new(1).unwrap(), 1).build();
+    let full = crate::SheddingSender::new(sender, 10);
+    assert_eq!(full.send(0, 1), Ok(true));
+    assert_eq!(full.clone().send(0, 2), Ok(false));
+    assert_eq!(full.dropped()[0].full, 1);
+}
+
+#[tokio::test]
+async fn test_unbounded_shedding_sender_recovers_after_receive() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u32, u32>(NonZeroUsize::new(1).unwrap());
+    let shedder = crate::SheddingSender::new(sender, 1);
+
+    assert_eq!(shedder.send(0, 1), Ok(true));
+    assert_eq!(shedder.send(0, 2), Ok(false));
+    let mut buffer = Vec::new();
+    assert_eq!(receivers[0].recv_many(&mut buffer, 10).await, 1);
+    assert_eq!(shedder.send(0, 3), Ok(true));
+    assert_eq!(shedder.dropped()[0].depth, 1);
+}
+
+#[tokio::test]
+async fn test_shedding_sender_samples_under_pressure() {
+    let (sender, _receivers) = unbounded_sticky_channel::<u32, u32>(NonZeroUsize::new(1).unwrap());
+    let shedder =
+        crate::SheddingSender::new(sender, 1000).with_sampling(crate::SamplingPolicy::default());
+
+    for message in 0..500 {
+        assert_eq!(shedder.send(0, message), Ok(true));
+    }
+
+    let mut kept = 0;
+    for message in 500..2500 {
+        if shedder.send(0, message).unwrap() {
+            kept += 1;
+        }
+    }
+    let dropped = shedder.dropped()[0];
+    assert_eq!(kept + dropped.total(), 2000);
+    assert!(dropped.sampled > 100, "sampled {}", dropped.sampled);
+    assert!(kept > 100, "kept {kept}");
+}
+
+#[cfg(feature = "test-util")]
+#[tokio::test(start_paused = true)]
+async fn test_faulty_sender_is_deterministic() {
+    use crate::test_util::{FaultConfig, FaultySender};
+
+    let config = FaultConfig::new()
+        .with_send_delay(Duration::from_millis(5))
+        .with_full_probability(0.5)
+        .with_seed(42);
+
+    let mut outcomes = Vec::new();
+    for _ in 0..2 {
+        let (sender, _receivers) =
+            unbounded_sticky_channel::<u32, u32>(NonZeroUsize::new(2).unwrap());
+        let sender = FaultySender::new(sender, config);
+        let start = tokio::time::Instant::now();
+        let mut results = Vec::new();
+        for message in 0..20 {
+            results.push(sender.send(message, message).await.is_ok());
+        }
+        assert_eq!(start.elapsed(), Duration::from_millis(100));
+        outcomes.push(results);
+    }
+    assert_eq!(outcomes[0], outcomes[1]);
+    assert!(outcomes[0].contains(&true) && outcomes[0].contains(&false));
+
+    let (sender, _receivers) = sticky_channel::<u32, u32>(NonZeroUsize::new(1).unwrap(), 4);
+    let sender = FaultySender::new(sender, FaultConfig::new().with_full_probability(1.0));
+    assert_eq!(sender.try_send(0, 1), Err(SendError::ChannelFull(1, 0)));
+}
+
+#[cfg(feature = "test-util")]
+#[tokio::test]
+async fn test_stallable_receiver() {
+    use crate::test_util::StallableReceiver;
+
+    let (sender, receivers) = sticky_channel::<u32, u32>(NonZeroUsize::new(1).unwrap(), 2);
+    let mut receiver = StallableReceiver::new(receivers.into_iter().next().unwrap());
+    let stall = receiver.stall_handle();
+
+    stall.stall();
+    sender.send(0, 1).await.unwrap();
+    sender.send(0, 2).await.unwrap();
+    assert_eq!(sender.try_send(0, 3), Err(SendError::ChannelFull(3, 0)));
+    assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+
+    let resume = tokio::spawn({
+        let stall = stall.clone();
+        async move {
+            tokio::task::yield_now().await;
+            stall.resume();
+        }
+    });
+    assert_eq!(receiver.recv().await, Some(1));
+    resume.await.unwrap();
+    assert!(!stall.is_stalled());
+    assert_eq!(receiver.try_recv(), Ok(2));
+}
+
+#[cfg(feature = "test-util")]
+#[tokio::test]
+async fn test_fixed_state_pins_routes() {
+    use crate::test_util::{FixedState, assert_routes_to};
+
+    let (sender, mut receivers) = crate::unbounded_sticky_channel_with_hasher::<u64, u64, _>(
+        NonZeroUsize::new(3).unwrap(),
+        FixedState,
+    );
+    for id in 0..9 {
+        assert_routes_to(&sender, id, id as usize % 3);
+        sender.send(id, id).unwrap();
+    }
+    assert_eq!(receivers[1].try_recv(), Ok(1));
+
+    let (sender, _receivers) = crate::sticky_channel_with_hasher::<&str, u64, _>(
+        NonZeroUsize::new(3).unwrap(),
+        8,
+        FixedState,
+    );
+    let index = sender.route_of("tenant-a").unwrap();
+    assert_routes_to(&sender, "tenant-a", index);
+    assert!(
+        std::panic::catch_unwind(|| assert_routes_to(&sender, "tenant-a", (index + 1) % 3))
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_sequenced_receiver_restores_send_order() {
+    use crate::{Sequenced, SequencedReceiver};
+
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u64, Sequenced<&str>>(NonZeroUsize::new(1).unwrap());
+    let sequenced = |key, seq, message| Sequenced {
+        key,
+        seq: Some(seq),
+        message,
+    };
+
+    // Messages of ID 1 arrive out of order, interleaved with an ID that is in order and an unsequenced message.
+    sender.send(1, sequenced(1, 1, "b")).unwrap();
+    sender.send(2, sequenced(2, 0, "x")).unwrap();
+    sender
+        .send(
+            3,
+            Sequenced {
+                key: 3,
+                seq: None,
+                message: "-",
+            },
+        )
+        .unwrap();
+    sender.send(1, sequenced(1, 2, "c")).unwrap();
+    sender.send(1, sequenced(1, 0, "a")).unwrap();
+    drop(sender);
+
+    let mut receiver = SequencedReceiver::new(receivers.remove(0));
+    let mut received = Vec::new();
+    while let Some(message) = receiver.recv().await {
+        received.push(message);
+    }
+    assert_eq!(received, vec!["x", "-", "a", "b", "c"]);
+}
+
+#[tokio::test]
+async fn test_sequenced_receiver_skips_gap_when_window_is_full() {
+    use crate::{Sequenced, SequencedReceiver, SequencedSender};
+
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u64, Sequenced<u64>>(NonZeroUsize::new(1).unwrap());
+    let raw = sender.clone();
+    let sender = SequencedSender::new(sender);
+
+    // Clones share the numbering of an ID.
+    sender.send(7, 10).unwrap();
+    sender.clone().send(7, 11).unwrap();
+    let first = receivers[0].try_recv().unwrap();
+    let second = receivers[0].try_recv().unwrap();
+    assert_eq!((first.seq, second.seq), (Some(0), Some(1)));
+
+    let key = first.key;
+    raw.send(7, first).unwrap();
+    raw.send(7, second).unwrap();
+    // Sequence number 2 is missing until after the window has filled up.
+    for seq in [3, 4, 5, 2] {
+        let message = Sequenced {
+            key,
+            seq: Some(seq),
+            message: seq * 10,
+        };
+        raw.send(7, message).unwrap();
+    }
+    drop((sender, raw));
+
+    let mut receiver = SequencedReceiver::new(receivers.remove(0)).with_window(2);
+    let mut received = Vec::new();
+    while let Some(message) = receiver.recv().await {
+        received.push(message);
+    }
+    assert_eq!(received, vec![10, 11, 30, 40, 50, 20]);
+}
+
+#[tokio::test]
+async fn test_build_queued_priority_and_lifo() {
+    use std::{cmp::Reverse, collections::BinaryHeap};
+
+    use crate::{StickyChannelBuilder, UnboundedStickyChannelBuilder};
+
+    let (sender, mut receivers) =
+        StickyChannelBuilder::<u8, u32>::new(NonZeroUsize::new(1).unwrap(), 8)
+            .build_queued(BinaryHeap::<Reverse<u32>>::new);
+    for message in [5, 1, 4, 2, 3] {
+        sender.try_send(0, message).unwrap();
+    }
+    drop(sender);
+    let mut received = Vec::new();
+    while let Some(message) = receivers[0].recv().await {
+        received.push(message);
+    }
+    assert_eq!(received, vec![1, 2, 3, 4, 5]);
+
+    let (sender, mut receivers) =
+        UnboundedStickyChannelBuilder::<u8, u32>::new(NonZeroUsize::new(1).unwrap())
+            .build_queued(Vec::<u32>::new);
+    for message in [1, 2, 3] {
+        sender.send(0, message).unwrap();
+    }
+    assert_eq!(receivers[0].try_recv(), Ok(3));
+    sender.send(0, 4).unwrap();
+    assert_eq!(receivers[0].try_recv(), Ok(4));
+    assert_eq!(receivers[0].try_recv(), Ok(2));
+    assert_eq!(receivers[0].try_recv(), Ok(1));
+    assert_eq!(receivers[0].try_recv(), Err(TryRecvError::Empty));
+}
+
+#[cfg(all(feature = "sled", feature = "bincode"))]
+#[tokio::test]
+async fn test_sled_queue_keeps_messages_across_restarts() {
+    use crate::{Bincode, ConsumerQueue, SledQueue, UnboundedStickyChannelBuilder};
+
+    let path = std::env::temp_dir().join(format!("sticky-sled-queue-{}", std::process::id()));
+    let open = || {
+        let db = sled::open(&path).unwrap();
+        SledQueue::<String, _>::open(db.open_tree("consumer-0").unwrap(), Bincode).unwrap()
+    };
+
+    let (sender, mut receivers) =
+        UnboundedStickyChannelBuilder::<u8, String>::new(NonZeroUsize::new(1).unwrap())
+            .build_queued(open);
+    for message in ["a", "b", "c"] {
+        sender.send(0, message.to_string()).unwrap();
+    }
+    assert_eq!(receivers[0].recv().await.as_deref(), Some("a"));
+    assert_eq!(receivers[0].queue().len(), 2);
+    drop((sender, receivers));
+
+    let (sender, mut receivers) =
+        UnboundedStickyChannelBuilder::<u8, String>::new(NonZeroUsize::new(1).unwrap())
+            .build_queued(open);
+    assert_eq!(receivers[0].queue().len(), 2);
+    sender.send(0, "d".to_string()).unwrap();
+    drop(sender);
+    let mut received = Vec::new();
+    while let Some(message) = receivers[0].recv().await {
+        received.push(message);
+    }
+    assert_eq!(received, ["b", "c", "d"]);
+    assert!(receivers[0].queue().tree().is_empty());
+    drop(receivers);
+
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn test_queued_receiver_respects_capacity() {
+    use std::collections::BinaryHeap;
+
+    use crate::QueuedReceiver;
+
+    let (sender, mut receivers) = sticky_channel::<u8, u32>(NonZeroUsize::new(1).unwrap(), 4);
+    for message in [1, 2, 3, 4] {
+        sender.try_send(0, message).unwrap();
+    }
+
+    // Only two messages are taken out of the channel at a time, the others keep their slots.
+    let mut receiver = QueuedReceiver::new(
+        receivers.remove(0),
+        BinaryHeap::<u32>::new(),
+        NonZeroUsize::new(2).unwrap(),
+    );
+    assert_eq!(receiver.try_recv(), Ok(2));
+    assert_eq!(receiver.queue().len(), 1);
+    assert!(sender.try_send(0, 5).is_ok());
+    assert!(sender.try_send(0, 6).is_ok());
+    assert!(sender.try_send(0, 7).unwrap_err().is_full());
+    assert_eq!(receiver.try_recv(), Ok(3));
+}
+
+#[tokio::test]
+async fn test_sticky_priority_channel_is_stable() {
+    use std::cmp::Ordering;
+
+    use crate::sticky_priority_channel;
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Job {
+        urgency: u8,
+        name: &'static str,
+    }
+
+    impl PartialOrd for Job {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Job {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.urgency.cmp(&other.urgency)
+        }
+    }
+
+    let (sender, mut receivers) =
+        sticky_priority_channel::<&str, Job>(NonZeroUsize::new(1).unwrap(), 16);
+    for (urgency, name) in [(0, "a"), (2, "b"), (1, "c"), (2, "d"), (0, "e")] {
+        sender.send("tenant", Job { urgency, name }).await.unwrap();
+    }
+    drop(sender);
+
+    let mut received = Vec::new();
+    while let Some(job) = receivers[0].recv().await {
+        received.push(job.name);
+    }
+    assert_eq!(received, vec!["b", "d", "c", "a", "e"]);
+}
+
+#[tokio::test]
+async fn test_unbounded_send_wait_blocks_only_offending_key() {
+    let (sender, mut receivers) =
+        crate::UnboundedStickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(1).unwrap())
+            .max_pending_per_key(NonZeroUsize::new(2).unwrap())
+            .build();
+
+    sender.send_wait(1, 10).await.unwrap();
+    sender.send_wait(1, 11).await.unwrap();
+
+    let sender = Arc::new(sender);
+    let waiting = tokio::spawn({
+        let sender = sender.clone();
+        async move { sender.send_wait(1, 12).await }
+    });
+    tokio::task::yield_now().await;
+    assert!(!waiting.is_finished());
+
+    // Other IDs of the same consumer are not limited by the waiting ID.
+    for message in 0..2 {
+        sender.send_wait(2, message).await.unwrap();
+    }
+
+    assert_eq!(receivers[0].recv().await, Some(10));
+    waiting.await.unwrap().unwrap();
+    let received: Vec<_> = std::iter::from_fn(|| receivers[0].try_recv().ok()).collect();
+    assert_eq!(received, vec![11, 0, 1, 12]);
+}
+
+#[tokio::test]
+async fn test_labeled_consumers() {
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<&str, i32>::new(NonZeroUsize::new(3).unwrap(), 4)
+            .consumer("eu-west")
+            .consumer("us-east")
+            .build();
+
+    assert_eq!(receivers[0].label(), Some("eu-west"));
+    assert_eq!(receivers[1].label(), Some("us-east"));
+    assert_eq!(receivers[2].label(), None);
+    assert_eq!(sender.index_of("us-east"), Some(1));
+    assert_eq!(sender.label(0), Some("eu-west"));
+    assert_eq!(
+        sender.label_of("tenant"),
+        sender.label(sender.route_of("tenant").unwrap())
+    );
+
+    sender.send_to("us-east", "tenant", 1).await.unwrap();
+    sender.try_send_to("eu-west", "tenant", 2).unwrap();
+    assert_eq!(receivers[1].try_recv(), Ok(1));
+    assert_eq!(receivers[0].try_recv(), Ok(2));
+
+    let err = sender.try_send_to("ap-south", "tenant", 3).unwrap_err();
+    assert_eq!(err, SendError::UnknownLabel(3));
+    assert_eq!(err.consumer_index(), None);
+}
+
+#[test]
+#[should_panic(expected = "duplicate consumer label")]
+fn test_duplicate_consumer_label_panics() {
+    let _ = crate::UnboundedStickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(2).unwrap())
+        .consumer("a")
+        .consumer("a")
+        .build();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_shared_receiver_competing_workers() {
+    use crate::SharedReceiver;
+
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap());
+    let shared = SharedReceiver::new(receivers.remove(0));
+
+    let workers: Vec<_> = (0..4)
+        .map(|_| {
+            let shared = shared.clone();
+            tokio::spawn(async move {
+                let mut received = Vec::new();
+                while let Some(message) = shared.recv().await {
+                    received.push(message);
+                    tokio::task::yield_now().await;
+                }
+                received
+            })
+        })
+        .collect();
+
+    for message in 0..1000 {
+        sender.send(message % 7, message).unwrap();
+    }
+    drop(sender);
+
+    let mut received = Vec::new();
+    for worker in workers {
+        received.extend(worker.await.unwrap());
+    }
+    received.sort_unstable();
+    assert_eq!(received, (0..1000).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_admin_reassigns_partition() {
+    let (sender, mut receivers, channel_admin) =
+        crate::UnboundedStickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(2).unwrap())
+            .partitions(NonZeroUsize::new(8).unwrap())
+            .build_with_admin();
+    let admin = channel_admin.partitions().unwrap();
+    assert_eq!(admin.partitions(), 8);
+
+    let partition = sender.partition_of(42).unwrap();
+    let old = admin.consumer_of(partition);
+    assert_eq!(sender.route_of(42), Some(old));
+
+    sender.send(42, 1).unwrap();
+    assert_eq!(admin.backlog(partition), 1);
+
+    let new = 1 - old;
+    assert_eq!(admin.reassign(partition, new), old);
+    assert_eq!(sender.route_of(42), Some(new));
+    sender.send(42, 2).unwrap();
+
+    assert_eq!(receivers[old].try_recv(), Ok(1));
+    assert_eq!(receivers[new].try_recv(), Ok(2));
+    assert_eq!(admin.backlog(partition), 0);
+}
+
+#[tokio::test]
+async fn test_admin_reassign_drained_waits_for_backlog() {
+    let (sender, mut receivers, channel_admin) =
+        crate::StickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(2).unwrap(), 8)
+            .partitions(NonZeroUsize::new(4).unwrap())
+            .build_with_admin();
+    let admin = channel_admin.partitions().unwrap();
+    let partition = sender.partition_of(7).unwrap();
+    let old = admin.consumer_of(partition);
+
+    sender.send(7, 1).await.unwrap();
+    sender.send(7, 2).await.unwrap();
+
+    let reassign = tokio::spawn({
+        let admin = admin.clone();
+        async move { admin.reassign_drained(partition, 1 - old).await }
+    });
+    tokio::task::yield_now().await;
+    assert!(!reassign.is_finished());
+    assert_eq!(admin.consumer_of(partition), old);
+
+    assert_eq!(receivers[old].recv().await, Some(1));
+    assert_eq!(receivers[old].recv().await, Some(2));
+    assert_eq!(reassign.await.unwrap(), old);
+    assert_eq!(sender.route_of(7), Some(1 - old));
+}
+
+#[tokio::test]
+async fn test_admin_rebalance_reports_moved_partitions() {
+    let (sender, mut receivers, channel_admin) =
+        crate::UnboundedStickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(2).unwrap())
+            .partitions(NonZeroUsize::new(4).unwrap())
+            .build_with_admin();
+    let admin = channel_admin.partitions().unwrap();
+    let partition = sender.partition_of(42).unwrap();
+    let old = admin.consumer_of(partition);
+    sender.send(42, 1).unwrap();
+    sender.send(42, 2).unwrap();
+
+    let mut assignment = (0..4)
+        .map(|partition| admin.consumer_of(partition))
+        .collect::<Vec<_>>();
+    assert!(admin.rebalance(&assignment).moves.is_empty());
+
+    assignment[partition] = 1 - old;
+    let report = admin.rebalance(&assignment);
+    assert_eq!(
+        report.moves,
+        vec![crate::PartitionMove {
+            partition,
+            from: old,
+            to: 1 - old,
+            drained: 0,
+            left_behind: 2,
+        }]
+    );
+    assert_eq!(report.left_behind(), 2);
+    assert_eq!(receivers[old].try_recv(), Ok(1));
+    assert_eq!(sender.route_of(42), Some(1 - old));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_admin_rebalance_drained_reports_drained_backlog() {
+    let (sender, mut receivers, channel_admin) =
+        crate::StickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(2).unwrap(), 8)
+            .partitions(NonZeroUsize::new(2).unwrap())
+            .build_with_admin();
+    let admin = channel_admin.partitions().unwrap();
+    let partition = sender.partition_of(7).unwrap();
+    let old = admin.consumer_of(partition);
+    sender.send(7, 1).await.unwrap();
+    sender.send(7, 2).await.unwrap();
+
+    let mut assignment = vec![admin.consumer_of(0), admin.consumer_of(1)];
+    assignment[partition] = 1 - old;
+    let rebalance = tokio::spawn({
+        let admin = admin.clone();
+        async move { admin.rebalance_drained(&assignment).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(receivers[old].recv().await, Some(1));
+    assert_eq!(receivers[old].recv().await, Some(2));
+
+    let report = rebalance.await.unwrap();
+    assert_eq!(report.drained(), 2);
+    assert_eq!(report.left_behind(), 0);
+    assert_eq!(report.moves[0].to, 1 - old);
+    assert_eq!(report.elapsed, Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn test_fan_in_keeps_key_affinity_across_sources() {
+    let (target, receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(3).unwrap(), 4);
+    let routes: Vec<usize> = (0..10).map(|key| target.route_of(key).unwrap()).collect();
+    let mut fan_in = crate::FanIn::new(target);
+
+    for offset in [0, 100] {
+        let (upstream, upstream_receivers) =
+            unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(2).unwrap());
+        for receiver in upstream_receivers {
+            fan_in.add_source(receiver, |message| message % 10);
+        }
+        for message in offset..offset + 20 {
+            upstream.send(message, message).unwrap();
+        }
+    }
+    assert_eq!(fan_in.sources(), 4);
+
+    let consumers: Vec<_> = receivers
+        .into_iter()
+        .map(|mut receiver| {
+            tokio::spawn(async move {
+                let mut received = Vec::new();
+                while let Some(message) = receiver.recv().await {
+                    received.push(message);
+                }
+                received
+            })
+        })
+        .collect();
+
+    fan_in.join().await.unwrap();
+    assert_eq!(fan_in.sources(), 0);
+    drop(fan_in);
+
+    let mut total = 0;
+    for (index, consumer) in consumers.into_iter().enumerate() {
+        for message in consumer.await.unwrap() {
+            assert_eq!(routes[(message % 10) as usize], index);
+            total += 1;
+        }
+    }
+    assert_eq!(total, 40);
+}
+
+#[tokio::test]
+async fn test_rekey_repartitions_with_backpressure() {
+    let (upstream, upstream_receivers) =
+        sticky_channel::<u64, u64>(NonZeroUsize::new(2).unwrap(), 2);
+    let (target, mut receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(3).unwrap(), 1);
+    let routes: Vec<usize> = (0..4).map(|key| target.route_of(key).unwrap()).collect();
+    let mut stage = crate::rekey(upstream_receivers, |message: &u64| message % 4, target);
+
+    let producer = tokio::spawn(async move {
+        for message in 0..40 {
+            upstream.send(message / 4, message).await.unwrap();
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(!producer.is_finished());
+
+    let consumers: Vec<_> = receivers
+        .drain(..)
+        .map(|mut receiver| {
+            tokio::spawn(async move {
+                let mut received = Vec::new();
+                while let Some(message) = receiver.recv().await {
+                    received.push(message);
+                }
+                received
+            })
+        })
+        .collect();
+
+    producer.await.unwrap();
+    stage.join().await.unwrap();
+    drop(stage);
+
+    let mut total = 0;
+    for (index, consumer) in consumers.into_iter().enumerate() {
+        for message in consumer.await.unwrap() {
+            assert_eq!(routes[(message % 4) as usize], index);
+            total += 1;
+        }
+    }
+    assert_eq!(total, 40);
+}
+
+#[tokio::test]
+async fn test_poll_sticky_sender_reserves_before_sending() {
+    let (sender, mut receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap(), 1);
+    let mut poll_sender = crate::PollStickySender::new(sender.clone());
+
+    sender.try_send(1, 10).unwrap();
+    let waker = std::task::Waker::noop();
+    let mut cx = std::task::Context::from_waker(waker);
+    assert!(poll_sender.poll_reserve(&mut cx, &2).is_pending());
+    assert!(matches!(
+        poll_sender.send_item(2, 20),
+        Err(SendError::ChannelFull(20, 0))
+    ));
+
+    assert_eq!(receivers[0].recv().await, Some(10));
+    std::future::poll_fn(|cx| poll_sender.poll_reserve(cx, &2))
+        .await
+        .unwrap();
+    assert!(sender.try_send(3, 30).unwrap_err().is_full());
+    poll_sender.send_item(2, 20).unwrap();
+    assert_eq!(receivers[0].recv().await, Some(20));
+
+    std::future::poll_fn(|cx| poll_sender.poll_reserve(cx, &4))
+        .await
+        .unwrap();
+    assert!(poll_sender.abort_send());
+    sender.try_send(5, 50).unwrap();
+
+    poll_sender.close();
+    assert!(poll_sender.is_closed());
+    assert!(matches!(
+        std::future::poll_fn(|cx| poll_sender.poll_reserve(cx, &2)).await,
+        Err(SendError::ChannelClosed((), 0))
+    ));
+}
+
+#[tokio::test]
+async fn test_recv_many_into_fills_fixed_buffer() {
+    use std::mem::MaybeUninit;
+
+    let (sender, mut receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap(), 8);
+    for message in 0..5 {
+        sender.send(0, message).await.unwrap();
+    }
+
+    let mut buffer = [const { MaybeUninit::<u64>::uninit() }; 3];
+    let received = receivers[0].recv_many_into(&mut buffer).await;
+    assert_eq!(received, 3);
+    let messages: Vec<u64> = buffer[..received]
+        .iter()
+        .map(|slot| unsafe { slot.assume_init_read() })
+        .collect();
+    assert_eq!(messages, [0, 1, 2]);
+    assert_eq!(receivers[0].recv_many_into(&mut []).await, 0);
+
+    let mut messages: Vec<u64> = Vec::with_capacity(4);
+    drop(sender);
+    let received = receivers[0]
+        .recv_many_into(messages.spare_capacity_mut())
+        .await;
+    unsafe { messages.set_len(received) };
+    assert_eq!(messages, [3, 4]);
+    assert_eq!(
+        receivers[0]
+            .recv_many_into(messages.spare_capacity_mut())
+            .await,
+        0
+    );
+
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap());
+    sender.send(0, 7).unwrap();
+    let mut buffer = [MaybeUninit::<u64>::uninit(); 2];
+    assert_eq!(receivers[0].recv_many_into(&mut buffer).await, 1);
+    assert_eq!(unsafe { buffer[0].assume_init() }, 7);
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn test_receiver_stream_yields_until_closed() {
+    use futures::StreamExt;
+
+    let (sender, receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap(), 4);
+    let mut stream = crate::StickyReceiverStream::from(receivers.into_iter().next().unwrap());
+    sender.send(0, 1).await.unwrap();
+    sender.send(0, 2).await.unwrap();
+    stream.close();
+    assert!(sender.send(0, 3).await.is_err());
+    assert_eq!(stream.collect::<Vec<_>>().await, [1, 2]);
+
+    let (sender, receivers) = unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap());
+    let mut stream =
+        crate::UnboundedStickyReceiverStream::new(receivers.into_iter().next().unwrap());
+    sender.send(0, 4).unwrap();
+    assert_eq!(stream.next().await, Some(4));
+    let mut receiver = stream.into_inner();
+    drop(sender);
+    assert_eq!(receiver.recv().await, None);
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn test_merged_stream_round_robins_receivers() {
+    use futures::StreamExt;
+
+    let (sender, receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(2).unwrap(), 8);
+    let busy = (0..).find(|&id| sender.route_of(id) == Some(0)).unwrap();
+    let quiet = (0..).find(|&id| sender.route_of(id) == Some(1)).unwrap();
+    for message in 0..3 {
+        sender.send(busy, message).await.unwrap();
+    }
+    sender.send(quiet, 10).await.unwrap();
+    drop(sender);
+
+    let merged = crate::merge(receivers).collect::<Vec<_>>().await;
+    assert_eq!(merged, [(0, 0), (1, 10), (0, 1), (0, 2)]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_blocking_recv_many_on_worker_thread() {
+    let (sender, receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap(), 8);
+    let mut receiver = receivers.into_iter().next().unwrap();
+
+    let worker = std::thread::spawn(move || {
+        let mut batches = Vec::new();
+        assert_eq!(
+            receiver.blocking_recv_many(&mut Vec::new(), 4, Some(Duration::from_millis(10))),
+            None
+        );
+        loop {
+            let mut batch = Vec::new();
+            match receiver.blocking_recv_many(&mut batch, 4, None) {
+                Some(0) => return batches,
+                Some(_) => batches.push(batch),
+                None => unreachable!("no timeout was given"),
+            }
+        }
+    });
+
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    for message in 0..6 {
+        sender.send(0, message).await.unwrap();
+    }
+    drop(sender);
+
+    let batches = tokio::task::spawn_blocking(move || worker.join().unwrap())
+        .await
+        .unwrap();
+    assert!(batches.iter().all(|batch| batch.len() <= 4));
+    assert_eq!(batches.concat(), [0, 1, 2, 3, 4, 5]);
+}
+
+#[tokio::test]
+async fn test_drain_all_reports_leftovers_per_consumer() {
+    let (sender, mut receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(3).unwrap(), 12);
+    for id in 0..12 {
+        sender.send(id, id).await.unwrap();
+    }
+    let first = receivers[0].try_recv().ok();
+
+    let reports = crate::drain_all(&mut receivers);
+    assert_eq!(reports.len(), 3);
+    let mut leftovers: Vec<u64> = first.into_iter().collect();
+    for (index, report) in reports.into_iter().enumerate() {
+        assert_eq!(report.consumer_index, index);
+        for message in &report.messages {
+            assert_eq!(sender.route_of(*message), Some(index));
+        }
+        leftovers.extend(report.messages);
+    }
+    leftovers.sort();
+    assert_eq!(leftovers, (0..12).collect::<Vec<_>>());
+
+    assert!(sender.send(0, 0).await.unwrap_err().is_closed());
+    assert!(receivers[1].take_remaining().is_empty());
+}
+
+#[tokio::test]
+async fn test_validate_rejects_before_routing() {
+    #[derive(Debug, PartialEq, thiserror::Error)]
+    #[error("negative amount {0}")]
+    struct NegativeAmount(i64);
+
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<&str, i64>::new(NonZeroUsize::new(2).unwrap(), 4)
+            .validate(|_, amount| match *amount {
+                amount if amount < 0 => Err(NegativeAmount(amount)),
+                _ => Ok(()),
+            })
+            .build();
+
+    let err = sender.send("account", -5).await.unwrap_err();
+    assert_eq!(
+        err.rejection().unwrap().downcast_ref::<NegativeAmount>(),
+        Some(&NegativeAmount(-5))
+    );
+    assert_eq!(err.to_string(), "message rejected: negative amount -5");
+    assert_eq!(err.consumer_index(), None);
+    assert_eq!(err.into_inner(), -5);
+
+    let batch = sender.try_send_batch([("account", 1), ("account", -1)]);
+    assert_eq!(batch.sent, 1);
+    assert!(matches!(batch.failed[0].error, SendError::Rejected(-1, _)));
+
+    let index = sender.route_of("account").unwrap();
+    assert_eq!(receivers[index].try_recv(), Ok(1));
+    assert_eq!(receivers[index].try_recv(), Err(TryRecvError::Empty));
+
+    let (sender, _receivers) =
+        crate::UnboundedStickyChannelBuilder::<u64, String>::new(NonZeroUsize::new(1).unwrap())
+            .validate(|_, message: &String| {
+                if message.is_empty() {
+                    Err(std::fmt::Error)
+                } else {
+                    Ok(())
+                }
+            })
+            .build();
+    assert!(sender.send(0, "ok".to_owned()).is_ok());
+    assert!(
+        sender
+            .send(0, String::new())
+            .unwrap_err()
+            .rejection()
+            .unwrap()
+            .is::<std::fmt::Error>()
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_send_with_retry_backs_off_while_full() {
+    use crate::{Backoff, RetryPolicy};
+
+    let policy = RetryPolicy::new(
+        NonZeroUsize::new(4).unwrap(),
+        Backoff::Exponential {
+            initial: Duration::from_millis(10),
+            max: Duration::from_millis(25),
+        },
+    );
+    assert_eq!(policy.backoff(0), Duration::from_millis(10));
+    assert_eq!(policy.backoff(1), Duration::from_millis(20));
+    assert_eq!(policy.backoff(2), Duration::from_millis(25));
+
+    let (sender, mut receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap(), 1);
+    sender.try_send(0, 1).unwrap();
+
+    let start = tokio::time::Instant::now();
+    let err = sender.send_with_retry(&0, 2, &policy).await.unwrap_err();
+    assert!(matches!(err, SendError::ChannelFull(2, 0)));
+    assert_eq!(start.elapsed(), Duration::from_millis(55));
+
+    let consumer = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(15)).await;
+        let first = receivers[0].recv().await;
+        (first, receivers[0].recv().await)
+    });
+    let policy = RetryPolicy::new(
+        NonZeroUsize::new(5).unwrap(),
+        Backoff::Fixed(Duration::from_millis(10)),
+    )
+    .with_jitter();
+    sender.send_with_retry(&0, 3, &policy).await.unwrap();
+    assert_eq!(consumer.await.unwrap(), (Some(1), Some(3)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_timeout_sender_returns_message_on_timeout() {
+    let (sender, mut receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap(), 1);
+    let sender = crate::TimeoutSender::new(sender, Duration::from_millis(50));
+    sender.send(0, 1).await.unwrap();
+
+    let start = tokio::time::Instant::now();
+    let err = sender.send(0, 2).await.unwrap_err();
+    assert!(matches!(err, SendError::Timeout(2, 0)));
+    assert_eq!(err.consumer_index(), Some(0));
+    assert_eq!(start.elapsed(), Duration::from_millis(50));
+
+    let consumer = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let first = receivers[0].recv().await;
+        (first, receivers[0].recv().await)
+    });
+    sender.send(0, 3).await.unwrap();
+    assert_eq!(consumer.await.unwrap(), (Some(1), Some(3)));
+    assert!(sender.send(0, 4).await.unwrap_err().is_closed());
+}
+
+#[tokio::test]
+async fn test_keyed_sender_sends_without_repeating_id() {
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<String, u64>::new(NonZeroUsize::new(3).unwrap(), 2)
+            .validate(|_, message| match *message {
+                0 => Err(std::fmt::Error),
+                _ => Ok(()),
+            })
+            .build();
+    let session = sender.keyed("session-1".to_owned()).unwrap();
+    let index = session.consumer_index();
+    assert_eq!(sender.route_of("session-1".to_owned()), Some(index));
+
+    session.send(1).await.unwrap();
+    let permit = session.reserve().await.unwrap();
+    assert!(session.clone().try_send(2).unwrap_err().is_full());
+    assert!(matches!(
+        session.try_send(0),
+        Err(SendError::Rejected(0, _))
+    ));
+    permit.send(3).unwrap();
+
+    assert_eq!(receivers[index].recv().await, Some(1));
+    assert_eq!(receivers[index].recv().await, Some(3));
+    let permit = session.reserve().await.unwrap();
+    drop(permit);
+    session.try_send(4).unwrap();
+    session.try_send(5).unwrap();
+    assert_eq!(receivers[index].try_recv(), Ok(4));
+}
+
+#[tokio::test]
+async fn test_subscribe_diverts_messages_of_one_id() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u64, (u64, u64)>(NonZeroUsize::new(1).unwrap());
+    let mut subscription = receivers[0].subscribe(&sender, &1).unwrap();
+    for (id, message) in [(0, 0), (1, 1), (2, 2), (1, 3)] {
+        sender.send(id, (id, message)).unwrap();
+    }
+
+    let mut buffer = Vec::new();
+    assert_eq!(receivers[0].recv_many(&mut buffer, 10).await, 2);
+    assert_eq!(buffer, vec![(0, 0), (2, 2)]);
+    assert_eq!(subscription.recv().await, Some((1, 1)));
+    assert_eq!(subscription.try_recv(), Ok((1, 3)));
+
+    // Without a subscription, the ID is received by the main receiver again.
+    drop(subscription);
+    sender.send(1, (1, 4)).unwrap();
+    assert_eq!(receivers[0].recv().await, Some((1, 4)));
+}
+
+#[tokio::test]
+async fn test_subscribe_requires_id_of_receiver() {
+    let (sender, mut receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(2).unwrap(), 1);
+    let index = sender.route_of(7).unwrap();
+    assert!(receivers[1 - index].subscribe(&sender, &7).is_none());
+
+    let mut subscription = receivers[index].subscribe(&sender, &7).unwrap();
+    sender.send(7, 1).await.unwrap();
+    assert!(sender.try_send(7, 2).unwrap_err().is_full());
+
+    // Diverted messages no longer take up capacity.
+    assert_eq!(receivers[index].try_recv(), Err(TryRecvError::Empty));
+    sender.try_send(7, 2).unwrap();
+    drop(receivers);
+    assert_eq!(subscription.recv().await, Some(1));
+    assert_eq!(subscription.recv().await, None);
+}
+
+#[tokio::test]
+async fn test_demux_splits_by_secondary_key() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u64, (u64, u64)>(NonZeroUsize::new(1).unwrap());
+    let mut demux = crate::Demux::new(
+        receivers.pop().unwrap(),
+        NonZeroUsize::new(2).unwrap(),
+        |(key, _): &(u64, u64)| *key,
+    );
+    for message in [(1, 0), (2, 1), (1, 2), (2, 3), (3, 4), (2, 5)] {
+        sender.send(0, message).unwrap();
+    }
+
+    let (key, mut first) = demux.accept().await.unwrap();
+    assert_eq!(key, 1);
+    let (key, mut second) = demux.accept().await.unwrap();
+    assert_eq!(key, 2);
+    // Key 1 was used least recently when key 3 arrived, so its sub-queue is closed.
+    let (key, mut third) = demux.accept().await.unwrap();
+    assert_eq!(key, 3);
+    assert_eq!(demux.evicted(), 1);
+    assert_eq!(demux.len(), 2);
+    assert!(matches!(demux.try_accept(), Err(TryRecvError::Empty)));
+
+    assert_eq!(first.recv().await, Some((1, 0)));
+    assert_eq!(first.recv().await, Some((1, 2)));
+    assert_eq!(first.recv().await, None);
+    assert_eq!(second.try_recv(), Ok((2, 1)));
+    assert_eq!(second.try_recv(), Ok((2, 3)));
+    assert_eq!(second.try_recv(), Ok((2, 5)));
+    assert_eq!(third.try_recv(), Ok((3, 4)));
+
+    drop(sender);
+    assert!(demux.accept().await.is_none());
+    assert_eq!(second.recv().await, None);
+    assert_eq!(third.recv().await, None);
+}
+
+#[tokio::test]
+async fn test_demux_evicts_oldest_and_reopens_dropped_keys() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap());
+    let mut demux = crate::Demux::new(
+        receivers.pop().unwrap(),
+        NonZeroUsize::new(2).unwrap(),
+        |message: &u64| message % 10,
+    )
+    .with_eviction(crate::Eviction::Oldest);
+    for message in [1, 2, 11, 3] {
+        sender.send(0, message).unwrap();
+    }
+    let (_, mut first) = demux.try_accept().unwrap();
+    let (_, second) = demux.try_accept().unwrap();
+    let (key, _third) = demux.try_accept().unwrap();
+    assert_eq!(key, 3);
+    assert_eq!(first.try_recv(), Ok(1));
+    assert_eq!(first.try_recv(), Ok(11));
+    assert_eq!(first.try_recv(), Err(TryRecvError::Disconnected));
+
+    // Dropped sub-receivers make room without evicting anything.
+    drop(second);
+    sender.send(0, 4).unwrap();
+    assert_eq!(demux.try_accept().unwrap().0, 4);
+    assert_eq!(demux.evicted(), 1);
+}
+
+#[tokio::test]
+async fn test_filter_receiver_skips_and_counts() {
+    let (sender, mut receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap(), 4);
+    let mut receiver = receivers.pop().unwrap().filter(|message| message % 2 == 0);
+    for message in 1..=4 {
+        sender.send(0, message).await.unwrap();
+    }
+
+    assert_eq!(receiver.recv().await, Some(2));
+    // Skipped messages free their capacity.
+    for message in 5..=6 {
+        sender.try_send(0, message).unwrap();
+    }
+    let mut buffer = Vec::new();
+    assert_eq!(receiver.recv_many(&mut buffer, 10).await, 2);
+    assert_eq!(buffer, vec![4, 6]);
+    assert_eq!(receiver.skipped(), 3);
+
+    drop(sender);
+    assert_eq!(receiver.recv_many(&mut buffer, 10).await, 0);
+    assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+}
+
+#[tokio::test]
+async fn test_mapped_sender_converts_at_the_boundary() {
+    let (sender, mut receivers) = sticky_channel::<u64, String>(NonZeroUsize::new(2).unwrap(), 1);
+    let index = sender.route_of(3).unwrap();
+    let sender = sender.with_map(|count: usize| format!("count={count}"));
+
+    sender.clone().send(3, 1).await.unwrap();
+    assert!(matches!(
+        sender.try_send(3, 2),
+        Err(SendError::ChannelFull(message, _)) if message == "count=2"
+    ));
+    assert_eq!(receivers[index].recv().await.as_deref(), Some("count=1"));
+}
+
+#[tokio::test]
+async fn test_totals_count_sent_received_and_in_flight() {
+    let (sender, mut receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(2).unwrap(), 8);
+    let index = sender.route_of(0).unwrap();
+    let other = (1..)
+        .find(|id| sender.route_of(*id) != Some(index))
+        .unwrap();
+    for id in [0, 1, 2, 3, 4, other] {
+        sender.send(id, id).await.unwrap();
+    }
+    assert_eq!(receivers[index].recv().await, Some(0));
+    let mut buffer = Vec::new();
+    receivers[1 - index].recv_many(&mut buffer, 8).await;
+
+    let expected = crate::Totals {
+        sent: 6,
+        received: 1 + buffer.len() as u64,
+        in_flight: 5 - buffer.len() as u64,
+    };
+    assert_eq!(sender.totals(), expected);
+    assert_eq!(receivers[1 - index].totals(), expected);
+
+    // Messages that cannot be queued are not counted.
+    receivers[index].close();
+    assert!(sender.send(0, 6).await.is_err());
+    assert_eq!(sender.totals(), expected);
+
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap());
+    sender.send(0, 1).unwrap();
+    sender.send(0, 2).unwrap();
+    assert_eq!(receivers[0].try_recv(), Ok(1));
+    assert_eq!(
+        receivers[0].totals(),
+        crate::Totals {
+            sent: 2,
+            received: 1,
+            in_flight: 1,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_reconcile_attributes_loss_to_dropped_receivers_and_shedding() {
+    let (sender, mut receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap(), 4);
+    let sender = crate::SheddingSender::new(sender, 3);
+    for message in 0..5 {
+        sender.send(0, message).unwrap();
+    }
+    assert_eq!(receivers[0].recv().await, Some(0));
+
+    let reconciliation = crate::Reconciliation {
+        consumer_index: 0,
+        accepted: 3,
+        consumed: 1,
+        dropped: 0,
+        shed: 2,
+    };
+    assert_eq!(sender.reconcile(), vec![reconciliation]);
+    assert_eq!(reconciliation.pending(), 2);
+
+    drop(receivers);
+    let reconciliation = crate::Reconciliation {
+        dropped: 2,
+        ..reconciliation
+    };
+    assert_eq!(sender.reconcile(), vec![reconciliation]);
+    assert_eq!(reconciliation.pending(), 0);
+    assert_eq!(reconciliation.lost(), 4);
+}
+
+#[tokio::test]
+async fn test_health_watch_follows_consumer_state() {
+    use crate::ChannelHealth;
+
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(2).unwrap(), 1)
+            .watch_health()
+            .build();
+    let mut health = sender.health().unwrap();
+    assert_eq!(*health.borrow_and_update(), ChannelHealth::Healthy);
+
+    let index = sender.route_of(0).unwrap();
+    sender.try_send(0, 1).unwrap();
+    assert!(sender.try_send(0, 2).is_err());
+    assert!(health.has_changed().unwrap());
+    assert_eq!(
+        *health.borrow_and_update(),
+        ChannelHealth::Degraded {
+            full_consumers: vec![index]
+        }
+    );
+
+    assert_eq!(receivers[index].recv().await, Some(1));
+    assert_eq!(*health.borrow_and_update(), ChannelHealth::Healthy);
+
+    let other = receivers.remove(1 - index);
+    drop(other);
+    assert_eq!(
+        *health.borrow_and_update(),
+        ChannelHealth::ConsumersClosed {
+            indices: vec![1 - index]
+        }
+    );
+    receivers[0].close();
+    assert_eq!(*health.borrow_and_update(), ChannelHealth::Closed);
+
+    let (sender, _receivers) = unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap());
+    assert!(sender.health().is_none());
+}
+
+#[tokio::test]
+async fn test_builder_hooks_report_full_closed_and_dropped() {
+    use std::sync::Mutex;
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let (full, closed, dropped) = (events.clone(), events.clone(), events.clone());
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(1).unwrap(), 1)
+            .on_full(move |index, message| full.lock().unwrap().push(("full", index, *message)))
+            .on_closed(move |index| closed.lock().unwrap().push(("closed", index, 0)))
+            .on_dropped(move |index, message| {
+                dropped.lock().unwrap().push(("dropped", index, *message))
+            })
+            .build();
+
+    sender.try_send(0, 1).unwrap();
+    assert!(sender.try_send(0, 2).unwrap_err().is_full());
+    let shedder = crate::SheddingSender::new(sender.clone(), 10);
+    assert!(!shedder.send(0, 3).unwrap());
+
+    receivers[0].close();
+    drop(receivers);
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            ("full", 0, 2),
+            ("full", 0, 3),
+            ("dropped", 0, 3),
+            ("closed", 0, 0)
+        ]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_skew_alarm_fires_for_skewed_windows() {
+    use std::sync::Mutex;
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let alarm = reports.clone();
+    let (sender, _receivers) =
+        crate::UnboundedStickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(2).unwrap())
+            .skew_alarm(2.0, Duration::from_millis(100), move |report| {
+                alarm.lock().unwrap().push(report.clone())
+            })
+            .build();
+    let hot = 0;
+    let cold = (1..)
+        .find(|id| sender.route_of(*id) != sender.route_of(hot))
+        .unwrap();
+    let hot_index = sender.route_of(hot).unwrap();
+
+    for message in 0..9 {
+        sender.send(hot, message).unwrap();
+    }
+    sender.send(cold, 0).unwrap();
+    tokio::time::sleep(Duration::from_millis(150)).await;
+
+    // The next window is balanced and does not fire.
+    sender.send(hot, 0).unwrap();
+    sender.send(cold, 0).unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].loads[hot_index], 9);
+    assert_eq!(reports[0].loads[1 - hot_index], 1);
+    assert_eq!(reports[0].ratio, 9.0);
+}
+
+#[tokio::test]
+async fn test_track_frequencies_estimates_counts_per_id() {
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<&str, u64>::new(NonZeroUsize::new(2).unwrap(), 16)
+            .track_frequencies(
+                NonZeroUsize::new(1024).unwrap(),
+                NonZeroUsize::new(4).unwrap(),
+            )
+            .build();
+    for i in 0..5 {
+        sender.send("hot", i).await.unwrap();
+    }
+    sender.send("cold", 0).await.unwrap();
+    assert_eq!(sender.approx_count(&"hot"), Some(5));
+    assert_eq!(sender.approx_count(&"cold"), Some(1));
+    assert_eq!(sender.approx_count(&"unseen"), Some(0));
+
+    let index = sender.route_of("cold").unwrap();
+    receivers[index].close();
+    assert!(sender.try_send("cold", 1).is_err());
+    assert_eq!(sender.approx_count(&"cold"), Some(1));
+
+    let (sender, _receivers) = unbounded_sticky_channel::<&str, u64>(NonZeroUsize::new(2).unwrap());
+    sender.send("hot", 0).unwrap();
+    assert_eq!(sender.approx_count(&"hot"), None);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_queue_depths_samples_all_consumers() {
+    let (sender, mut receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(3).unwrap(), 8);
+    let hot = sender.route_of(1).unwrap();
+    for i in 0..3 {
+        sender.send(1, i).await.unwrap();
+    }
+    receivers[hot].recv().await.unwrap();
+
+    let snapshot = sender.queue_depths();
+    assert_eq!(snapshot.depths.len(), 3);
+    assert_eq!(snapshot.depths[hot], 2);
+    assert_eq!(snapshot.total(), 2);
+    assert_eq!(snapshot.deepest(), Some(hot));
+    assert_eq!(snapshot.spread, Duration::ZERO);
+    assert_eq!(snapshot.taken_at, tokio::time::Instant::now());
+
+    let (sender, _receivers) = unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(2).unwrap());
+    assert_eq!(sender.queue_depths().deepest(), Some(0));
+}
+
+#[tokio::test]
+async fn test_sticky_channel_with_meta_delivers_metadata() {
+    let (sender, mut receivers) =
+        crate::sticky_channel_with_meta::<u64, &str, u32>(NonZeroUsize::new(2).unwrap(), 1);
+    let index = sender.route_of(7).unwrap();
+    sender.send_with_meta(7, "job", 42).await.unwrap();
+
+    let err = sender.try_send_with_meta(7, "late", 43).unwrap_err();
+    assert!(matches!(
+        err,
+        SendError::ChannelFull(
+            crate::WithMeta {
+                message: "late",
+                meta: 43
+            },
+            _
+        )
+    ));
+
+    let received = receivers[index].recv().await.unwrap();
+    assert_eq!(received.into_parts(), ("job", 42));
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn test_instrumented_reenters_sender_span() {
+    use std::sync::Mutex;
+
+    use tracing::{
+        Event, Id, Instrument, Metadata, Subscriber,
+        span::{Attributes, Record},
+    };
+    use tracing_core::span::Current;
+
+    /// Subscriber recording which span was entered when events were emitted.
+    #[derive(Default)]
+    struct Spans {
+        spans: Mutex<Vec<&'static Metadata<'static>>>,
+        entered: Mutex<Vec<Id>>,
+        events: Arc<Mutex<Vec<Option<Id>>>>,
+    }
+
+    impl Subscriber for Spans {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(attributes.metadata());
+            Id::from_u64(spans.len() as u64)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {
+            let current = self.entered.lock().unwrap().last().cloned();
+            self.events.lock().unwrap().push(current);
+        }
+        fn enter(&self, span: &Id) {
+            self.entered.lock().unwrap().push(span.clone());
+        }
+        fn exit(&self, _: &Id) {
+            self.entered.lock().unwrap().pop();
+        }
+        fn current_span(&self) -> Current {
+            match self.entered.lock().unwrap().last() {
+                Some(id) => {
+                    let metadata = self.spans.lock().unwrap()[id.into_u64() as usize - 1];
+                    Current::new(id.clone(), metadata)
+                }
+                None => Current::none(),
+            }
+        }
+    }
+
+    let subscriber = Spans::default();
+    let events = subscriber.events.clone();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let (sender, mut receivers) =
+        sticky_channel::<u64, crate::Instrumented<u64>>(NonZeroUsize::new(1).unwrap(), 4);
+    let span = tracing::info_span!("request");
+    let id = span.id();
+    async { sender.send_instrumented(0, 1).await.unwrap() }
+        .instrument(span)
+        .await;
+    sender.try_send_instrumented(0, 2).unwrap();
+
+    let message = receivers[0].recv().await.unwrap();
+    assert_eq!(message.span().id(), id);
+    assert_eq!(
+        message.in_scope(|message| {
+            tracing::info!(message);
+            message
+        }),
+        1
+    );
+    let message = receivers[0].recv().await.unwrap();
+    message
+        .instrument(|message| async move { tracing::info!(message) })
+        .await;
+    assert_eq!(*events.lock().unwrap(), [id, None]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_recv_with_deadline_reports_remaining_time() {
+    use tokio::time::Instant;
+
+    let (sender, mut receivers) =
+        sticky_channel::<u64, crate::WithDeadline<u64>>(NonZeroUsize::new(1).unwrap(), 4);
+    let deadline = Instant::now() + Duration::from_millis(100);
+    sender.send_with_deadline(0, 1, deadline).await.unwrap();
+    sender.try_send_with_deadline(0, 2, deadline).unwrap();
+
+    tokio::time::sleep(Duration::from_millis(40)).await;
+    let (message, remaining) = receivers[0].recv_with_deadline().await.unwrap();
+    assert_eq!(message, 1);
+    assert_eq!(remaining.left(), Some(Duration::from_millis(60)));
+
+    tokio::time::sleep(Duration::from_millis(90)).await;
+    let (message, remaining) = receivers[0].recv_with_deadline().await.unwrap();
+    assert_eq!(message, 2);
+    assert_eq!(
+        remaining,
+        crate::Remaining::Expired(Duration::from_millis(30))
+    );
+    assert!(remaining.is_expired());
+
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u64, crate::WithDeadline<u64>>(NonZeroUsize::new(1).unwrap());
+    sender.send_with_deadline(0, 3, Instant::now()).unwrap();
+    let (_, remaining) = receivers[0].recv_with_deadline().await.unwrap();
+    assert!(remaining.is_expired());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_recv_with_meta_reports_producer_and_enqueue_time() {
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(1).unwrap(), 4)
+            .track_lag()
+            .build();
+    let clone = sender.clone();
+    assert_ne!(sender.producer_id(), clone.producer_id());
+
+    let sent_at = tokio::time::Instant::now();
+    sender.send(0, 1).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    clone.send(0, 2).await.unwrap();
+
+    let (message, meta) = receivers[0].recv_with_meta().await.unwrap();
+    assert_eq!(message, 1);
+    assert_eq!(meta.producer, sender.producer_id());
+    assert_eq!(meta.enqueued_at, Some(sent_at));
+    assert_eq!(meta.attempt, 1);
+    let (_, meta) = receivers[0].recv_with_meta().await.unwrap();
+    assert_eq!(meta.producer, clone.producer_id());
+
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap());
+    sender.send(0, 3).unwrap();
+    let (_, meta) = receivers[0].recv_with_meta().await.unwrap();
+    assert_eq!(meta.enqueued_at, None);
+    assert_eq!(meta.producer, sender.producer_id());
+}
+
+#[tokio::test]
+async fn test_redelivery_counts_attempts() {
+    let (sender, receivers) = unbounded_sticky_channel::<u32, u32>(NonZeroUsize::new(1).unwrap());
+    let mut receiver = crate::RedeliveryReceiver::new(receivers.into_iter().next().unwrap());
+    sender.send(0, 1).unwrap();
+
+    let delivery = receiver.recv().await.unwrap();
+    assert_eq!(delivery.attempt(), 1);
+    drop(delivery);
+    let delivery = receiver.recv().await.unwrap();
+    assert_eq!(delivery.attempt(), 2);
+    drop(delivery);
+    assert_eq!(receiver.recv().await.unwrap().attempt(), 3);
+}
+
+#[test]
+fn test_routing_module_matches_channel_routing() {
+    use crate::routing::{consumer_index, hash_id};
+
+    let num_consumers = NonZeroUsize::new(7).unwrap();
+    let build_hasher = std::hash::RandomState::new();
+    let (sender, _receivers) = crate::unbounded_sticky_channel_with_hasher::<u64, u64, _>(
+        num_consumers,
+        build_hasher.clone(),
+    );
+    for id in 0..100 {
+        let hash = hash_id(&id, &build_hasher);
+        assert_eq!(
+            sender.route_of(id),
+            Some(consumer_index(hash, num_consumers))
+        );
+    }
+    assert_eq!(
+        consumer_index(u64::MAX, num_consumers),
+        (u64::MAX % 7) as usize
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_buffered_sender_pushes_on_size_linger_and_flush() {
+    let (sender, mut receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap(), 16);
+    let mut buffered = crate::BufferedSender::new(
+        sender,
+        Duration::from_millis(50),
+        NonZeroUsize::new(3).unwrap(),
+    );
+
+    buffered.send(0, 1).await.unwrap();
+    buffered.try_send(0, 2).unwrap();
+    assert_eq!(receivers[0].try_recv(), Err(TryRecvError::Empty));
+    buffered.send(0, 3).await.unwrap();
+    assert_eq!(receivers[0].try_recv(), Ok(1));
+    assert_eq!(receivers[0].try_recv(), Ok(2));
+    assert_eq!(receivers[0].try_recv(), Ok(3));
+
+    buffered.send(0, 4).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    buffered.send(0, 5).await.unwrap();
+    assert_eq!(receivers[0].try_recv(), Ok(4));
+    assert_eq!(receivers[0].try_recv(), Ok(5));
+
+    buffered.send(0, 6).await.unwrap();
+    buffered.flush();
+    assert_eq!(receivers[0].try_recv(), Ok(6));
+    buffered.send(0, 7).await.unwrap();
+    let sender = buffered.into_inner();
+    assert_eq!(receivers[0].try_recv(), Ok(7));
+    assert_eq!(sender.totals().sent, 7);
+}
+
+#[tokio::test]
+async fn test_buffered_sender_pushes_batch_before_waiting_for_capacity() {
+    let (sender, mut receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap(), 4);
+    let mut buffered = crate::BufferedSender::new(
+        sender,
+        Duration::from_secs(60),
+        NonZeroUsize::new(8).unwrap(),
+    );
+    for message in 1..=4 {
+        buffered.send(0, message).await.unwrap();
+    }
+    assert_eq!(receivers[0].try_recv(), Err(TryRecvError::Empty));
+
+    let (sent, received) = tokio::time::timeout(Duration::from_secs(1), async {
+        tokio::join!(buffered.send(0, 5), receivers[0].recv())
+    })
+    .await
+    .unwrap();
+    sent.unwrap();
+    assert_eq!(received, Some(1));
+
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(1).unwrap(), 8)
+            .max_pending_per_key(NonZeroUsize::new(1).unwrap())
+            .build();
+    let mut buffered = crate::BufferedSender::new(
+        sender,
+        Duration::from_secs(60),
+        NonZeroUsize::new(8).unwrap(),
+    );
+    buffered.send(0, 1).await.unwrap();
+    let (sent, received) = tokio::time::timeout(Duration::from_secs(1), async {
+        tokio::join!(buffered.send(0, 2), receivers[0].recv())
+    })
+    .await
+    .unwrap();
+    sent.unwrap();
+    assert_eq!(received, Some(1));
+}
+
+#[tokio::test]
+async fn test_unbounded_buffered_sender_flushes_on_drop() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(2).unwrap());
+    let mut buffered = crate::UnboundedBufferedSender::new(
+        sender.clone(),
+        Duration::from_secs(60),
+        NonZeroUsize::new(8).unwrap(),
+    );
+    let index = sender.route_of(9).unwrap();
+    buffered.send(9, 1).unwrap();
+    buffered.send(9, 2).unwrap();
+    assert_eq!(receivers[index].try_recv(), Err(TryRecvError::Empty));
+    drop(buffered);
+    assert_eq!(receivers[index].recv().await, Some(1));
+    assert_eq!(receivers[index].recv().await, Some(2));
+}
+
+#[test]
+fn test_round_consumers_to_power_of_two_masks_routes() {
+    use crate::routing::consumer_index;
+
+    let (sender, receivers) =
+        crate::UnboundedStickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(5).unwrap())
+            .round_consumers_to_power_of_two()
+            .build();
+    assert_eq!(receivers.len(), 8);
+    assert!(sender.route_of(42).unwrap() < 8);
+
+    let (_, receivers) =
+        crate::StickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(4).unwrap(), 1)
+            .round_consumers_to_power_of_two()
+            .build();
+    assert_eq!(receivers.len(), 4);
+
+    for hash in [0, 7, 8, 1 << 40, u64::MAX] {
+        assert_eq!(
+            consumer_index(hash, NonZeroUsize::new(8).unwrap()),
+            (hash % 8) as usize
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_prehashed_ids_hash_once_and_route_like_equal_ids() {
+    use std::{
+        hash::{Hash, Hasher},
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    static HASHED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct OrderKey(u64, String);
+
+    impl Hash for OrderKey {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            HASHED.fetch_add(1, Ordering::Relaxed);
+            self.0.hash(state);
+            self.1.hash(state);
+        }
+    }
+
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<OrderKey, u64>(NonZeroUsize::new(4).unwrap());
+    let key = sender.prehash(OrderKey(7, "eu".to_owned()));
+    for message in 0..10 {
+        sender.send_prehashed(&key, message).unwrap();
+    }
+    assert_eq!(HASHED.load(Ordering::Relaxed), 1);
+
+    let index = sender.route_of(OrderKey(7, "eu".to_owned())).unwrap();
+    assert_eq!(receivers[index].try_recv(), Ok(0));
+    assert_eq!(key.1, "eu");
+
+    let (sender, mut receivers) = sticky_channel::<OrderKey, u64>(NonZeroUsize::new(4).unwrap(), 1);
+    let key = sender.prehash(OrderKey(8, "us".to_owned()));
+    sender.send_prehashed(&key, 1).await.unwrap();
+    assert!(sender.try_send_prehashed(&key, 2).unwrap_err().is_full());
+    let index = sender.route_of(key.into_inner()).unwrap();
+    assert_eq!(receivers[index].try_recv(), Ok(1));
+}
+
+#[tokio::test]
+async fn test_prepare_and_send_routed_message() {
+    let (sender, mut receivers) = sticky_channel::<u64, String>(NonZeroUsize::new(3).unwrap(), 1);
+    let mut prepared = sender.prepare(&5, "order".to_owned()).unwrap();
+    let index = prepared.consumer_index();
+    assert_eq!(sender.route_of(5), Some(index));
+
+    prepared
+        .get_mut()
+        .push_str(&format!(" for consumer {index}"));
+    sender.send_routed(prepared).await.unwrap();
+    let full = sender.prepare(&5, "late".to_owned()).unwrap();
+    assert!(matches!(
+        sender.try_send_routed(full),
+        Err(SendError::ChannelFull(message, _)) if message == "late"
+    ));
+    assert_eq!(
+        receivers[index].recv().await,
+        Some(format!("order for consumer {index}"))
+    );
+
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(2).unwrap());
+    let prepared = sender.prepare(&1, 10).unwrap().map(|message| message * 2);
+    let index = prepared.consumer_index();
+    sender.send_routed(prepared).unwrap();
+    assert_eq!(receivers[index].try_recv(), Ok(20));
+}
+
+#[tokio::test]
+async fn test_recv_first_returns_ready_receiver() {
+    let (sender, mut receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(3).unwrap(), 4);
+    let index = sender.route_of(11).unwrap();
+
+    let first = tokio::spawn(async move {
+        let message = crate::recv_first(&mut receivers).await;
+        (message, receivers)
+    });
+    tokio::task::yield_now().await;
+    sender.send(11, 5).await.unwrap();
+    let (message, mut receivers) = first.await.unwrap();
+    assert_eq!(message, Some((index, 5)));
+
+    drop(sender);
+    assert_eq!(crate::recv_first(&mut receivers).await, None);
+    assert_eq!(
+        crate::recv_first::<crate::Receiver<u64>>(&mut []).await,
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_consume_with_counts_progress_and_panics() {
+    let (sender, receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(2).unwrap(), 16);
+    let sum = Arc::new(std::sync::atomic::AtomicU64::new(0));
+
+    let handle = {
+        let sum = sum.clone();
+        crate::consume_with(receivers, NonZeroUsize::new(2).unwrap(), move |message| {
+            let sum = sum.clone();
+            async move {
+                assert_ne!(message, 0, "handler rejects zero");
+                sum.fetch_add(message, std::sync::atomic::Ordering::Relaxed);
+            }
+        })
+    };
+
+    for id in 0..8 {
+        sender.send(id, id).await.unwrap();
+    }
+    drop(sender);
+
+    let progress = loop {
+        let progress = handle.progress();
+        if progress.iter().all(|progress| progress.in_flight() == 0)
+            && progress
+                .iter()
+                .map(|progress| progress.received)
+                .sum::<u64>()
+                == 8
+        {
+            break progress;
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    };
+    assert_eq!(progress.len(), 2);
+    assert_eq!(
+        progress
+            .iter()
+            .map(|progress| progress.panicked)
+            .sum::<u64>(),
+        1
+    );
+    assert_eq!(
+        progress
+            .iter()
+            .map(|progress| progress.completed)
+            .sum::<u64>(),
+        7
+    );
+
+    let receivers = handle.join().await;
+    assert_eq!(receivers.len(), 2);
+    assert_eq!(sum.load(std::sync::atomic::Ordering::Relaxed), 28);
+}
+
+#[tokio::test]
+async fn test_consume_with_stop_leaves_unreceived_messages_queued() {
+    let (sender, receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap(), 16);
+    let (started_tx, mut started_rx) = tokio::sync::mpsc::unbounded_channel();
+    let release = Arc::new(tokio::sync::Notify::new());
+
+    let handle = {
+        let release = release.clone();
+        crate::consume_with(receivers, NonZeroUsize::new(1).unwrap(), move |message| {
+            let started_tx = started_tx.clone();
+            let release = release.clone();
+            async move {
+                started_tx.send(message).unwrap();
+                release.notified().await;
+            }
+        })
+    };
+
+    for message in 1..=3 {
+        sender.send(7, message).await.unwrap();
+    }
+    assert_eq!(started_rx.recv().await, Some(1));
+
+    let stopping = tokio::spawn(handle.stop());
+    tokio::task::yield_now().await;
+    release.notify_one();
+    let mut receivers = stopping.await.unwrap();
+
+    let reports = crate::drain_all(&mut receivers);
+    assert_eq!(reports[0].messages, vec![2, 3]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_consume_supervised_backs_off_and_reassigns_after_panics() {
+    let (sender, receivers, channel_admin) =
+        crate::UnboundedStickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(2).unwrap())
+            .partitions(NonZeroUsize::new(4).unwrap())
+            .build_with_admin();
+    let admin = channel_admin.partitions().unwrap();
+    let partition = sender.partition_of(42).unwrap();
+    let failing = admin.consumer_of(partition);
+
+    use std::sync::Mutex;
+
+    let restarts = Arc::new(Mutex::new(Vec::new()));
+    let supervision = {
+        let restarts = restarts.clone();
+        crate::Supervision::new()
+            .with_backoff(crate::Backoff::Exponential {
+                initial: Duration::from_millis(10),
+                max: Duration::from_secs(1),
+            })
+            .reassign_after(std::num::NonZeroU32::new(2).unwrap(), admin.clone())
+            .on_restart(move |restart: &crate::Restart| {
+                restarts.lock().unwrap().push(restart.clone())
+            })
+    };
+    let handle = crate::consume_supervised(
+        receivers,
+        NonZeroUsize::new(1).unwrap(),
+        supervision,
+        |message: u64| async move { assert_ne!(message, 0, "handler rejects zero") },
+    );
+
+    let started = tokio::time::Instant::now();
+    sender.send(42, 0).unwrap();
+    sender.send(42, 0).unwrap();
+    while restarts.lock().unwrap().len() < 2 {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    let restarts = restarts.lock().unwrap().clone();
+    assert!(restarts.iter().all(|restart| restart.consumer == failing));
+    assert_eq!(restarts[0].consecutive_panics, 1);
+    assert_eq!(restarts[0].backoff, Duration::from_millis(10));
+    assert!(restarts[0].reassigned.is_empty());
+    assert_eq!(restarts[1].consecutive_panics, 2);
+    assert_eq!(restarts[1].backoff, Duration::from_millis(20));
+    assert!(started.elapsed() >= Duration::from_millis(10));
+
+    let moved = restarts[1]
+        .reassigned
+        .iter()
+        .find(|moved| moved.partition == partition)
+        .unwrap();
+    assert_eq!((moved.from, moved.to), (failing, 1 - failing));
+    assert_eq!(sender.route_of(42), Some(1 - failing));
+
+    drop(sender);
+    let receivers = handle.join().await;
+    assert_eq!(receivers.len(), 2);
+}
+
+#[tokio::test]
+async fn test_consume_supervised_reports_offending_message_and_keeps_going() {
+    let (sender, receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap(), 16);
+    let (panics_tx, mut panics_rx) = tokio::sync::mpsc::unbounded_channel();
+    let handled = Arc::new(std::sync::atomic::AtomicU64::new(0));
+
+    let supervision = crate::Supervision::new().on_panic(move |panic: crate::HandlerPanic<u64>| {
+        panics_tx
+            .send((
+                panic.consumer,
+                panic.message,
+                panic.panic_message().map(str::to_owned),
+            ))
+            .unwrap();
+    });
+    let handle = {
+        let handled = handled.clone();
+        crate::consume_supervised(
+            receivers,
+            NonZeroUsize::new(2).unwrap(),
+            supervision,
+            move |message| {
+                let handled = handled.clone();
+                async move {
+                    if message % 3 == 0 {
+                        panic!("cannot handle {message}");
+                    }
+                    handled.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                }
+            },
+        )
+    };
+
+    for message in 1..=6 {
+        sender.send(5, message).await.unwrap();
+    }
+    drop(sender);
+    handle.join().await;
+
+    let mut panics = Vec::new();
+    while let Ok(panic) = panics_rx.try_recv() {
+        panics.push(panic);
+    }
+    panics.sort();
+    assert_eq!(
+        panics,
+        vec![
+            (0, 3, Some("cannot handle 3".to_owned())),
+            (0, 6, Some("cannot handle 6".to_owned())),
+        ]
+    );
+    assert_eq!(handled.load(std::sync::atomic::Ordering::Relaxed), 4);
+}
+
+#[tokio::test]
+async fn test_offsets_are_assigned_per_consumer_and_committed() {
+    let (sender, mut receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(2).unwrap(), 8);
+    let index = sender.route_of(3).unwrap();
+    for message in 0..3 {
+        sender.send(3, message).await.unwrap();
+    }
+
+    let receiver = &mut receivers[index];
+    assert_eq!(receiver.recv().await, Some(0));
+    assert_eq!(receiver.recv_with_offset().await, Some((1, 1)));
+    assert_eq!(receiver.next_offset(), 2);
+    assert_eq!(receiver.committed(), None);
+
+    receiver.commit(1);
+    receiver.commit(0);
+    assert_eq!(receiver.committed(), Some(1));
+    let mut expected = vec![None; 2];
+    expected[index] = Some(1);
+    assert_eq!(sender.committed_offsets(), expected);
+
+    let mut buffer = Vec::new();
+    receiver.recv_many(&mut buffer, 8).await;
+    assert_eq!(receiver.next_offset(), 3);
+}
+
+#[tokio::test]
+#[should_panic(expected = "has not been delivered yet")]
+async fn test_commit_of_undelivered_offset_panics() {
+    let (_sender, receivers) = unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap());
+    receivers[0].commit(0);
+}
+
+#[tokio::test]
+async fn test_last_values_keep_latest_message_per_id() {
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<u64, (u64, u64)>::new(NonZeroUsize::new(2).unwrap(), 16)
+            .last_values()
+            .build();
+
+    for (id, version) in [(1, 1), (2, 1), (1, 2), (3, 1), (1, 3)] {
+        sender.send(id, (id, version)).await.unwrap();
+    }
+    assert_eq!(sender.last_value(&1), Some((1, 3)));
+    assert_eq!(sender.last_value(&4), None);
+
+    let mut snapshot: Vec<_> = receivers
+        .iter()
+        .flat_map(|receiver| receiver.snapshot().unwrap())
+        .collect();
+    snapshot.sort();
+    assert_eq!(snapshot, [(1, 3), (2, 1), (3, 1)]);
+
+    let index = sender.route_of(1).unwrap();
+    assert!(receivers[index].snapshot().unwrap().contains(&(1, 3)));
+
+    assert_eq!(sender.clear_last_value(&1), Some((1, 3)));
+    assert_eq!(sender.last_value(&1), None);
+    receivers[index].close();
+    assert!(
+        receivers[index]
+            .snapshot()
+            .unwrap()
+            .iter()
+            .all(|&(id, _)| id != 1)
+    );
+
+    let (sender, receivers) = unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap());
+    sender.send(1, 1).unwrap();
+    assert_eq!(sender.last_value(&1), None);
+    assert_eq!(receivers[0].snapshot(), None);
+}
+
+#[tokio::test]
+async fn test_last_values_skip_messages_that_were_not_queued() {
+    let (sender, mut receivers) =
+        crate::UnboundedStickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(1).unwrap())
+            .last_values()
+            .build();
+    sender.send(1, 1).unwrap();
+    receivers[0].close();
+    assert!(sender.send(1, 2).is_err());
+    assert_eq!(sender.last_value(&1), Some(1));
+
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(1).unwrap(), 4)
+            .last_values()
+            .build();
+    sender.send(1, 1).await.unwrap();
+    let permit = sender.keyed(1).unwrap().reserve().await.unwrap();
+    receivers[0].close();
+    assert!(permit.send(2).is_err());
+    assert_eq!(sender.last_value(&1), Some(1));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_last_value_ttl_forgets_idle_ids() {
+    let (sender, receivers) =
+        crate::UnboundedStickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(2).unwrap())
+            .last_values()
+            .last_value_ttl(Duration::from_secs(10))
+            .build();
+
+    sender.send(1, 10).unwrap();
+    sender.send(2, 20).unwrap();
+    tokio::time::advance(Duration::from_secs(6)).await;
+    sender.send(2, 21).unwrap();
+    assert_eq!(sender.last_value_count(), Some(2));
+
+    tokio::time::advance(Duration::from_secs(5)).await;
+    assert_eq!(sender.last_value(&1), None);
+    assert_eq!(sender.last_value(&2), Some(21));
+    let snapshot: Vec<_> = receivers
+        .iter()
+        .flat_map(|receiver| receiver.snapshot().unwrap())
+        .collect();
+    assert_eq!(snapshot, [21]);
+    assert_eq!(sender.last_value_count(), Some(2));
+
+    sender.send(3, 30).unwrap();
+    assert_eq!(sender.last_value_count(), Some(2));
+    assert_eq!(sender.last_value(&3), Some(30));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_max_tracked_keys_evicts_by_policy() {
+    for (policy, evicted) in [
+        (crate::EvictionPolicy::Lru, 1),
+        (crate::EvictionPolicy::Lfu, 2),
+    ] {
+        let (sender, _receivers) =
+            crate::UnboundedStickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(2).unwrap())
+                .last_values()
+                .max_tracked_keys(NonZeroUsize::new(2).unwrap(), policy)
+                .build();
+
+        for id in [1, 1, 1, 2] {
+            tokio::time::advance(Duration::from_millis(1)).await;
+            sender.send(id, id).unwrap();
+        }
+        tokio::time::advance(Duration::from_millis(1)).await;
+        sender.send(3, 3).unwrap();
+
+        assert_eq!(sender.last_value_count(), Some(2), "{policy:?}");
+        assert_eq!(sender.last_value_evictions(), Some(1), "{policy:?}");
+        assert_eq!(sender.last_value(&evicted), None, "{policy:?}");
+        assert_eq!(sender.last_value(&3), Some(3), "{policy:?}");
+
+        // Cleared IDs no longer count towards the limit.
+        assert_eq!(sender.clear_last_value(&3), Some(3));
+        sender.send(4, 4).unwrap();
+        assert_eq!(sender.last_value_count(), Some(2), "{policy:?}");
+        assert_eq!(sender.last_value_evictions(), Some(1), "{policy:?}");
+    }
+}
+
+#[cfg(feature = "bincode")]
+#[tokio::test]
+async fn test_wire_envelope_round_trips_through_bincode() {
+    let (sender, mut receivers) = sticky_channel::<u64, String>(NonZeroUsize::new(3).unwrap(), 4);
+    let routed = sender.prepare(&9, "hello".to_string()).unwrap();
+    let index = routed.consumer_index();
+
+    let envelope = crate::WireEnvelope::from(routed);
+    assert_eq!(envelope.version, crate::WIRE_VERSION);
+    assert_eq!(envelope.consumer as usize, index);
+    assert_eq!(envelope.meta.producer, sender.producer_id());
+
+    let bytes = envelope.to_bincode().unwrap();
+    let decoded = crate::WireEnvelope::<String>::from_bincode(&bytes).unwrap();
+    assert_eq!(decoded, envelope);
+
+    sender.send_routed(decoded.into_routed()).await.unwrap();
+    assert_eq!(receivers[index].recv().await.as_deref(), Some("hello"));
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn test_wire_envelope_rejects_other_versions() {
+    let envelope = crate::WireEnvelope {
+        version: crate::WIRE_VERSION + 1,
+        hash: 1,
+        consumer: 0,
+        meta: crate::WireMeta::default(),
+        payload: 5u64,
+    };
+    let bytes = envelope.to_bincode().unwrap();
+    assert!(matches!(
+        crate::WireEnvelope::<u64>::from_bincode(&bytes),
+        Err(crate::WireError::UnsupportedVersion(version)) if version == crate::WIRE_VERSION + 1
+    ));
+    assert!(matches!(
+        crate::WireEnvelope::<u64>::from_bincode(&[]),
+        Err(crate::WireError::Decode(_))
+    ));
+}
+
+#[cfg(all(feature = "postcard", feature = "cbor", feature = "json"))]
+#[test]
+fn test_wire_codecs_round_trip_and_check_version() {
+    use crate::{Cbor, Json, Postcard, WireCodec, WireEnvelope, WireError};
+
+    fn round_trip<C: WireCodec>(codec: C) {
+        let envelope = WireEnvelope {
+            version: crate::WIRE_VERSION,
+            hash: u64::MAX,
+            consumer: 3,
+            meta: crate::WireMeta {
+                producer: 7,
+                attempt: 2,
+            },
+            payload: vec!["a".to_string(), "b".to_string()],
+        };
+        let bytes = envelope.encode(&codec).unwrap();
+        assert_eq!(WireEnvelope::decode(&codec, &bytes).unwrap(), envelope);
+
+        let newer = WireEnvelope {
+            version: crate::WIRE_VERSION + 1,
+            ..envelope
+        };
+        let bytes = newer.encode(&codec).unwrap();
+        assert!(matches!(
+            WireEnvelope::<Vec<String>>::decode(&codec, &bytes),
+            Err(WireError::UnsupportedVersion(_))
+        ));
+    }
+
+    round_trip(Postcard);
+    round_trip(Cbor);
+    round_trip(Json);
+}
+
+#[tokio::test]
+async fn test_bridge_to_std_hands_off_in_order() {
+    let (sender, receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap(), 4);
+    let receiver = receivers.into_iter().next().unwrap();
+    let (std_receiver, bridge) = crate::bridge_to_std(receiver, 1);
+
+    let worker = std::thread::spawn(move || std_receiver.iter().collect::<Vec<_>>());
+    for message in 0..10 {
+        sender.send(0, message).await.unwrap();
+    }
+    drop(sender);
+
+    let received = tokio::task::spawn_blocking(move || worker.join().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(received, (0..10).collect::<Vec<_>>());
+    let (_receiver, left) = tokio::task::spawn_blocking(move || bridge.join())
+        .await
+        .unwrap();
+    assert_eq!(left, None);
+}
+
+#[test]
+fn test_bridge_to_std_returns_message_when_std_side_drops() {
+    let (sender, receivers) = unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(1).unwrap());
+    let receiver = receivers.into_iter().next().unwrap();
+    let (std_receiver, bridge) = crate::bridge_to_std(receiver, 0);
+    drop(std_receiver);
+
+    sender.send(0, 1).unwrap();
+    sender.send(0, 2).unwrap();
+    let (mut receiver, left) = bridge.join();
+    assert_eq!(left, Some(1));
+    assert_eq!(receiver.try_recv(), Ok(2));
+}
+
+#[cfg(feature = "io")]
+#[tokio::test]
+async fn test_write_to_writes_encoded_batches() {
+    use tokio::io::AsyncReadExt;
+
+    let (sender, receivers) = sticky_channel::<u64, u32>(NonZeroUsize::new(1).unwrap(), 8);
+    let mut receiver = receivers.into_iter().next().unwrap();
+    for message in 1..=5 {
+        sender.send(0, message).await.unwrap();
+    }
+    drop(sender);
+
+    let (writer, mut reader) = tokio::io::duplex(64);
+    let written = crate::write_to(
+        &mut receiver,
+        writer,
+        NonZeroUsize::new(2).unwrap(),
+        crate::FlushPolicy::EveryBatch,
+        |message, buffer| {
+            buffer.extend_from_slice(&message.to_be_bytes());
+            Ok(())
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(written, 5);
+
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await.unwrap();
+    let decoded: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_be_bytes(chunk.try_into().unwrap()))
+        .collect();
+    assert_eq!(decoded, [1, 2, 3, 4, 5]);
+}
+
+#[cfg(feature = "io")]
+#[tokio::test]
+async fn test_write_to_stops_at_encode_error() {
+    let (sender, receivers) = unbounded_sticky_channel::<u64, u32>(NonZeroUsize::new(1).unwrap());
+    let mut receiver = receivers.into_iter().next().unwrap();
+    sender.send(0, 1).unwrap();
+    sender.send(0, 2).unwrap();
+
+    let result = crate::write_to(
+        &mut receiver,
+        tokio::io::sink(),
+        NonZeroUsize::new(1).unwrap(),
+        crate::FlushPolicy::default(),
+        |message, _| {
+            if message == 1 {
+                Err(std::io::Error::other("cannot encode"))
+            } else {
+                Ok(())
+            }
+        },
+    )
+    .await;
+    assert!(result.is_err());
+    assert_eq!(receiver.try_recv(), Ok(2));
+}
+
+#[cfg(feature = "console")]
+#[tokio::test]
+async fn test_helper_tasks_are_spawned_in_named_spans() {
+    use std::sync::Mutex;
+
+    use tracing::{
+        Event, Id, Metadata, Subscriber,
+        field::{Field, Visit},
+        span::{Attributes, Record},
+    };
+
+    /// Subscriber recording the task names of the spans created.
+    #[derive(Default)]
+    struct TaskNames(Arc<Mutex<Vec<String>>>);
+
+    impl Visit for &TaskNames {
+        fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {}
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "task.name" {
+                self.0.lock().unwrap().push(value.to_string());
+            }
+        }
+    }
+
+    impl Subscriber for TaskNames {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+            let mut visitor = self;
+            attributes.record(&mut visitor);
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let subscriber = TaskNames::default();
+    let names = subscriber.0.clone();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let (sender, receivers) = sticky_channel::<u64, u64>(NonZeroUsize::new(2).unwrap(), 4);
+    let handle = crate::consume_with(receivers, NonZeroUsize::new(1).unwrap(), |_| async {});
+    drop(sender);
+    handle.join().await;
+
+    assert_eq!(
+        *names.lock().unwrap(),
+        ["sticky-channel consumer", "sticky-channel consumer"]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_gather_returns_partial_results_on_timeout() {
+    type Ask = crate::Request<u64, u64>;
+
+    let (sender, receivers) = sticky_channel::<u64, Ask>(NonZeroUsize::new(2).unwrap(), 8);
+    for mut receiver in receivers {
+        tokio::spawn(async move {
+            while let Some(request) = receiver.recv().await {
+                let value = *request.get_ref();
+                match value {
+                    // Never answered in time.
+                    3 => {
+                        tokio::time::sleep(Duration::from_secs(60)).await;
+                        let _ = request.respond(value);
+                    }
+                    // Dropped without a response.
+                    4 => drop(request),
+                    _ => request.respond(value * 10).unwrap(),
+                }
+            }
+        });
+    }
+
+    let gathered = sender
+        .gather((1..=4).map(|id| (id, id)), Duration::from_secs(1))
+        .await;
+    assert!(!gathered.is_complete());
+    assert_eq!(gathered.responses, HashMap::from([(1, 10), (2, 20)]));
+    let mut missing = gathered.missing;
+    missing.sort();
+    assert_eq!(missing, [3, 4]);
+
+    let response = sender.send_request(5, 5).await.unwrap();
+    assert_eq!(response.await, Ok(50));
+}
+
+#[tokio::test]
+async fn test_unbounded_gather_collects_all_responses() {
+    let (sender, mut receivers) =
+        unbounded_sticky_channel::<&str, crate::Request<(), usize>>(NonZeroUsize::new(1).unwrap());
+    let mut receiver = receivers.remove(0);
+    let worker = tokio::spawn(async move {
+        let mut handled = 0;
+        while let Some(request) = receiver.recv().await {
+            handled += 1;
+            let (_, responder) = request.into_parts();
+            responder.respond(handled).unwrap();
+        }
+    });
+
+    let gathered = sender
+        .gather([("a", ()), ("b", ())], Duration::from_secs(5))
+        .await;
+    assert!(gathered.is_complete());
+    assert_eq!(gathered.responses, HashMap::from([("a", 1), ("b", 2)]));
+    drop(sender);
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_broadcast_fn_collects_per_consumer_acknowledgments() {
+    type Flush = crate::Command<HashMap<u64, u64>, String>;
+
+    let (sender, receivers) = unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(3).unwrap());
+    let (control, mut receivers) = crate::control_channel::<_, Flush>(receivers);
+    receivers[2].close();
+
+    let mut workers = Vec::new();
+    for (index, mut receiver) in receivers.into_iter().enumerate().take(2) {
+        workers.push(tokio::spawn(async move {
+            let mut cache = HashMap::from([(index as u64, 1)]);
+            while let Some(event) = receiver.recv_event().await {
+                if let crate::Event::Control(command) = event {
+                    command.run(&mut cache);
+                }
+            }
+            cache
+        }));
+    }
+
+    let results = control
+        .broadcast_fn(|cache: &mut HashMap<u64, u64>| {
+            if cache.contains_key(&1) {
+                return Err("busy".to_string());
+            }
+            cache.clear();
+            Ok(())
+        })
+        .await;
+    assert_eq!(
+        results,
+        [
+            Ok(()),
+            Err(crate::CommandError::Failed("busy".to_string())),
+            Err(crate::CommandError::NotRun),
+        ]
+    );
+
+    drop(sender);
+    drop(control);
+    let caches: Vec<_> = futures::future::join_all(workers)
+        .await
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert!(caches[0].is_empty());
+    assert_eq!(caches[1].len(), 1);
+}
+
+#[cfg(feature = "prometheus")]
+#[tokio::test]
+async fn test_prometheus_registry_gathers_per_consumer_metrics() {
+    let (sender, mut receivers) =
+        crate::UnboundedStickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(2).unwrap())
+            .track_lag()
+            .build();
+    let registry = crate::PrometheusRegistry::new();
+    registry.register("orders \"eu\"", sender.metrics());
+
+    let index = sender.route_of(7).unwrap();
+    for message in 0..3 {
+        sender.send(7, message).unwrap();
+    }
+    receivers[index].recv().await.unwrap();
+
+    let output = registry.gather();
+    let labels = format!(r#"{{channel="orders \"eu\"",consumer="{index}""#);
+    for line in [
+        format!("sticky_channel_queue_depth{labels}}} 2"),
+        format!("sticky_channel_messages_sent_total{labels}}} 3"),
+        format!("sticky_channel_messages_received_total{labels}}} 1"),
+        format!("sticky_channel_messages_dropped_total{labels}}} 0"),
+        format!(r#"sticky_channel_queue_delay_seconds_bucket{labels},le="+Inf"}} 1"#),
+        format!("sticky_channel_queue_delay_seconds_count{labels}}} 1"),
+    ] {
+        assert!(
+            output.lines().any(|l| l == line),
+            "missing `{line}` in\n{output}"
+        );
+    }
+    assert_eq!(
+        output
+            .matches("# TYPE sticky_channel_queue_depth gauge")
+            .count(),
+        1
+    );
+
+    drop(receivers);
+    assert!(registry.gather().contains(&format!(
+        "sticky_channel_messages_dropped_total{labels}}} 2"
+    )));
+    assert!(registry.unregister("orders \"eu\""));
+    assert!(!registry.gather().contains("orders"));
+}
+
+#[tokio::test]
+async fn test_channel_admin_owns_administration() {
+    let (sender, mut receivers, admin) =
+        crate::StickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(2).unwrap(), 8)
+            .partitions(NonZeroUsize::new(4).unwrap())
+            .build_with_admin();
+    assert_eq!(admin.consumers(), 2);
+
+    let partition = sender.partition_of(9).unwrap();
+    let old = sender.route_of(9).unwrap();
+    let partitions = admin.partitions().unwrap();
+    partitions.reassign(partition, 1 - old);
+    assert_eq!(sender.route_of(9), Some(1 - old));
+
+    sender.send(9, 1).await.unwrap();
+    assert_eq!(admin.queue_depths().depths[1 - old], 1);
+    assert_eq!(admin.totals().sent, 1);
+    assert!(admin.latency_report().is_none());
+
+    assert!(admin.finish());
+    let receiver = &mut receivers[1 - old];
+    assert_eq!(receiver.recv().await, Some(1));
+    assert_eq!(receiver.recv_event().await, Some(crate::Event::Finished));
+    assert_eq!(admin.reconcile()[1 - old].consumed, 1);
+
+    drop(sender);
+    assert!(!admin.finish());
+}
+
+#[tokio::test]
+async fn test_channel_admin_closes_all_consumers() {
+    let (sender, mut receivers, admin) =
+        crate::StickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(2).unwrap(), 1)
+            .build_with_admin();
+    let index = sender.route_of(1).unwrap();
+    sender.send(1, 1).await.unwrap();
+    let waiting = tokio::spawn({
+        let sender = sender.clone();
+        async move { sender.send(1, 2).await }
+    });
+    tokio::task::yield_now().await;
+
+    assert!(admin.close_all());
+    assert!(!admin.close_all());
+    assert!(matches!(
+        waiting.await.unwrap(),
+        Err(SendError::ChannelClosed(2, _))
+    ));
+    assert!(matches!(
+        sender.try_send(1, 3),
+        Err(SendError::ChannelClosed(3, _))
+    ));
+    assert_eq!(receivers[index].recv().await, Some(1));
+    assert_eq!(receivers[index].recv().await, None);
+    assert_eq!(receivers[1 - index].recv().await, None);
+
+    let (sender, mut receivers, admin) =
+        crate::UnboundedStickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(1).unwrap())
+            .block_size(NonZeroUsize::new(4).unwrap())
+            .build_with_admin();
+    sender.send(1, 1).unwrap();
+    assert!(admin.close_all());
+    assert!(matches!(
+        sender.send(1, 2),
+        Err(SendError::ChannelClosed(2, 0))
+    ));
+    let mut buffer = Vec::new();
+    assert_eq!(receivers[0].recv_many(&mut buffer, 4).await, 1);
+    assert_eq!(receivers[0].recv_many(&mut buffer, 4).await, 0);
+    assert_eq!(buffer, vec![1]);
+
+    drop(sender);
+    assert!(!admin.close_all());
+}
+
+#[tokio::test]
+async fn test_channel_admin_grows_and_shrinks_capacity() {
+    let (sender, mut receivers, admin) =
+        crate::StickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(1).unwrap(), 1)
+            .build_with_admin();
+    sender.try_send(1, 1).unwrap();
+    assert!(matches!(
+        sender.try_send(1, 2),
+        Err(SendError::ChannelFull(2, 0))
+    ));
+
+    assert!(admin.grow_capacity(0, 2));
+    assert_eq!(admin.capacity(0), Some(3));
+    sender.try_send(1, 2).unwrap();
+    sender.try_send(1, 3).unwrap();
+
+    assert!(!admin.shrink_capacity(0, 3).await);
+    let mut shrink = std::pin::pin!(admin.shrink_capacity(0, 2));
+    assert!(futures::poll!(&mut shrink).is_pending());
+    assert_eq!(admin.capacity(0), Some(1));
+
+    assert_eq!(receivers[0].recv().await, Some(1));
+    assert!(futures::poll!(&mut shrink).is_pending());
+    assert_eq!(receivers[0].recv().await, Some(2));
+    assert!(shrink.await);
+
+    assert!(matches!(
+        sender.try_send(1, 4),
+        Err(SendError::ChannelFull(4, 0))
+    ));
+    assert_eq!(receivers[0].recv().await, Some(3));
+    sender.try_send(1, 4).unwrap();
+
+    let (_sender, _receivers, admin) =
+        crate::UnboundedStickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(1).unwrap())
+            .build_with_admin();
+    assert_eq!(admin.capacity(0), None);
+    assert!(!admin.grow_capacity(0, 1));
+}
+
+#[test]
+fn test_seeded_state_is_siphash_2_4_with_the_seed_as_keys() {
+    use std::hash::{BuildHasher, Hasher};
+
+    let seed = 0x0f0e_0d0c_0b0a_0908_0706_0504_0302_0100_u128;
+    let state = crate::SeededState::new(seed);
+    let input: Vec<u8> = (0..64).collect();
+    for len in [0, 1, 7, 8, 9, 15, 16, 63] {
+        #[allow(deprecated)]
+        let mut expected = std::hash::SipHasher::new_with_keys(seed as u64, (seed >> 64) as u64);
+        expected.write(&input[..len]);
+
+        // Split writes must hash like a single one.
+        let mut hasher = state.build_hasher();
+        let (head, tail) = input[..len].split_at(len / 3);
+        hasher.write(head);
+        hasher.write(tail);
+        assert_eq!(hasher.finish(), expected.finish(), "{len} bytes");
+    }
+
+    assert_eq!(state.hash_one(7_usize), state.hash_one(7_u64));
+    assert_ne!(
+        state.hash_one("id"),
+        crate::SeededState::new(seed + 1).hash_one("id")
+    );
+}
+
+#[test]
+fn test_channels_with_the_same_seed_route_alike() {
+    let consumers = NonZeroUsize::new(5).unwrap();
+    let (bounded, _receivers) = crate::sticky_channel_with_seed::<String, u64>(consumers, 4, 42);
+    let (unbounded, _receivers) =
+        crate::unbounded_sticky_channel_with_seed::<String, u64>(consumers, 42);
+    let (other, _receivers) =
+        crate::unbounded_sticky_channel_with_seed::<String, u64>(consumers, 43);
+
+    let ids: Vec<_> = (0..64).map(|id| format!("user-{id}")).collect();
+    assert!(
+        ids.iter()
+            .all(|id| bounded.route_of(id.clone()) == unbounded.route_of(id.clone()))
+    );
+    assert!(
+        ids.iter()
+            .any(|id| unbounded.route_of(id.clone()) != other.route_of(id.clone()))
+    );
+}
+
+#[cfg(all(
+    feature = "fxhash",
+    not(any(feature = "ahash", feature = "stable-routing"))
+))]
+#[test]
+fn test_fxhash_feature_routes_with_fxhash() {
+    use std::hash::BuildHasher;
+
+    let (sender, _receivers) = unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(3).unwrap());
+    let build_hasher = std::hash::BuildHasherDefault::<fxhash::FxHasher>::default();
+    for id in 0..16 {
+        let hash = build_hasher.hash_one(id);
+        assert_eq!(
+            sender.route_of(id),
+            Some(crate::routing::consumer_index(
+                hash,
+                NonZeroUsize::new(3).unwrap()
+            ))
+        );
+    }
+}
+
+#[cfg(feature = "stable-routing")]
+#[test]
+fn test_stable_routing_feature_pins_routes() {
+    use std::hash::BuildHasher;
+
+    let build_hasher = crate::DefaultBuildHasher::default();
+    assert_eq!(build_hasher, crate::SeededState::new(0));
+    assert_eq!(build_hasher.hash_one(42_u64), 0x0fc2_553f_0761_9dd3);
+
+    let (sender, _receivers) = unbounded_sticky_channel::<u64, u64>(NonZeroUsize::new(10).unwrap());
+    assert_eq!(
+        sender.route_of(42),
+        Some((0x0fc2_553f_0761_9dd3_u64 % 10) as usize)
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_burst_absorbs_spikes_until_allowance_runs_out() {
+    let (sender, mut receivers) =
+        crate::StickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(1).unwrap(), 1)
+            .burst(3, Duration::from_secs(10))
+            .build();
+    sender.try_send(1, 1).unwrap();
+    sender.try_send(1, 2).unwrap();
+    sender.send(1, 3).await.unwrap();
+    assert!(matches!(
+        sender.try_send(1, 4),
+        Err(SendError::ChannelFull(4, 0))
+    ));
+    assert_eq!(
+        sender.burst_report().unwrap()[0],
+        crate::BurstReport {
+            burst_slots: 2,
+            in_use: 2,
+            allowance: 0,
+            bursts: 2,
+        }
+    );
+
+    assert_eq!(receivers[0].recv().await, Some(1));
+    assert_eq!(receivers[0].recv().await, Some(2));
+    sender.try_send(1, 4).unwrap();
+    assert!(matches!(
+        sender.try_send(1, 5),
+        Err(SendError::ChannelFull(5, 0))
+    ));
+    let mut send = std::pin::pin!(sender.send(1, 5));
+    assert!(futures::poll!(&mut send).is_pending());
+
+    tokio::time::advance(Duration::from_secs(5)).await;
+    let report = sender.burst_report().unwrap()[0];
+    assert_eq!((report.in_use, report.allowance), (1, 1));
+    sender.try_send(1, 6).unwrap();
+    assert_eq!(sender.burst_report().unwrap()[0].bursts, 3);
+
+    let (sender, _receivers) =
+        crate::StickyChannelBuilder::<u64, u64>::new(NonZeroUsize::new(1).unwrap(), 1).build();
+    assert!(sender.burst_report().is_none());
+}
@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use tokio::time::{Instant, MissedTickBehavior, interval_at};
+
+use crate::{
+    envelope::{Payload, inject},
+    queue::WeakQueue,
+};
+
+/// Starts the tick task of a channel once its internal queues exist.
+pub(crate) type TickStarter<T> = Box<dyn FnOnce(Vec<WeakQueue<T>>) + Send>;
+
+/// Returns a starter that spawns a task injecting `tick()` into every queue once per `period`.
+///
+/// The task only holds weak handles to the queues, so it does not keep the channel open. It stops once every sender
+/// has been dropped or every receiver has been closed.
+pub(crate) fn ticker<T, F>(period: Duration, tick: F) -> TickStarter<T>
+where
+    T: Send + 'static,
+    F: Fn() -> T + Send + Sync + 'static,
+{
+    Box::new(move |mut queues| {
+        crate::util::spawn("sticky-channel tick", async move {
+            let mut interval = interval_at(Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            while !queues.is_empty() {
+                interval.tick().await;
+
+                queues.retain(|queue| match queue.upgrade() {
+                    Some(queue) => inject(&queue, Payload::Message(tick())),
+                    None => false,
+                });
+            }
+        });
+    })
+}
@@ -0,0 +1,177 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use crate::sketch::FrequencySketch;
+
+/// Message counts of a whole channel, summed over all consumers.
+///
+/// Returned by [`Sender::totals`](crate::Sender::totals), [`Receiver::totals`](crate::Receiver::totals) and their
+/// unbounded counterparts. The counts are always maintained and cost two relaxed atomic updates per message. They are
+/// read one after the other, so a snapshot taken while messages are moving may be off by the messages in transit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Totals {
+    /// Messages sent to any consumer.
+    pub sent: u64,
+    /// Messages received by any receiver.
+    pub received: u64,
+    /// Messages sent but not received yet, including those buffered in dropped receivers.
+    pub in_flight: u64,
+}
+
+/// Accounting of the messages of a single consumer, as returned by [`Sender::reconcile`](crate::Sender::reconcile).
+///
+/// Every message accepted by the consumer's queue is eventually consumed by its receiver, left behind when the receiver
+/// was dropped, or still pending. Messages shed by a [`SheddingSender`](crate::SheddingSender) were never accepted and
+/// are reported next to them, see [`SheddingSender::reconcile`](crate::SheddingSender::reconcile).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Reconciliation {
+    /// Index of the consumer, i.e. the position of its receiver in the vector returned when the channel was built.
+    pub consumer_index: usize,
+    /// Messages queued to the consumer.
+    pub accepted: u64,
+    /// Messages received by the consumer's receiver.
+    pub consumed: u64,
+    /// Messages still queued when the consumer's receiver was dropped, which are lost.
+    pub dropped: u64,
+    /// Messages dropped by a shedding sender instead of being queued. Always `0` when reported by a plain sender.
+    pub shed: u64,
+}
+
+impl Reconciliation {
+    /// Returns the number of accepted messages that have not been consumed or dropped yet.
+    pub fn pending(&self) -> u64 {
+        self.accepted
+            .saturating_sub(self.consumed)
+            .saturating_sub(self.dropped)
+    }
+
+    /// Returns the number of messages that were sent but will never be consumed.
+    pub fn lost(&self) -> u64 {
+        self.dropped + self.shed
+    }
+}
+
+/// Counters of a single consumer.
+#[derive(Default)]
+struct ConsumerCounters {
+    accepted: AtomicU64,
+    consumed: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// Counters of all consumers of a channel, shared by its consumers and receivers.
+pub(crate) struct Counters {
+    consumers: Box<[ConsumerCounters]>,
+    sketch: Option<FrequencySketch>,
+}
+
+impl Counters {
+    pub(crate) fn new(num_consumers: usize, sketch: Option<FrequencySketch>) -> Arc<Self> {
+        Arc::new(Self {
+            consumers: (0..num_consumers)
+                .map(|_| ConsumerCounters::default())
+                .collect(),
+            sketch,
+        })
+    }
+
+    /// Returns the estimated number of messages queued for the ID with `hash`, if the channel tracks frequencies.
+    pub(crate) fn approx_count(&self, hash: u64) -> Option<u64> {
+        self.sketch.as_ref().map(|sketch| sketch.estimate(hash))
+    }
+
+    pub(crate) fn totals(&self) -> Totals {
+        let mut received = 0;
+        let mut sent = 0;
+        for consumer in &self.consumers {
+            // Reading `consumed` first makes it unlikely to overtake `accepted`, the subtraction saturates in case it
+            // does.
+            received += consumer.consumed.load(Ordering::Relaxed);
+            sent += consumer.accepted.load(Ordering::Relaxed);
+        }
+        Totals {
+            sent,
+            received,
+            in_flight: sent.saturating_sub(received),
+        }
+    }
+
+    /// Returns the number of messages accepted by every consumer so far.
+    pub(crate) fn accepted(&self) -> Vec<u64> {
+        self.consumers
+            .iter()
+            .map(|consumer| consumer.accepted.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    pub(crate) fn reconcile(&self) -> Vec<Reconciliation> {
+        self.consumers
+            .iter()
+            .enumerate()
+            .map(|(consumer_index, consumer)| Reconciliation {
+                consumer_index,
+                accepted: consumer.accepted.load(Ordering::Relaxed),
+                consumed: consumer.consumed.load(Ordering::Relaxed),
+                dropped: consumer.dropped.load(Ordering::Relaxed),
+                shed: 0,
+            })
+            .collect()
+    }
+}
+
+/// Handle to the counters of a single consumer.
+#[derive(Clone)]
+pub(crate) struct Tally {
+    counters: Arc<Counters>,
+    index: usize,
+}
+
+impl Tally {
+    pub(crate) fn new(counters: Arc<Counters>, index: usize) -> Self {
+        Self { counters, index }
+    }
+
+    /// Returns the counters of the whole channel.
+    pub(crate) fn channel(&self) -> &Counters {
+        &self.counters
+    }
+
+    pub(crate) fn shared(&self) -> Arc<Counters> {
+        self.counters.clone()
+    }
+
+    fn consumer(&self) -> &ConsumerCounters {
+        &self.counters.consumers[self.index]
+    }
+
+    /// Counts a message of the ID with `hash` as sent.
+    pub(crate) fn sent(&self, hash: u64) {
+        self.consumer().accepted.fetch_add(1, Ordering::Relaxed);
+        if let Some(sketch) = &self.counters.sketch {
+            sketch.increment(hash);
+        }
+    }
+
+    /// Takes back a message that was counted as sent but could not be queued.
+    pub(crate) fn unsent(&self, hash: u64) {
+        self.consumer().accepted.fetch_sub(1, Ordering::Relaxed);
+        if let Some(sketch) = &self.counters.sketch {
+            sketch.decrement(hash);
+        }
+    }
+
+    pub(crate) fn received(&self, count: usize) {
+        self.consumer()
+            .consumed
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Records the messages left behind by a dropped receiver.
+    pub(crate) fn dropped(&self, count: usize) {
+        self.consumer()
+            .dropped
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+}
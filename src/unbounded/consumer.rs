@@ -0,0 +1,194 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use tokio::{
+    sync::{TryAcquireError, mpsc::UnboundedReceiver as MpscReceiver},
+    time::Instant,
+};
+
+use crate::{
+    SendError,
+    compact::LastValues,
+    envelope::{Envelope, Payload, Slot},
+    hooks::Hooks,
+    keys::{KeyLimiter, KeyPermit},
+    latency::LatencyHistogram,
+    partition::PartitionTable,
+    queue::{Block, Queue},
+    totals::Tally,
+    util::Route,
+};
+
+/// Sending half of a single unbounded consumer's queue.
+pub(crate) struct Consumer<T> {
+    pub(crate) sender: Queue<T>,
+    pub(crate) keys: Option<Arc<KeyLimiter>>,
+    pub(crate) latency: Option<Arc<LatencyHistogram>>,
+    /// Number of messages sent to the consumer that it has not received yet.
+    pub(crate) depth: Arc<AtomicUsize>,
+    /// Committed offset of the consumer plus one, or zero if none was committed.
+    pub(crate) committed: Arc<AtomicU64>,
+    pub(crate) label: Option<Arc<str>>,
+    pub(crate) partitions: Option<Arc<PartitionTable>>,
+    pub(crate) tally: Tally,
+    pub(crate) hooks: Option<Arc<Hooks<T>>>,
+    pub(crate) last_values: Option<Arc<LastValues<T>>>,
+}
+
+impl<T> Consumer<T> {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        max_pending_per_key: Option<usize>,
+        block: Option<Block<T>>,
+        track_lag: bool,
+        label: Option<Arc<str>>,
+        partitions: Option<Arc<PartitionTable>>,
+        tally: Tally,
+        hooks: Option<Arc<Hooks<T>>>,
+        last_values: Option<Arc<LastValues<T>>>,
+    ) -> (Self, MpscReceiver<Envelope<T>>) {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        let consumer = Self {
+            sender: Queue::new(sender, block),
+            keys: max_pending_per_key.map(|limit| Arc::new(KeyLimiter::new(limit))),
+            latency: track_lag.then(|| Arc::new(LatencyHistogram::new())),
+            depth: Arc::new(AtomicUsize::new(0)),
+            committed: Arc::new(AtomicU64::new(0)),
+            label,
+            partitions,
+            tally,
+            hooks,
+            last_values,
+        };
+        (consumer, receiver)
+    }
+
+    /// Returns the number of messages sent to the consumer that it has not received yet.
+    pub(crate) fn depth(&self) -> usize {
+        self.depth.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn send(&self, message: T, route: Route) -> Result<(), SendError<T>> {
+        let key = match &self.keys {
+            Some(keys) => match keys.try_acquire(route.hash) {
+                Ok(permit) => Some(permit),
+                Err(TryAcquireError::NoPermits) => {
+                    return Err(SendError::KeyBackpressure(message, route.index));
+                }
+                Err(TryAcquireError::Closed) => {
+                    return Err(SendError::ChannelClosed(message, route.index));
+                }
+            },
+            None => None,
+        };
+
+        self.enqueue(message, route, key)
+    }
+
+    /// Sends a message, waiting while its ID has reached the per-ID limit.
+    pub(crate) async fn send_wait(&self, message: T, route: Route) -> Result<(), SendError<T>> {
+        let key = match &self.keys {
+            Some(keys) => match keys.acquire(route.hash).await {
+                Some(permit) => Some(permit),
+                None => return Err(SendError::ChannelClosed(message, route.index)),
+            },
+            None => None,
+        };
+
+        self.enqueue(message, route, key)
+    }
+
+    fn enqueue(
+        &self,
+        message: T,
+        route: Route,
+        key: Option<KeyPermit<'_>>,
+    ) -> Result<(), SendError<T>> {
+        // The message is only cached once it has been queued, so a failed send leaves the cache untouched.
+        let copy = self
+            .last_values
+            .as_ref()
+            .map(|last_values| last_values.copy(&message));
+        let envelope = self.seal(message, route);
+
+        match self.sender.send(envelope) {
+            Ok(()) => {
+                if let Some(key) = key {
+                    key.forget();
+                }
+                if let (Some(last_values), Some(copy)) = (&self.last_values, copy) {
+                    last_values.record(route.hash, route.index, copy);
+                }
+                Ok(())
+            }
+            Err(envelope) => {
+                self.depth.fetch_sub(1, Ordering::Relaxed);
+                self.tally.unsent(envelope.hash);
+                if let Some(partitions) = &self.partitions {
+                    partitions.received([envelope.hash]);
+                }
+                Err(SendError::ChannelClosed(
+                    envelope.into_message(),
+                    route.index,
+                ))
+            }
+        }
+    }
+
+    /// Takes the per-ID slot for a message without queueing it.
+    ///
+    /// The returned envelope owns the slot until it is received.
+    pub(crate) fn reserve(&self, message: T, route: Route) -> Result<Envelope<T>, SendError<T>> {
+        if let Some(keys) = &self.keys {
+            match keys.try_acquire(route.hash) {
+                Ok(permit) => permit.forget(),
+                Err(TryAcquireError::NoPermits) => {
+                    return Err(SendError::KeyBackpressure(message, route.index));
+                }
+                Err(TryAcquireError::Closed) => {
+                    return Err(SendError::ChannelClosed(message, route.index));
+                }
+            }
+        }
+
+        if let Some(last_values) = &self.last_values {
+            last_values.record(route.hash, route.index, last_values.copy(&message));
+        }
+        Ok(self.seal(message, route))
+    }
+
+    /// Wraps a message into an envelope, stamping it with the current time if the channel tracks lag.
+    fn seal(&self, message: T, route: Route) -> Envelope<T> {
+        self.depth.fetch_add(1, Ordering::Relaxed);
+        self.tally.sent(route.hash);
+        if let Some(partitions) = &self.partitions {
+            partitions.enqueued(route.hash);
+        }
+        Envelope {
+            payload: Payload::Message(message),
+            hash: route.hash,
+            slot: Slot::Unbounded,
+            enqueued: self.latency.as_ref().map(|_| Instant::now()),
+            producer: route.producer,
+        }
+    }
+}
+
+impl<T> Clone for Consumer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            keys: self.keys.clone(),
+            latency: self.latency.clone(),
+            depth: self.depth.clone(),
+            committed: self.committed.clone(),
+            label: self.label.clone(),
+            partitions: self.partitions.clone(),
+            tally: self.tally.clone(),
+            hooks: self.hooks.clone(),
+            last_values: self.last_values.clone(),
+        }
+    }
+}
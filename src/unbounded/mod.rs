@@ -1,14 +1,20 @@
+mod builder;
+mod consumer;
 mod receiver;
 mod sender;
 
-pub use self::{receiver::UnboundedReceiver, sender::UnboundedSender};
+pub use self::{
+    builder::UnboundedStickyChannelBuilder, receiver::UnboundedReceiver, sender::UnboundedSender,
+};
 
 use std::{
-    hash::{BuildHasher, Hash, RandomState},
+    hash::{BuildHasher, Hash},
     num::NonZeroUsize,
 };
 
-/// Creates a sticky channel with the specified number of consumers and default hasher ([`RandomState`]).
+use crate::{DefaultBuildHasher, SeededState};
+
+/// Creates a sticky channel with the specified number of consumers and default hasher ([`DefaultBuildHasher`]).
 ///
 /// This function returns a tuple containing a [`UnboundedSender`] and a vector of [`UnboundedReceiver`]s.
 ///
@@ -22,7 +28,7 @@ pub fn unbounded_sticky_channel<ID, T>(
 where
     ID: Hash,
 {
-    unbounded_sticky_channel_with_hasher(num_consumers, RandomState::new())
+    unbounded_sticky_channel_with_hasher(num_consumers, DefaultBuildHasher::default())
 }
 
 /// Creates a sticky channel with the specified number of consumers and a [`BuildHasher`].
@@ -41,18 +47,25 @@ where
     ID: Hash,
     S: BuildHasher,
 {
-    let mut receivers = Vec::with_capacity(num_consumers.get());
-    let mut sender = UnboundedSender {
-        consumers: Vec::with_capacity(num_consumers.get()),
-        build_hasher,
-        _phantom: std::marker::PhantomData,
-    };
-
-    for _ in 0..num_consumers.get() {
-        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
-        sender.consumers.push(tx);
-        receivers.push(UnboundedReceiver { receiver: rx });
-    }
+    UnboundedStickyChannelBuilder::new(num_consumers)
+        .hasher(build_hasher)
+        .build()
+}
 
-    (sender, receivers)
+/// Creates a sticky channel with the specified number of consumers, hashing IDs with a [`SeededState`] keyed by
+/// `seed`.
+///
+/// Channels created with the same seed and number of consumers route every ID to the same consumer, in this process or
+/// any other, which lets independent processes agree on the owner of an ID or keep routes stable across restarts.
+pub fn unbounded_sticky_channel_with_seed<ID, T>(
+    num_consumers: NonZeroUsize,
+    seed: u128,
+) -> (
+    UnboundedSender<ID, T, SeededState>,
+    Vec<UnboundedReceiver<T>>,
+)
+where
+    ID: Hash,
+{
+    unbounded_sticky_channel_with_hasher(num_consumers, SeededState::new(seed))
 }
@@ -1,10 +1,56 @@
-use tokio::sync::mpsc::UnboundedReceiver as MpscReceiver;
+use std::{
+    collections::VecDeque,
+    hash::{BuildHasher, Hash},
+    mem::MaybeUninit,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::{Context, Poll, ready},
+    time::Duration,
+};
 
-use crate::TryRecvError;
+use tokio::{
+    sync::mpsc::{UnboundedReceiver as MpscReceiver, error::TryRecvError as MpscTryRecvError},
+    time::{Instant, timeout_at},
+};
+
+use crate::{
+    Event, FilterReceiver, KeyReceiver, MessageMeta, TryRecvError, UnboundedSender,
+    compact::LastValues,
+    envelope::{Envelope, Payload, Slot, advance_watermark},
+    health::ConsumerHealth,
+    hooks::OnClosed,
+    keys::KeyLimiter,
+    latency::{LatencyHistogram, LatencyReport},
+    offset::Offsets,
+    partition::PartitionTable,
+    queue::Block,
+    subscribe::Subscriptions,
+    totals::{Reconciliation, Tally, Totals},
+    util::block_on,
+};
 
 /// Receive values from the associated [`UnboundedSender`](crate::UnboundedSender).
 pub struct UnboundedReceiver<T> {
-    pub(crate) receiver: MpscReceiver<T>,
+    pub(crate) receiver: MpscReceiver<Envelope<T>>,
+    pub(crate) keys: Option<Arc<KeyLimiter>>,
+    pub(crate) buffer: Vec<Envelope<T>>,
+    pub(crate) unpacked: VecDeque<Envelope<T>>,
+    pub(crate) block: Option<Arc<Block<T>>>,
+    pub(crate) watermark: Option<u64>,
+    pub(crate) finished: bool,
+    pub(crate) latency: Option<Arc<LatencyHistogram>>,
+    pub(crate) depth: Arc<AtomicUsize>,
+    pub(crate) offsets: Offsets,
+    /// Cache of the latest message per ID and the index of this receiver's consumer.
+    pub(crate) last_values: Option<(Arc<LastValues<T>>, usize)>,
+    pub(crate) label: Option<Arc<str>>,
+    pub(crate) partitions: Option<Arc<PartitionTable>>,
+    pub(crate) subscriptions: Subscriptions<T>,
+    pub(crate) tally: Tally,
+    pub(crate) on_closed: Option<OnClosed>,
+    pub(crate) health: Option<ConsumerHealth>,
 }
 
 impl<T> UnboundedReceiver<T> {
@@ -22,7 +68,132 @@ impl<T> UnboundedReceiver<T> {
     /// This method is cancel safe. If `recv` is used as the event in a `tokio::select!` statement and some other branch
     /// completes first, it is guaranteed that no messages were received on this channel.
     pub async fn recv(&mut self) -> Option<T> {
-        self.receiver.recv().await
+        loop {
+            let envelope = self.next_envelope().await?;
+            if let Some(Event::Data(message)) = self.open(envelope) {
+                return Some(message);
+            }
+        }
+    }
+
+    /// Receives messages until the channel is closed and empty or `timeout` has passed.
+    ///
+    /// Returns the messages received and whether the drain completed, i.e. `true` if the channel was closed and all of
+    /// its messages have been received before the deadline. Call [`close`](UnboundedReceiver::close) first to stop new messages from
+    /// being sent while draining.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is not cancel safe. Messages received before the future is dropped are lost.
+    pub async fn drain_with_timeout(&mut self, timeout: Duration) -> (Vec<T>, bool) {
+        let deadline = Instant::now() + timeout;
+        let mut messages = Vec::new();
+        loop {
+            match timeout_at(deadline, self.recv()).await {
+                Ok(Some(message)) => messages.push(message),
+                Ok(None) => return (messages, true),
+                Err(_) => return (messages, false),
+            }
+        }
+    }
+
+    /// Closes the receiver and returns the messages still buffered, without waiting.
+    ///
+    /// Meant for shutdown: the returned messages were sent but never processed, and can be persisted or logged. A
+    /// message whose send was in progress at the time of closing may still arrive afterwards; call
+    /// [`recv`](UnboundedReceiver::recv) until it returns `None` to be sure nothing is left.
+    pub fn take_remaining(&mut self) -> Vec<T> {
+        self.close();
+        let mut messages = Vec::new();
+        while let Ok(message) = self.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    /// Wraps the receiver in a [`FilterReceiver`] that skips the messages for which `predicate` returns `false`.
+    pub fn filter<F>(self, predicate: F) -> FilterReceiver<Self, F>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        FilterReceiver::new(self, predicate)
+    }
+
+    /// Receives the next message for this receiver together with the time it spent in the queue.
+    ///
+    /// The lag is only measured if the channel was built with
+    /// [`track_lag`](crate::UnboundedStickyChannelBuilder::track_lag); otherwise, and for injected messages such as ticks, it is
+    /// zero. See [`recv`](UnboundedReceiver::recv) for the meaning of the returned value.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe.
+    pub async fn recv_with_lag(&mut self) -> Option<(T, Duration)> {
+        loop {
+            let envelope = self.next_envelope().await?;
+            let lag = envelope
+                .enqueued
+                .map_or(Duration::ZERO, |enqueued| enqueued.elapsed());
+            if let Some(Event::Data(message)) = self.open(envelope) {
+                return Some((message, lag));
+            }
+        }
+    }
+
+    /// Receives the next message for this receiver together with its queueing information, see [`MessageMeta`].
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe.
+    pub async fn recv_with_meta(&mut self) -> Option<(T, MessageMeta)> {
+        loop {
+            let envelope = self.next_envelope().await?;
+            let meta = MessageMeta {
+                enqueued_at: envelope.enqueued,
+                producer: envelope.producer,
+                attempt: 1,
+            };
+            if let Some(Event::Data(message)) = self.open(envelope) {
+                return Some((message, meta));
+            }
+        }
+    }
+
+    /// Receives the next message for this receiver together with its offset.
+    ///
+    /// Every message delivered by this receiver, through any receive method, gets the next offset of its consumer,
+    /// starting at zero, so offsets are gap-free and in the order the messages were received. Processed messages can be
+    /// recorded with [`commit`](UnboundedReceiver::commit).
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe.
+    pub async fn recv_with_offset(&mut self) -> Option<(u64, T)> {
+        loop {
+            let offset = self.offsets.next();
+            let envelope = self.next_envelope().await?;
+            if let Some(Event::Data(message)) = self.open(envelope) {
+                return Some((offset, message));
+            }
+        }
+    }
+
+    /// Receives the next message or marker for this receiver.
+    ///
+    /// [`recv`](UnboundedReceiver::recv) acknowledges markers such as barriers and watermarks without returning them. This method
+    /// returns them as events, in the position they were sent at relative to the messages. Watermarks that do not
+    /// advance the receiver's [`watermark`](UnboundedReceiver::watermark) are skipped.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe.
+    pub async fn recv_event(&mut self) -> Option<Event<T>> {
+        loop {
+            let envelope = self.next_envelope().await?;
+            if let Some(event) = self.open(envelope) {
+                return Some(event);
+            }
+        }
     }
 
     /// Receives the next messages for this receiver and extends `buffer`.
@@ -45,7 +216,181 @@ impl<T> UnboundedReceiver<T> {
     /// This method is cancel safe. If `recv_many` is used as the event in a `tokio::select!` statement and some other
     /// branch completes first, it is guaranteed that no messages were received on this channel.
     pub async fn recv_many(&mut self, buffer: &mut Vec<T>, limit: usize) -> usize {
-        self.receiver.recv_many(buffer, limit).await
+        self.recv_many_with(limit, |message| buffer.push(message))
+            .await
+    }
+
+    /// Blocking variant of [`recv_many`](UnboundedReceiver::recv_many), for dedicated threads outside of the async runtime.
+    ///
+    /// Waits up to `timeout` for at least one message, or without a time limit if `timeout` is `None`, and then
+    /// extends `buffer` by up to `limit` messages. Returns `None` if the timeout passed before any message arrived, and
+    /// otherwise the number of messages added, which is `0` only if `limit` is zero or the channel is closed and empty.
+    ///
+    /// The thread is parked while waiting, so the receiver can be moved to an OS thread doing CPU-heavy work that pulls
+    /// batches off its consumer without entering the runtime for every message.
+    ///
+    /// Do not call this method from asynchronous code: it blocks the thread it is called on, including runtime worker
+    /// threads. Call it from a thread of its own or from within [`tokio::task::spawn_blocking`].
+    pub fn blocking_recv_many(
+        &mut self,
+        buffer: &mut Vec<T>,
+        limit: usize,
+        timeout: Option<Duration>,
+    ) -> Option<usize> {
+        let deadline = timeout.map(|timeout| std::time::Instant::now() + timeout);
+        block_on(self.recv_many(buffer, limit), deadline)
+    }
+
+    /// Receives the next messages for this receiver into the uninitialized slots of `buffer`, without allocating.
+    ///
+    /// This method initializes no more than `buffer.len()` slots, starting at the front, and returns their number.
+    /// The caller owns the initialized messages, e.g. by reading them with [`MaybeUninit::assume_init_read`];
+    /// messages that are never read are leaked rather than dropped. Apart from that it behaves like
+    /// [`recv_many`](UnboundedReceiver::recv_many) with a `limit` of `buffer.len()`: it returns `0` right away for an empty
+    /// buffer, and otherwise only once the channel is closed and empty.
+    ///
+    /// To fill a `Vec` or a `SmallVec` without growing it, pass its `spare_capacity_mut()` and then extend its
+    /// length by the returned count with `set_len`.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. If `recv_many_into` is used as the event in a `tokio::select!` statement and some
+    /// other branch completes first, it is guaranteed that no messages were received on this channel.
+    pub async fn recv_many_into(&mut self, buffer: &mut [MaybeUninit<T>]) -> usize {
+        let mut slots = buffer.iter_mut();
+        self.recv_many_with(slots.len(), |message| {
+            slots
+                .next()
+                .expect("no more messages than slots are received")
+                .write(message);
+        })
+        .await
+    }
+
+    /// Receives up to `limit` messages, handing each of them to `push`, and returns their number.
+    async fn recv_many_with(&mut self, limit: usize, mut push: impl FnMut(T)) -> usize {
+        loop {
+            let received = if self.unpacked.is_empty() {
+                self.receiver.recv_many(&mut self.buffer, limit).await
+            } else {
+                let received = limit.min(self.unpacked.len());
+                self.buffer.extend(self.unpacked.drain(..received));
+                // Top up with whatever is already queued behind the unpacked envelopes.
+                while self.buffer.len() < limit {
+                    match self.receiver.try_recv() {
+                        Ok(envelope) => self.buffer.push(envelope),
+                        Err(_) => break,
+                    }
+                }
+                self.buffer.len()
+            };
+            if received == 0 {
+                if self.unpack_unsignalled() {
+                    continue;
+                }
+                return 0;
+            }
+            self.unpack_buffer(limit);
+
+            if let Some(keys) = &self.keys {
+                keys.release(
+                    self.buffer
+                        .iter()
+                        .filter(|envelope| envelope.slot != Slot::Injected)
+                        .map(|envelope| envelope.hash),
+                );
+            }
+            if let Some(partitions) = &self.partitions {
+                partitions.received(
+                    self.buffer
+                        .iter()
+                        .filter(|envelope| envelope.slot != Slot::Injected)
+                        .map(|envelope| envelope.hash),
+                );
+            }
+
+            let received = self
+                .buffer
+                .iter()
+                .filter(|envelope| envelope.slot != Slot::Injected)
+                .count();
+            self.depth.fetch_sub(received, Ordering::Relaxed);
+            self.tally.received(received);
+
+            let mut count = 0;
+            let mut close = false;
+            for envelope in self.buffer.drain(..) {
+                if let Some(latency) = &self.latency {
+                    latency.record(&envelope);
+                }
+                match envelope.payload {
+                    Payload::Message(message) => {
+                        if let Some(message) = self.subscriptions.divert(envelope.hash, message) {
+                            push(message);
+                            count += 1;
+                        }
+                    }
+                    Payload::Barrier(marker) => {
+                        marker.arrive();
+                    }
+                    Payload::Watermark(timestamp) => {
+                        advance_watermark(&mut self.watermark, timestamp);
+                    }
+                    Payload::Finish => self.finished = true,
+                    Payload::Close => close = true,
+                    Payload::Batch(_) | Payload::Block(_) => {
+                        unreachable!("blocks are unpacked before processing")
+                    }
+                }
+            }
+
+            self.offsets.delivered(count);
+            if close {
+                self.close();
+            }
+
+            // Only markers were received, which are not counted.
+            if count > 0 {
+                return count;
+            }
+        }
+    }
+
+    /// Polls to receive the next message for this receiver.
+    ///
+    /// This method returns:
+    ///
+    /// - `Poll::Pending` if no messages are available but the channel is not closed, or if a spurious failure happens.
+    /// - `Poll::Ready(Some(message))` if a message is available.
+    /// - `Poll::Ready(None)` if the channel has been closed and all messages sent before it was closed have been
+    ///   received.
+    ///
+    /// When the method returns `Poll::Pending`, the `Waker` in the provided `Context` is scheduled to receive a
+    /// wakeup when a message is sent or when the channel is closed. Only the `Waker` from the most recent call is
+    /// scheduled.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        loop {
+            let Some(envelope) = ready!(self.poll_next_envelope(cx)) else {
+                return Poll::Ready(None);
+            };
+            if let Some(Event::Data(message)) = self.open(envelope) {
+                return Poll::Ready(Some(message));
+            }
+        }
+    }
+
+    /// Polls to receive the next message or marker for this receiver.
+    ///
+    /// See [`recv_event`](UnboundedReceiver::recv_event) and [`poll_recv`](UnboundedReceiver::poll_recv).
+    pub fn poll_recv_event(&mut self, cx: &mut Context<'_>) -> Poll<Option<Event<T>>> {
+        loop {
+            let Some(envelope) = ready!(self.poll_next_envelope(cx)) else {
+                return Poll::Ready(None);
+            };
+            if let Some(event) = self.open(envelope) {
+                return Poll::Ready(Some(event));
+            }
+        }
     }
 
     /// Tries to receive the next message for this receiver.
@@ -56,10 +401,136 @@ impl<T> UnboundedReceiver<T> {
     /// This method returns the [`Disconnected`](TryRecvError::Disconnected) error if the channel is currently empty,
     /// and there are no outstanding [`UnboundedSender`](crate::UnboundedSender).
     pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
-        self.receiver.try_recv().map_err(|err| match err {
-            tokio::sync::mpsc::error::TryRecvError::Empty => TryRecvError::Empty,
-            tokio::sync::mpsc::error::TryRecvError::Disconnected => TryRecvError::Disconnected,
-        })
+        loop {
+            let envelope = self.try_next_envelope()?;
+            if let Some(Event::Data(message)) = self.open(envelope) {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Tries to receive the next message or marker for this receiver.
+    ///
+    /// See [`recv_event`](UnboundedReceiver::recv_event) and [`try_recv`](UnboundedReceiver::try_recv).
+    pub fn try_recv_event(&mut self) -> Result<Event<T>, TryRecvError> {
+        loop {
+            let envelope = self.try_next_envelope()?;
+            if let Some(event) = self.open(envelope) {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Returns the offset the next message delivered by this receiver will get, i.e. the number of messages delivered
+    /// so far.
+    pub fn next_offset(&self) -> u64 {
+        self.offsets.next()
+    }
+
+    /// Records that the messages of this consumer up to and including `offset` have been processed.
+    ///
+    /// Committed offsets only move forward; committing an offset below the committed one has no effect. The committed
+    /// offsets of all consumers can be queried through the sender with [`committed_offsets`](crate::UnboundedSender::committed_offsets).
+    ///
+    /// # Panics
+    ///
+    /// Panics if no message with `offset` has been delivered yet.
+    pub fn commit(&self, offset: u64) {
+        self.offsets.commit(offset);
+    }
+
+    /// Returns the highest offset committed with [`commit`](UnboundedReceiver::commit), if any.
+    pub fn committed(&self) -> Option<u64> {
+        self.offsets.committed()
+    }
+
+    /// Returns the latest message of every ID whose last message was sent to this receiver's consumer, in no
+    /// particular order.
+    ///
+    /// Returns `None` unless the channel was built with [`last_values`](crate::UnboundedStickyChannelBuilder::last_values).
+    /// The snapshot includes messages that are still queued for this receiver, so they may be received again
+    /// afterwards.
+    pub fn snapshot(&self) -> Option<Vec<T>> {
+        let (last_values, index) = self.last_values.as_ref()?;
+        Some(last_values.snapshot(*index))
+    }
+
+    /// Returns the latest watermark received by this receiver, if any.
+    ///
+    /// The watermark is updated by every receive method, including the ones that do not return markers.
+    pub fn watermark(&self) -> Option<u64> {
+        self.watermark
+    }
+
+    /// Returns a snapshot of the queueing delays of the messages received by this receiver.
+    ///
+    /// Returns `None` unless the channel was built with [`track_lag`](crate::UnboundedStickyChannelBuilder::track_lag).
+    pub fn latency_report(&self) -> Option<LatencyReport> {
+        self.latency.as_ref().map(|latency| latency.report())
+    }
+
+    /// Returns the message counts of the whole channel, see [`Totals`].
+    pub fn totals(&self) -> Totals {
+        self.tally.channel().totals()
+    }
+
+    /// Returns how the messages sent to every consumer of the channel were accounted for, see [`Reconciliation`].
+    pub fn reconcile(&self) -> Vec<Reconciliation> {
+        self.tally.channel().reconcile()
+    }
+
+    /// Returns `true` once this receiver has received the sentinel sent with [`finish`](crate::UnboundedSender::finish).
+    ///
+    /// Like the watermark, this is updated by every receive method. Messages sent after the sentinel, for example by
+    /// other sender clones, can still be received.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Returns the label of this receiver's consumer, if it was given one when the channel was built.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Returns the number of [`UnboundedSender`](crate::UnboundedSender) handles that can still send messages to this receiver.
+    ///
+    /// Every clone of a [`UnboundedSender`](crate::UnboundedSender) holds a handle to each receiver of the channel, so this is the
+    /// number of live sender clones. Once it drops to zero and the buffer is drained, no more messages can arrive.
+    pub fn sender_strong_count(&self) -> usize {
+        self.receiver.sender_strong_count()
+    }
+
+    /// Returns the number of weak sender handles associated with this receiver.
+    ///
+    /// Weak handles do not keep the channel open.
+    pub fn sender_weak_count(&self) -> usize {
+        self.receiver.sender_weak_count()
+    }
+
+    /// Subscribes to the messages of a single ID, which are then received by the returned [`KeyReceiver`] instead of
+    /// this receiver.
+    ///
+    /// Receivers do not know how IDs are hashed, so the subscription is made through `sender`, which has to belong to
+    /// the same channel. Returns `None` if `id` is not routed to this receiver. Messages of other IDs are still
+    /// received as before, and messages of `id` are diverted as this receiver receives them, so it has to keep
+    /// receiving. IDs are matched by their hash, so IDs with the same hash share a subscription. Subscribing to an ID
+    /// again replaces the earlier subscription, and a subscription stops receiving messages once its ID is moved to
+    /// another receiver by reassigning partitions.
+    pub fn subscribe<ID, S>(
+        &mut self,
+        sender: &UnboundedSender<ID, T, S>,
+        id: &ID,
+    ) -> Option<KeyReceiver<T>>
+    where
+        ID: Hash,
+        S: BuildHasher,
+    {
+        let route = sender.route_ref(id).ok()?;
+        let consumer = sender.consumers.get(route.index)?;
+        if !Arc::ptr_eq(&consumer.depth, &self.depth) {
+            return None;
+        }
+        Some(self.subscriptions.subscribe(route.hash))
     }
 
     /// Closes the receiver without dropping it.
@@ -71,5 +542,190 @@ impl<T> UnboundedReceiver<T> {
     /// returned.
     pub fn close(&mut self) {
         self.receiver.close();
+        self.close_limits();
+    }
+
+    /// Gives back the per-ID pending slot of a received envelope and acknowledges markers.
+    ///
+    /// Returns `None` for watermarks that do not advance the receiver's watermark and for messages diverted to a
+    /// [`KeyReceiver`].
+    fn open(&mut self, envelope: Envelope<T>) -> Option<Event<T>> {
+        if envelope.slot != Slot::Injected {
+            self.depth.fetch_sub(1, Ordering::Relaxed);
+            self.tally.received(1);
+            if let Some(keys) = &self.keys {
+                keys.release([envelope.hash]);
+            }
+            if let Some(partitions) = &self.partitions {
+                partitions.received([envelope.hash]);
+            }
+        }
+        if let Some(latency) = &self.latency {
+            latency.record(&envelope);
+        }
+        match envelope.payload {
+            Payload::Message(message) => {
+                let message = self.subscriptions.divert(envelope.hash, message)?;
+                self.offsets.delivered(1);
+                Some(Event::Data(message))
+            }
+            Payload::Barrier(marker) => Some(Event::Barrier(marker.arrive())),
+            Payload::Watermark(timestamp) => advance_watermark(&mut self.watermark, timestamp)
+                .then_some(Event::Watermark(timestamp)),
+            Payload::Finish => {
+                self.finished = true;
+                Some(Event::Finished)
+            }
+            Payload::Close => {
+                self.close();
+                None
+            }
+            Payload::Batch(batch) => {
+                self.unpack_front(batch);
+                None
+            }
+            Payload::Block(generation) => {
+                if let Some(block) = &self.block {
+                    let envelopes = block.take(generation);
+                    self.unpack_front(envelopes);
+                }
+                None
+            }
+        }
+    }
+
+    /// Takes the next envelope, preferring the ones unpacked from an earlier batch.
+    async fn next_envelope(&mut self) -> Option<Envelope<T>> {
+        match self.unpacked.pop_front() {
+            Some(envelope) => Some(envelope),
+            None => match self.receiver.recv().await {
+                Some(envelope) => Some(envelope),
+                None => self.next_unsignalled(),
+            },
+        }
+    }
+
+    fn poll_next_envelope(&mut self, cx: &mut Context<'_>) -> Poll<Option<Envelope<T>>> {
+        match self.unpacked.pop_front() {
+            Some(envelope) => Poll::Ready(Some(envelope)),
+            None => match ready!(self.receiver.poll_recv(cx)) {
+                Some(envelope) => Poll::Ready(Some(envelope)),
+                None => Poll::Ready(self.next_unsignalled()),
+            },
+        }
+    }
+
+    fn try_next_envelope(&mut self) -> Result<Envelope<T>, TryRecvError> {
+        match self.unpacked.pop_front() {
+            Some(envelope) => Ok(envelope),
+            None => match self.receiver.try_recv() {
+                Ok(envelope) => Ok(envelope),
+                Err(MpscTryRecvError::Disconnected) => {
+                    self.next_unsignalled().ok_or(TryRecvError::Disconnected)
+                }
+                Err(err) => Err(err.into()),
+            },
+        }
+    }
+
+    /// Takes the first envelope of a coalesced block that was never signalled, once the channel is closed and empty.
+    fn next_unsignalled(&mut self) -> Option<Envelope<T>> {
+        self.unpack_unsignalled();
+        self.unpacked.pop_front()
+    }
+
+    /// Queues the envelopes of a coalesced block that was never signalled, returning whether there were any.
+    fn unpack_unsignalled(&mut self) -> bool {
+        let Some(block) = &self.block else {
+            return false;
+        };
+        let envelopes = block.take_all();
+        let unpacked = !envelopes.is_empty();
+        self.unpack_front(envelopes);
+        unpacked
+    }
+
+    /// Queues unpacked envelopes ahead of everything else, so that they keep their position.
+    fn unpack_front(&mut self, envelopes: Vec<Envelope<T>>) {
+        for envelope in envelopes.into_iter().rev() {
+            self.unpacked.push_front(envelope);
+        }
+    }
+
+    /// Replaces batches and blocks in the scratch buffer with their envelopes, keeping at most `limit` envelopes in it.
+    fn unpack_buffer(&mut self, limit: usize) {
+        if !self
+            .buffer
+            .iter()
+            .any(|envelope| matches!(envelope.payload, Payload::Batch(_) | Payload::Block(_)))
+        {
+            return;
+        }
+
+        let mut pending = VecDeque::from(std::mem::take(&mut self.buffer));
+        while let Some(envelope) = pending.pop_front() {
+            let envelopes = match envelope.payload {
+                Payload::Batch(batch) => batch,
+                Payload::Block(generation) => match &self.block {
+                    Some(block) => block.take(generation),
+                    None => Vec::new(),
+                },
+                payload => {
+                    self.buffer.push(Envelope {
+                        payload,
+                        ..envelope
+                    });
+                    continue;
+                }
+            };
+            for envelope in envelopes.into_iter().rev() {
+                pending.push_front(envelope);
+            }
+        }
+
+        // Envelopes beyond the limit come before anything that is still queued.
+        if self.buffer.len() > limit {
+            for envelope in self.buffer.drain(limit..).rev() {
+                self.unpacked.push_front(envelope);
+            }
+        }
+    }
+
+    /// Makes senders observe the closed channel when checking per-ID limits.
+    fn close_limits(&self) {
+        if let Some(keys) = &self.keys {
+            keys.close();
+        }
+        if let Some(health) = &self.health {
+            health.closed();
+        }
+        if let Some(on_closed) = &self.on_closed {
+            on_closed.call();
+        }
+    }
+}
+
+impl<T> UnboundedReceiver<Arc<T>>
+where
+    T: Clone,
+{
+    /// Receives the next shared message, unwrapping it from its [`Arc`].
+    ///
+    /// The payload is only cloned if another consumer still holds a reference to it, so the last consumer of a message
+    /// sent with [`send_shared`](crate::UnboundedSender::send_shared) takes it without copying.
+    pub async fn recv_owned(&mut self) -> Option<T> {
+        self.recv().await.map(Arc::unwrap_or_clone)
+    }
+}
+
+impl<T> Drop for UnboundedReceiver<T> {
+    fn drop(&mut self) {
+        self.close_limits();
+        // Closing first keeps messages from being queued after the block was cleared and they were counted.
+        self.receiver.close();
+        if let Some(block) = &self.block {
+            block.clear();
+        }
+        self.tally.dropped(self.depth.load(Ordering::Relaxed));
     }
 }
@@ -1,13 +1,38 @@
-use std::hash::{BuildHasher, Hash, RandomState};
+use std::{
+    hash::{BuildHasher, Hash},
+    num::TryFromIntError,
+    sync::Arc,
+};
 
-use tokio::sync::mpsc::UnboundedSender as MpscSender;
+use tokio::sync::watch;
 
-use crate::{SendError, util::compute_route_id};
+use crate::{
+    Barrier, BarrierId, ChannelAdmin, DefaultBuildHasher, DepthSnapshot, PreHashed, RoutedMessage,
+    SendError, StickyRoute,
+    channel_admin::ConsumerHandles,
+    envelope::{Payload, inject},
+    health::{ChannelHealth, Health},
+    latency::LatencyReport,
+    offset::committed,
+    partition::PartitionTable,
+    routing::hash_id,
+    totals::{Reconciliation, Totals},
+    util::{Route, compute_route, distinct_routes, hash_route, next_producer},
+    validate::Validator,
+};
+
+use super::consumer::Consumer;
 
 /// Send values to the associated [`UnboundedReceiver`](crate::UnboundedReceiver).
-pub struct UnboundedSender<ID, T, S = RandomState> {
-    pub(crate) consumers: Vec<MpscSender<T>>,
+pub struct UnboundedSender<ID, T, S = DefaultBuildHasher> {
+    pub(crate) consumers: Vec<Consumer<T>>,
     pub(crate) build_hasher: S,
+    pub(crate) partitions: Option<Arc<PartitionTable>>,
+    pub(crate) validator: Option<Validator<ID, T>>,
+    pub(crate) health: Option<Arc<Health>>,
+    /// Whether the partition admin is reserved to the [`ChannelAdmin`](crate::ChannelAdmin) of the channel.
+    /// Producer ID of this sender clone, stamped on the messages it sends.
+    pub(crate) producer: u64,
     pub(crate) _phantom: std::marker::PhantomData<ID>,
 }
 
@@ -25,16 +50,378 @@ where
     /// the [`UnboundedReceiver`](crate::UnboundedReceiver) having been dropped, this function returns an error. The error includes the
     /// value passed to `send`.
     pub fn send(&self, id: ID, message: T) -> Result<(), SendError<T>> {
-        match compute_route_id(id, self.consumers.len(), &self.build_hasher) {
-            Ok(route_id) => match self.consumers.get(route_id) {
-                Some(sender) => sender
-                    .send(message)
-                    .map_err(|err| SendError::ChannelClosed(err.0)),
-                None => Err(SendError::NoConsumer(message)),
-            },
+        let message = self.validate(&id, message)?;
+        match self.route(id) {
+            Ok(route) => self.send_route(message, route),
+            Err(_) => Err(SendError::FailedToComputeRouteID(message)),
+        }
+    }
+
+    /// Sends a message to the consumer identified by `id`, waiting while the ID has reached its per-ID limit.
+    ///
+    /// Channels built with [`max_pending_per_key`](crate::UnboundedStickyChannelBuilder::max_pending_per_key) reject
+    /// messages of an ID with too many pending messages in [`send`](UnboundedSender::send). This method waits for the
+    /// ID's consumer to receive some of them instead, so only producers of the offending ID are held up while the
+    /// consumer's queue as a whole stays unbounded. Without a per-ID limit, it never waits.
+    ///
+    /// If the receive half of the channel is closed, this function returns an error. The error includes the value
+    /// passed to `send_wait`.
+    pub async fn send_wait(&self, id: ID, message: T) -> Result<(), SendError<T>> {
+        let message = self.validate(&id, message)?;
+        let route = match self.route(id) {
+            Ok(route) => route,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+        match self.consumers.get(route.index) {
+            Some(consumer) => consumer.send_wait(message, route).await,
+            None => Err(SendError::NoConsumer(message, route.index)),
+        }
+    }
+
+    /// Validates a message and decides its route without sending it, see [`RoutedMessage`].
+    ///
+    /// Fails like [`send`](UnboundedSender::send) if the message is rejected or its route cannot be computed.
+    pub fn prepare(&self, id: &ID, message: T) -> Result<RoutedMessage<T>, SendError<T>> {
+        let message = self.validate(id, message)?;
+        match self.route_ref(id) {
+            Ok(route) => Ok(RoutedMessage::new(message, route)),
+            Err(_) => Err(SendError::FailedToComputeRouteID(message)),
+        }
+    }
+
+    /// Sends a message prepared with [`prepare`](UnboundedSender::prepare) to its consumer, like
+    /// [`send`](UnboundedSender::send).
+    pub fn send_routed(&self, message: RoutedMessage<T>) -> Result<(), SendError<T>> {
+        let (message, route) = message.into_parts();
+        self.send_route(message, route)
+    }
+
+    /// Hashes `id` with the channel's hasher once, for sending many messages with it through
+    /// [`send_prehashed`](UnboundedSender::send_prehashed).
+    pub fn prehash(&self, id: ID) -> PreHashed<ID> {
+        PreHashed::new(id, &self.build_hasher)
+    }
+
+    /// Sends a message with an ID hashed by [`prehash`](UnboundedSender::prehash), like
+    /// [`send`](UnboundedSender::send). The ID is not hashed again.
+    pub fn send_prehashed(&self, id: &PreHashed<ID>, message: T) -> Result<(), SendError<T>> {
+        let message = self.validate(id, message)?;
+        match self.route_hash(id.hash_value()) {
+            Ok(route) => self.send_route(message, route),
             Err(_) => Err(SendError::FailedToComputeRouteID(message)),
         }
     }
+
+    /// Returns the index of the receiver that messages with the given ID are delivered to.
+    ///
+    /// Returns `None` if the route cannot be computed, in which case sends with this ID fail with
+    /// [`SendError::FailedToComputeRouteID`].
+    pub fn route_of(&self, id: ID) -> Option<usize> {
+        self.route(id).ok().map(|route| route.index)
+    }
+
+    /// Sends a message to the consumer with the given label.
+    ///
+    /// The message bypasses routing, so it is not necessarily received by the consumer that other messages of `id` go
+    /// to. `id` is still used for the per-ID limit. Returns [`SendError::UnknownLabel`] if no consumer has the label.
+    pub fn send_to(&self, label: &str, id: ID, message: T) -> Result<(), SendError<T>> {
+        let message = self.validate(&id, message)?;
+        match self.route_to(label, id) {
+            Some(route) => self.send_route(message, route),
+            None => Err(SendError::UnknownLabel(message)),
+        }
+    }
+
+    /// Returns the label of the consumer at `index`, if it was given one when the channel was built.
+    pub fn label(&self, index: usize) -> Option<&str> {
+        self.consumers.get(index)?.label.as_deref()
+    }
+
+    /// Returns the label of the consumer that messages with the given ID are delivered to, if it has one.
+    pub fn label_of(&self, id: ID) -> Option<&str> {
+        self.label(self.route_of(id)?)
+    }
+
+    /// Returns the index of the consumer with the given label.
+    pub fn index_of(&self, label: &str) -> Option<usize> {
+        self.consumers
+            .iter()
+            .position(|consumer| consumer.label.as_deref() == Some(label))
+    }
+
+    /// Routes `id` to the consumer with the given label instead of the one its hash selects.
+    fn route_to(&self, label: &str, id: ID) -> Option<Route> {
+        let index = self.index_of(label)?;
+        let route = self.route(id).ok()?;
+        Some(Route { index, ..route })
+    }
+
+    /// Runs the channel's validator, if any, on a message about to be sent with the given ID.
+    pub(crate) fn validate(&self, id: &ID, message: T) -> Result<T, SendError<T>> {
+        match &self.validator {
+            Some(validator) => validator.check(id, message),
+            None => Ok(message),
+        }
+    }
+
+    /// Computes where messages with the given ID are delivered.
+    pub(crate) fn route(&self, id: ID) -> Result<Route, TryFromIntError> {
+        self.route_ref(&id)
+    }
+
+    /// Computes where messages with the given ID are delivered, without taking the ID.
+    pub(crate) fn route_ref(&self, id: &ID) -> Result<Route, TryFromIntError> {
+        self.route_hash(hash_id(id, &self.build_hasher))
+    }
+
+    /// Computes where messages with an ID of the given hash are delivered.
+    pub(crate) fn route_hash(&self, hash: u64) -> Result<Route, TryFromIntError> {
+        let route = Route {
+            producer: self.producer,
+            ..hash_route(hash, self.consumers.len())?
+        };
+        Ok(match &self.partitions {
+            Some(partitions) => partitions.route(route),
+            None => route,
+        })
+    }
+
+    /// Returns the partition messages with the given ID are routed through, for channels built with partitions.
+    pub fn partition_of(&self, id: ID) -> Option<usize> {
+        let partitions = self.partitions.as_ref()?;
+        let route = compute_route(id, self.consumers.len(), &self.build_hasher).ok()?;
+        Some(partitions.partition(route.hash))
+    }
+
+    /// Returns the administrative handle of the channel.
+    pub(crate) fn channel_admin(&self) -> ChannelAdmin<T> {
+        ChannelAdmin::new(
+            self.consumers.iter().map(|consumer| ConsumerHandles {
+                queue: consumer.sender.downgrade(),
+                depth: consumer.depth.clone(),
+                latency: consumer.latency.clone(),
+                slots: None,
+            }),
+            self.consumers[0].tally.shared(),
+            self.partitions.clone(),
+            self.health.clone(),
+        )
+    }
+
+    /// Returns the estimated number of messages sent with the given ID, for channels built with
+    /// [`UnboundedStickyChannelBuilder::track_frequencies`](crate::UnboundedStickyChannelBuilder::track_frequencies).
+    ///
+    /// Counts the messages queued to any consumer since the channel was built, including those received since. The
+    /// estimate may be higher than the actual count, but never lower.
+    pub fn approx_count(&self, id: &ID) -> Option<u64> {
+        let route = self.route_ref(id).ok()?;
+        self.consumers[0].tally.channel().approx_count(route.hash)
+    }
+
+    /// Returns a clone of the latest message sent with the given ID, for channels built with
+    /// [`last_values`](crate::UnboundedStickyChannelBuilder::last_values).
+    pub fn last_value(&self, id: &ID) -> Option<T> {
+        let route = self.route_ref(id).ok()?;
+        self.consumers[0].last_values.as_ref()?.get(route.hash)
+    }
+
+    /// Removes the latest message sent with the given ID from the cache of a channel built with
+    /// [`last_values`](crate::UnboundedStickyChannelBuilder::last_values), and returns it.
+    ///
+    /// Like a tombstone in a compacted log, this keeps the cache from growing with IDs that are no longer used.
+    pub fn clear_last_value(&self, id: &ID) -> Option<T> {
+        let route = self.route_ref(id).ok()?;
+        self.consumers[0].last_values.as_ref()?.remove(route.hash)
+    }
+
+    /// Returns the number of IDs in the cache of a channel built with
+    /// [`last_values`](crate::UnboundedStickyChannelBuilder::last_values).
+    ///
+    /// IDs that went idle for longer than the [`last_value_ttl`](crate::UnboundedStickyChannelBuilder::last_value_ttl)
+    /// are counted until they are swept out.
+    pub fn last_value_count(&self) -> Option<usize> {
+        Some(self.consumers[0].last_values.as_ref()?.len())
+    }
+
+    /// Returns the number of IDs evicted from the cache of a channel built with
+    /// [`last_values`](crate::UnboundedStickyChannelBuilder::last_values) because of its [`max_tracked_keys`](crate::UnboundedStickyChannelBuilder::max_tracked_keys).
+    pub fn last_value_evictions(&self) -> Option<u64> {
+        Some(self.consumers[0].last_values.as_ref()?.evictions())
+    }
+}
+
+impl<ID, T, S> UnboundedSender<ID, T, S>
+where
+    ID: Hash,
+    T: StickyRoute<Key = ID>,
+    S: BuildHasher,
+{
+    /// Attempts to send a message to the consumer identified by its [`route_key`](StickyRoute::route_key) without
+    /// blocking.
+    ///
+    /// This behaves exactly like [`send`](UnboundedSender::send) with the ID extracted from the message.
+    pub fn send_by_key(&self, message: T) -> Result<(), SendError<T>> {
+        self.send(message.route_key(), message)
+    }
+}
+
+impl<ID, T, S> UnboundedSender<ID, Arc<T>, S>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    /// Sends a shared message to the consumers of all `ids`, cloning only the [`Arc`].
+    ///
+    /// The message is delivered once to every consumer that at least one of the IDs is routed to, even if several IDs
+    /// share a consumer. Consumers are served in the order their first ID appears in `ids`.
+    ///
+    /// If sending to a consumer fails, the error for that consumer is returned and the remaining consumers are
+    /// skipped. Consumers served before it keep the message.
+    pub fn send_shared<I>(&self, ids: I, message: Arc<T>) -> Result<(), SendError<Arc<T>>>
+    where
+        I: IntoIterator<Item = ID>,
+    {
+        let routes = match distinct_routes(
+            ids,
+            self.consumers.len(),
+            &self.build_hasher,
+            self.partitions.as_deref(),
+            self.producer,
+        ) {
+            Ok(routes) => routes,
+            Err(_) => return Err(SendError::FailedToComputeRouteID(message)),
+        };
+
+        for route in routes {
+            self.send_route(message.clone(), route)?;
+        }
+        Ok(())
+    }
+}
+
+impl<ID, T, S> UnboundedSender<ID, T, S> {
+    /// Sends a barrier marker to every consumer.
+    ///
+    /// Each marker is queued behind all messages sent through this sender (or its clones) before the call, and ahead
+    /// of all messages sent after it. The returned [`Barrier`] completes once every consumer has received its marker,
+    /// at which point all of those earlier messages have been received too. Consumers observe the marker as
+    /// [`Event::Barrier`](crate::Event::Barrier) from [`recv_event`](crate::UnboundedReceiver::recv_event); other receive methods
+    /// acknowledge it silently.
+    pub fn send_barrier(&self, id: BarrierId) -> Barrier {
+        let barrier = Barrier::new(id, self.consumers.len());
+        for consumer in &self.consumers {
+            // A marker that cannot be queued is dropped, which marks the barrier as abandoned.
+            inject(&consumer.sender, Payload::Barrier(barrier.marker()));
+        }
+        barrier
+    }
+
+    /// Advances the event-time watermark of every consumer to `timestamp`.
+    ///
+    /// The watermark is queued behind all messages sent before the call, so a consumer that observes it as
+    /// [`Event::Watermark`](crate::Event::Watermark) from [`recv_event`](crate::UnboundedReceiver::recv_event) has received all of
+    /// those messages. Senders promise not to send messages with an event time at or before `timestamp` afterwards,
+    /// which lets consumers close windows. Each receiver skips watermarks that do not advance its current one.
+    pub fn advance_watermark(&self, timestamp: u64) {
+        for consumer in &self.consumers {
+            inject(&consumer.sender, Payload::Watermark(timestamp));
+        }
+    }
+
+    /// Returns a snapshot of the queueing delays of every consumer, indexed like the receivers.
+    ///
+    /// Returns `None` unless the channel was built with [`track_lag`](crate::UnboundedStickyChannelBuilder::track_lag).
+    pub fn latency_report(&self) -> Option<Vec<LatencyReport>> {
+        self.consumers
+            .iter()
+            .map(|consumer| consumer.latency.as_ref().map(|latency| latency.report()))
+            .collect()
+    }
+
+    /// Returns the producer ID of this sender, reported by receivers in
+    /// [`MessageMeta::producer`](crate::MessageMeta::producer).
+    ///
+    /// Every sender and every clone of a sender has its own ID, unique within the process.
+    pub fn producer_id(&self) -> u64 {
+        self.producer
+    }
+
+    /// Samples the queue depth of every consumer at once, for comparing consumers with each other.
+    ///
+    /// See [`DepthSnapshot`] for how close to simultaneous the samples are.
+    pub fn queue_depths(&self) -> DepthSnapshot {
+        DepthSnapshot::sample(self.consumers.iter().map(|consumer| &*consumer.depth))
+    }
+
+    /// Returns the offset committed by every consumer with [`UnboundedReceiver::commit`](crate::UnboundedReceiver::commit), indexed like the
+    /// receivers.
+    pub fn committed_offsets(&self) -> Vec<Option<u64>> {
+        self.consumers
+            .iter()
+            .map(|consumer| committed(&consumer.committed))
+            .collect()
+    }
+
+    /// Returns a handle to the channel's metrics, to publish them in the Prometheus format through a
+    /// [`PrometheusRegistry`](crate::PrometheusRegistry).
+    #[cfg(feature = "prometheus")]
+    pub fn metrics(&self) -> crate::ChannelMetrics {
+        crate::ChannelMetrics::new(
+            self.consumers[0].tally.shared(),
+            self.consumers
+                .iter()
+                .map(|consumer| consumer.depth.clone())
+                .collect(),
+            self.consumers
+                .iter()
+                .map(|consumer| consumer.latency.clone())
+                .collect(),
+        )
+    }
+
+    /// Returns the message counts of the whole channel, see [`Totals`].
+    pub fn totals(&self) -> Totals {
+        self.consumers[0].tally.channel().totals()
+    }
+
+    /// Returns how the messages sent to every consumer were accounted for, indexed like the receivers.
+    ///
+    /// Comparing the messages accepted by a consumer with the ones its receiver consumed shows whether messages were
+    /// lost, and the ones left in a dropped receiver are counted separately. See [`Reconciliation`].
+    pub fn reconcile(&self) -> Vec<Reconciliation> {
+        self.consumers[0].tally.channel().reconcile()
+    }
+
+    /// Returns a watch of the channel's health, for channels built with
+    /// [`watch_health`](crate::UnboundedStickyChannelBuilder::watch_health).
+    ///
+    /// The watch is updated whenever a consumer runs out of capacity, receives a message after that, or is closed.
+    pub fn health(&self) -> Option<watch::Receiver<ChannelHealth>> {
+        self.health.as_ref().map(|health| health.subscribe())
+    }
+
+    /// Queues a terminal sentinel to every consumer, behind all messages sent through this sender (or its clones)
+    /// before the call.
+    ///
+    /// Consumers observe the sentinel as [`Event::Finished`](crate::Event::Finished) from
+    /// [`recv_event`](crate::UnboundedReceiver::recv_event) and through [`is_finished`](crate::UnboundedReceiver::is_finished), so worker loops can
+    /// exit once their backlog is done even while other sender clones keep the channel open. The channel itself stays
+    /// open.
+    ///
+    /// Markers do not take up capacity, so this method never waits.
+    pub fn finish(&self) {
+        for consumer in &self.consumers {
+            inject(&consumer.sender, Payload::Finish);
+        }
+    }
+
+    /// Sends a message to the consumer selected by `route`.
+    pub(crate) fn send_route(&self, message: T, route: Route) -> Result<(), SendError<T>> {
+        match self.consumers.get(route.index) {
+            Some(consumer) => consumer.send(message, route),
+            None => Err(SendError::NoConsumer(message, route.index)),
+        }
+    }
 }
 
 impl<ID, T, S> Clone for UnboundedSender<ID, T, S>
@@ -45,6 +432,10 @@ where
         UnboundedSender {
             consumers: self.consumers.clone(),
             build_hasher: self.build_hasher.clone(),
+            partitions: self.partitions.clone(),
+            validator: self.validator.clone(),
+            health: self.health.clone(),
+            producer: next_producer(),
             _phantom: std::marker::PhantomData,
         }
     }
@@ -1,17 +1,209 @@
 use std::{
+    future::Future,
     hash::{BuildHasher, Hash},
-    num::TryFromIntError,
+    num::{NonZeroUsize, TryFromIntError},
+    pin::pin,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+    time::Instant,
 };
 
-pub fn compute_route_id<ID, S>(
+use tokio::task::{AbortHandle, JoinHandle, JoinSet};
+
+use crate::{
+    partition::PartitionTable,
+    routing::{consumer_index, hash_id},
+};
+
+/// Where a message with a given ID is delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Route {
+    /// Hash of the ID.
+    pub(crate) hash: u64,
+    /// Index of the consumer the ID is routed to.
+    pub(crate) index: usize,
+    /// Producer ID of the sender clone the message is sent from, or `0` if not sent from a sender.
+    pub(crate) producer: u64,
+}
+
+/// Returns a producer ID that has not been handed out before, for a new sender or sender clone.
+pub(crate) fn next_producer() -> u64 {
+    static NEXT_PRODUCER: AtomicU64 = AtomicU64::new(1);
+    NEXT_PRODUCER.fetch_add(1, Ordering::Relaxed)
+}
+
+pub fn compute_route<ID, S>(
     id: ID,
     num_consumers: usize,
     build_hasher: &S,
-) -> Result<usize, TryFromIntError>
+) -> Result<Route, TryFromIntError>
+where
+    ID: Hash,
+    S: BuildHasher,
+{
+    hash_route(hash_id(&id, build_hasher), num_consumers)
+}
+
+/// Routes an ID that has already been hashed.
+pub(crate) fn hash_route(hash: u64, num_consumers: usize) -> Result<Route, TryFromIntError> {
+    let num_consumers = NonZeroUsize::try_from(num_consumers)?;
+    let index = consumer_index(hash, num_consumers);
+    Ok(Route {
+        hash,
+        index,
+        producer: 0,
+    })
+}
+
+/// Computes the routes of `ids`, keeping only the first route to each consumer.
+pub(crate) fn distinct_routes<ID, S>(
+    ids: impl IntoIterator<Item = ID>,
+    num_consumers: usize,
+    build_hasher: &S,
+    partitions: Option<&PartitionTable>,
+    producer: u64,
+) -> Result<Vec<Route>, TryFromIntError>
 where
     ID: Hash,
     S: BuildHasher,
 {
-    let hash = usize::try_from(build_hasher.hash_one(id))?;
-    Ok(hash % num_consumers)
+    let mut seen = vec![false; num_consumers];
+    let mut routes = Vec::new();
+    for id in ids {
+        let mut route = compute_route(id, num_consumers, build_hasher)?;
+        if let Some(partitions) = partitions {
+            route = partitions.route(route);
+        }
+        if !std::mem::replace(&mut seen[route.index], true) {
+            routes.push(Route { producer, ..route });
+        }
+    }
+    Ok(routes)
+}
+
+/// Lock-free SplitMix64 generator shared between threads, for decisions that only need to look random.
+pub(crate) struct Rng {
+    state: AtomicU64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self {
+            state: AtomicU64::new(seed),
+        }
+    }
+
+    /// Returns a number uniformly distributed in `0.0..1.0`.
+    pub(crate) fn next_f64(&self) -> f64 {
+        const GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+        let mut z = self
+            .state
+            .fetch_add(GAMMA, Ordering::Relaxed)
+            .wrapping_add(GAMMA);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Wakes a thread parked in [`block_on`].
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs a future to completion on the current thread, parking it while the future is pending.
+///
+/// Returns `None` if `deadline` passes first, in which case the future is dropped. Only suitable for futures that do
+/// not need a runtime to make progress, like receiving from a channel.
+pub(crate) fn block_on<F: Future>(future: F, deadline: Option<Instant>) -> Option<F::Output> {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return Some(output);
+        }
+
+        match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return None;
+                }
+                thread::park_timeout(deadline - now);
+            }
+            None => thread::park(),
+        }
+    }
+}
+
+/// Spawns a helper task of this crate.
+///
+/// With the `console` feature, the task runs inside a `tracing` span carrying `name`, and is given `name` as its task
+/// name when built with `--cfg tokio_unstable`, so that it can be told apart in tokio-console.
+pub(crate) fn spawn<F>(name: &'static str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(feature = "console")]
+    let future = tracing::Instrument::instrument(future, task_span(name));
+
+    #[cfg(all(feature = "console", tokio_unstable))]
+    return tokio::task::Builder::new()
+        .name(name)
+        .spawn(future)
+        .expect("failed to spawn task");
+
+    #[cfg(not(all(feature = "console", tokio_unstable)))]
+    {
+        let _ = name;
+        tokio::spawn(future)
+    }
+}
+
+/// Spawns a helper task of this crate on `tasks`, named like [`spawn`].
+pub(crate) fn spawn_in<F>(
+    tasks: &mut JoinSet<F::Output>,
+    name: &'static str,
+    future: F,
+) -> AbortHandle
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(feature = "console")]
+    let future = tracing::Instrument::instrument(future, task_span(name));
+
+    #[cfg(all(feature = "console", tokio_unstable))]
+    return tasks
+        .build_task()
+        .name(name)
+        .spawn(future)
+        .expect("failed to spawn task");
+
+    #[cfg(not(all(feature = "console", tokio_unstable)))]
+    {
+        let _ = name;
+        tasks.spawn(future)
+    }
+}
+
+#[cfg(feature = "console")]
+fn task_span(name: &'static str) -> tracing::Span {
+    tracing::info_span!("tokio_sticky_channel::task", task.name = name)
 }
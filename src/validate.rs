@@ -0,0 +1,83 @@
+use std::{any::Any, error::Error, panic::RefUnwindSafe, sync::Arc};
+
+use crate::{Rejection, SendError};
+
+/// Validation bound to a single ID, for handles that always send with the same ID.
+pub(crate) type BoundValidator<T> = Arc<dyn Fn(&T) -> Result<(), Rejection> + Send + Sync>;
+
+/// Check a channel runs on every message before it is routed.
+///
+/// The validation function is stored without its type, so that the sender does not own anything of type `ID` and
+/// still accepts IDs that borrow data dropped before the sender.
+pub(crate) struct Validator<ID, T> {
+    validate: Arc<dyn Any + Send + Sync + RefUnwindSafe>,
+    check: fn(&(dyn Any + Send + Sync), &ID, &T) -> Result<(), Rejection>,
+}
+
+impl<ID, T> Validator<ID, T> {
+    /// Wraps a validation function, erasing its type and the type of its errors.
+    pub(crate) fn new<E, F>(validate: F) -> Self
+    where
+        E: Error + Send + Sync + 'static,
+        F: Fn(&ID, &T) -> Result<(), E> + Send + Sync + RefUnwindSafe + 'static,
+    {
+        Self {
+            validate: Arc::new(validate),
+            check: check_with::<ID, T, E, F>,
+        }
+    }
+
+    /// Runs the validation function, handing the message back if it passes.
+    pub(crate) fn check(&self, id: &ID, message: T) -> Result<T, SendError<T>> {
+        match (self.check)(&*self.validate, id, &message) {
+            Ok(()) => Ok(message),
+            Err(rejection) => Err(SendError::Rejected(message, rejection)),
+        }
+    }
+}
+
+impl<ID, T> Validator<ID, T>
+where
+    ID: Send + Sync + 'static,
+    T: 'static,
+{
+    /// Binds the validation to `id`.
+    pub(crate) fn bind(self, id: ID) -> BoundValidator<T> {
+        Arc::new(move |message| (self.check)(&*self.validate, &id, message))
+    }
+}
+
+impl<ID, T> Clone for Validator<ID, T> {
+    fn clone(&self) -> Self {
+        Self {
+            validate: self.validate.clone(),
+            check: self.check,
+        }
+    }
+}
+
+fn check_with<ID, T, E, F>(
+    validate: &(dyn Any + Send + Sync),
+    id: &ID,
+    message: &T,
+) -> Result<(), Rejection>
+where
+    E: Error + Send + Sync + 'static,
+    F: Fn(&ID, &T) -> Result<(), E> + 'static,
+{
+    let validate = validate
+        .downcast_ref::<F>()
+        .expect("validators are checked with the check of their own type");
+    validate(id, message).map_err(Rejection::new)
+}
+
+/// Runs a bound validator, if any, handing the message back if it passes.
+pub(crate) fn check_bound<T>(
+    validator: Option<&BoundValidator<T>>,
+    message: T,
+) -> Result<T, SendError<T>> {
+    match validator.map(|validator| validator(&message)) {
+        Some(Err(rejection)) => Err(SendError::Rejected(message, rejection)),
+        Some(Ok(())) | None => Ok(message),
+    }
+}
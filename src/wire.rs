@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{RoutedMessage, WireError, util::Route};
+
+/// Version of the [`WireEnvelope`] format written by this crate.
+///
+/// Increased whenever the layout of the envelope changes. Decoders reject envelopes of other versions instead of
+/// misreading them.
+pub const WIRE_VERSION: u16 = 1;
+
+/// Metadata carried by a [`WireEnvelope`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct WireMeta {
+    /// Identifier of the sender the message was sent through, see [`MessageMeta::producer`](crate::MessageMeta).
+    pub producer: u64,
+    /// Number of times the message has been delivered, see [`MessageMeta::attempt`](crate::MessageMeta).
+    pub attempt: u32,
+}
+
+/// Serialized form of a routed message, shared by everything that moves messages out of the process.
+///
+/// The envelope carries the hash of the message's ID rather than the ID itself, so a message can be routed again on
+/// the receiving side without knowing the ID type: [`into_routed`](WireEnvelope::into_routed) turns it back into a
+/// [`RoutedMessage`] to send with `send_routed`. The hash depends on the hasher of the sending channel, so both sides
+/// should use a hasher with fixed keys.
+///
+/// The version is always the first field, so it can be checked before the rest of the envelope is decoded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WireEnvelope<T> {
+    /// Format version, [`WIRE_VERSION`] for envelopes created by this crate.
+    pub version: u16,
+    /// Hash of the message's ID.
+    pub hash: u64,
+    /// Index of the consumer the message was routed to.
+    pub consumer: u32,
+    /// Metadata of the message.
+    pub meta: WireMeta,
+    /// The message.
+    pub payload: T,
+}
+
+impl<T> WireEnvelope<T> {
+    /// Converts the envelope back into a message routed to the same consumer by the same ID hash.
+    pub fn into_routed(self) -> RoutedMessage<T> {
+        RoutedMessage::new(
+            self.payload,
+            Route {
+                hash: self.hash,
+                index: self.consumer as usize,
+                producer: self.meta.producer,
+            },
+        )
+    }
+}
+
+impl<T> From<RoutedMessage<T>> for WireEnvelope<T> {
+    fn from(message: RoutedMessage<T>) -> Self {
+        let (payload, route) = message.into_parts();
+        Self {
+            version: WIRE_VERSION,
+            hash: route.hash,
+            consumer: u32::try_from(route.index).expect("consumer index fits in 32 bits"),
+            meta: WireMeta {
+                producer: route.producer,
+                attempt: 1,
+            },
+            payload,
+        }
+    }
+}
+
+impl<T> WireEnvelope<T>
+where
+    T: Serialize,
+{
+    /// Encodes the envelope with `codec`.
+    pub fn encode<C>(&self, codec: &C) -> Result<Vec<u8>, WireError>
+    where
+        C: WireCodec,
+    {
+        codec.encode(self)
+    }
+
+    /// Encodes the envelope with bincode's standard configuration, like [`encode`](WireEnvelope::encode) with
+    /// [`Bincode`].
+    #[cfg(feature = "bincode")]
+    pub fn to_bincode(&self) -> Result<Vec<u8>, WireError> {
+        self.encode(&Bincode)
+    }
+}
+
+impl<T> WireEnvelope<T>
+where
+    T: DeserializeOwned,
+{
+    /// Decodes an envelope encoded with the same codec.
+    ///
+    /// Fails with [`WireError::UnsupportedVersion`] for envelopes of another format version, without decoding the rest.
+    pub fn decode<C>(codec: &C, bytes: &[u8]) -> Result<Self, WireError>
+    where
+        C: WireCodec,
+    {
+        let Version { version } = codec.decode(bytes)?;
+        if version != WIRE_VERSION {
+            return Err(WireError::UnsupportedVersion(version));
+        }
+        codec.decode(bytes)
+    }
+
+    /// Decodes an envelope encoded with [`to_bincode`](WireEnvelope::to_bincode).
+    #[cfg(feature = "bincode")]
+    pub fn from_bincode(bytes: &[u8]) -> Result<Self, WireError> {
+        Self::decode(&Bincode, bytes)
+    }
+}
+
+/// Leading field of every envelope, decoded on its own to check the format version first.
+#[derive(Deserialize)]
+struct Version {
+    version: u16,
+}
+
+/// Serialization format of [`WireEnvelope`]s.
+///
+/// Every end of a bridge picks the codec its peers can read: [`Bincode`] between processes of this crate,
+/// [`Postcard`] for embedded consumers, [`Cbor`] for consumers written in other languages, and [`Json`] for
+/// debugging. Each codec is enabled by the feature of the same name in lowercase.
+pub trait WireCodec {
+    /// Serializes `value`.
+    fn encode<V>(&self, value: &V) -> Result<Vec<u8>, WireError>
+    where
+        V: Serialize;
+
+    /// Deserializes a value from `bytes`, ignoring whatever follows it.
+    fn decode<V>(&self, bytes: &[u8]) -> Result<V, WireError>
+    where
+        V: DeserializeOwned;
+}
+
+/// [`WireCodec`] using bincode's standard configuration.
+#[cfg(feature = "bincode")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl WireCodec for Bincode {
+    fn encode<V>(&self, value: &V) -> Result<Vec<u8>, WireError>
+    where
+        V: Serialize,
+    {
+        bincode::serde::encode_to_vec(value, bincode::config::standard())
+            .map_err(|err| WireError::Encode(Box::new(err)))
+    }
+
+    fn decode<V>(&self, bytes: &[u8]) -> Result<V, WireError>
+    where
+        V: DeserializeOwned,
+    {
+        bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+            .map(|(value, _)| value)
+            .map_err(|err| WireError::Decode(Box::new(err)))
+    }
+}
+
+/// [`WireCodec`] using postcard, a compact format for `no_std` targets.
+#[cfg(feature = "postcard")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Postcard;
+
+#[cfg(feature = "postcard")]
+impl WireCodec for Postcard {
+    fn encode<V>(&self, value: &V) -> Result<Vec<u8>, WireError>
+    where
+        V: Serialize,
+    {
+        postcard::to_stdvec(value).map_err(|err| WireError::Encode(Box::new(err)))
+    }
+
+    fn decode<V>(&self, bytes: &[u8]) -> Result<V, WireError>
+    where
+        V: DeserializeOwned,
+    {
+        postcard::take_from_bytes(bytes)
+            .map(|(value, _)| value)
+            .map_err(|err| WireError::Decode(Box::new(err)))
+    }
+}
+
+/// [`WireCodec`] using CBOR, a self-describing format with implementations in most languages.
+#[cfg(feature = "cbor")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl WireCodec for Cbor {
+    fn encode<V>(&self, value: &V) -> Result<Vec<u8>, WireError>
+    where
+        V: Serialize,
+    {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes).map_err(|err| WireError::Encode(Box::new(err)))?;
+        Ok(bytes)
+    }
+
+    fn decode<V>(&self, bytes: &[u8]) -> Result<V, WireError>
+    where
+        V: DeserializeOwned,
+    {
+        ciborium::from_reader(bytes).map_err(|err| WireError::Decode(Box::new(err)))
+    }
+}
+
+/// [`WireCodec`] using JSON, meant for debugging and inspection rather than throughput.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl WireCodec for Json {
+    fn encode<V>(&self, value: &V) -> Result<Vec<u8>, WireError>
+    where
+        V: Serialize,
+    {
+        serde_json::to_vec(value).map_err(|err| WireError::Encode(Box::new(err)))
+    }
+
+    fn decode<V>(&self, bytes: &[u8]) -> Result<V, WireError>
+    where
+        V: DeserializeOwned,
+    {
+        let mut values = serde_json::Deserializer::from_slice(bytes).into_iter();
+        match values.next() {
+            Some(value) => value.map_err(|err| WireError::Decode(Box::new(err))),
+            None => Err(WireError::Decode("no JSON value".into())),
+        }
+    }
+}